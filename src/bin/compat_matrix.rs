@@ -0,0 +1,319 @@
+//!
+//! Checks the device setup against every adapter on the machine and reports what works.
+//!
+//! cargo run --features compat_matrix --bin compat_matrix [-- report.md]
+//!
+//! For each adapter: creates a device with the engine features and limits,
+//! reads back a cleared offscreen target, then builds the engine on the adapter,
+//! validates every shader it registers and renders the [`GOLDEN_SCENES`]
+//! against their references.
+//!
+//! Shaders are preprocessed with the defs of the default pipelines, scenes without
+//! a reference in `tests/golden` are reported as skipped.
+//!
+
+use std::{
+    borrow::Cow,
+    fmt::Write,
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+use bevy::prelude::{App, Assets};
+use flat::{
+    exit::AppExitCondition,
+    golden::{compare_images, GoldenScene, GOLDEN_SCENES},
+    render::{
+        debug_view::DebugViewMode,
+        required_features, required_limits,
+        resource::{renderer::RenderDevice, shader::Shader},
+    },
+    FlatEngineComplete, FlatEngineConfig,
+};
+
+const TARGET_SIZE: u32 = 64;
+const TARGET_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+const CLEAR_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+enum Check {
+    Pass,
+    Fail(String),
+    Skipped,
+}
+
+impl Check {
+    fn cell(&self) -> String {
+        match self {
+            Check::Pass => "ok".to_string(),
+            Check::Fail(reason) => format!("FAIL: {}", reason.replace('\n', " ")),
+            Check::Skipped => "-".to_string(),
+        }
+    }
+}
+
+struct AdapterReport {
+    info: wgpu::AdapterInfo,
+    missing_features: wgpu::Features,
+    device: Check,
+    clear_readback: Check,
+    /// Registered shaders that were validated, with the failures by handle.
+    shaders: (usize, Vec<String>),
+    engine: Check,
+    scenes: Vec<(&'static str, Check)>,
+}
+
+fn main() {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let reports: Vec<AdapterReport> = instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .map(|adapter| futures_lite::future::block_on(check_adapter(adapter)))
+        .collect();
+
+    let report = format_report(&reports);
+    print!("{}", report);
+
+    if let Some(path) = std::env::args().nth(1) {
+        std::fs::write(&path, &report).expect("Failed to write the report");
+    }
+
+    let failed = reports.iter().any(|r| {
+        !matches!(r.clear_readback, Check::Pass)
+            || !matches!(r.engine, Check::Pass)
+            || !r.shaders.1.is_empty()
+            || r.scenes.iter().any(|(_, check)| matches!(check, Check::Fail(_)))
+    });
+    std::process::exit(if reports.is_empty() || failed { 1 } else { 0 });
+}
+
+async fn check_adapter(adapter: wgpu::Adapter) -> AdapterReport {
+    let mut report = AdapterReport {
+        info: adapter.get_info(),
+        missing_features: required_features() - adapter.features(),
+        device: Check::Skipped,
+        clear_readback: Check::Skipped,
+        shaders: (0, Vec::new()),
+        engine: Check::Skipped,
+        scenes: GOLDEN_SCENES
+            .iter()
+            .map(|scene| (scene.name, Check::Skipped))
+            .collect(),
+    };
+
+    let (device, queue) = match adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: required_features(),
+                limits: required_limits(&adapter.limits()),
+            },
+            None,
+        )
+        .await
+    {
+        Ok(device_queue) => device_queue,
+        Err(err) => {
+            report.device = Check::Fail(err.to_string());
+            return report;
+        }
+    };
+    report.device = Check::Pass;
+    report.clear_readback = clear_readback(&device, &queue).await;
+
+    let config = FlatEngineConfig::default()
+        .with_adapter(&report.info)
+        .headless()
+        .with_exit_condition(AppExitCondition::DontExit);
+    let app = catch_unwind(AssertUnwindSafe(|| {
+        let mut app = App::new();
+        app.add_plugins(FlatEngineComplete::with_config(config.clone()));
+        app
+    }));
+    let Ok(app) = app else {
+        report.engine = Check::Fail("engine setup panicked".to_string());
+        return report;
+    };
+    report.engine = Check::Pass;
+    report.shaders = validate_shaders(&app).await;
+
+    for (scene, (_, check)) in GOLDEN_SCENES.iter().zip(report.scenes.iter_mut()) {
+        *check = check_scene(scene, config.clone());
+    }
+    report
+}
+
+/// Validates the shaders registered by the engine plugins on the device of `app`.
+async fn validate_shaders(app: &App) -> (usize, Vec<String>) {
+    let device = app.world.resource::<RenderDevice>();
+    // Preprocessed with the defs of the default pipelines, `#ifdef` lines are not WGSL
+    let shader_defs = DebugViewMode::default().shader_defs();
+
+    let shaders = app.world.resource::<Assets<Shader>>();
+    let mut failures = Vec::new();
+    for (handle_id, shader) in shaders.iter() {
+        let source = match shader.preprocess(&shader_defs) {
+            Ok(source) => source,
+            Err(err) => {
+                failures.push(format!("{:?}: {}", handle_id, err));
+                continue;
+            }
+        };
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let _ = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+        if let Some(err) = device.pop_error_scope().await {
+            failures.push(format!("{:?}: {}", handle_id, err));
+        }
+    }
+    (shaders.len(), failures)
+}
+
+/// Renders `scene` on the adapter of `config`, skipped without a reference.
+fn check_scene(scene: &GoldenScene, config: FlatEngineConfig) -> Check {
+    let Ok(reference) = image::open(scene.reference()) else {
+        return Check::Skipped;
+    };
+    let reference = reference.into_rgba8();
+
+    let test = scene.test().with_config(config);
+    let tolerance = test.tolerance;
+    let Ok(actual) = catch_unwind(AssertUnwindSafe(|| test.render(scene.setup))) else {
+        return Check::Fail("render panicked".to_string());
+    };
+    match compare_images(&actual, &reference, &tolerance) {
+        Some(diff) if diff.matches(&tolerance) => Check::Pass,
+        Some(diff) => Check::Fail(format!(
+            "{} of {} pixels differ",
+            diff.differing, diff.total
+        )),
+        None => Check::Fail("size differs from the reference".to_string()),
+    }
+}
+
+async fn clear_readback(device: &wgpu::Device, queue: &wgpu::Queue) -> Check {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("compat_target"),
+        size: wgpu::Extent3d {
+            width: TARGET_SIZE,
+            height: TARGET_SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TARGET_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    // 64 * 4 bytes is already a multiple of COPY_BYTES_PER_ROW_ALIGNMENT
+    let bytes_per_row = TARGET_SIZE * 4;
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("compat_readback"),
+        size: (bytes_per_row * TARGET_SIZE) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut command_encoder = device.create_command_encoder(&Default::default());
+    command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 1.0,
+                    g: 0.0,
+                    b: 1.0,
+                    a: 1.0,
+                }),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+    command_encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit([command_encoder.finish()]);
+
+    let slice = readback.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+
+    match receiver.recv() {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => return Check::Fail(err.to_string()),
+        Err(_) => return Check::Fail("readback was never mapped".to_string()),
+    }
+
+    let data = slice.get_mapped_range();
+    let mismatch = data.chunks_exact(4).position(|pixel| pixel != CLEAR_COLOR);
+    drop(data);
+    readback.unmap();
+
+    match mismatch {
+        Some(index) => Check::Fail(format!("pixel {} differs from the clear color", index)),
+        None => Check::Pass,
+    }
+}
+
+fn format_report(reports: &[AdapterReport]) -> String {
+    let mut out = String::new();
+
+    let _ = write!(
+        out,
+        "| Adapter | Backend | Type | Missing features | Device | Clear readback | Engine | Shaders |"
+    );
+    for scene in GOLDEN_SCENES {
+        let _ = write!(out, " {} |", scene.name);
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "|{}", "---|".repeat(8 + GOLDEN_SCENES.len()));
+
+    for report in reports {
+        let _ = write!(
+            out,
+            "| {} | {:?} | {:?} | {:?} | {} | {} | {} | {} |",
+            report.info.name,
+            report.info.backend,
+            report.info.device_type,
+            report.missing_features,
+            report.device.cell(),
+            report.clear_readback.cell(),
+            report.engine.cell(),
+            shaders_cell(&report.shaders),
+        );
+        for (_, check) in &report.scenes {
+            let _ = write!(out, " {} |", check.cell());
+        }
+        let _ = writeln!(out);
+    }
+
+    if reports.is_empty() {
+        let _ = writeln!(out, "No adapters found");
+    }
+
+    out
+}
+
+fn shaders_cell((count, failures): &(usize, Vec<String>)) -> String {
+    match failures.is_empty() {
+        true if *count == 0 => Check::Skipped.cell(),
+        true => format!("ok ({})", count),
+        false => Check::Fail(failures.join("; ")).cell(),
+    }
+}
@@ -0,0 +1,81 @@
+//!
+//! When closing windows ends the app.
+//!
+//! bevy's window runner checks for `AppExit` right after every update,
+//! the exit sent here on a `WindowClosed` is seen in the update the close caused,
+//! also with a reactive `UpdateMode` that does not redraw on its own.
+//! With [`FlatEngineConfig::return_from_run`](crate::FlatEngineConfig::return_from_run)
+//! `App::run` then returns instead of ending the process.
+//!
+
+use bevy::{
+    app::AppExit,
+    prelude::{EventReader, EventWriter, Res, Resource},
+    window::{WindowClosed, WindowId, Windows},
+};
+
+/// Changeable at runtime, e.g. `DontExit` while a "save before quitting" dialog is open.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AppExitCondition {
+    /// Exits once the primary window is closed, other windows close with it.
+    OnPrimaryClosed,
+    #[default]
+    OnAllClosed,
+    /// Keeps running without windows, only an `AppExit` sent by the app ends it.
+    DontExit,
+}
+
+impl AppExitCondition {
+    pub fn should_exit(&self, primary_closed: bool, windows_left: usize) -> bool {
+        match self {
+            AppExitCondition::OnPrimaryClosed => primary_closed,
+            AppExitCondition::OnAllClosed => windows_left == 0,
+            AppExitCondition::DontExit => false,
+        }
+    }
+}
+
+///
+/// Sends `AppExit` when the [`AppExitCondition`] is met.
+///
+/// Checked when a window closes or the condition changes, so headless apps
+/// that never had a window keep running.
+///
+pub fn exit_on_condition(
+    condition: Res<AppExitCondition>,
+    windows: Option<Res<Windows>>,
+    mut closed_events: EventReader<WindowClosed>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let Some(windows) = windows else {
+        return;
+    };
+    let mut closed = false;
+    let mut primary_closed = false;
+    for event in closed_events.iter() {
+        closed = true;
+        primary_closed |= event.id == WindowId::primary();
+    }
+    let condition_changed = condition.is_changed() && !condition.is_added();
+    if !closed && !condition_changed {
+        return;
+    }
+    primary_closed |= condition_changed && windows.get_primary().is_none();
+    if condition.should_exit(primary_closed, windows.iter().count()) {
+        app_exit.send_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions_follow_closed_windows() {
+        assert!(AppExitCondition::OnPrimaryClosed.should_exit(true, 1));
+        assert!(!AppExitCondition::OnPrimaryClosed.should_exit(false, 0));
+        assert!(AppExitCondition::OnAllClosed.should_exit(false, 0));
+        assert!(!AppExitCondition::OnAllClosed.should_exit(true, 1));
+        assert!(!AppExitCondition::DontExit.should_exit(true, 0));
+    }
+}
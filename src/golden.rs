@@ -0,0 +1,475 @@
+//!
+//! Golden-image tests, renders a scene headless and compares it against a reference PNG.
+//!
+//! ```ignore
+//! #[test]
+//! fn sprite_tint() {
+//!     GoldenTest::new(128, 128)
+//!         .with_frames(3)
+//!         .assert_matches("tests/golden/sprite_tint.png", |app, target| {
+//!             app.world.spawn(CameraBundle {
+//!                 camera: Camera::with_render_target(RenderTarget::Image(target)),
+//!                 ..Default::default()
+//!             });
+//!             // spawn the scene
+//!         });
+//! }
+//! ```
+//!
+//! - Needs a GPU adapter, a software one (lavapipe, WARP) keeps results stable on CI.
+//! - [`RenderOrdering`] is deterministic, entities must be spawned in the same order.
+//! - A missing reference fails the test, running with `FLAT_BLESS=1` writes the
+//!   rendered image as the reference instead.
+//! - On a mismatch the rendered image is written next to the reference as `<name>.actual.png`.
+//!
+//! The engine's own scenes are [`GOLDEN_SCENES`], tested in `tests/golden.rs` against
+//! the references in `tests/golden`. They need an adapter, so the tests are ignored by default,
+//! run them with `cargo test --test golden -- --ignored`.
+//!
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::{App, Assets, Entity, Handle, Transform, Vec3};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::{
+    exit::AppExitCondition,
+    render::{
+        camera::component::{Camera, CameraBundle, PerspectiveProjection, RenderTarget},
+        phase::RenderOrdering,
+        resource::renderer::{RenderDevice, RenderQueue},
+        texture::{Image, ImageSampling},
+        RenderAssets,
+    },
+    FlatEngineComplete, FlatEngineConfig,
+};
+
+/// Set to write the rendered images as the references.
+pub const BLESS_VAR: &str = "FLAT_BLESS";
+
+/// How far a rendered image can be from its reference and still match.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GoldenTolerance {
+    /// Largest difference of a channel, 0..=255, for the pixel to count as the same.
+    pub channel: u8,
+    /// Fraction of the pixels, 0.0..=1.0, that can differ.
+    pub pixels: f32,
+}
+
+impl Default for GoldenTolerance {
+    fn default() -> Self {
+        Self {
+            channel: 2,
+            pixels: 0.001,
+        }
+    }
+}
+
+/// Result of [`compare_images`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GoldenDiff {
+    /// Pixels differing by more than the channel tolerance.
+    pub differing: usize,
+    pub total: usize,
+    /// Largest channel difference over all pixels.
+    pub max_channel: u8,
+}
+
+impl GoldenDiff {
+    pub fn matches(&self, tolerance: &GoldenTolerance) -> bool {
+        self.differing as f32 <= self.total as f32 * tolerance.pixels
+    }
+}
+
+/// Compares pixel by pixel, images of different sizes never match.
+pub fn compare_images(
+    actual: &RgbaImage,
+    reference: &RgbaImage,
+    tolerance: &GoldenTolerance,
+) -> Option<GoldenDiff> {
+    if actual.dimensions() != reference.dimensions() {
+        return None;
+    }
+    let mut diff = GoldenDiff {
+        total: (actual.width() * actual.height()) as usize,
+        ..Default::default()
+    };
+    for (a, r) in actual.pixels().zip(reference.pixels()) {
+        let channels = a.0.iter().zip(r.0.iter());
+        let max = channels.map(|(a, r)| a.abs_diff(*r)).max().unwrap_or(0);
+        diff.max_channel = diff.max_channel.max(max);
+        if max > tolerance.channel {
+            diff.differing += 1;
+        }
+    }
+    Some(diff)
+}
+
+///
+/// Engine app rendering into an image for a number of frames.
+///
+/// The config is made headless, without the window feature the engine always is.
+///
+pub struct GoldenTest {
+    pub width: u32,
+    pub height: u32,
+    /// Updates before the readback, assets loaded from files may need a few.
+    pub frames: u32,
+    pub tolerance: GoldenTolerance,
+    pub config: FlatEngineConfig,
+}
+
+impl GoldenTest {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            frames: 1,
+            tolerance: Default::default(),
+            config: Default::default(),
+        }
+    }
+
+    pub fn with_frames(mut self, frames: u32) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: GoldenTolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_config(mut self, config: FlatEngineConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    ///
+    /// Builds the app, `setup` spawns the scene and a camera rendering into the given image,
+    /// then runs the frames and reads the image back.
+    ///
+    pub fn render(self, setup: impl FnOnce(&mut App, Handle<Image>)) -> RgbaImage {
+        let mut app = App::new();
+        app.add_plugins(FlatEngineComplete::with_config(
+            self.config
+                .headless()
+                .with_exit_condition(AppExitCondition::DontExit),
+        ))
+        .insert_resource(RenderOrdering {
+            deterministic: true,
+        });
+
+        let target = app
+            .world
+            .resource_mut::<Assets<Image>>()
+            .add(Image::render_target(self.width, self.height));
+        setup(&mut app, target.clone());
+
+        for _ in 0..self.frames.max(1) {
+            app.update();
+        }
+
+        read_render_target(&app, &target)
+    }
+
+    ///
+    /// [`render`](Self::render) and compare against the reference at `path`,
+    /// panics with the difference when they do not match.
+    ///
+    pub fn assert_matches(
+        self,
+        path: impl AsRef<Path>,
+        setup: impl FnOnce(&mut App, Handle<Image>),
+    ) {
+        let path = path.as_ref();
+        let tolerance = self.tolerance;
+        let actual = self.render(setup);
+
+        if std::env::var_os(BLESS_VAR).is_some() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            actual.save(path).unwrap();
+            return;
+        }
+
+        let reference = match image::open(path) {
+            Ok(reference) => reference.into_rgba8(),
+            Err(err) => {
+                let actual_path = actual_path(path);
+                actual.save(&actual_path).unwrap();
+                panic!(
+                    "No reference {:?} ({}), rendered {:?}, run with {}=1 to accept it",
+                    path, err, actual_path, BLESS_VAR
+                );
+            }
+        };
+
+        match compare_images(&actual, &reference, &tolerance) {
+            Some(diff) if diff.matches(&tolerance) => {}
+            diff => {
+                let actual_path = actual_path(path);
+                actual.save(&actual_path).unwrap();
+                match diff {
+                    Some(diff) => panic!(
+                        "{:?} differs in {} of {} pixels (max channel difference {}), rendered {:?}",
+                        path, diff.differing, diff.total, diff.max_channel, actual_path
+                    ),
+                    None => panic!(
+                        "{:?} is {:?}, rendered {:?} is {:?}",
+                        path,
+                        reference.dimensions(),
+                        actual_path,
+                        actual.dimensions()
+                    ),
+                }
+            }
+        }
+    }
+}
+
+fn actual_path(path: &Path) -> PathBuf {
+    path.with_extension("actual.png")
+}
+
+/// Copies the prepared texture of `target` into a buffer and waits for it to map.
+pub fn read_render_target(app: &App, target: &Handle<Image>) -> RgbaImage {
+    let device = app.world.resource::<RenderDevice>();
+    let queue = app.world.resource::<RenderQueue>();
+    let gpu_texture = app
+        .world
+        .resource::<RenderAssets<Image>>()
+        .get(&target.id())
+        .expect("Render target was not prepared");
+    let size = gpu_texture.texture.size();
+
+    // Rows of the copy are padded to COPY_BYTES_PER_ROW_ALIGNMENT
+    let row_bytes = size.width * 4;
+    let padded_row_bytes = {
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        (row_bytes + align - 1) / align * align
+    };
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("golden_readback"),
+        size: (padded_row_bytes * size.height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut command_encoder = device.create_command_encoder(&Default::default());
+    command_encoder.copy_texture_to_buffer(
+        gpu_texture.texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(padded_row_bytes),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+    queue.submit([command_encoder.finish()]);
+
+    let slice = readback.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .expect("Readback was never mapped")
+        .expect("Failed to map the readback");
+
+    let data = slice.get_mapped_range();
+    let mut pixels = Vec::with_capacity((row_bytes * size.height) as usize);
+    for row in data.chunks_exact(padded_row_bytes as usize) {
+        pixels.extend_from_slice(&row[..row_bytes as usize]);
+    }
+    drop(data);
+    readback.unmap();
+
+    // Render targets are in the engine default format, BGRA
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    RgbaImage::from_raw(size.width, size.height, pixels).unwrap()
+}
+
+/// Scene rendered by the golden tests, `compat_matrix` renders them on every adapter.
+#[derive(Clone, Copy)]
+pub struct GoldenScene {
+    pub name: &'static str,
+    pub setup: fn(&mut App, Handle<Image>),
+}
+
+impl GoldenScene {
+    /// Width and height of the rendered image.
+    pub const SIZE: u32 = 128;
+    /// Updates before the readback, the textures are prepared on the first.
+    pub const FRAMES: u32 = 3;
+
+    /// Reference image under `tests/golden`.
+    pub fn reference(&self) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.png", self.name))
+    }
+
+    pub fn test(&self) -> GoldenTest {
+        GoldenTest::new(Self::SIZE, Self::SIZE).with_frames(Self::FRAMES)
+    }
+
+    pub fn assert_matches(&self) {
+        self.test().assert_matches(self.reference(), self.setup);
+    }
+}
+
+pub const GOLDEN_SCENES: &[GoldenScene] = &[
+    #[cfg(feature = "sprite2d")]
+    GoldenScene {
+        name: "sprites",
+        setup: sprites_scene,
+    },
+    #[cfg(feature = "mesh3d")]
+    GoldenScene {
+        name: "mesh_cube",
+        setup: mesh_cube_scene,
+    },
+    #[cfg(feature = "sprite2d")]
+    GoldenScene {
+        name: "post_grading",
+        setup: post_grading_scene,
+    },
+];
+
+/// Camera of the scenes, at +Z looking at the origin.
+fn spawn_camera(app: &mut App, target: Handle<Image>) -> Entity {
+    app.world
+        .spawn(CameraBundle::<PerspectiveProjection> {
+            transform: Transform::from_xyz(0.0, 0.0, 4.0),
+            camera: Camera::with_render_target(RenderTarget::Image(target)),
+            ..Default::default()
+        })
+        .id()
+}
+
+/// 2x2 checker of `a` and `b`, sampled nearest so the texels stay sharp.
+fn checker_image(app: &mut App, a: [u8; 4], b: [u8; 4]) -> Handle<Image> {
+    let img = RgbaImage::from_fn(2, 2, |x, y| Rgba(if (x + y) % 2 == 0 { a } else { b }));
+    app.world.resource_mut::<Assets<Image>>().add(Image {
+        img: DynamicImage::ImageRgba8(img),
+        prepare: true,
+        render_target: false,
+        sampling: ImageSampling::Nearest,
+    })
+}
+
+/// An opaque sprite under a half transparent one.
+#[cfg(feature = "sprite2d")]
+fn spawn_sprites(app: &mut App) {
+    use crate::{
+        render::{blend::BlendMode, color::Color},
+        sprite::{bundle::SpriteBundle, BASE_QUAD_HANDLE},
+    };
+
+    let texture = checker_image(app, [255, 64, 64, 255], [64, 64, 255, 255]);
+    app.world.spawn(SpriteBundle {
+        transform: Transform::from_xyz(-0.3, 0.0, 0.0).with_scale(Vec3::splat(2.0)),
+        mesh: BASE_QUAD_HANDLE.typed(),
+        texture,
+        ..Default::default()
+    });
+    let texture = checker_image(app, [255, 255, 255, 128], [255, 255, 255, 128]);
+    app.world.spawn((
+        SpriteBundle {
+            transform: Transform::from_xyz(0.5, 0.3, 0.5),
+            mesh: BASE_QUAD_HANDLE.typed(),
+            texture,
+            color: Color::NONE,
+            ..Default::default()
+        },
+        BlendMode::AlphaBlend,
+    ));
+}
+
+#[cfg(feature = "sprite2d")]
+fn sprites_scene(app: &mut App, target: Handle<Image>) {
+    spawn_camera(app, target);
+    spawn_sprites(app);
+}
+
+/// A textured cube turned so three faces are seen.
+#[cfg(feature = "mesh3d")]
+fn mesh_cube_scene(app: &mut App, target: Handle<Image>) {
+    use bevy::prelude::{EulerRot, Quat};
+
+    use crate::{
+        mesh3d::bundle::TexturedMeshBundle,
+        render::{
+            mesh::{
+                primitive::{cube::create_unit_cube, FaceDirection},
+                Mesh,
+            },
+            resource::buffer::Vertex,
+        },
+    };
+
+    spawn_camera(app, target);
+    let texture = checker_image(app, [240, 240, 240, 255], [40, 120, 40, 255]);
+    let mesh = app
+        .world
+        .resource_mut::<Assets<Mesh<Vertex>>>()
+        .add(create_unit_cube(FaceDirection::Out));
+    app.world.spawn(TexturedMeshBundle {
+        transform: Transform::from_rotation(Quat::from_euler(EulerRot::YXZ, 0.6, 0.5, 0.0))
+            .with_scale(Vec3::splat(1.5)),
+        mesh,
+        texture,
+        ..Default::default()
+    });
+}
+
+/// The sprites through a desaturating `ColorGrading` pass.
+#[cfg(feature = "sprite2d")]
+fn post_grading_scene(app: &mut App, target: Handle<Image>) {
+    use crate::render::post::grading::ColorGrading;
+
+    let camera = spawn_camera(app, target);
+    app.world.entity_mut(camera).insert(ColorGrading {
+        saturation: 0.0,
+        ..Default::default()
+    });
+    spawn_sprites(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn differences_within_tolerance_match() {
+        let reference = RgbaImage::from_pixel(10, 10, Rgba([100, 100, 100, 255]));
+        let mut actual = reference.clone();
+        actual.put_pixel(0, 0, Rgba([102, 100, 100, 255]));
+        actual.put_pixel(1, 0, Rgba([100, 110, 100, 255]));
+
+        let tolerance = GoldenTolerance {
+            channel: 2,
+            pixels: 0.01,
+        };
+        let diff = compare_images(&actual, &reference, &tolerance).unwrap();
+        assert_eq!(diff.differing, 1);
+        assert_eq!(diff.max_channel, 10);
+        assert!(diff.matches(&tolerance));
+        assert!(!diff.matches(&GoldenTolerance {
+            pixels: 0.0,
+            ..tolerance
+        }));
+
+        let smaller = RgbaImage::new(5, 10);
+        assert_eq!(compare_images(&smaller, &reference, &tolerance), None);
+    }
+}
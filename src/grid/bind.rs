@@ -0,0 +1,234 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{
+        Commands, Entity, FromWorld, GlobalTransform, Query, Res, ResMut, Resource, Vec4, World,
+    },
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        blend::BlendMode,
+        camera::component::{CameraUniforms, ExtractedVisibility},
+        color::ColorSpace,
+        resource::{
+            buffer_pool::BufferPool,
+            component_uniform::ComponentUniforms,
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, VertexState,
+            },
+            renderer::{RenderDevice, RenderQueue},
+            shader::Shader,
+            specialized_pipeline::{PipelineSpecialize, Specialized},
+            uniform::{uniform_buffer_layout_entry, DynamicUniformId},
+        },
+        texture,
+    },
+    util::EngineDefault,
+};
+
+use super::{GroundGrid, GRID_SHADER_HANDLE};
+
+#[derive(Resource)]
+pub struct GridPipeline {
+    pub view_layout: BindGroupLayout,
+    pub grid_layout: BindGroupLayout,
+}
+
+impl FromWorld for GridPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            ResMut<PipelineCache>,
+            ResMut<Specialized<Self>>,
+        )> = SystemState::new(world);
+        let (render_device, mut pipeline_cache, mut specialized_self) = state.get_mut(world);
+
+        let view_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[uniform_buffer_layout_entry::<CameraUniforms>(
+                    0,
+                    wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    true,
+                )],
+                label: Some("grid_view_layout"),
+            });
+
+        let grid_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[uniform_buffer_layout_entry::<GridUniform>(
+                    0,
+                    wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    true,
+                )],
+                label: Some("grid_uniform_layout"),
+            });
+
+        let grid_pipeline = GridPipeline {
+            view_layout,
+            grid_layout,
+        };
+
+        for key in BlendMode::ALL {
+            let id = pipeline_cache.queue(grid_pipeline.specialize(&render_device, *key));
+            specialized_self.pipelines.insert(*key, id);
+        }
+
+        grid_pipeline
+    }
+}
+
+impl PipelineSpecialize for GridPipeline {
+    type Key = BlendMode;
+
+    fn specialize(&self, _render_device: &RenderDevice, key: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: None,
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![self.view_layout.clone(), self.grid_layout.clone()],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: GRID_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: GRID_SHADER_HANDLE.typed(),
+                shader_defs: key.shader_defs(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(key.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None, // Seen from below as well
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::DepthTexture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        }
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct GridUniform {
+    color: Vec4,
+    x_axis_color: Vec4,
+    z_axis_color: Vec4,
+    height: f32,
+    cell_size: f32,
+    line_width: f32,
+    fade_distance: f32,
+}
+
+impl GridUniform {
+    pub fn new(
+        grid: &GroundGrid,
+        global_transform: &GlobalTransform,
+        color_space: ColorSpace,
+    ) -> Self {
+        Self {
+            color: grid.color.as_gpu_vec(color_space),
+            x_axis_color: grid.x_axis_color.as_gpu_vec(color_space),
+            z_axis_color: grid.z_axis_color.as_gpu_vec(color_space),
+            height: global_transform.translation().y,
+            cell_size: grid.cell_size,
+            line_width: grid.line_width,
+            fade_distance: grid.fade_distance,
+        }
+    }
+}
+
+pub fn prepare_grid_uniforms(
+    mut commands: Commands,
+    mut grid_uniforms: ResMut<ComponentUniforms<GridUniform>>,
+    color_space: Res<ColorSpace>,
+    query: Query<(
+        Entity,
+        &GroundGrid,
+        &GlobalTransform,
+        Option<&ExtractedVisibility>,
+    )>,
+) {
+    let mut spawns: Vec<(Entity, DynamicUniformId<GridUniform>)> = Vec::new();
+
+    grid_uniforms.clear();
+    for (entity, grid, global_transform, computed_visibility) in query.iter() {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        let grid_uniform = GridUniform::new(grid, global_transform, *color_space);
+        spawns.push((entity, grid_uniforms.push(grid_uniform).into()));
+    }
+
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn queue_grid_uniforms(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffer_pool: ResMut<BufferPool>,
+    mut grid_uniforms: ResMut<ComponentUniforms<GridUniform>>,
+) {
+    grid_uniforms.write_buffer_pooled(&render_device, &render_queue, &mut buffer_pool);
+}
+
+#[derive(Default, Resource)]
+pub struct GridBindGroups {
+    pub view_bind_group: Option<wgpu::BindGroup>,
+    pub grid_bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn create_grid_bind_groups(
+    mut grid_bind_groups: ResMut<GridBindGroups>,
+    render_device: Res<RenderDevice>,
+    grid_pipeline: Res<GridPipeline>,
+    view_uniforms: Res<ComponentUniforms<CameraUniforms>>,
+    grid_uniforms: Res<ComponentUniforms<GridUniform>>,
+) {
+    let (Some(view_binding), Some(grid_binding)) = (view_uniforms.binding(), grid_uniforms.binding())
+    else {
+        return;
+    };
+    let view_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &grid_pipeline.view_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: view_binding,
+        }],
+    });
+    let grid_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &grid_pipeline.grid_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: grid_binding,
+        }],
+    });
+
+    grid_bind_groups.view_bind_group = Some(view_bind_group);
+    grid_bind_groups.grid_bind_group = Some(grid_bind_group);
+}
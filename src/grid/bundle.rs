@@ -0,0 +1,30 @@
+use bevy::prelude::{Bundle, GlobalTransform, Transform};
+
+use crate::render::{
+    blend::BlendMode, camera::component::Visibility, system::RenderFunctionId,
+};
+
+use super::{GroundGrid, GRID_DEFAULT_BLEND_MODE, GRID_RENDER_FUNCTION};
+
+#[derive(Bundle)]
+pub struct GroundGridBundle {
+    pub global_transform: GlobalTransform,
+    pub transform: Transform,
+    pub grid: GroundGrid,
+    pub blend_mode: BlendMode,
+    pub visibility: Visibility,
+    pub render_function: RenderFunctionId,
+}
+
+impl Default for GroundGridBundle {
+    fn default() -> Self {
+        Self {
+            global_transform: GlobalTransform::default(),
+            transform: Transform::default(),
+            grid: GroundGrid::default(),
+            blend_mode: GRID_DEFAULT_BLEND_MODE,
+            visibility: Visibility::Inherited,
+            render_function: GRID_RENDER_FUNCTION.into(),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+
+// -- Vertex -----
+
+struct Fog {
+    color: vec4<f32>,
+    mode: u32,
+    start: f32,
+    end: f32,
+    density: f32,
+}
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    fog: Fog,
+}
+
+struct Grid {
+    color: vec4<f32>,
+    x_axis_color: vec4<f32>,
+    z_axis_color: vec4<f32>,
+    height: f32,
+    cell_size: f32,
+    // in pixels
+    line_width: f32,
+    fade_distance: f32,
+}
+
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        world_position: vec3<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> grid: Grid;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Two triangles of a quad centered under the camera, camera.view is its world transform
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let center = camera.view[3].xz;
+    let corner = center + corners[vertex_index] * grid.fade_distance;
+    let world_position = vec3<f32>(corner.x, grid.height, corner.y);
+
+    out.clip_position = camera.view_proj * vec4<f32>(world_position, 1.0);
+    out.world_position = world_position;
+
+    return out;
+}
+
+// -- Fragment -----
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coord = in.world_position.xz / grid.cell_size;
+    let derivative = fwidth(coord);
+
+    // Distance to the closest line in pixels
+    let to_line = abs(fract(coord - 0.5) - 0.5) / derivative;
+    let line = 1.0 - min(min(to_line.x, to_line.y) / grid.line_width, 1.0);
+
+    var color = grid.color;
+    // The line at x = 0 runs along the Z axis, the one at z = 0 along the X axis
+    let to_axis = abs(coord) / derivative;
+    if (to_axis.x < grid.line_width) {
+        color = grid.z_axis_color;
+    }
+    if (to_axis.y < grid.line_width) {
+        color = grid.x_axis_color;
+    }
+
+    let distance = length(in.world_position - camera.view[3].xyz);
+    let fade = 1.0 - clamp(distance / grid.fade_distance, 0.0, 1.0);
+    let alpha = color.a * line * fade;
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    color = vec4<f32>(color.rgb, alpha);
+#ifdef BLEND_MULTIPLY
+    // The Multiply blend state expects premultiplied alpha
+    color = vec4<f32>(color.rgb * color.a, color.a);
+#endif
+    return color;
+}
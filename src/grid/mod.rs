@@ -0,0 +1,145 @@
+use bevy::{
+    asset::load_internal_asset,
+    prelude::{Component, Entity, HandleUntyped, Plugin, World},
+    reflect::TypeUuid,
+};
+
+use crate::render::{
+    blend::BlendMode,
+    camera::component::CameraUniforms,
+    color::Color,
+    resource::{
+        component_uniform::ComponentUniforms, pipeline::PipelineCache, shader::Shader,
+        specialized_pipeline::Specialized, uniform::DynamicUniformId,
+    },
+    stats::RenderStats,
+    system::{AddRenderFunction, RenderResult},
+    RenderStage,
+};
+
+use self::bind::{
+    create_grid_bind_groups, prepare_grid_uniforms, queue_grid_uniforms, GridBindGroups,
+    GridPipeline, GridUniform,
+};
+
+pub mod bind;
+pub mod bundle;
+
+const GRID_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 65678909876445673);
+
+pub struct FlatGridPlugin;
+impl Plugin for FlatGridPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        load_internal_asset!(app, GRID_SHADER_HANDLE, "grid.wgsl", Shader::from_wgsl);
+
+        app.init_resource::<Specialized<GridPipeline>>()
+            .init_resource::<GridPipeline>()
+            .init_resource::<GridBindGroups>()
+            .init_resource::<ComponentUniforms<GridUniform>>()
+            .add_render_function(GRID_RENDER_FUNCTION, render_grid)
+            .add_system_to_stage(RenderStage::Prepare, prepare_grid_uniforms)
+            .add_system_to_stage(RenderStage::Create, queue_grid_uniforms)
+            .add_system_to_stage(RenderStage::Create, create_grid_bind_groups);
+    }
+}
+
+///
+/// Infinite ground grid on the world XZ plane at the height of the entity.
+///
+/// A quad of `fade_distance` radius follows the camera, the lines are antialiased
+/// in screen space and fade out towards its edge, so the grid never shows an end.
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GroundGrid {
+    /// World units between two lines
+    pub cell_size: f32,
+    /// Line width in pixels
+    pub line_width: f32,
+    pub color: Color,
+    /// Color of the line along the X axis
+    pub x_axis_color: Color,
+    /// Color of the line along the Z axis
+    pub z_axis_color: Color,
+    /// Distance from the camera where the grid has faded out
+    pub fade_distance: f32,
+}
+
+impl Default for GroundGrid {
+    fn default() -> Self {
+        Self {
+            cell_size: 1.0,
+            line_width: 1.0,
+            color: Color::rgba(0.5, 0.5, 0.5, 0.8),
+            x_axis_color: Color::RED,
+            z_axis_color: Color::BLUE,
+            fade_distance: 100.0,
+        }
+    }
+}
+
+pub const GRID_RENDER_FUNCTION: usize = 8;
+/// Used for grids without a [`BlendMode`].
+pub const GRID_DEFAULT_BLEND_MODE: BlendMode = BlendMode::AlphaBlend;
+fn render_grid<'w>(
+    camera: Entity,
+    object: Entity,
+    world: &'w World,
+    render_pass: &mut wgpu::RenderPass<'w>,
+) -> RenderResult {
+    // -- Set Pipeline --
+    let Some(specialized_grid_pipeline) = world.get_resource::<Specialized<GridPipeline>>() else {
+        return RenderResult::Failure("Specialized<GridPipeline> resource missing");
+    };
+    let Some(pipeline_cache) = world.get_resource::<PipelineCache>() else {
+        return RenderResult::Failure("PipelineCache resource missing");
+    };
+
+    let blend_mode = world
+        .get::<BlendMode>(object)
+        .copied()
+        .unwrap_or(GRID_DEFAULT_BLEND_MODE);
+    let Some(pipeline_id) = specialized_grid_pipeline.pipelines.get(&blend_mode) else {
+        return RenderResult::Failure("pipeline not specialized");
+    };
+    let Some(render_pipeline) = pipeline_cache.get(pipeline_id) else {
+        return RenderResult::Failure("pipeline not compiled yet");
+    };
+    render_pass.set_pipeline(render_pipeline);
+    let Some(render_stats) = world.get_resource::<RenderStats>() else {
+        return RenderResult::Failure("RenderStats resource missing");
+    };
+    render_stats.pipeline_switch();
+    // -- -- -- -------- -- -- --
+
+    // -- Bind View, Grid BindGroups --
+    let Some(grid_bind_groups) = world.get_resource::<GridBindGroups>() else {
+        return RenderResult::Failure("GridBindGroups resource missing");
+    };
+    let (Some(view_bind_group), Some(grid_bind_group)) = (
+        grid_bind_groups.view_bind_group.as_ref(),
+        grid_bind_groups.grid_bind_group.as_ref(),
+    ) else {
+        return RenderResult::Failure("bind groups not created");
+    };
+
+    let Some(view_uniform_id) = world.get::<DynamicUniformId<CameraUniforms>>(camera) else {
+        return RenderResult::Failure("no CameraUniforms id");
+    };
+    render_pass.set_bind_group(0, view_bind_group, &[**view_uniform_id]);
+
+    let Some(grid_uniform_id) = world.get::<DynamicUniformId<GridUniform>>(object) else {
+        return RenderResult::Failure("no GridUniform id");
+    };
+    render_pass.set_bind_group(1, grid_bind_group, &[**grid_uniform_id]);
+    render_stats.bind_group_switches(2);
+    // -- -- -- -------- -- -- --
+
+    // -- Draw --
+    // Quad under the camera, expanded in the vertex shader
+    render_pass.draw(0..6, 0..1);
+    render_stats.draw(6, 1);
+    // -- -- -- -------- -- -- --
+
+    RenderResult::Success
+}
@@ -0,0 +1,91 @@
+use bevy::{
+    prelude::{Res, ResMut, Resource, Vec2},
+    utils::HashMap,
+    window::{Window, WindowId, Windows},
+};
+
+/// Cursor over a window, every position has its origin at the bottom left and Y up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindowCursor {
+    pub logical: Vec2,
+    pub physical: Vec2,
+    /// `-1.0..=1.0` across the window, the space of the cameras rendering to it.
+    pub ndc: Vec2,
+    /// Whether the cursor is over the window, positions are the last ones inside while not.
+    pub inside: bool,
+}
+
+impl WindowCursor {
+    pub fn from_logical(logical: Vec2, logical_size: Vec2, scale_factor: f64) -> Self {
+        Self {
+            logical,
+            physical: logical * scale_factor as f32,
+            ndc: logical / logical_size.max(Vec2::ONE) * 2.0 - Vec2::ONE,
+            inside: true,
+        }
+    }
+}
+
+///
+/// Cursor of every window, updated at the start of the frame
+/// in `CoreStage::PreUpdate` from bevy's `Windows`.
+///
+#[derive(Resource, Default)]
+pub struct CursorState {
+    windows: HashMap<WindowId, WindowCursor>,
+}
+
+impl CursorState {
+    pub fn get(&self, window: WindowId) -> Option<&WindowCursor> {
+        self.windows.get(&window)
+    }
+
+    pub fn primary(&self) -> Option<&WindowCursor> {
+        self.get(WindowId::primary())
+    }
+
+    /// Window the cursor is over, if any.
+    pub fn hovered_window(&self) -> Option<(WindowId, &WindowCursor)> {
+        self.windows
+            .iter()
+            .find(|(_, cursor)| cursor.inside)
+            .map(|(id, cursor)| (*id, cursor))
+    }
+
+    fn update(&mut self, window: &Window) {
+        let cursor = self.windows.entry(window.id()).or_default();
+        match window.cursor_position() {
+            Some(logical) => {
+                let size = Vec2::new(window.width(), window.height());
+                *cursor = WindowCursor::from_logical(logical, size, window.scale_factor());
+            }
+            None => cursor.inside = false,
+        }
+    }
+}
+
+pub fn update_cursor_state(windows: Option<Res<Windows>>, mut cursor_state: ResMut<CursorState>) {
+    let Some(windows) = windows else {
+        return;
+    };
+    cursor_state
+        .windows
+        .retain(|id, _| windows.get(*id).is_some());
+    for window in windows.iter() {
+        cursor_state.update(window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_logical_to_physical_and_ndc() {
+        let cursor =
+            WindowCursor::from_logical(Vec2::new(200.0, 450.0), Vec2::new(800.0, 600.0), 2.0);
+        assert_eq!(cursor.physical, Vec2::new(400.0, 900.0));
+        assert_eq!(cursor.ndc, Vec2::new(-0.5, 0.5));
+        assert!(cursor.inside);
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::{
+    input::Input,
+    prelude::{EventWriter, Local, MouseButton, Res, Resource, Vec2},
+    time::Time,
+    utils::HashMap,
+};
+
+use super::cursor::CursorState;
+
+/// Positions are logical window pixels, origin at the bottom left like `Window::cursor_position`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DragStart {
+    pub button: MouseButton,
+    pub start: Vec2,
+    pub position: Vec2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dragging {
+    pub button: MouseButton,
+    pub start: Vec2,
+    pub position: Vec2,
+    /// Movement since the last `Dragging` or the `DragStart`.
+    pub delta: Vec2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DragEnd {
+    pub button: MouseButton,
+    pub start: Vec2,
+    pub position: Vec2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DoubleClick {
+    pub button: MouseButton,
+    pub position: Vec2,
+}
+
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GestureSettings {
+    /// Pixels the cursor moves with a button held before it is a drag.
+    pub drag_threshold: f32,
+    /// Seconds between the presses of a double click.
+    pub double_click_time: f64,
+    /// Pixels the cursor can move between the presses of a double click.
+    pub double_click_distance: f32,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        Self {
+            drag_threshold: 4.0,
+            double_click_time: 0.3,
+            double_click_distance: 4.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Gesture {
+    DragStart(DragStart),
+    Dragging(Dragging),
+    DragEnd(DragEnd),
+    DoubleClick(DoubleClick),
+}
+
+#[derive(Default)]
+struct ButtonGesture {
+    /// Where the button was pressed, while it is held
+    pressed_at: Option<Vec2>,
+    dragging: bool,
+    last_position: Vec2,
+    /// Time and position of the last press that can start a double click
+    last_click: Option<(f64, Vec2)>,
+}
+
+impl ButtonGesture {
+    fn press(
+        &mut self,
+        button: MouseButton,
+        position: Vec2,
+        time: f64,
+        settings: &GestureSettings,
+    ) -> Option<Gesture> {
+        self.pressed_at = Some(position);
+        self.dragging = false;
+        self.last_position = position;
+        match self.last_click.take() {
+            Some((click_time, click_position))
+                if time - click_time <= settings.double_click_time
+                    && position.distance(click_position) <= settings.double_click_distance =>
+            {
+                Some(Gesture::DoubleClick(DoubleClick { button, position }))
+            }
+            _ => {
+                self.last_click = Some((time, position));
+                None
+            }
+        }
+    }
+
+    fn hold(
+        &mut self,
+        button: MouseButton,
+        position: Vec2,
+        settings: &GestureSettings,
+    ) -> Option<Gesture> {
+        let start = self.pressed_at?;
+        let delta = position - self.last_position;
+        if !self.dragging {
+            if position.distance(start) < settings.drag_threshold {
+                return None;
+            }
+            self.dragging = true;
+            // A drag is not the first click of a double click
+            self.last_click = None;
+            self.last_position = position;
+            return Some(Gesture::DragStart(DragStart {
+                button,
+                start,
+                position,
+            }));
+        }
+        if delta == Vec2::ZERO {
+            return None;
+        }
+        self.last_position = position;
+        Some(Gesture::Dragging(Dragging {
+            button,
+            start,
+            position,
+            delta,
+        }))
+    }
+
+    fn release(&mut self, button: MouseButton, position: Vec2) -> Option<Gesture> {
+        let start = self.pressed_at.take()?;
+        if !std::mem::take(&mut self.dragging) {
+            return None;
+        }
+        Some(Gesture::DragEnd(DragEnd {
+            button,
+            start,
+            position,
+        }))
+    }
+}
+
+/// Turns the mouse buttons and the cursor of the primary window into gesture events.
+pub fn detect_gestures(
+    time: Res<Time>,
+    settings: Res<GestureSettings>,
+    cursor_state: Res<CursorState>,
+    mouse: Option<Res<Input<MouseButton>>>,
+    mut buttons: Local<HashMap<MouseButton, ButtonGesture>>,
+    mut drag_starts: EventWriter<DragStart>,
+    mut draggings: EventWriter<Dragging>,
+    mut drag_ends: EventWriter<DragEnd>,
+    mut double_clicks: EventWriter<DoubleClick>,
+) {
+    let Some(mouse) = mouse else {
+        return;
+    };
+    let Some(cursor) = cursor_state.primary() else {
+        return;
+    };
+    let now = time.elapsed_seconds_f64();
+
+    for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle] {
+        let gesture = buttons.entry(button).or_default();
+        // Off the window the cursor keeps its last position, so drags end where they left it
+        let position = cursor.logical;
+
+        let mut gestures = Vec::with_capacity(2);
+        if mouse.just_pressed(button) {
+            gestures.extend(gesture.press(button, position, now, &settings));
+        }
+        if mouse.pressed(button) {
+            gestures.extend(gesture.hold(button, position, &settings));
+        }
+        if mouse.just_released(button) {
+            gestures.extend(gesture.release(button, position));
+        }
+
+        for gesture in gestures {
+            match gesture {
+                Gesture::DragStart(event) => drag_starts.send(event),
+                Gesture::Dragging(event) => draggings.send(event),
+                Gesture::DragEnd(event) => drag_ends.send(event),
+                Gesture::DoubleClick(event) => double_clicks.send(event),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drags_past_threshold_and_double_clicks_in_time() {
+        let settings = GestureSettings::default();
+        let button = MouseButton::Left;
+        let mut gesture = ButtonGesture::default();
+
+        assert_eq!(gesture.press(button, Vec2::ZERO, 0.0, &settings), None);
+        assert_eq!(gesture.hold(button, Vec2::new(2.0, 0.0), &settings), None);
+        assert!(matches!(
+            gesture.hold(button, Vec2::new(5.0, 0.0), &settings),
+            Some(Gesture::DragStart(_))
+        ));
+        assert_eq!(
+            gesture.hold(button, Vec2::new(8.0, 1.0), &settings),
+            Some(Gesture::Dragging(Dragging {
+                button,
+                start: Vec2::ZERO,
+                position: Vec2::new(8.0, 1.0),
+                delta: Vec2::new(3.0, 1.0),
+            }))
+        );
+        assert!(matches!(
+            gesture.release(button, Vec2::new(8.0, 1.0)),
+            Some(Gesture::DragEnd(_))
+        ));
+
+        // The drag does not count as the first click
+        assert_eq!(gesture.press(button, Vec2::ZERO, 0.1, &settings), None);
+        assert_eq!(gesture.release(button, Vec2::ZERO), None);
+        assert!(matches!(
+            gesture.press(button, Vec2::new(1.0, 1.0), 0.3, &settings),
+            Some(Gesture::DoubleClick(_))
+        ));
+        gesture.release(button, Vec2::ZERO);
+        assert_eq!(gesture.press(button, Vec2::ZERO, 1.0, &settings), None);
+    }
+}
@@ -0,0 +1,217 @@
+//!
+//! Keys by their position on the keyboard, for bindings that stay put on any layout.
+//!
+//! bevy tracks both the virtual [`KeyCode`] (the symbol of the key in the active layout)
+//! and the [`ScanCode`] (the physical key) of every `KeyboardInput`. Scan codes differ
+//! between platforms, [`PhysicalKey`] names them after the key at that position
+//! on a US QWERTY keyboard, so `PhysicalKey::W` is `Z` on AZERTY.
+//!
+
+use std::marker::PhantomData;
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::{keyboard::ScanCode, Input},
+    prelude::{KeyCode, Res},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PhysicalKey {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Key0,
+    Escape,
+    Tab,
+    Space,
+    LShift,
+    LControl,
+}
+
+impl PhysicalKey {
+    /// PC set 1 scan code, what winit reports on Windows and Linux.
+    #[cfg(not(target_os = "macos"))]
+    pub fn scan_code(&self) -> ScanCode {
+        use PhysicalKey::*;
+        ScanCode(match self {
+            Escape => 0x01,
+            Key1 => 0x02,
+            Key2 => 0x03,
+            Key3 => 0x04,
+            Key4 => 0x05,
+            Key5 => 0x06,
+            Key6 => 0x07,
+            Key7 => 0x08,
+            Key8 => 0x09,
+            Key9 => 0x0A,
+            Key0 => 0x0B,
+            Tab => 0x0F,
+            Q => 0x10,
+            W => 0x11,
+            E => 0x12,
+            R => 0x13,
+            T => 0x14,
+            Y => 0x15,
+            U => 0x16,
+            I => 0x17,
+            O => 0x18,
+            P => 0x19,
+            LControl => 0x1D,
+            A => 0x1E,
+            S => 0x1F,
+            D => 0x20,
+            F => 0x21,
+            G => 0x22,
+            H => 0x23,
+            J => 0x24,
+            K => 0x25,
+            L => 0x26,
+            LShift => 0x2A,
+            Z => 0x2C,
+            X => 0x2D,
+            C => 0x2E,
+            V => 0x2F,
+            B => 0x30,
+            N => 0x31,
+            M => 0x32,
+            Space => 0x39,
+        })
+    }
+
+    /// macOS virtual key code, what winit reports as the scan code there.
+    #[cfg(target_os = "macos")]
+    pub fn scan_code(&self) -> ScanCode {
+        use PhysicalKey::*;
+        ScanCode(match self {
+            A => 0x00,
+            S => 0x01,
+            D => 0x02,
+            F => 0x03,
+            H => 0x04,
+            G => 0x05,
+            Z => 0x06,
+            X => 0x07,
+            C => 0x08,
+            V => 0x09,
+            B => 0x0B,
+            Q => 0x0C,
+            W => 0x0D,
+            E => 0x0E,
+            R => 0x0F,
+            Y => 0x10,
+            T => 0x11,
+            Key1 => 0x12,
+            Key2 => 0x13,
+            Key3 => 0x14,
+            Key4 => 0x15,
+            Key6 => 0x16,
+            Key5 => 0x17,
+            Key9 => 0x19,
+            Key7 => 0x1A,
+            Key8 => 0x1C,
+            Key0 => 0x1D,
+            O => 0x1F,
+            U => 0x20,
+            I => 0x22,
+            P => 0x23,
+            L => 0x25,
+            J => 0x26,
+            K => 0x28,
+            N => 0x2D,
+            M => 0x2E,
+            Tab => 0x30,
+            Space => 0x31,
+            Escape => 0x35,
+            LShift => 0x38,
+            LControl => 0x3B,
+        })
+    }
+}
+
+/// A key by its symbol in the active layout, or by its position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyBinding {
+    Logical(KeyCode),
+    Physical(PhysicalKey),
+}
+
+impl From<KeyCode> for KeyBinding {
+    fn from(key_code: KeyCode) -> Self {
+        KeyBinding::Logical(key_code)
+    }
+}
+
+impl From<PhysicalKey> for KeyBinding {
+    fn from(physical_key: PhysicalKey) -> Self {
+        KeyBinding::Physical(physical_key)
+    }
+}
+
+/// Both bevy keyboard inputs, queried through [`KeyBinding`]s.
+#[derive(SystemParam)]
+pub struct Keys<'w, 's> {
+    key_codes: Res<'w, Input<KeyCode>>,
+    scan_codes: Res<'w, Input<ScanCode>>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> Keys<'w, 's> {
+    pub fn pressed(&self, binding: impl Into<KeyBinding>) -> bool {
+        match binding.into() {
+            KeyBinding::Logical(key_code) => self.key_codes.pressed(key_code),
+            KeyBinding::Physical(key) => self.scan_codes.pressed(key.scan_code()),
+        }
+    }
+
+    pub fn just_pressed(&self, binding: impl Into<KeyBinding>) -> bool {
+        match binding.into() {
+            KeyBinding::Logical(key_code) => self.key_codes.just_pressed(key_code),
+            KeyBinding::Physical(key) => self.scan_codes.just_pressed(key.scan_code()),
+        }
+    }
+
+    pub fn just_released(&self, binding: impl Into<KeyBinding>) -> bool {
+        match binding.into() {
+            KeyBinding::Logical(key_code) => self.key_codes.just_released(key_code),
+            KeyBinding::Physical(key) => self.scan_codes.just_released(key.scan_code()),
+        }
+    }
+
+    /// Whether any of `bindings` is pressed, e.g. `[PhysicalKey::W.into(), KeyCode::Up.into()]`.
+    pub fn any_pressed(&self, bindings: impl IntoIterator<Item = KeyBinding>) -> bool {
+        bindings.into_iter().any(|binding| self.pressed(binding))
+    }
+}
@@ -0,0 +1,35 @@
+use bevy::{
+    input::InputSystem,
+    prelude::{App, CoreStage, IntoSystemDescriptor, Plugin},
+};
+
+use self::{
+    cursor::{update_cursor_state, CursorState},
+    gesture::{detect_gestures, DoubleClick, DragEnd, DragStart, Dragging, GestureSettings},
+};
+
+pub mod cursor;
+pub mod gesture;
+pub mod keyboard;
+pub mod ray;
+
+///
+/// Input built on bevy's `Input` resources, runs in `CoreStage::PreUpdate`
+/// so systems in `CoreStage::Update` read it the same frame.
+///
+pub struct FlatInputPlugin;
+impl Plugin for FlatInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CursorState>()
+            .init_resource::<GestureSettings>()
+            .add_event::<DragStart>()
+            .add_event::<Dragging>()
+            .add_event::<DragEnd>()
+            .add_event::<DoubleClick>()
+            .add_system_to_stage(CoreStage::PreUpdate, update_cursor_state.after(InputSystem))
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                detect_gestures.after(update_cursor_state),
+            );
+    }
+}
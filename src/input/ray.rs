@@ -0,0 +1,125 @@
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{Entity, Query, Res, Vec2, Vec3},
+};
+
+use crate::render::camera::component::Camera;
+
+use super::cursor::CursorState;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Normalized.
+    pub direction: Vec3,
+}
+
+impl Ray {
+    ///
+    /// Ray through `cursor` (logical pixels, origin at the bottom left) of a target of `size`,
+    /// starting on the near plane of the camera.
+    ///
+    pub fn from_viewport(camera: &Camera, cursor: Vec2, size: Vec2) -> Option<Self> {
+        if size.x <= 0.0 || size.y <= 0.0 {
+            return None;
+        }
+        Self::from_ndc(camera, cursor / size * 2.0 - Vec2::ONE)
+    }
+
+    /// Ray through the normalized device coordinates `ndc` of the camera.
+    pub fn from_ndc(camera: &Camera, ndc: Vec2) -> Option<Self> {
+        // `computed.view` is the world transform of the camera
+        let world_from_ndc = camera.computed.view * camera.computed.proj.inverse();
+        let near = world_from_ndc.project_point3(ndc.extend(0.0));
+        let far = world_from_ndc.project_point3(ndc.extend(1.0));
+        let direction = (far - near).try_normalize()?;
+        Some(Self {
+            origin: near,
+            direction,
+        })
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Where the ray crosses the plane `Z = z`, the ground of 2D scenes at `0.0`.
+    pub fn intersect_plane_z(&self, z: f32) -> Option<Vec3> {
+        if self.direction.z.abs() <= f32::EPSILON {
+            return None;
+        }
+        let distance = (z - self.origin.z) / self.direction.z;
+        (distance >= 0.0).then(|| self.at(distance))
+    }
+}
+
+///
+/// World space ray under the cursor of the window a camera renders to.
+///
+/// Uses the matrices computed in `CoreStage::PostUpdate`, so in `CoreStage::Update`
+/// the ray follows the camera of the last frame.
+///
+#[derive(SystemParam)]
+pub struct CursorRay<'w, 's> {
+    cursor_state: Res<'w, CursorState>,
+    cameras: Query<'w, 's, (Entity, &'static Camera)>,
+}
+
+impl<'w, 's> CursorRay<'w, 's> {
+    ///
+    /// Ray of the first active camera rendering to the window under the cursor,
+    /// with an overlay camera on the same window pick the camera with [`CursorRay::ray_from`].
+    ///
+    pub fn ray(&self) -> Option<Ray> {
+        self.cameras
+            .iter()
+            .filter(|(_, camera)| camera.is_active)
+            .find_map(|(entity, _)| self.ray_from(entity))
+    }
+
+    pub fn ray_from(&self, camera: Entity) -> Option<Ray> {
+        let (_, camera) = self.cameras.get(camera).ok()?;
+        let cursor = self
+            .cursor_state
+            .get(camera.render_target.get_window()?)
+            .filter(|cursor| cursor.inside)?;
+        Ray::from_ndc(camera, cursor.ndc)
+    }
+
+    /// Point under the cursor on the `Z = 0` plane, for orthographic cameras of 2D scenes.
+    pub fn world_point_2d(&self) -> Option<Vec2> {
+        Some(self.ray()?.intersect_plane_z(0.0)?.truncate())
+    }
+
+    pub fn world_point_2d_from(&self, camera: Entity) -> Option<Vec2> {
+        Some(self.ray_from(camera)?.intersect_plane_z(0.0)?.truncate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::camera::component::{OrthographicProjection, Projection};
+    use bevy::prelude::Mat4;
+
+    #[test]
+    fn orthographic_ray_hits_cursor_point() {
+        let projection = OrthographicProjection {
+            left: -400.0,
+            right: 400.0,
+            bottom: -300.0,
+            top: 300.0,
+            near: 0.1,
+            far: 100.0,
+        };
+        let mut camera = Camera::default();
+        camera.computed.view = Mat4::from_translation(Vec3::new(50.0, 0.0, 10.0));
+        camera.computed.proj = projection.build_projection_matrix();
+
+        let ray =
+            Ray::from_viewport(&camera, Vec2::new(600.0, 150.0), Vec2::new(800.0, 600.0)).unwrap();
+        assert!(ray.direction.abs_diff_eq(-Vec3::Z, 1e-5));
+        let point = ray.intersect_plane_z(0.0).unwrap();
+        assert!(point.abs_diff_eq(Vec3::new(250.0, -150.0, 0.0), 1e-3));
+    }
+}
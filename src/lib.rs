@@ -1,19 +1,53 @@
+use std::time::Duration;
+
+#[cfg(feature = "window")]
+use bevy::winit::{UpdateMode, WinitSettings};
 use bevy::{
     app::PluginGroupBuilder,
-    prelude::{App, Plugin, PluginGroup},
+    log::Level,
+    prelude::{App, CoreStage, Plugin, PluginGroup},
+    window::WindowDescriptor,
     DefaultPlugins,
 };
+use exit::{exit_on_condition, AppExitCondition};
+use grid::FlatGridPlugin;
+use input::FlatInputPlugin;
+#[cfg(feature = "mesh3d")]
 use mesh3d::FlatMeshPlugin;
-use render::FlatRenderPlugin;
+use render::{
+    post::FlatPostPlugin, resource::renderer::RenderAdapterChoice, texture::ImageSampling,
+    FlatRenderPlugin,
+};
+#[cfg(feature = "sprite2d")]
+use shapes::FlatShapePlugin;
+#[cfg(feature = "sprite2d")]
 use sprite::FlatSpritePlugin;
+#[cfg(feature = "sprite2d")]
+use tilemap::FlatTilemapPlugin;
+use time::FixedUpdatePlugin;
+use trail::FlatTrailPlugin;
 
+pub mod grid;
+pub mod input;
+#[cfg(feature = "mesh3d")]
 pub mod mesh3d;
 pub mod render;
+#[cfg(feature = "sprite2d")]
 pub mod shapes;
+#[cfg(feature = "sprite2d")]
 pub mod sprite;
+#[cfg(feature = "sprite2d")]
+pub mod tilemap;
+pub mod trail;
 
+pub mod exit;
+pub mod golden;
 pub mod misc;
+pub mod prelude;
+#[cfg(feature = "text")]
 pub mod text;
+pub mod time;
+pub mod transform;
 pub mod util;
 
 /*
@@ -26,25 +60,294 @@ ED280816-E404-444A-A2D9-FFD2D171F928 - BatchMesh<V: MeshVertex>
 D952EB9F-7AD2-4B1B-B3CE-386735205990 - Quad
 3F897E85-62CE-4B2C-A957-FCF0CCE649FD - Image
 8E7C2F0A-6BB8-485C-917E-6B605A0DDF29 - ImageArray
+C4F1A7D2-3B58-4E69-8A0C-5D2E7B91F346 - AnimatedImage
 1AD2F3EF-87C8-46B4-BD1D-94C174C278EE
 AA97B177-9383-4934-8543-0F91A7A02836 - Vertex3Tex: MeshVertex
 10929DF8-15C5-472B-9398-7158AB89A0A6 - Vertex: MeshVertex
+5B0C3D8E-7F41-4A2B-9E6D-1C8A4F2B7D93 - VertexSkinned: MeshVertex
+8C752C5D-C9B7-4C40-8C9C-DE88D22CD8EB - Tilemap
+256E63CA-B9F3-40D4-8CEB-BC0BBC9D7A8F - TiledMap
+3E6A1F4B-9D27-4C85-B0E3-7A5C2D18F964 - Model
 */
 
-pub struct FlatEngineComplete;
+///
+/// Engine wide settings.
+///
+/// Engine logs use the module path as their target (`flat::render`, `flat::sprite`,
+/// `flat::render::view::window`, ...), a target filter applies to all modules under it.
+///
+#[derive(Clone)]
+pub struct FlatEngineConfig {
+    pub log_level: Level,
+    pub log_targets: Vec<(String, Level)>,
+    /// Title, size, position, mode, decorations, ... of the primary window.
+    pub window: WindowDescriptor,
+    /// How often the app updates while a window is focused, `Continuous` by default.
+    #[cfg(feature = "window")]
+    pub focused_mode: UpdateMode,
+    /// How often the app updates while no window is focused.
+    #[cfg(feature = "window")]
+    pub unfocused_mode: UpdateMode,
+    ///
+    /// Closes windows on `WindowCloseRequested`, turn off to intercept closes
+    /// (e.g. an "unsaved changes" dialog) and call `Window::close` yourself.
+    ///
+    pub close_when_requested: bool,
+    /// Initial [`AppExitCondition`], the resource can be changed while running.
+    pub exit_condition: AppExitCondition,
+    /// `App::run` returns after `AppExit` instead of ending the process.
+    #[cfg(feature = "window")]
+    pub return_from_run: bool,
+    ///
+    /// Runs without winit and windows, cameras can only render into `Image::render_target`s.
+    ///
+    /// Always the case without the `window` feature.
+    ///
+    pub headless: bool,
+    /// Sampling of the loaded images.
+    pub image_sampling: ImageSampling,
+    /// Reloads changed asset files, the GPU resources and bind groups using them are recreated.
+    pub watch_for_changes: bool,
+    /// Directory keeping compiled shaders between runs, see [`render::resource::shader_cache`].
+    #[cfg(feature = "shader_cache")]
+    pub shader_cache: Option<std::path::PathBuf>,
+    /// Adapter the device is created on, chosen by the engine when `None`.
+    pub adapter: Option<RenderAdapterChoice>,
+    ///
+    /// Adds bevy's `DefaultPlugins` and `WinitSettings` set up from this config.
+    ///
+    /// Turn off to add the engine to an app that already has them,
+    /// the log, window, update mode and asset settings above are then left to that app.
+    ///
+    pub default_plugins: bool,
+}
+
+impl Default for FlatEngineConfig {
+    fn default() -> Self {
+        Self {
+            log_level: Level::INFO,
+            log_targets: vec![
+                ("wgpu".to_string(), Level::ERROR),
+                ("naga".to_string(), Level::WARN),
+            ],
+            window: Default::default(),
+            #[cfg(feature = "window")]
+            focused_mode: UpdateMode::Continuous,
+            #[cfg(feature = "window")]
+            unfocused_mode: UpdateMode::Continuous,
+            close_when_requested: true,
+            exit_condition: AppExitCondition::OnAllClosed,
+            #[cfg(feature = "window")]
+            return_from_run: false,
+            headless: false,
+            image_sampling: ImageSampling::Linear,
+            watch_for_changes: false,
+            #[cfg(feature = "shader_cache")]
+            shader_cache: None,
+            adapter: None,
+            default_plugins: true,
+        }
+    }
+}
+
+impl FlatEngineConfig {
+    pub fn with_log_level(mut self, level: Level) -> Self {
+        self.log_level = level;
+        self
+    }
+
+    pub fn with_window(mut self, window: WindowDescriptor) -> Self {
+        self.window = window;
+        self
+    }
+
+    #[cfg(feature = "window")]
+    pub fn with_update_mode(mut self, focused: UpdateMode, unfocused: UpdateMode) -> Self {
+        self.focused_mode = focused;
+        self.unfocused_mode = unfocused;
+        self
+    }
+
+    ///
+    /// Updates only on window and input events or `RequestRedraw`,
+    /// for tools that should not use a CPU core while idle.
+    ///
+    #[cfg(feature = "window")]
+    pub fn reactive(self) -> Self {
+        self.with_update_mode(
+            UpdateMode::Reactive {
+                max_wait: Duration::from_secs(5),
+            },
+            UpdateMode::ReactiveLowPower {
+                max_wait: Duration::from_secs(60),
+            },
+        )
+    }
+
+    pub fn headless(mut self) -> Self {
+        self.headless = true;
+        self
+    }
+
+    pub fn with_exit_condition(mut self, exit_condition: AppExitCondition) -> Self {
+        self.exit_condition = exit_condition;
+        self
+    }
+
+    /// Code after `App::run` runs once the app exits, e.g. to save or open another app.
+    #[cfg(feature = "window")]
+    pub fn return_from_run(mut self) -> Self {
+        self.return_from_run = true;
+        self
+    }
+
+    pub fn without_default_plugins(mut self) -> Self {
+        self.default_plugins = false;
+        self
+    }
+
+    ///
+    /// Samples loaded images nearest so texels stay sharp,
+    /// pair it with a [`PixelPerfect`](render::camera::pixel_perfect::PixelPerfect) camera.
+    ///
+    pub fn pixel_art(mut self) -> Self {
+        self.image_sampling = ImageSampling::Nearest;
+        self
+    }
+
+    ///
+    /// Reloads images, shaders, ... when their file under `res` changes,
+    /// textures show up changed without restarting.
+    ///
+    pub fn hot_reload(mut self) -> Self {
+        self.watch_for_changes = true;
+        self
+    }
+
+    /// Compiled shaders are kept in `dir`, e.g. a directory in the user's cache folder.
+    #[cfg(feature = "shader_cache")]
+    pub fn with_shader_cache(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.shader_cache = Some(dir.into());
+        self
+    }
+
+    /// Renders on the adapter of `info`, e.g. one from `wgpu::Instance::enumerate_adapters`.
+    pub fn with_adapter(mut self, info: &wgpu::AdapterInfo) -> Self {
+        self.adapter = Some(RenderAdapterChoice {
+            name: info.name.clone(),
+            backend: info.backend,
+        });
+        self
+    }
+
+    /// Sets the level of a target, e.g. `with_log_target("flat::render", Level::DEBUG)`.
+    pub fn with_log_target(mut self, target: impl Into<String>, level: Level) -> Self {
+        let target = target.into();
+        self.log_targets.retain(|(t, _)| *t != target);
+        self.log_targets.push((target, level));
+        self
+    }
+
+    /// Filter in the `EnvFilter` syntax used by `LogPlugin`.
+    pub fn log_filter(&self) -> String {
+        self.log_targets
+            .iter()
+            .map(|(target, level)| format!("{}={}", target, level.as_str().to_lowercase()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+///
+/// Bevy's plugins and every engine plugin, configured through the builder methods:
+///
+/// ```ignore
+/// app.add_plugins(
+///     FlatEngineComplete::new()
+///         .with_log_level(Level::DEBUG)
+///         .with_window(window)
+///         .without_default_plugins(),
+/// );
+/// ```
+///
+#[derive(Default)]
+pub struct FlatEngineComplete {
+    pub config: FlatEngineConfig,
+}
+
+impl FlatEngineComplete {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: FlatEngineConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn with_log_level(mut self, level: Level) -> Self {
+        self.config = self.config.with_log_level(level);
+        self
+    }
+
+    pub fn with_window(mut self, window: WindowDescriptor) -> Self {
+        self.config = self.config.with_window(window);
+        self
+    }
+
+    pub fn headless(mut self) -> Self {
+        self.config = self.config.headless();
+        self
+    }
+
+    /// See [`FlatEngineConfig::default_plugins`].
+    pub fn without_default_plugins(mut self) -> Self {
+        self.config = self.config.without_default_plugins();
+        self
+    }
+}
 
 impl PluginGroup for FlatEngineComplete {
     fn build(self) -> PluginGroupBuilder {
         PluginGroupBuilder::start::<Self>()
-            .add(FlatBevyPlugins)
+            .add(FlatBevyPlugins {
+                config: self.config,
+            })
             .add(FlatEngineCore)
     }
 }
 
-pub struct FlatBevyPlugins;
+#[derive(Default)]
+pub struct FlatBevyPlugins {
+    pub config: FlatEngineConfig,
+}
 impl Plugin for FlatBevyPlugins {
     fn build(&self, app: &mut App) {
-        app.add_plugin(BevyPluginSettings);
+        // Read by the image loader of FlatRenderPlugin
+        app.insert_resource(self.config.image_sampling)
+            .insert_resource(self.config.exit_condition);
+        // Read by FlatRenderPlugin when it creates the device
+        #[cfg(feature = "shader_cache")]
+        if let Some(dir) = &self.config.shader_cache {
+            app.insert_resource(render::resource::shader_cache::ShaderCacheDir(dir.clone()));
+        }
+        if let Some(adapter) = &self.config.adapter {
+            app.insert_resource(adapter.clone());
+        }
+
+        if !self.config.default_plugins {
+            return;
+        }
+
+        let headless = self.config.headless || !cfg!(feature = "window");
+
+        #[cfg(feature = "window")]
+        if !headless {
+            app.add_plugin(BevyPluginSettings {
+                focused_mode: self.config.focused_mode,
+                unfocused_mode: self.config.unfocused_mode,
+                return_from_run: self.config.return_from_run,
+            });
+        }
 
         // app.add_plugin(bevy::log::LogPlugin::default())
         //     .add_plugin(bevy::core::CorePlugin::default())
@@ -66,38 +369,76 @@ impl Plugin for FlatBevyPlugins {
         //         watch_for_changes: false,
         //     });
 
-        app.add_plugins(
-            DefaultPlugins
-                .set(bevy::log::LogPlugin {
-                    level: bevy::log::Level::INFO,
-                    ..Default::default()
-                })
-                .set(bevy::window::WindowPlugin {
-                    window: Default::default(),
-                    add_primary_window: true,
-                    exit_on_all_closed: true,
-                    close_when_requested: true,
-                })
-                .set(bevy::asset::AssetPlugin {
-                    asset_folder: "res".to_string(),
-                    watch_for_changes: false,
-                }), // .disable::<bevy::render::RenderPlugin>()
-        );
+        let plugins = DefaultPlugins
+            .set(bevy::log::LogPlugin {
+                level: self.config.log_level,
+                filter: self.config.log_filter(),
+            })
+            .set(bevy::window::WindowPlugin {
+                window: self.config.window.clone(),
+                add_primary_window: !headless,
+                // Left to `AppExitCondition`
+                exit_on_all_closed: false,
+                close_when_requested: self.config.close_when_requested,
+            })
+            .set(bevy::asset::AssetPlugin {
+                asset_folder: "res".to_string(),
+                watch_for_changes: self.config.watch_for_changes,
+            }); // .disable::<bevy::render::RenderPlugin>()
+
+        if headless {
+            // Without a primary window the adapter is requested without a compatible surface
+            #[cfg(feature = "window")]
+            let plugins = plugins.disable::<bevy::winit::WinitPlugin>();
+            app.add_plugins(plugins)
+                .add_plugin(bevy::app::ScheduleRunnerPlugin::default());
+        } else {
+            app.add_plugins(plugins);
+        }
     }
 }
 
-pub struct BevyPluginSettings;
+#[cfg(feature = "window")]
+pub struct BevyPluginSettings {
+    pub focused_mode: UpdateMode,
+    pub unfocused_mode: UpdateMode,
+    pub return_from_run: bool,
+}
+#[cfg(feature = "window")]
 impl Plugin for BevyPluginSettings {
     fn build(&self, app: &mut App) {
-        app.insert_resource(bevy::winit::WinitSettings::game());
+        app.insert_resource(WinitSettings {
+            focused_mode: self.focused_mode,
+            unfocused_mode: self.unfocused_mode,
+            return_from_run: self.return_from_run,
+            ..WinitSettings::game()
+        });
     }
 }
 
+///
+/// [`FlatRenderPlugin`] goes first, it registers the mesh and image assets the other plugins use.
+///
+/// The sprite, shape and tilemap plugins need the `sprite2d` feature, the mesh plugin `mesh3d`.
+///
 pub struct FlatEngineCore;
 impl Plugin for FlatEngineCore {
     fn build(&self, app: &mut App) {
         app.add_plugin(FlatRenderPlugin)
-            .add_plugin(FlatSpritePlugin)
-            .add_plugin(FlatMeshPlugin);
+            .add_plugin(FlatInputPlugin)
+            .add_plugin(FixedUpdatePlugin)
+            .init_resource::<AppExitCondition>()
+            .add_system_to_stage(CoreStage::PostUpdate, exit_on_condition);
+        #[cfg(feature = "sprite2d")]
+        app.add_plugin(FlatSpritePlugin);
+        #[cfg(feature = "mesh3d")]
+        app.add_plugin(FlatMeshPlugin);
+        #[cfg(feature = "sprite2d")]
+        app.add_plugin(FlatShapePlugin);
+        app.add_plugin(FlatTrailPlugin).add_plugin(FlatGridPlugin);
+        #[cfg(feature = "sprite2d")]
+        app.add_plugin(FlatTilemapPlugin);
+        // Last, its passes run after the camera passes of the other plugins
+        app.add_plugin(FlatPostPlugin);
     }
 }
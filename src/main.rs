@@ -5,18 +5,7 @@ use bevy::{
         Transform, Vec3, With, ResMut,
     },
 };
-use flat::{
-    mesh3d::{bundle::MeshBundle, bind::MeshPipelineKey},
-    render::{
-        camera::component::{CameraBundle, PerspectiveProjection},
-        mesh::Mesh,
-        resource::buffer::{Vertex, VertexTex3},
-        texture::texture_arr::ImageArrayHandle,
-    },
-    shapes::skybox,
-    sprite::{bundle::SpriteBundle, BASE_QUAD_HANDLE},
-    FlatEngineComplete,
-};
+use flat::{prelude::*, shapes::skybox};
 
 fn exit_on_esc(key: Res<Input<KeyCode>>, mut app_exit: EventWriter<AppExit>) {
     if key.pressed(KeyCode::Escape) {
@@ -55,7 +44,9 @@ fn spawn_objects(
         mesh: skybox_mesh,
         textures: ImageArrayHandle::with_images(skybox_images),
         render_key: MeshPipelineKey {
-            texture_count: 6
+            texture_count: 6,
+            skinned: false,
+            morphed: false,
         },
         ..Default::default()
     });
@@ -66,17 +57,18 @@ fn spawn_objects(
     });
 }
 
-fn control_player(key: Res<Input<KeyCode>>, mut player: Query<&mut Transform, With<Player>>) {
+fn control_player(keys: Keys, mut player: Query<&mut Transform, With<Player>>) {
     const SPEED: f32 = 0.4;
 
+    // By position, so the same keys move the player on AZERTY
     let dif = SPEED
-        * if key.pressed(KeyCode::W) {
+        * if keys.pressed(PhysicalKey::W) {
             Vec3::NEG_Z
-        } else if key.pressed(KeyCode::A) {
+        } else if keys.pressed(PhysicalKey::A) {
             Vec3::NEG_X
-        } else if key.pressed(KeyCode::S) {
+        } else if keys.pressed(PhysicalKey::S) {
             Vec3::Z
-        } else if key.pressed(KeyCode::D) {
+        } else if keys.pressed(PhysicalKey::D) {
             Vec3::X
         } else {
             Vec3::ZERO
@@ -89,7 +81,7 @@ fn control_player(key: Res<Input<KeyCode>>, mut player: Query<&mut Transform, Wi
 
 fn main() {
     let mut app = App::new();
-    app.add_plugins(FlatEngineComplete)
+    app.add_plugins(FlatEngineComplete::default())
         // .add_plugin(FlatBevyPlugins)
         // .add_plugin(bevy::core_pipeline::CorePipelinePlugin)
         // .add_plugin(bevy::sprite::SpritePlugin)
@@ -1,14 +1,24 @@
 use bevy::{
     ecs::system::SystemState,
-    prelude::{FromWorld, Res, ResMut, Resource, World, Component, Deref, DerefMut}, utils::HashMap, asset::HandleId,
+    prelude::{
+        AssetEvent, Changed, Component, Deref, DerefMut, EventReader, FromWorld, Handle, Mat4, Or,
+        Query, Res, ResMut, Resource, World,
+    },
+    utils::HashMap,
+    asset::HandleId,
 };
 use encase::ShaderType;
 
 use crate::{
     render::{
+        blend::{AlphaMode, BlendMode},
         camera::component::CameraUniforms,
+        debug_view::DebugViewMode,
+        globals::{globals_layout_entry, GlobalsUniform},
+        mesh::morph::MorphTargetData,
+        raster::{CullMode, DepthBias, RasterKey},
         resource::{
-            buffer::{MeshVertex, VertexTex3},
+            buffer::{MeshVertex, VertexSkinned, VertexTex3},
             component_uniform::{ComponentUniforms, ModelUniform},
             pipeline::{
                 BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
@@ -17,19 +27,28 @@ use crate::{
             renderer::{RenderDevice, RenderQueue},
             shader::Shader,
             specialized_pipeline::{PipelineSpecialize, Specialized},
+            storage::storage_buffer_layout_entry,
         },
         texture::{GpuTexture, ImageDim, PixelFormat, texture_arr::ImageArray, self}, RenderAssets,
     },
     util::EngineDefault,
 };
 
-use super::MESH_SHADER_HANDLE;
+use super::{
+    uniform::MeshUniform, MESH_DEFAULT_BLEND_MODE, MESH_MORPHED_SHADER_HANDLE, MESH_SHADER_HANDLE,
+    MESH_SKINNED_SHADER_HANDLE,
+};
 
 #[derive(Resource)]
 pub struct MeshPipeline {
     pub model_layout: BindGroupLayout,
     pub view_layout: BindGroupLayout,
     // pub texture_arr_layout: BindGroupLayout,
+    pub mesh_layout: BindGroupLayout,
+    /// [`MeshUniform`] together with the joint matrices of skinned meshes
+    pub skinned_mesh_layout: BindGroupLayout,
+    /// [`MeshUniform`] together with the weights and deltas of morphed meshes
+    pub morphed_mesh_layout: BindGroupLayout,
     pub dummy_texture_arr: GpuTexture,
     pub dummy_texture_arr_bind_group: wgpu::BindGroup,
 }
@@ -62,6 +81,24 @@ impl FromWorld for MeshPipeline {
             });
 
         let view_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: Some(CameraUniforms::min_size()),
+                        },
+                        count: None,
+                    },
+                    globals_layout_entry(1),
+                ],
+                label: Some("mesh_view_layout"),
+            });
+
+        let mesh_layout =
             render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
@@ -69,11 +106,63 @@ impl FromWorld for MeshPipeline {
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: true,
-                        min_binding_size: Some(CameraUniforms::min_size()),
+                        min_binding_size: Some(MeshUniform::min_size()),
                     },
                     count: None,
                 }],
-                label: Some("mesh_view_layout"),
+                label: Some("mesh_uniform_layout"),
+            });
+
+        let skinned_mesh_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: Some(MeshUniform::min_size()),
+                        },
+                        count: None,
+                    },
+                    storage_buffer_layout_entry::<Vec<Mat4>>(
+                        1,
+                        wgpu::ShaderStages::VERTEX,
+                        true,
+                        false,
+                    ),
+                ],
+                label: Some("skinned_mesh_uniform_layout"),
+            });
+
+        let morphed_mesh_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: Some(MeshUniform::min_size()),
+                        },
+                        count: None,
+                    },
+                    storage_buffer_layout_entry::<Vec<f32>>(
+                        1,
+                        wgpu::ShaderStages::VERTEX,
+                        true,
+                        false,
+                    ),
+                    storage_buffer_layout_entry::<MorphTargetData>(
+                        2,
+                        wgpu::ShaderStages::VERTEX,
+                        true,
+                        false,
+                    ),
+                ],
+                label: Some("morphed_mesh_uniform_layout"),
             });
 
         let dummy_texture_arr_layout =
@@ -132,31 +221,116 @@ impl FromWorld for MeshPipeline {
             model_layout,
             view_layout,
             // arr_texture_layout,
+            mesh_layout,
+            skinned_mesh_layout,
+            morphed_mesh_layout,
             dummy_texture_arr,
             dummy_texture_arr_bind_group,
         };
 
         const MESH_PIPELINE_KEYS: &'static [MeshPipelineKey] =
-            &[MeshPipelineKey { texture_count: 6 }];
+            &[MeshPipelineKey {
+                texture_count: 6,
+                skinned: false,
+                morphed: false,
+            }];
 
-        for key in MESH_PIPELINE_KEYS {
-            let id = pipeline_cache.queue(mesh_pipeline.specialize(&render_device, *key));
-            specialized_self.pipelines.insert(*key, id);
+        for mesh_key in MESH_PIPELINE_KEYS {
+            for blend_mode in BlendMode::ALL {
+                let key = (
+                    *mesh_key,
+                    *blend_mode,
+                    RasterKey::default(),
+                    DebugViewMode::None,
+                );
+                let id = pipeline_cache.queue(mesh_pipeline.specialize(&render_device, key));
+                specialized_self.pipelines.insert(key, id);
+            }
         }
 
         mesh_pipeline
     }
 }
 
+/// Queues the pipelines of meshes with a [`CullMode`], [`DepthBias`]
+/// or masked [`AlphaMode`], the default ones are queued up front.
+///
+/// A new [`DebugViewMode`] queues every pipeline queued so far again in that mode.
+pub fn specialize_mesh_pipelines(
+    render_device: Res<RenderDevice>,
+    debug_view: Res<DebugViewMode>,
+    mesh_pipeline: Res<MeshPipeline>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    mut specialized_mesh_pipeline: ResMut<Specialized<MeshPipeline>>,
+    query: Query<
+        (
+            &MeshPipelineKey,
+            Option<&BlendMode>,
+            Option<&CullMode>,
+            Option<&DepthBias>,
+            Option<&AlphaMode>,
+        ),
+        Or<(
+            Changed<MeshPipelineKey>,
+            Changed<BlendMode>,
+            Changed<CullMode>,
+            Changed<DepthBias>,
+            Changed<AlphaMode>,
+        )>,
+    >,
+) {
+    if debug_view.is_changed() {
+        let keys: Vec<_> = specialized_mesh_pipeline
+            .pipelines
+            .keys()
+            .map(|(mesh_key, blend_mode, raster_key, _)| {
+                (*mesh_key, *blend_mode, *raster_key, *debug_view)
+            })
+            .collect();
+        for key in keys {
+            specialized_mesh_pipeline.specialize(
+                &mut pipeline_cache,
+                &mesh_pipeline,
+                &render_device,
+                key,
+            );
+        }
+    }
+
+    for (mesh_key, blend_mode, cull_mode, depth_bias, alpha_mode) in query.iter() {
+        let key = (
+            *mesh_key,
+            blend_mode.copied().unwrap_or(MESH_DEFAULT_BLEND_MODE),
+            RasterKey::new(cull_mode, depth_bias, alpha_mode),
+            *debug_view,
+        );
+        specialized_mesh_pipeline.specialize(
+            &mut pipeline_cache,
+            &mesh_pipeline,
+            &render_device,
+            key,
+        );
+    }
+}
+
 #[derive(Component, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct MeshPipelineKey {
     pub texture_count: u32,
+    /// [`VertexSkinned`] meshes posed by a [`SkinnedMesh`](super::skin::SkinnedMesh)
+    pub skinned: bool,
+    /// [`VertexTex3`] meshes with morph targets weighted by [`MorphWeights`](super::morph::MorphWeights),
+    /// not combined with `skinned`
+    pub morphed: bool,
 }
 
 impl PipelineSpecialize for MeshPipeline {
-    type Key = MeshPipelineKey;
+    type Key = (MeshPipelineKey, BlendMode, RasterKey, DebugViewMode);
 
-    fn specialize(&self, render_device: &RenderDevice, key: Self::Key) -> RenderPipelineDescriptor {
+    fn specialize(
+        &self,
+        render_device: &RenderDevice,
+        (key, blend_mode, raster_key, debug_view): Self::Key,
+    ) -> RenderPipelineDescriptor {
         let texture_arr_layout =
             render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("mesh_texture_arr_layout"),
@@ -180,54 +354,85 @@ impl PipelineSpecialize for MeshPipeline {
                 ],
             });
 
-        RenderPipelineDescriptor {
+        let (shader, vertex_layout, mesh_layout) = match (key.skinned, key.morphed) {
+            (true, _) => (
+                MESH_SKINNED_SHADER_HANDLE,
+                VertexSkinned::layout(),
+                &self.skinned_mesh_layout,
+            ),
+            (false, true) => (
+                MESH_MORPHED_SHADER_HANDLE,
+                VertexTex3::layout(),
+                &self.morphed_mesh_layout,
+            ),
+            (false, false) => (MESH_SHADER_HANDLE, VertexTex3::layout(), &self.mesh_layout),
+        };
+
+        mesh_pipeline_descriptor(
+            vec![
+                self.model_layout.clone(),
+                self.view_layout.clone(),
+                texture_arr_layout,
+                mesh_layout.clone(),
+            ],
+            shader.typed(),
+            vertex_layout,
+            (blend_mode, raster_key, debug_view),
+        )
+    }
+}
+
+/// Descriptor shared by the mesh pipelines, they differ in bind group layouts, shader and vertex layout.
+pub fn mesh_pipeline_descriptor(
+    bind_group_layouts: Vec<BindGroupLayout>,
+    shader: Handle<Shader>,
+    vertex_layout: wgpu::VertexBufferLayout<'static>,
+    (blend_mode, raster_key, debug_view): (BlendMode, RasterKey, DebugViewMode),
+) -> RenderPipelineDescriptor {
+    // Overdraw accumulates every fragment, hidden or not
+    let (blend_mode, depth_compare) = match debug_view {
+        DebugViewMode::Overdraw => (BlendMode::Additive, wgpu::CompareFunction::Always),
+        _ => (blend_mode, wgpu::CompareFunction::Less),
+    };
+    let shader_defs = debug_view.shader_defs();
+
+    RenderPipelineDescriptor {
+        label: None,
+        layout: PipelineLayoutDescriptor {
             label: None,
-            layout: PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts: vec![
-                    self.model_layout.clone(),
-                    self.view_layout.clone(),
-                    texture_arr_layout.clone(),
-                ],
-                push_constant_ranges: Vec::new(),
-            },
-            vertex: VertexState {
-                shader: MESH_SHADER_HANDLE.typed(),
-                entry_point: Shader::VS_ENTRY_DEFAULT,
-                buffers: vec![VertexTex3::layout()],
-            },
-            fragment: Some(FragmentState {
-                shader: MESH_SHADER_HANDLE.typed(),
-                entry_point: Shader::FS_ENTRY_DEFAULT,
-                targets: vec![Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::engine_default(),
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::DepthTexture::DEPTH_FORMAT, // wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less, // 1.
-                stencil: wgpu::StencilState::default(),     // 2.
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        }
+            bind_group_layouts,
+            push_constant_ranges: Vec::new(),
+        },
+        vertex: VertexState {
+            shader: shader.clone(),
+            shader_defs: shader_defs.clone(),
+            entry_point: Shader::VS_ENTRY_DEFAULT,
+            buffers: vec![vertex_layout],
+        },
+        fragment: Some(FragmentState {
+            shader,
+            shader_defs: [shader_defs, blend_mode.shader_defs()].concat(),
+            entry_point: Shader::FS_ENTRY_DEFAULT,
+            targets: vec![Some(wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::engine_default(),
+                blend: Some(blend_mode.blend_state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: raster_key.primitive_state(wgpu::PrimitiveTopology::TriangleList),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DepthTexture::DEPTH_FORMAT, // wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: raster_key.depth_write_enabled(blend_mode),
+            depth_compare, // 1.
+            stencil: wgpu::StencilState::default(),     // 2.
+            bias: raster_key.depth_bias.state(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
     }
 }
 
@@ -235,6 +440,7 @@ impl PipelineSpecialize for MeshPipeline {
 pub struct MeshBindGroups {
     pub model_bind_group: Option<wgpu::BindGroup>,
     pub view_bind_group: Option<wgpu::BindGroup>,
+    pub mesh_bind_group: Option<wgpu::BindGroup>,
 }
 
 pub fn create_mesh3d_bind_groups(
@@ -243,6 +449,8 @@ pub fn create_mesh3d_bind_groups(
     mesh3d_pipeline: Res<MeshPipeline>,
     model_uniforms: Res<ComponentUniforms<ModelUniform>>,
     view_uniforms: Res<ComponentUniforms<CameraUniforms>>,
+    globals_uniforms: Res<ComponentUniforms<GlobalsUniform>>,
+    mesh_uniforms: Res<ComponentUniforms<MeshUniform>>,
 ) {
     let Some(model_binding) = model_uniforms.binding() else {
         return;
@@ -256,20 +464,41 @@ pub fn create_mesh3d_bind_groups(
         }],
     });
 
-    let Some(view_binding) = view_uniforms.binding() else {
+    let (Some(view_binding), Some(globals_binding)) =
+        (view_uniforms.binding(), globals_uniforms.binding())
+    else {
         return;
     };
     let view_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: None,
         layout: &mesh3d_pipeline.view_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: view_binding,
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: globals_binding,
+            },
+        ],
+    });
+
+    let Some(mesh_binding) = mesh_uniforms.binding() else {
+        return;
+    };
+    let mesh_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &mesh3d_pipeline.mesh_layout,
         entries: &[wgpu::BindGroupEntry {
             binding: 0,
-            resource: view_binding,
+            resource: mesh_binding,
         }],
     });
 
     mesh3d_bind_groups.model_bind_group = Some(model_bind_group);
     mesh3d_bind_groups.view_bind_group = Some(view_bind_group);
+    mesh3d_bind_groups.mesh_bind_group = Some(mesh_bind_group);
 }
 
 #[derive(Resource, Default, Deref, DerefMut)]
@@ -280,7 +509,18 @@ pub fn create_texture_arr_bind_groups(
     // mesh_pipeline: Res<MeshPipeline>,
     mut texture_arr_bind_groups: ResMut<TextureArrayBindGroups>,
     render_images: Res<RenderAssets<ImageArray>>,
+    mut image_arr_events: EventReader<AssetEvent<ImageArray>>,
 ) {
+    // Modified arrays are prepared again, the bind group would keep the old texture
+    for event in image_arr_events.iter() {
+        match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => {
+                texture_arr_bind_groups.remove(&handle.id());
+            }
+            AssetEvent::Created { .. } => {}
+        }
+    }
+
     let texture_arr_layout =
             render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("mesh_texture_arr_layout"),
@@ -1,11 +1,19 @@
 use bevy::prelude::{Bundle, GlobalTransform, Handle, Transform};
 
 use crate::render::{
-    camera::component::Visibility, color::Color, mesh::Mesh, resource::buffer::MeshVertex,
-    system::RenderFunctionId, texture::texture_arr::ImageArrayHandle,
+    camera::component::Visibility,
+    color::Color,
+    mesh::Mesh,
+    resource::buffer::{MeshVertex, Vertex},
+    system::RenderFunctionId,
+    texture::{texture_arr::ImageArrayHandle, Image},
 };
 
-use super::{bind::MeshPipelineKey, MESH_RENDER_FUNCTION};
+use super::{
+    bind::MeshPipelineKey,
+    textured::{TexturedMesh, TEXTURED_MESH_RENDER_FUNCTION},
+    MESH_RENDER_FUNCTION,
+};
 
 #[derive(Bundle)]
 pub struct MeshBundle<V: MeshVertex> {
@@ -26,10 +34,42 @@ impl<V: MeshVertex> Default for MeshBundle<V> {
             transform: Transform::default(),
             mesh: Handle::default(),
             textures: ImageArrayHandle::default(),
-            color: Color::WHITE,
-            visibility: Visibility { visible: true },
-            render_key: MeshPipelineKey { texture_count: 1 },
+            color: Color::NO_TINT,
+            visibility: Visibility::Inherited,
+            render_key: MeshPipelineKey {
+                texture_count: 1,
+                skinned: false,
+                morphed: false,
+            },
             render_function: MESH_RENDER_FUNCTION.into(),
         }
     }
 }
+
+/// [`Vertex`] mesh with a single texture, drawn with [`DrawTexturedMesh`](super::textured::DrawTexturedMesh).
+#[derive(Bundle)]
+pub struct TexturedMeshBundle {
+    pub global_transform: GlobalTransform,
+    pub transform: Transform,
+    pub mesh: Handle<Mesh<Vertex>>,
+    pub texture: Handle<Image>,
+    pub color: Color,
+    pub visibility: Visibility,
+    pub textured_mesh: TexturedMesh,
+    pub render_function: RenderFunctionId,
+}
+
+impl Default for TexturedMeshBundle {
+    fn default() -> Self {
+        Self {
+            global_transform: GlobalTransform::default(),
+            transform: Transform::default(),
+            mesh: Handle::default(),
+            texture: Handle::default(),
+            color: Color::NO_TINT,
+            visibility: Visibility::Inherited,
+            textured_mesh: TexturedMesh,
+            render_function: TEXTURED_MESH_RENDER_FUNCTION.into(),
+        }
+    }
+}
@@ -1,6 +1,15 @@
 
 // -- Vertex -----
 
+struct Fog {
+    color: vec4<f32>,
+    // 0 is no fog, then linear, exponential and exponential squared
+    mode: u32,
+    start: f32,
+    end: f32,
+    density: f32,
+}
+
 struct Camera {
     view_proj: mat4x4<f32>,
     // inverse_view_proj: mat4x4<f32>,
@@ -11,6 +20,14 @@ struct Camera {
     // world_position: vec3<f32>,
     // viewport(x_origin, y_origin, width, height)
     // viewport: vec4<f32>,
+    fog: Fog,
+}
+
+struct Globals {
+    time: f32,
+    delta_time: f32,
+    frame_count: u32,
+    resolution: vec2<f32>,
 }
 
 struct Model {
@@ -27,6 +44,7 @@ struct VertexOutput {
     @builtin(position)  clip_position: vec4<f32>,
     @location(0)        uv: vec3<f32>,
     @location(2)        color: vec4<f32>,
+    @location(3)        world_position: vec3<f32>,
 }
 
 @group(0) @binding(0)
@@ -35,13 +53,18 @@ var<uniform> model: Model;
 @group(1) @binding(0)
 var<uniform> camera: Camera;
 
+@group(1) @binding(1)
+var<uniform> globals: Globals;
+
 @vertex
 fn vs_main(
     vertex: VertexInput,
 ) -> VertexOutput {
     var out: VertexOutput;
 
-    out.clip_position = camera.view_proj * model.model * vec4<f32>(vertex.position, 1.0);
+    let world_position = model.model * vec4<f32>(vertex.position, 1.0);
+    out.clip_position = camera.view_proj * world_position;
+    out.world_position = world_position.xyz;
     out.uv = vertex.uv;
     out.color = vertex.color;
 
@@ -50,15 +73,77 @@ fn vs_main(
 
 // -- Fragment -----
 
+fn fog_intensity(distance: f32) -> f32 {
+    let fog = camera.fog;
+    if (fog.mode == 1u) {
+        return clamp((distance - fog.start) / (fog.end - fog.start), 0.0, 1.0);
+    }
+    if (fog.mode == 2u) {
+        return 1.0 - exp(-fog.density * distance);
+    }
+    if (fog.mode == 3u) {
+        let d = fog.density * distance;
+        return 1.0 - exp(-d * d);
+    }
+    return 0.0;
+}
+
+// camera.view is the world transform of the camera
+fn apply_fog(color: vec4<f32>, world_position: vec3<f32>) -> vec4<f32> {
+    let distance = length(world_position - camera.view[3].xyz);
+    let intensity = fog_intensity(distance);
+    return vec4<f32>(mix(color.rgb, camera.fog.color.rgb, intensity * camera.fog.color.a), color.a);
+}
+
 @group(2) @binding(0)
 var t_diffuse: texture_2d_array<f32>;
 @group(2) @binding(1)
 var s_diffuse: sampler;
 
+struct Mesh {
+    // fragments below this alpha are discarded, zero means no masking
+    alpha_cutoff: f32,
+    joint_offset: u32,
+    morph_weight_offset: u32,
+    // added to the layer in uv.z
+    texture_index: u32,
+}
+
+@group(3) @binding(0)
+var<uniform> mesh: Mesh;
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    var tex_color = textureSample(t_diffuse, s_diffuse, in.uv.xy, i32(in.uv.z));
+    let layer = i32(in.uv.z) + i32(mesh.texture_index);
+    var tex_color = textureSample(t_diffuse, s_diffuse, in.uv.xy, layer);
     tex_color += in.color;
 
-    return tex_color;
+    if (mesh.alpha_cutoff > 0.0) {
+        if (tex_color.a < mesh.alpha_cutoff) {
+            discard;
+        }
+        tex_color.a = 1.0;
+    }
+
+#ifdef DEBUG_NORMALS
+    // Meshes have no normals, the face normal comes from the screen space derivatives
+    let normal = normalize(cross(dpdy(in.world_position), dpdx(in.world_position)));
+    return vec4<f32>(normal * 0.5 + 0.5, 1.0);
+#endif
+#ifdef DEBUG_UVS
+    return vec4<f32>(fract(in.uv.xy), 0.0, 1.0);
+#endif
+#ifdef DEBUG_DEPTH
+    return vec4<f32>(vec3<f32>(1.0 - in.clip_position.z), 1.0);
+#endif
+#ifdef DEBUG_OVERDRAW
+    return vec4<f32>(1.0, 0.6, 0.2, 0.1);
+#endif
+
+    var color = apply_fog(tex_color, in.world_position);
+#ifdef BLEND_MULTIPLY
+    // The Multiply blend state expects premultiplied alpha
+    color = vec4<f32>(color.rgb * color.a, color.a);
+#endif
+    return color;
 }
\ No newline at end of file
@@ -1,33 +1,84 @@
 use bevy::{
     asset::load_internal_asset,
-    prelude::{Entity, Handle, HandleUntyped, Plugin, World},
+    prelude::{
+        AddAsset, CoreStage, Entity, Handle, HandleUntyped, IntoSystemDescriptor, Plugin, World,
+    },
     reflect::TypeUuid,
+    transform::TransformSystem,
 };
 
 use crate::{
     mesh3d::bind::{
-        create_mesh3d_bind_groups, create_texture_arr_bind_groups, MeshBindGroups, MeshPipeline,
+        create_mesh3d_bind_groups, create_texture_arr_bind_groups, specialize_mesh_pipelines,
+        MeshBindGroups, MeshPipeline,
     },
     render::{
-        camera::component::CameraUniforms,
-        mesh::{GpuMeshAssembly, Mesh},
+        blend::BlendMode,
+        camera::component::{Camera, CameraUniforms},
+        cleanup::AddEntityCleanup,
+        command::AddRenderCommand,
+        debug_view::DebugViewMode,
+        globals::GlobalsUniform,
+        mesh::{GpuMesh, GpuMeshAssembly, Mesh},
+        pass::{AddCameraPass, CameraPassStage},
+        phase::QueueRenderPhases,
+        raster::RasterKey,
         resource::{
-            buffer::VertexTex3, component_uniform::ModelUniform, pipeline::PipelineCache,
-            shader::Shader, specialized_pipeline::Specialized, uniform::DynamicUniformId,
+            buffer::{MeshVertex, VertexSkinned, VertexTex3},
+            component_uniform::{
+                queue_component_uniforms, AddComponentUniform, ComponentUniforms, ModelUniform,
+            },
+            pipeline::PipelineCache,
+            shader::Shader,
+            specialized_pipeline::{AddPipelineWarmup, Specialized},
+            uniform::DynamicUniformId,
         },
+        stats::{RenderCategory, RenderStats},
         system::{AddRenderFunction, RenderResult},
         texture::texture_arr::ImageArrayHandle,
         RenderAssets, RenderStage,
     },
 };
 
-use self::bind::{MeshPipelineKey, TextureArrayBindGroups};
+use self::{
+    bind::{MeshPipelineKey, TextureArrayBindGroups},
+    model::{spawn_models, Model},
+    morph::{create_morph_bind_groups, prepare_morph_weights, MorphTargetWeights},
+    outline::{
+        create_outline_bind_groups, encode_outlines, prepare_outline_masks, OutlineBindGroups,
+        OutlineMasks, OutlinePipeline, Outlined,
+    },
+    render_bundle::{record_static_mesh_bundles, StaticMeshBundles},
+    skin::{create_skin_bind_group, prepare_skins, SkinJoints},
+    textured::{
+        create_mesh_texture_bind_groups, specialize_textured_mesh_pipelines, DrawTexturedMesh,
+        MeshTextureBindGroups, TexturedMeshPipeline, TEXTURED_MESH_RENDER_FUNCTION,
+    },
+    uniform::{prepare_mesh_uniforms, queue_mesh_uniforms, MeshUniform},
+};
 
 pub mod bind;
 pub mod bundle;
+pub mod model;
+pub mod morph;
+pub mod outline;
+pub mod render_bundle;
+pub mod skin;
+pub mod textured;
+pub mod uniform;
 
 const MESH_SHADER_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 15678909876445673);
+const MESH_SKINNED_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 15678909876445675);
+const MESH_MORPHED_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 15678909876445676);
+const MESH_TEXTURED_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 15678909876445677);
+const OUTLINE_MASK_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 15678909876445678);
+const OUTLINE_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 15678909876445679);
 
 // pub const BASE_CUBE_HANDLE: HandleUntyped =
 //     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 15678909876445674);
@@ -41,6 +92,31 @@ impl Plugin for FlatMeshPlugin {
             "mesh_texarr.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(
+            app,
+            MESH_SKINNED_SHADER_HANDLE,
+            "mesh_skinned.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            MESH_MORPHED_SHADER_HANDLE,
+            "mesh_morphed.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            MESH_TEXTURED_SHADER_HANDLE,
+            "mesh_textured.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            OUTLINE_MASK_SHADER_HANDLE,
+            "outline_mask.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(app, OUTLINE_SHADER_HANDLE, "outline.wgsl", Shader::from_wgsl);
 
         // {
         //     let mut meshes = app
@@ -50,17 +126,73 @@ impl Plugin for FlatMeshPlugin {
         //     meshes.set_untracked(BASE_CUBE_HANDLE, create_unit_cube(FaceDirection::Out));
         // }
 
-        app.init_resource::<Specialized<MeshPipeline>>()
+        app.add_asset::<Model>()
+            .init_resource::<Specialized<MeshPipeline>>()
             .init_resource::<MeshPipeline>()
             .init_resource::<MeshBindGroups>()
             .init_resource::<TextureArrayBindGroups>()
+            .init_resource::<Specialized<TexturedMeshPipeline>>()
+            .init_resource::<TexturedMeshPipeline>()
+            .add_pipeline_warmup::<MeshPipeline>()
+            .add_pipeline_warmup::<TexturedMeshPipeline>()
+            .init_resource::<MeshTextureBindGroups>()
+            .init_resource::<OutlinePipeline>()
+            .init_resource::<OutlineBindGroups>()
+            .init_resource::<OutlineMasks>()
+            .init_resource::<ComponentUniforms<MeshUniform>>()
+            .init_resource::<StaticMeshBundles>()
+            .init_resource::<SkinJoints>()
+            .init_resource::<MorphTargetWeights>()
+            .add_entity_cleanup::<StaticMeshBundles, Camera>()
+            .add_entity_cleanup::<OutlineMasks, Camera>()
+            .add_component_uniform::<Outlined>()
+            .add_camera_pass(CameraPassStage::AfterMain, encode_outlines)
             .add_render_function(MESH_RENDER_FUNCTION, render_mesh)
+            .add_render_command_with_id::<DrawTexturedMesh>(TEXTURED_MESH_RENDER_FUNCTION)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                spawn_models.before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(RenderStage::Prepare, specialize_mesh_pipelines)
+            .add_system_to_stage(RenderStage::Prepare, specialize_textured_mesh_pipelines)
+            .add_system_to_stage(RenderStage::Prepare, prepare_skins)
+            .add_system_to_stage(RenderStage::Prepare, prepare_morph_weights)
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_mesh_uniforms
+                    .after(prepare_skins)
+                    .after(prepare_morph_weights),
+            )
+            .add_system_to_stage(RenderStage::Create, queue_mesh_uniforms)
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_skin_bind_group.after(queue_mesh_uniforms),
+            )
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_morph_bind_groups.after(queue_mesh_uniforms),
+            )
             .add_system_to_stage(RenderStage::Create, create_mesh3d_bind_groups)
-            .add_system_to_stage(RenderStage::Create, create_texture_arr_bind_groups);
+            .add_system_to_stage(RenderStage::Create, create_texture_arr_bind_groups)
+            .add_system_to_stage(RenderStage::Create, create_mesh_texture_bind_groups)
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_outline_bind_groups.after(queue_component_uniforms::<Outlined>),
+            )
+            .add_system_to_stage(RenderStage::Create, prepare_outline_masks)
+            .add_system_to_stage(
+                RenderStage::Create,
+                record_static_mesh_bundles
+                    .after(create_mesh3d_bind_groups)
+                    .after(create_texture_arr_bind_groups)
+                    .before(QueueRenderPhases),
+            );
     }
 }
 
 const MESH_RENDER_FUNCTION: usize = 2;
+/// Used for meshes without a [`BlendMode`].
+pub const MESH_DEFAULT_BLEND_MODE: BlendMode = BlendMode::Opaque;
 fn render_mesh<'w>(
     camera: Entity,
     object: Entity,
@@ -68,54 +200,80 @@ fn render_mesh<'w>(
     render_pass: &mut wgpu::RenderPass<'w>,
 ) -> RenderResult {
     // -- Set Pipeline --
-    let mesh_pipeline = world.get_resource::<MeshPipeline>().unwrap();
-    let specialized_mesh_pipeline = world.get_resource::<Specialized<MeshPipeline>>().unwrap();
-    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
+    let Some(mesh_pipeline) = world.get_resource::<MeshPipeline>() else {
+        return RenderResult::Failure("MeshPipeline resource missing");
+    };
+    let Some(specialized_mesh_pipeline) = world.get_resource::<Specialized<MeshPipeline>>() else {
+        return RenderResult::Failure("Specialized<MeshPipeline> resource missing");
+    };
+    let Some(pipeline_cache) = world.get_resource::<PipelineCache>() else {
+        return RenderResult::Failure("PipelineCache resource missing");
+    };
 
     let Some(pipeline_key) = world.get::<MeshPipelineKey>(object) else {
-        return RenderResult::Failure;
+        return RenderResult::Failure("no MeshPipelineKey");
+    };
+    let blend_mode = world
+        .get::<BlendMode>(object)
+        .copied()
+        .unwrap_or(MESH_DEFAULT_BLEND_MODE);
+    let raster_key = RasterKey::of(world, object);
+    let Some(debug_view) = world.get_resource::<DebugViewMode>() else {
+        return RenderResult::Failure("DebugViewMode resource missing");
     };
-    let Some(pipeline_id) = specialized_mesh_pipeline.pipelines.get(pipeline_key) else {
-        return RenderResult::Failure;
+    let specialized_key = (*pipeline_key, blend_mode, raster_key, *debug_view);
+    let Some(pipeline_id) = specialized_mesh_pipeline.pipelines.get(&specialized_key) else {
+        return RenderResult::Failure("pipeline not specialized");
     };
     let Some(render_pipeline) = pipeline_cache.get(pipeline_id) else {
-        return RenderResult::Failure;
+        return RenderResult::Failure("pipeline not compiled yet");
     };
     render_pass.set_pipeline(render_pipeline);
+    let Some(render_stats) = world.get_resource::<RenderStats>() else {
+        return RenderResult::Failure("RenderStats resource missing");
+    };
+    render_stats.pipeline_switch();
     // -- -- -- -------- -- -- --
 
     // -- Get Mesh --
-    let Some(mesh_handle) = world.get::<Handle<Mesh<VertexTex3>>>(object) else {
-        return RenderResult::Failure;
+    let mesh = match pipeline_key.skinned {
+        true => get_gpu_mesh::<VertexSkinned>(object, world),
+        false => get_gpu_mesh::<VertexTex3>(object, world),
     };
-    let gpu_meshes = world
-        .get_resource::<RenderAssets<Mesh<VertexTex3>>>()
-        .unwrap();
-    let Some(mesh) = gpu_meshes.get(&mesh_handle.id()) else {
-        return RenderResult::Failure;
+    let mesh = match mesh {
+        Ok(mesh) => mesh,
+        Err(failure) => return failure,
     };
     // -- -- -- -------- -- -- --
 
-    // -- Bind Model, View, Texture BindGroups --
-    let mesh3d_bind_groups = world.get_resource::<MeshBindGroups>().unwrap();
-
-    let model_uniform_id = world.get::<DynamicUniformId<ModelUniform>>(object).unwrap();
-    render_pass.set_bind_group(
-        0,
-        mesh3d_bind_groups.model_bind_group.as_ref().unwrap(),
-        &[**model_uniform_id],
-    );
-
-    let view_uniform_id = world
-        .get::<DynamicUniformId<CameraUniforms>>(camera)
-        .unwrap();
-    render_pass.set_bind_group(
-        1,
-        mesh3d_bind_groups.view_bind_group.as_ref().unwrap(),
-        &[**view_uniform_id],
-    );
-
-    let texture_array_bind_groups = world.get_resource::<TextureArrayBindGroups>().unwrap();
+    // -- Bind Model, View, Texture, Mesh BindGroups --
+    let Some(mesh3d_bind_groups) = world.get_resource::<MeshBindGroups>() else {
+        return RenderResult::Failure("MeshBindGroups resource missing");
+    };
+    let (Some(model_bind_group), Some(view_bind_group), Some(mesh_bind_group)) = (
+        mesh3d_bind_groups.model_bind_group.as_ref(),
+        mesh3d_bind_groups.view_bind_group.as_ref(),
+        mesh3d_bind_groups.mesh_bind_group.as_ref(),
+    ) else {
+        return RenderResult::Failure("bind groups not created");
+    };
+
+    let Some(model_uniform_id) = world.get::<DynamicUniformId<ModelUniform>>(object) else {
+        return RenderResult::Failure("no ModelUniform id");
+    };
+    render_pass.set_bind_group(0, model_bind_group, &[**model_uniform_id]);
+
+    let Some(view_uniform_id) = world.get::<DynamicUniformId<CameraUniforms>>(camera) else {
+        return RenderResult::Failure("no CameraUniforms id");
+    };
+    let Some(globals_uniform_id) = world.get::<DynamicUniformId<GlobalsUniform>>(camera) else {
+        return RenderResult::Failure("no GlobalsUniform id");
+    };
+    render_pass.set_bind_group(1, view_bind_group, &[**view_uniform_id, **globals_uniform_id]);
+
+    let Some(texture_array_bind_groups) = world.get_resource::<TextureArrayBindGroups>() else {
+        return RenderResult::Failure("TextureArrayBindGroups resource missing");
+    };
     let texture_bind_group = match world.get::<ImageArrayHandle>(object) {
         Some(image_array_handle) => match &image_array_handle.image_arr {
             Some(handle) => match texture_array_bind_groups.get(&handle.id()) {
@@ -127,6 +285,37 @@ fn render_mesh<'w>(
         None => &mesh_pipeline.dummy_texture_arr_bind_group,
     };
     render_pass.set_bind_group(2, texture_bind_group, &[]);
+
+    let Some(mesh_uniform_id) = world.get::<DynamicUniformId<MeshUniform>>(object) else {
+        return RenderResult::Failure("no MeshUniform id");
+    };
+    let mesh_bind_group = match (pipeline_key.skinned, pipeline_key.morphed) {
+        (true, _) => {
+            let Some(skin_joints) = world.get_resource::<SkinJoints>() else {
+                return RenderResult::Failure("SkinJoints resource missing");
+            };
+            let Some(skinned_mesh_bind_group) = skin_joints.bind_group.as_ref() else {
+                return RenderResult::Failure("skin bind group not created");
+            };
+            skinned_mesh_bind_group
+        }
+        (false, true) => {
+            let Some(morph_weights) = world.get_resource::<MorphTargetWeights>() else {
+                return RenderResult::Failure("MorphTargetWeights resource missing");
+            };
+            let Some(mesh_handle) = world.get::<Handle<Mesh<VertexTex3>>>(object) else {
+                return RenderResult::Failure("no mesh handle");
+            };
+            let Some(morphed_mesh_bind_group) = morph_weights.bind_groups.get(&mesh_handle.id())
+            else {
+                return RenderResult::Failure("morph bind group not created");
+            };
+            morphed_mesh_bind_group
+        }
+        (false, false) => mesh_bind_group,
+    };
+    render_pass.set_bind_group(3, mesh_bind_group, &[**mesh_uniform_id]);
+    render_stats.bind_group_switches(4);
     // -- -- -- -------- -- -- --
 
     // -- Set Mesh Buffers --
@@ -141,12 +330,28 @@ fn render_mesh<'w>(
         } => {
             render_pass.set_index_buffer(index_buffer.slice(..), *index_format);
             render_pass.draw_indexed(0..*index_count as u32, 0, 0..instance_count);
+            render_stats.draw(*index_count as u32, instance_count);
         }
         GpuMeshAssembly::NonIndexed { vertex_count } => {
             render_pass.draw(0..*vertex_count as u32, 0..instance_count);
+            render_stats.draw(*vertex_count as u32, instance_count);
         }
     }
+    render_stats.category_draw(RenderCategory::Mesh, 1);
     // -- -- -- -------- -- -- --
 
     RenderResult::Success
 }
+
+fn get_gpu_mesh<'w, V: MeshVertex>(
+    object: Entity,
+    world: &'w World,
+) -> Result<&'w GpuMesh, RenderResult> {
+    let Some(mesh_handle) = world.get::<Handle<Mesh<V>>>(object) else {
+        return Err(RenderResult::Failure("no mesh handle"));
+    };
+    let gpu_meshes = world.get_resource::<RenderAssets<Mesh<V>>>().unwrap();
+    gpu_meshes
+        .get(&mesh_handle.id())
+        .ok_or(RenderResult::Failure("mesh not prepared yet"))
+}
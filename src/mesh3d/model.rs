@@ -0,0 +1,124 @@
+use bevy::{
+    prelude::{
+        Assets, BuildChildren, Bundle, Commands, Component, Entity, GlobalTransform, Handle,
+        Query, Res, Transform, Without,
+    },
+    reflect::TypeUuid,
+};
+
+use crate::render::{
+    camera::component::Visibility, color::Color, mesh::Mesh, resource::buffer::Vertex,
+    texture::Image,
+};
+
+use super::bundle::TexturedMeshBundle;
+
+///
+/// Model made of several meshes, each with its own texture and placement.
+///
+/// Spawned through a [`ModelBundle`], every part becomes a child entity
+/// drawn like a [`TexturedMeshBundle`].
+///
+#[derive(TypeUuid, Default)]
+#[uuid = "3E6A1F4B-9D27-4C85-B0E3-7A5C2D18F964"]
+pub struct Model {
+    pub parts: Vec<ModelPart>,
+}
+
+#[derive(Clone)]
+pub struct ModelPart {
+    pub mesh: Handle<Mesh<Vertex>>,
+    /// Parts without a texture are drawn white, tinted by `color`
+    pub texture: Option<Handle<Image>>,
+    pub color: Color,
+    /// Relative to the model entity
+    pub transform: Transform,
+}
+
+impl ModelPart {
+    pub fn new(mesh: Handle<Mesh<Vertex>>) -> Self {
+        Self {
+            mesh,
+            texture: None,
+            color: Color::NO_TINT,
+            transform: Transform::default(),
+        }
+    }
+
+    pub fn with_texture(mut self, texture: Handle<Image>) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+}
+
+impl Model {
+    pub fn new(parts: Vec<ModelPart>) -> Self {
+        Self { parts }
+    }
+
+    pub fn add_part(&mut self, part: ModelPart) {
+        self.parts.push(part);
+    }
+}
+
+#[derive(Bundle)]
+pub struct ModelBundle {
+    pub global_transform: GlobalTransform,
+    pub transform: Transform,
+    pub model: Handle<Model>,
+    pub visibility: Visibility,
+}
+
+impl Default for ModelBundle {
+    fn default() -> Self {
+        Self {
+            global_transform: GlobalTransform::default(),
+            transform: Transform::default(),
+            model: Handle::default(),
+            visibility: Visibility::Inherited,
+        }
+    }
+}
+
+/// Marks a [`Model`] entity whose parts are already spawned.
+#[derive(Component)]
+pub struct ModelSpawned;
+
+/// Spawns the parts of models as children once the [`Model`] asset is loaded.
+pub fn spawn_models(
+    mut commands: Commands,
+    models: Res<Assets<Model>>,
+    query: Query<(Entity, &Handle<Model>), Without<ModelSpawned>>,
+) {
+    for (entity, model_handle) in query.iter() {
+        let Some(model) = models.get(model_handle) else {
+            continue;
+        };
+
+        commands
+            .entity(entity)
+            .insert(ModelSpawned)
+            .with_children(|parent| {
+                for part in &model.parts {
+                    parent.spawn(TexturedMeshBundle {
+                        transform: part.transform,
+                        mesh: part.mesh.clone(),
+                        texture: part.texture.clone().unwrap_or_default(),
+                        color: part.color,
+                        visibility: Visibility::Inherited,
+                        ..Default::default()
+                    });
+                }
+            });
+    }
+}
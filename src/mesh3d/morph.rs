@@ -0,0 +1,125 @@
+use bevy::{
+    asset::HandleId,
+    prelude::{Commands, Component, Deref, DerefMut, Entity, Handle, Query, Res, ResMut, Resource},
+    utils::HashMap,
+};
+
+use crate::render::{
+    camera::component::ExtractedVisibility,
+    mesh::Mesh,
+    resource::{
+        buffer::VertexTex3,
+        component_uniform::ComponentUniforms,
+        renderer::{RenderDevice, RenderQueue},
+        storage::StorageBuffer,
+    },
+    RenderAssets,
+};
+
+use super::{bind::MeshPipeline, uniform::MeshUniform};
+
+///
+/// Weights of the [`MorphTarget`](crate::render::mesh::morph::MorphTarget)s of the entity's mesh.
+///
+/// Missing weights are zero, extra weights are ignored.
+///
+#[derive(Component, Clone, Default, Deref, DerefMut)]
+pub struct MorphWeights(pub Vec<f32>);
+
+/// Index of the first weight of the entity in [`MorphTargetWeights`].
+#[derive(Component, Clone, Copy, Deref)]
+pub struct MorphWeightOffset(pub u32);
+
+///
+/// Weights of the visible morphed meshes, rebuilt every frame.
+///
+/// Deltas belong to the mesh asset, so morphed meshes get a bind group per mesh
+/// that replaces the mesh bind group, binding the [`MeshUniform`]s, the weights and the deltas.
+///
+#[derive(Resource, Default)]
+pub struct MorphTargetWeights {
+    pub weights: StorageBuffer<Vec<f32>>,
+    pub bind_groups: HashMap<HandleId, wgpu::BindGroup>,
+}
+
+pub fn prepare_morph_weights(
+    mut commands: Commands,
+    mut morph_weights: ResMut<MorphTargetWeights>,
+    gpu_meshes: Res<RenderAssets<Mesh<VertexTex3>>>,
+    morphed_meshes: Query<(
+        Entity,
+        &Handle<Mesh<VertexTex3>>,
+        &MorphWeights,
+        Option<&ExtractedVisibility>,
+    )>,
+) {
+    let mut spawns: Vec<(Entity, MorphWeightOffset)> = Vec::new();
+
+    let weights = morph_weights.weights.get_mut();
+    weights.clear();
+    for (entity, mesh_handle, entity_weights, computed_visibility) in morphed_meshes.iter() {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        let Some(morph_targets) = gpu_meshes
+            .get(&mesh_handle.id())
+            .and_then(|gpu_mesh| gpu_mesh.morph_targets.as_ref())
+        else {
+            continue;
+        };
+        spawns.push((entity, MorphWeightOffset(weights.len() as u32)));
+        weights.extend(
+            (0..morph_targets.target_count as usize)
+                .map(|i| entity_weights.get(i).copied().unwrap_or(0.0)),
+        );
+    }
+    // Storage bindings can not be empty
+    if weights.is_empty() {
+        weights.push(0.0);
+    }
+
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn create_morph_bind_groups(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mesh_pipeline: Res<MeshPipeline>,
+    mesh_uniforms: Res<ComponentUniforms<MeshUniform>>,
+    gpu_meshes: Res<RenderAssets<Mesh<VertexTex3>>>,
+    mut morph_weights: ResMut<MorphTargetWeights>,
+) {
+    let morph_weights = &mut *morph_weights;
+    morph_weights.weights.write_buffer(&render_device, &render_queue);
+    morph_weights.bind_groups.clear();
+
+    let (Some(mesh_binding), Some(weights_binding)) =
+        (mesh_uniforms.binding(), morph_weights.weights.binding())
+    else {
+        return;
+    };
+    for (handle_id, gpu_mesh) in gpu_meshes.iter() {
+        let Some(morph_targets) = gpu_mesh.morph_targets.as_ref() else {
+            continue;
+        };
+        let bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("morphed_mesh_bind_group"),
+            layout: &mesh_pipeline.morphed_mesh_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: mesh_binding.clone(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: weights_binding.clone(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: morph_targets.buffer.as_entire_binding(),
+                },
+            ],
+        });
+        morph_weights.bind_groups.insert(*handle_id, bind_group);
+    }
+}
@@ -0,0 +1,429 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{
+        Assets, Component, Deref, DerefMut, Entity, FromWorld, Handle, Query, Res, ResMut,
+        Resource, Vec4, With, World,
+    },
+    utils::HashMap,
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        camera::component::{Camera, CameraUniforms, VisibleEntities},
+        cleanup::EntityRenderState,
+        color::{Color, ColorSpace},
+        command::{DrawMesh, RenderCommand},
+        globals::GlobalsUniform,
+        pass::CameraAttachments,
+        resource::{
+            buffer::{MeshVertex, Vertex},
+            component_uniform::{ComponentUniforms, ModelUniform},
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, RenderPipelineId, VertexState,
+            },
+            renderer::RenderDevice,
+            shader::Shader,
+            uniform::{DynamicUniformId, HandleGpuUniform},
+        },
+        texture::Image,
+        view::window::PreparedWindows,
+    },
+    util::EngineDefault,
+};
+
+use super::{
+    bind::{MeshBindGroups, MeshPipeline},
+    textured::{MeshTextureBindGroups, TexturedMeshPipeline},
+    OUTLINE_MASK_SHADER_HANDLE, OUTLINE_SHADER_HANDLE,
+};
+
+/// Widest outline in pixels, the search radius of the outline shader.
+pub const MAX_OUTLINE_WIDTH: f32 = 8.0;
+
+///
+/// Draws an outline around the silhouette of the entity, the usual highlight of selected objects.
+///
+/// Works for every entity with a `Handle<Mesh<Vertex>>`, sprites and textured meshes,
+/// transparent texels of their texture are not part of the silhouette.
+/// Outlines are drawn after the main pass of the camera over everything, occluded entities too.
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Outlined {
+    /// The alpha is ignored, outlines are opaque
+    pub color: Color,
+    /// Width in pixels, up to [`MAX_OUTLINE_WIDTH`]
+    pub width: f32,
+}
+
+impl Default for Outlined {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(1.0, 0.6, 0.0),
+            width: 2.0,
+        }
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct OutlineUniform {
+    /// rgb of the color, width over [`MAX_OUTLINE_WIDTH`] in alpha
+    color: Vec4,
+}
+
+impl HandleGpuUniform for Outlined {
+    type GU = OutlineUniform;
+
+    fn into_uniform(&self, color_space: ColorSpace) -> Self::GU {
+        let width = self.width.clamp(1.0, MAX_OUTLINE_WIDTH) / MAX_OUTLINE_WIDTH;
+        OutlineUniform {
+            color: self.color.as_gpu_vec(color_space).truncate().extend(width),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct OutlinePipeline {
+    pub outline_layout: BindGroupLayout,
+    pub mask_layout: BindGroupLayout,
+    /// Draws the silhouettes into the mask of the camera
+    pub mask_pipeline_id: RenderPipelineId,
+    /// Draws the outlines around the silhouettes of the mask
+    pub outline_pipeline_id: RenderPipelineId,
+}
+
+impl OutlinePipeline {
+    pub const MASK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+}
+
+impl FromWorld for OutlinePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<MeshPipeline>,
+            Res<TexturedMeshPipeline>,
+            ResMut<PipelineCache>,
+        )> = SystemState::new(world);
+        let (render_device, mesh_pipeline, textured_mesh_pipeline, mut pipeline_cache) =
+            state.get_mut(world);
+
+        let outline_layout: BindGroupLayout = render_device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("outline_uniform_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(OutlineUniform::min_size()),
+                    },
+                    count: None,
+                }],
+            })
+            .into();
+
+        let mask_layout: BindGroupLayout = render_device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("outline_mask_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            })
+            .into();
+
+        let mask_pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("outline_mask_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![
+                    mesh_pipeline.model_layout.clone(),
+                    mesh_pipeline.view_layout.clone(),
+                    textured_mesh_pipeline.texture_layout.clone(),
+                    outline_layout.clone(),
+                ],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: OUTLINE_MASK_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: vec![Vertex::layout()],
+            },
+            fragment: Some(FragmentState {
+                shader: OUTLINE_MASK_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: Self::MASK_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let outline_pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("outline_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![mask_layout.clone()],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: OUTLINE_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: OUTLINE_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            outline_layout,
+            mask_layout,
+            mask_pipeline_id,
+            outline_pipeline_id,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct OutlineBindGroups {
+    pub outline_bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn create_outline_bind_groups(
+    render_device: Res<RenderDevice>,
+    outline_pipeline: Res<OutlinePipeline>,
+    outline_uniforms: Res<ComponentUniforms<OutlineUniform>>,
+    mut outline_bind_groups: ResMut<OutlineBindGroups>,
+) {
+    let Some(outline_binding) = outline_uniforms.binding() else {
+        return;
+    };
+    outline_bind_groups.outline_bind_group =
+        Some(render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &outline_pipeline.outline_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: outline_binding,
+            }],
+        }));
+}
+
+/// Silhouettes of the outlined entities a camera sees.
+pub struct OutlineMask {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub size: (u32, u32),
+    pub bind_group: wgpu::BindGroup,
+    /// Outlined entities visible to the camera this frame
+    pub entities: Vec<Entity>,
+}
+
+impl OutlineMask {
+    pub fn create(
+        render_device: &RenderDevice,
+        outline_pipeline: &OutlinePipeline,
+        size: (u32, u32),
+    ) -> Self {
+        let texture = render_device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("outline_mask"),
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OutlinePipeline::MASK_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("outline_mask_bind_group"),
+            layout: &outline_pipeline.mask_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+
+        Self {
+            texture,
+            view,
+            size,
+            bind_group,
+            entities: Vec::new(),
+        }
+    }
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct OutlineMasks(pub HashMap<Entity, OutlineMask>);
+
+impl EntityRenderState for OutlineMasks {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(&entity);
+    }
+}
+
+/// Creates the masks of cameras seeing an [`Outlined`] entity, at the size of their target.
+pub fn prepare_outline_masks(
+    render_device: Res<RenderDevice>,
+    outline_pipeline: Res<OutlinePipeline>,
+    images: Res<Assets<Image>>,
+    windows: Res<PreparedWindows>,
+    mut outline_masks: ResMut<OutlineMasks>,
+    cameras: Query<(Entity, &Camera, &VisibleEntities)>,
+    outlined: Query<(), With<Outlined>>,
+) {
+    for (camera_entity, camera, visible_entities) in cameras.iter() {
+        let entities: Vec<Entity> = visible_entities
+            .iter()
+            .copied()
+            .filter(|entity| outlined.contains(*entity))
+            .collect();
+        if entities.is_empty() {
+            if let Some(mask) = outline_masks.get_mut(&camera_entity) {
+                mask.entities.clear();
+            }
+            continue;
+        }
+        let Some(size) = camera.render_target.get_size(&images, &windows) else {
+            continue;
+        };
+        if size.0 == 0 || size.1 == 0 {
+            continue;
+        }
+
+        if outline_masks.get(&camera_entity).map(|mask| mask.size) != Some(size) {
+            outline_masks.insert(
+                camera_entity,
+                OutlineMask::create(&render_device, &outline_pipeline, size),
+            );
+        }
+        outline_masks.get_mut(&camera_entity).unwrap().entities = entities;
+    }
+}
+
+/// Camera pass drawing the silhouettes into the mask, then the outlines around them.
+pub fn encode_outlines<'w>(
+    camera: Entity,
+    world: &'w World,
+    attachments: &CameraAttachments<'w>,
+    command_encoder: &mut wgpu::CommandEncoder,
+) {
+    let outline_masks = world.get_resource::<OutlineMasks>().unwrap();
+    let Some(mask) = outline_masks.get(&camera) else {
+        return;
+    };
+    if mask.entities.is_empty() {
+        return;
+    }
+
+    let outline_pipeline = world.get_resource::<OutlinePipeline>().unwrap();
+    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
+    let (Some(mask_pipeline), Some(render_pipeline)) = (
+        pipeline_cache.get(&outline_pipeline.mask_pipeline_id),
+        pipeline_cache.get(&outline_pipeline.outline_pipeline_id),
+    ) else {
+        return;
+    };
+
+    let mesh_bind_groups = world.get_resource::<MeshBindGroups>().unwrap();
+    let outline_bind_groups = world.get_resource::<OutlineBindGroups>().unwrap();
+    let (Some(model_bind_group), Some(view_bind_group), Some(outline_bind_group)) = (
+        mesh_bind_groups.model_bind_group.as_ref(),
+        mesh_bind_groups.view_bind_group.as_ref(),
+        outline_bind_groups.outline_bind_group.as_ref(),
+    ) else {
+        return;
+    };
+    let (Some(view_uniform_id), Some(globals_uniform_id)) = (
+        world.get::<DynamicUniformId<CameraUniforms>>(camera),
+        world.get::<DynamicUniformId<GlobalsUniform>>(camera),
+    ) else {
+        return;
+    };
+    let textured_mesh_pipeline = world.get_resource::<TexturedMeshPipeline>().unwrap();
+    let texture_bind_groups = world.get_resource::<MeshTextureBindGroups>().unwrap();
+
+    {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("outline_mask_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &mask.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(mask_pipeline);
+        render_pass.set_bind_group(1, view_bind_group, &[**view_uniform_id, **globals_uniform_id]);
+
+        for entity in &mask.entities {
+            let (Some(model_uniform_id), Some(outline_uniform_id)) = (
+                world.get::<DynamicUniformId<ModelUniform>>(*entity),
+                world.get::<DynamicUniformId<OutlineUniform>>(*entity),
+            ) else {
+                continue;
+            };
+            let texture_bind_group = world
+                .get::<Handle<Image>>(*entity)
+                .and_then(|image_handle| texture_bind_groups.get(&image_handle.id()))
+                .unwrap_or(&textured_mesh_pipeline.dummy_texture_bind_group);
+
+            render_pass.set_bind_group(0, model_bind_group, &[**model_uniform_id]);
+            render_pass.set_bind_group(2, texture_bind_group, &[]);
+            render_pass.set_bind_group(3, outline_bind_group, &[**outline_uniform_id]);
+            DrawMesh::<Vertex>::render(camera, *entity, world, &mut render_pass);
+        }
+    }
+
+    let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("outline_pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: attachments.color,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+    render_pass.set_pipeline(render_pipeline);
+    render_pass.set_bind_group(0, &mask.bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
@@ -0,0 +1,48 @@
+
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+}
+
+// One triangle covering the viewport
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+
+    return out;
+}
+
+@group(0) @binding(0)
+var t_mask: texture_2d<f32>;
+
+// Same as MAX_OUTLINE_WIDTH
+let MAX_WIDTH: i32 = 8;
+
+// Pixels outside of the silhouettes take the color of a silhouette
+// closer than its outline width, searched along 8 directions
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_mask));
+    let pixel = vec2<i32>(in.clip_position.xy);
+    if (textureLoad(t_mask, pixel, 0).a > 0.0) {
+        discard;
+    }
+
+    for (var radius = 1; radius <= MAX_WIDTH; radius += 1) {
+        for (var direction = 0; direction < 8; direction += 1) {
+            let angle = f32(direction) * 0.78539816;
+            let offset = vec2<i32>(round(vec2<f32>(cos(angle), sin(angle)) * f32(radius)));
+            let coords = clamp(pixel + offset, vec2<i32>(0), size - 1);
+            let mask = textureLoad(t_mask, coords, 0);
+            if (mask.a > 0.0 && f32(radius) <= mask.a * f32(MAX_WIDTH) + 0.5) {
+                return vec4<f32>(mask.rgb, 1.0);
+            }
+        }
+    }
+    discard;
+    return vec4<f32>(0.0);
+}
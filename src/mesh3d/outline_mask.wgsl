@@ -0,0 +1,84 @@
+
+// -- Vertex -----
+
+struct Fog {
+    color: vec4<f32>,
+    mode: u32,
+    start: f32,
+    end: f32,
+    density: f32,
+}
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    fog: Fog,
+}
+
+struct Globals {
+    time: f32,
+    delta_time: f32,
+    frame_count: u32,
+    resolution: vec2<f32>,
+}
+
+struct Model {
+    model: mat4x4<f32>,
+}
+
+struct VertexInput {
+    @location(0)    position: vec3<f32>,
+    @location(1)    uv: vec2<f32>,
+    @location(2)    color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> model: Model;
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(1)
+var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(
+    vertex: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.clip_position = camera.view_proj * model.model * vec4<f32>(vertex.position, 1.0);
+    out.uv = vertex.uv;
+
+    return out;
+}
+
+// -- Fragment -----
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(2) @binding(1)
+var s_diffuse: sampler;
+
+struct Outline {
+    // rgb is the outline color, a the width over the widest outline
+    color: vec4<f32>,
+}
+
+@group(3) @binding(0)
+var<uniform> outline: Outline;
+
+// Transparent texels are not part of the silhouette
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (textureSample(t_diffuse, s_diffuse, in.uv).a < 0.5) {
+        discard;
+    }
+    return outline.color;
+}
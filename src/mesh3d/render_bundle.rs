@@ -0,0 +1,391 @@
+use bevy::{
+    asset::{Asset, HandleId},
+    ecs::system::RemovedComponents,
+    prelude::{
+        Added, AssetEvent, Changed, Entity, EventReader, GlobalTransform, Handle, Or, Query, Res,
+        ResMut, Resource, With,
+    },
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    render::{
+        blend::{is_transparent, AlphaMode, BlendMode},
+        camera::component::{Camera, CameraUniforms, VisibleEntities},
+        cleanup::EntityRenderState,
+        debug_view::DebugViewMode,
+        globals::GlobalsUniform,
+        mesh::{GpuMeshAssembly, Mesh},
+        raster::{CullMode, DepthBias, RasterKey},
+        render_bundle::{RecordedBundle, RenderBundles, StaticGeometry},
+        resource::{
+            buffer::VertexTex3,
+            component_uniform::{ComponentUniforms, ModelUniform},
+            pipeline::PipelineCache,
+            renderer::{RenderDevice, RenderQueue},
+            specialized_pipeline::Specialized,
+            uniform::{DynamicUniformBuffer, DynamicUniformId},
+        },
+        stats::{FrameRenderStats, RenderCategory},
+        texture::{
+            texture_arr::{ImageArray, ImageArrayHandle, TextureIndex},
+            DepthTexture, DepthTextures,
+        },
+        RenderAssets,
+    },
+    util::EngineDefault,
+};
+
+use super::{
+    bind::{MeshBindGroups, MeshPipeline, MeshPipelineKey, TextureArrayBindGroups},
+    uniform::MeshUniform,
+    MESH_DEFAULT_BLEND_MODE,
+};
+
+const STATIC_MESH_BUNDLE_SOURCE: &str = "mesh3d";
+
+/// What a camera bundle was recorded with, it is recorded again when any of it changes.
+#[derive(PartialEq, Eq)]
+struct CameraBundleKey {
+    entities: Vec<Entity>,
+    view_offset: u32,
+    view_generation: u64,
+    globals_offset: u32,
+    globals_generation: u64,
+    revision: u64,
+    debug_view: DebugViewMode,
+}
+
+/// Uniforms of the [`StaticGeometry`] meshes, only rewritten when one of them changes
+/// so the offsets recorded into the bundles stay valid.
+#[derive(Resource, Default)]
+pub struct StaticMeshBundles {
+    model_uniforms: DynamicUniformBuffer<ModelUniform>,
+    mesh_uniforms: DynamicUniformBuffer<MeshUniform>,
+    model_bind_group: Option<wgpu::BindGroup>,
+    mesh_bind_group: Option<wgpu::BindGroup>,
+    offsets: HashMap<Entity, (u32, u32)>,
+    revision: u64,
+    cameras: HashMap<Entity, CameraBundleKey>,
+}
+
+type StaticMeshChanged = Or<(
+    Added<StaticGeometry>,
+    Changed<GlobalTransform>,
+    Changed<Handle<Mesh<VertexTex3>>>,
+    Changed<MeshPipelineKey>,
+    Changed<ImageArrayHandle>,
+    Changed<BlendMode>,
+    Changed<AlphaMode>,
+    Changed<CullMode>,
+    Changed<DepthBias>,
+    Changed<TextureIndex>,
+)>;
+
+/// Whether any of the events is about one of the `referenced` assets, reads all of them.
+fn touches_referenced<T: Asset>(
+    events: &mut EventReader<AssetEvent<T>>,
+    referenced: &HashSet<HandleId>,
+) -> bool {
+    events
+        .iter()
+        .filter(|event| {
+            let (AssetEvent::Created { handle }
+            | AssetEvent::Modified { handle }
+            | AssetEvent::Removed { handle }) = event;
+            referenced.contains(&handle.id())
+        })
+        .count()
+        > 0
+}
+
+/// Bundles of despawned cameras are dropped by the [`RenderBundles`] cleanup.
+impl EntityRenderState for StaticMeshBundles {
+    fn remove_entity(&mut self, camera: Entity) {
+        self.cameras.remove(&camera);
+    }
+}
+
+pub fn record_static_mesh_bundles(
+    (render_device, render_queue): (Res<RenderDevice>, Res<RenderQueue>),
+    (mut static_bundles, mut render_bundles): (ResMut<StaticMeshBundles>, ResMut<RenderBundles>),
+    (mesh_pipeline, specialized_mesh_pipeline, pipeline_cache): (
+        Res<MeshPipeline>,
+        Res<Specialized<MeshPipeline>>,
+        Res<PipelineCache>,
+    ),
+    (mesh_bind_groups, texture_arr_bind_groups, view_uniforms, globals_uniforms): (
+        Res<MeshBindGroups>,
+        Res<TextureArrayBindGroups>,
+        Res<ComponentUniforms<CameraUniforms>>,
+        Res<ComponentUniforms<GlobalsUniform>>,
+    ),
+    (gpu_meshes, depth_textures, debug_view): (
+        Res<RenderAssets<Mesh<VertexTex3>>>,
+        Res<DepthTextures>,
+        Res<DebugViewMode>,
+    ),
+    (mut mesh_events, mut image_arr_events): (
+        EventReader<AssetEvent<Mesh<VertexTex3>>>,
+        EventReader<AssetEvent<ImageArray>>,
+    ),
+    removed: RemovedComponents<StaticGeometry>,
+    cameras: Query<(
+        Entity,
+        &Camera,
+        &VisibleEntities,
+        &DynamicUniformId<CameraUniforms>,
+        &DynamicUniformId<GlobalsUniform>,
+    )>,
+    statics: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &Handle<Mesh<VertexTex3>>,
+            &MeshPipelineKey,
+            Option<&ImageArrayHandle>,
+            Option<&BlendMode>,
+            Option<&AlphaMode>,
+            Option<&CullMode>,
+            Option<&DepthBias>,
+            Option<&TextureIndex>,
+        ),
+        With<StaticGeometry>,
+    >,
+    changed: Query<(), (With<StaticGeometry>, StaticMeshChanged)>,
+) {
+    let static_bundles = &mut *static_bundles;
+
+    // -- Static Uniforms --
+    // Reloaded meshes and textures are recorded again, only the ones the statics use
+    let mut mesh_ids = HashSet::new();
+    let mut image_arr_ids = HashSet::new();
+    for (_, _, mesh_handle, _, image_arr_handle, ..) in statics.iter() {
+        mesh_ids.insert(mesh_handle.id());
+        if let Some(image_arr) = image_arr_handle.and_then(|handle| handle.image_arr.as_ref()) {
+            image_arr_ids.insert(image_arr.id());
+        }
+    }
+    // Both read every frame, unread events would mark the next frame dirty
+    let meshes_reloaded = touches_referenced(&mut mesh_events, &mesh_ids);
+    let image_arrs_reloaded = touches_referenced(&mut image_arr_events, &image_arr_ids);
+    let dirty = meshes_reloaded
+        || image_arrs_reloaded
+        || removed.iter().next().is_some()
+        || !changed.is_empty()
+        || static_bundles.model_bind_group.is_none();
+    if dirty {
+        static_bundles.model_uniforms.clear();
+        static_bundles.mesh_uniforms.clear();
+        static_bundles.offsets.clear();
+        for (entity, global_transform, _, _, _, _, alpha_mode, _, _, texture_index) in
+            statics.iter()
+        {
+            let model_offset = static_bundles
+                .model_uniforms
+                .push(ModelUniform::new(global_transform.compute_matrix()));
+            let mesh_offset = static_bundles
+                .mesh_uniforms
+                .push(MeshUniform::new(alpha_mode).with_texture_index(texture_index));
+            static_bundles
+                .offsets
+                .insert(entity, (model_offset, mesh_offset));
+        }
+
+        if static_bundles.offsets.is_empty() {
+            static_bundles.model_bind_group = None;
+            static_bundles.mesh_bind_group = None;
+        } else {
+            static_bundles
+                .model_uniforms
+                .write_buffer(&render_device, &render_queue);
+            static_bundles
+                .mesh_uniforms
+                .write_buffer(&render_device, &render_queue);
+
+            static_bundles.model_bind_group =
+                Some(render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("static_mesh_model_bind_group"),
+                    layout: &mesh_pipeline.model_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: static_bundles.model_uniforms.binding().unwrap(),
+                    }],
+                }));
+            static_bundles.mesh_bind_group =
+                Some(render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("static_mesh_uniform_bind_group"),
+                    layout: &mesh_pipeline.mesh_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: static_bundles.mesh_uniforms.binding().unwrap(),
+                    }],
+                }));
+        }
+        static_bundles.revision += 1;
+    }
+    // -- -- -- -------- -- -- --
+
+    for (camera_entity, camera, visible_entities, view_uniform_id, globals_uniform_id) in
+        cameras.iter()
+    {
+        let mut entities: Vec<Entity> = visible_entities
+            .iter()
+            .copied()
+            .filter(|entity| static_bundles.offsets.contains_key(entity))
+            .filter(|entity| {
+                let Ok((_, _, _, _, _, blend_mode, alpha_mode, ..)) = statics.get(*entity) else {
+                    return false;
+                };
+                let blend_mode = blend_mode.copied().unwrap_or(MESH_DEFAULT_BLEND_MODE);
+                !is_transparent(Some(&blend_mode), alpha_mode)
+            })
+            .collect();
+        entities.sort();
+
+        // Bundles are only compatible with passes that have a depth attachment
+        let (Some(view_bind_group), true, false) = (
+            mesh_bind_groups.view_bind_group.as_ref(),
+            depth_textures.contains_key(&camera.render_target),
+            entities.is_empty(),
+        ) else {
+            static_bundles.cameras.remove(&camera_entity);
+            render_bundles.remove(camera_entity, STATIC_MESH_BUNDLE_SOURCE);
+            continue;
+        };
+
+        let key = CameraBundleKey {
+            entities,
+            view_offset: **view_uniform_id,
+            view_generation: view_uniforms.generation(),
+            globals_offset: **globals_uniform_id,
+            globals_generation: globals_uniforms.generation(),
+            revision: static_bundles.revision,
+            debug_view: *debug_view,
+        };
+        if static_bundles.cameras.get(&camera_entity) == Some(&key) {
+            continue;
+        }
+
+        // -- Record Bundle --
+        let mut encoder =
+            render_device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                label: Some("static_mesh_bundle"),
+                color_formats: &[Some(wgpu::TextureFormat::engine_default())],
+                depth_stencil: Some(wgpu::RenderBundleDepthStencil {
+                    format: DepthTexture::DEPTH_FORMAT,
+                    depth_read_only: false,
+                    stencil_read_only: true,
+                }),
+                sample_count: 1,
+                multiview: None,
+            });
+
+        let mut recorded = HashSet::new();
+        let mut stats = FrameRenderStats::default();
+        let mut complete = true;
+        for entity in &key.entities {
+            let (
+                _,
+                _,
+                mesh_handle,
+                pipeline_key,
+                image_array_handle,
+                blend_mode,
+                alpha_mode,
+                cull_mode,
+                depth_bias,
+                _,
+            ) = statics.get(*entity).unwrap();
+            let (model_offset, mesh_offset) = static_bundles.offsets[entity];
+
+            let blend_mode = blend_mode.copied().unwrap_or(MESH_DEFAULT_BLEND_MODE);
+            let Some(render_pipeline) = specialized_mesh_pipeline
+                .pipelines
+                .get(&(
+                    *pipeline_key,
+                    blend_mode,
+                    RasterKey::new(cull_mode, depth_bias, alpha_mode),
+                    *debug_view,
+                ))
+                .and_then(|pipeline_id| pipeline_cache.get(pipeline_id)) else {
+                complete = false;
+                continue;
+            };
+            let Some(mesh) = gpu_meshes.get(&mesh_handle.id()) else {
+                complete = false;
+                continue;
+            };
+            let texture_bind_group = match image_array_handle.and_then(|h| h.image_arr.as_ref()) {
+                Some(handle) => match texture_arr_bind_groups.get(&handle.id()) {
+                    Some(bind) => bind,
+                    None => {
+                        complete = false;
+                        continue;
+                    }
+                },
+                None => &mesh_pipeline.dummy_texture_arr_bind_group,
+            };
+
+            encoder.set_pipeline(render_pipeline);
+            encoder.set_bind_group(
+                0,
+                static_bundles.model_bind_group.as_ref().unwrap(),
+                &[model_offset],
+            );
+            encoder.set_bind_group(
+                1,
+                view_bind_group,
+                &[**view_uniform_id, **globals_uniform_id],
+            );
+            encoder.set_bind_group(2, texture_bind_group, &[]);
+            encoder.set_bind_group(
+                3,
+                static_bundles.mesh_bind_group.as_ref().unwrap(),
+                &[mesh_offset],
+            );
+            stats.pipeline_switches += 1;
+            stats.bind_group_switches += 4;
+
+            encoder.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            match &mesh.assembly {
+                GpuMeshAssembly::Indexed {
+                    index_buffer,
+                    index_count,
+                    index_format,
+                } => {
+                    encoder.set_index_buffer(index_buffer.slice(..), *index_format);
+                    encoder.draw_indexed(0..*index_count as u32, 0, 0..1);
+                    stats.draw(*index_count as u32, 1);
+                }
+                GpuMeshAssembly::NonIndexed { vertex_count } => {
+                    encoder.draw(0..*vertex_count as u32, 0..1);
+                    stats.draw(*vertex_count as u32, 1);
+                }
+            }
+            stats.category_draw(RenderCategory::Mesh, 1);
+            recorded.insert(*entity);
+        }
+
+        let bundle = encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("static_mesh_bundle"),
+        });
+        render_bundles.insert(
+            camera_entity,
+            STATIC_MESH_BUNDLE_SOURCE,
+            RecordedBundle {
+                bundle,
+                entities: recorded,
+                stats,
+            },
+        );
+
+        // Entities still loading are drawn through the phases and recorded again next frame
+        if complete {
+            static_bundles.cameras.insert(camera_entity, key);
+        } else {
+            static_bundles.cameras.remove(&camera_entity);
+        }
+        // -- -- -- -------- -- -- --
+    }
+}
@@ -0,0 +1,118 @@
+use bevy::prelude::{
+    Commands, Component, Deref, Entity, GlobalTransform, Mat4, Query, Res, ResMut, Resource,
+};
+
+use crate::render::{
+    camera::component::ExtractedVisibility,
+    resource::{
+        component_uniform::ComponentUniforms,
+        renderer::{RenderDevice, RenderQueue},
+        storage::StorageBuffer,
+    },
+};
+
+use super::{bind::MeshPipeline, uniform::MeshUniform};
+
+///
+/// Joints posing a [`VertexSkinned`](crate::render::resource::buffer::VertexSkinned) mesh.
+///
+/// Joints are plain entities in the transform hierarchy, animating their
+/// `Transform`s moves the skin. `VertexSkinned::joint_indices` index into `joints`.
+///
+#[derive(Component, Clone, Default)]
+pub struct SkinnedMesh {
+    /// Inverse of each joint's model space transform in the bind pose
+    pub inverse_bindposes: Vec<Mat4>,
+    pub joints: Vec<Entity>,
+}
+
+/// Index of the first joint matrix of the entity in [`SkinJoints`].
+#[derive(Component, Clone, Copy, Deref)]
+pub struct SkinJointOffset(pub u32);
+
+///
+/// Model space joint matrices of the visible skinned meshes, rebuilt every frame.
+///
+/// `bind_group` replaces the mesh bind group of skinned meshes,
+/// binding the [`MeshUniform`]s next to the joints.
+///
+#[derive(Resource, Default)]
+pub struct SkinJoints {
+    pub matrices: StorageBuffer<Vec<Mat4>>,
+    pub bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn prepare_skins(
+    mut commands: Commands,
+    mut skin_joints: ResMut<SkinJoints>,
+    skinned_meshes: Query<(
+        Entity,
+        &GlobalTransform,
+        &SkinnedMesh,
+        Option<&ExtractedVisibility>,
+    )>,
+    joints: Query<&GlobalTransform>,
+) {
+    let mut spawns: Vec<(Entity, SkinJointOffset)> = Vec::new();
+
+    let matrices = skin_joints.matrices.get_mut();
+    matrices.clear();
+    for (entity, global_transform, skinned_mesh, computed_visibility) in skinned_meshes.iter() {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        let inverse_model = global_transform.compute_matrix().inverse();
+        spawns.push((entity, SkinJointOffset(matrices.len() as u32)));
+        for (joint, inverse_bindpose) in skinned_mesh
+            .joints
+            .iter()
+            .zip(skinned_mesh.inverse_bindposes.iter())
+        {
+            // A despawned joint leaves its vertices in the bind pose
+            let joint_matrix = match joints.get(*joint) {
+                Ok(joint) => inverse_model * joint.compute_matrix() * *inverse_bindpose,
+                Err(_) => Mat4::IDENTITY,
+            };
+            matrices.push(joint_matrix);
+        }
+    }
+    // Storage bindings can not be empty
+    if matrices.is_empty() {
+        matrices.push(Mat4::IDENTITY);
+    }
+
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn create_skin_bind_group(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mesh_pipeline: Res<MeshPipeline>,
+    mesh_uniforms: Res<ComponentUniforms<MeshUniform>>,
+    mut skin_joints: ResMut<SkinJoints>,
+) {
+    let skin_joints = &mut *skin_joints;
+    skin_joints.matrices.write_buffer(&render_device, &render_queue);
+
+    let (Some(mesh_binding), Some(joints_binding)) =
+        (mesh_uniforms.binding(), skin_joints.matrices.binding())
+    else {
+        skin_joints.bind_group = None;
+        return;
+    };
+    let bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("skinned_mesh_bind_group"),
+        layout: &mesh_pipeline.skinned_mesh_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mesh_binding,
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: joints_binding,
+            },
+        ],
+    });
+    skin_joints.bind_group = Some(bind_group);
+}
@@ -0,0 +1,384 @@
+use bevy::{
+    asset::HandleId,
+    ecs::system::SystemState,
+    prelude::{
+        AssetEvent, Changed, Component, Deref, DerefMut, Entity, EventReader, FromWorld, Handle,
+        Or, Query, Res, ResMut, Resource, With, World,
+    },
+    utils::HashMap,
+};
+
+use crate::render::{
+    blend::{AlphaMode, BlendMode},
+    camera::component::CameraUniforms,
+    command::{DrawMesh, RenderCommand},
+    debug_view::DebugViewMode,
+    globals::GlobalsUniform,
+    raster::{CullMode, DepthBias, RasterKey},
+    resource::{
+        buffer::{MeshVertex, Vertex},
+        component_uniform::ModelUniform,
+        pipeline::{BindGroupLayout, PipelineCache, RenderPipelineDescriptor},
+        renderer::{RenderDevice, RenderQueue},
+        specialized_pipeline::{PipelineSpecialize, Specialized},
+        uniform::DynamicUniformId,
+    },
+    stats::{RenderCategory, RenderStats},
+    system::RenderResult,
+    texture::{GpuTexture, Image, PixelFormat, RawImage},
+    RenderAssets,
+};
+
+use super::{
+    bind::{mesh_pipeline_descriptor, MeshBindGroups, MeshPipeline},
+    uniform::MeshUniform,
+    MESH_DEFAULT_BLEND_MODE, MESH_TEXTURED_SHADER_HANDLE,
+};
+
+pub const TEXTURED_MESH_RENDER_FUNCTION: usize = 7;
+
+///
+/// Mesh path for [`Vertex`] meshes sampling a single [`Image`],
+/// for ordinary 3D models that do not need a texture array.
+///
+/// Shares the model, view and mesh bind groups of the [`MeshPipeline`].
+///
+pub type DrawTexturedMesh = (
+    SetTexturedMeshPipeline,
+    SetTexturedMeshBindGroups,
+    DrawMesh<Vertex>,
+    CountTexturedMeshDraw,
+);
+
+/// Marks the entities drawn with [`DrawTexturedMesh`].
+#[derive(Component, Clone, Copy, Default)]
+pub struct TexturedMesh;
+
+#[derive(Resource)]
+pub struct TexturedMeshPipeline {
+    pub model_layout: BindGroupLayout,
+    pub view_layout: BindGroupLayout,
+    pub texture_layout: BindGroupLayout,
+    pub mesh_layout: BindGroupLayout,
+    pub dummy_texture: GpuTexture,
+    pub dummy_texture_bind_group: wgpu::BindGroup,
+}
+
+impl FromWorld for TexturedMeshPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<RenderQueue>,
+            Res<MeshPipeline>,
+            ResMut<PipelineCache>,
+            ResMut<Specialized<Self>>,
+        )> = SystemState::new(world);
+        let (render_device, render_queue, mesh_pipeline, mut pipeline_cache, mut specialized_self) =
+            state.get_mut(world);
+
+        let texture_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("textured_mesh_texture_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let dummy_texture = GpuTexture::from_raw_image(
+            &render_device,
+            &render_queue,
+            &RawImage::new(&[255u8; 4], (1, 1), PixelFormat::RGBA8),
+            None,
+        )
+        .unwrap();
+
+        let dummy_texture_bind_group =
+            render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &texture_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&dummy_texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&dummy_texture.sampler),
+                    },
+                ],
+            });
+
+        let textured_mesh_pipeline = TexturedMeshPipeline {
+            model_layout: mesh_pipeline.model_layout.clone(),
+            view_layout: mesh_pipeline.view_layout.clone(),
+            texture_layout,
+            mesh_layout: mesh_pipeline.mesh_layout.clone(),
+            dummy_texture,
+            dummy_texture_bind_group,
+        };
+
+        for blend_mode in BlendMode::ALL {
+            let key = (*blend_mode, RasterKey::default(), DebugViewMode::None);
+            let id = pipeline_cache.queue(textured_mesh_pipeline.specialize(&render_device, key));
+            specialized_self.pipelines.insert(key, id);
+        }
+
+        textured_mesh_pipeline
+    }
+}
+
+impl PipelineSpecialize for TexturedMeshPipeline {
+    type Key = (BlendMode, RasterKey, DebugViewMode);
+
+    fn specialize(&self, _render_device: &RenderDevice, key: Self::Key) -> RenderPipelineDescriptor {
+        mesh_pipeline_descriptor(
+            vec![
+                self.model_layout.clone(),
+                self.view_layout.clone(),
+                self.texture_layout.clone(),
+                self.mesh_layout.clone(),
+            ],
+            MESH_TEXTURED_SHADER_HANDLE.typed(),
+            Vertex::layout(),
+            key,
+        )
+    }
+}
+
+/// Queues the pipelines of textured meshes with a [`CullMode`], [`DepthBias`]
+/// or masked [`AlphaMode`], the default ones are queued up front.
+pub fn specialize_textured_mesh_pipelines(
+    render_device: Res<RenderDevice>,
+    debug_view: Res<DebugViewMode>,
+    textured_mesh_pipeline: Res<TexturedMeshPipeline>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    mut specialized_textured_mesh_pipeline: ResMut<Specialized<TexturedMeshPipeline>>,
+    query: Query<
+        (
+            Option<&BlendMode>,
+            Option<&CullMode>,
+            Option<&DepthBias>,
+            Option<&AlphaMode>,
+        ),
+        (
+            With<TexturedMesh>,
+            Or<(
+                Changed<BlendMode>,
+                Changed<CullMode>,
+                Changed<DepthBias>,
+                Changed<AlphaMode>,
+            )>,
+        ),
+    >,
+) {
+    if debug_view.is_changed() {
+        let keys: Vec<_> = specialized_textured_mesh_pipeline
+            .pipelines
+            .keys()
+            .map(|(blend_mode, raster_key, _)| (*blend_mode, *raster_key, *debug_view))
+            .collect();
+        for key in keys {
+            specialized_textured_mesh_pipeline.specialize(
+                &mut pipeline_cache,
+                &textured_mesh_pipeline,
+                &render_device,
+                key,
+            );
+        }
+    }
+
+    for (blend_mode, cull_mode, depth_bias, alpha_mode) in query.iter() {
+        let key = (
+            blend_mode.copied().unwrap_or(MESH_DEFAULT_BLEND_MODE),
+            RasterKey::new(cull_mode, depth_bias, alpha_mode),
+            *debug_view,
+        );
+        specialized_textured_mesh_pipeline.specialize(
+            &mut pipeline_cache,
+            &textured_mesh_pipeline,
+            &render_device,
+            key,
+        );
+    }
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct MeshTextureBindGroups(pub HashMap<HandleId, wgpu::BindGroup>);
+
+pub fn create_mesh_texture_bind_groups(
+    render_device: Res<RenderDevice>,
+    textured_mesh_pipeline: Res<TexturedMeshPipeline>,
+    mut texture_bind_groups: ResMut<MeshTextureBindGroups>,
+    render_images: Res<RenderAssets<Image>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+) {
+    // Modified images are prepared again, e.g. a resized render target, the old view is stale
+    for event in image_events.iter() {
+        match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => {
+                texture_bind_groups.remove(&handle.id());
+            }
+            AssetEvent::Created { .. } => {}
+        }
+    }
+    // Images evicted over the GPU memory budget are freed with their bind group
+    texture_bind_groups.retain(|handle_id, _| render_images.contains_key(handle_id));
+
+    for (handle_id, gpu_image) in render_images.iter() {
+        texture_bind_groups.entry(*handle_id).or_insert_with(|| {
+            render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &textured_mesh_pipeline.texture_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&gpu_image.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&gpu_image.sampler),
+                    },
+                ],
+            })
+        });
+    }
+}
+
+pub struct SetTexturedMeshPipeline;
+impl RenderCommand for SetTexturedMeshPipeline {
+    fn render<'w>(
+        _camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(specialized_textured_mesh_pipeline) =
+            world.get_resource::<Specialized<TexturedMeshPipeline>>()
+        else {
+            return RenderResult::Failure("Specialized<TexturedMeshPipeline> resource missing");
+        };
+        let Some(pipeline_cache) = world.get_resource::<PipelineCache>() else {
+            return RenderResult::Failure("PipelineCache resource missing");
+        };
+
+        let blend_mode = world
+            .get::<BlendMode>(object)
+            .copied()
+            .unwrap_or(MESH_DEFAULT_BLEND_MODE);
+        let raster_key = RasterKey::of(world, object);
+        let Some(debug_view) = world.get_resource::<DebugViewMode>() else {
+            return RenderResult::Failure("DebugViewMode resource missing");
+        };
+        let Some(pipeline_id) = specialized_textured_mesh_pipeline
+            .pipelines
+            .get(&(blend_mode, raster_key, *debug_view))
+        else {
+            return RenderResult::Failure("pipeline not specialized");
+        };
+        let Some(render_pipeline) = pipeline_cache.get(pipeline_id) else {
+            return RenderResult::Failure("pipeline not compiled yet");
+        };
+        render_pass.set_pipeline(render_pipeline);
+        let Some(render_stats) = world.get_resource::<RenderStats>() else {
+            return RenderResult::Failure("RenderStats resource missing");
+        };
+        render_stats.pipeline_switch();
+
+        RenderResult::Success
+    }
+}
+
+/// Binds the model, view, texture and mesh bind groups.
+pub struct SetTexturedMeshBindGroups;
+impl RenderCommand for SetTexturedMeshBindGroups {
+    fn render<'w>(
+        camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(mesh_bind_groups) = world.get_resource::<MeshBindGroups>() else {
+            return RenderResult::Failure("MeshBindGroups resource missing");
+        };
+        let (Some(model_bind_group), Some(view_bind_group), Some(mesh_bind_group)) = (
+            mesh_bind_groups.model_bind_group.as_ref(),
+            mesh_bind_groups.view_bind_group.as_ref(),
+            mesh_bind_groups.mesh_bind_group.as_ref(),
+        ) else {
+            return RenderResult::Failure("bind groups not created");
+        };
+
+        let Some(model_uniform_id) = world.get::<DynamicUniformId<ModelUniform>>(object) else {
+            return RenderResult::Failure("no ModelUniform id");
+        };
+        render_pass.set_bind_group(0, model_bind_group, &[**model_uniform_id]);
+
+        let Some(view_uniform_id) = world.get::<DynamicUniformId<CameraUniforms>>(camera) else {
+            return RenderResult::Failure("no CameraUniforms id");
+        };
+        let Some(globals_uniform_id) = world.get::<DynamicUniformId<GlobalsUniform>>(camera) else {
+            return RenderResult::Failure("no GlobalsUniform id");
+        };
+        render_pass.set_bind_group(1, view_bind_group, &[**view_uniform_id, **globals_uniform_id]);
+
+        let Some(textured_mesh_pipeline) = world.get_resource::<TexturedMeshPipeline>() else {
+            return RenderResult::Failure("TexturedMeshPipeline resource missing");
+        };
+        let Some(texture_bind_groups) = world.get_resource::<MeshTextureBindGroups>() else {
+            return RenderResult::Failure("MeshTextureBindGroups resource missing");
+        };
+        let texture_bind_group = match world.get::<Handle<Image>>(object) {
+            Some(image_handle) => match texture_bind_groups.get(&image_handle.id()) {
+                Some(bind) => bind,
+                None => &textured_mesh_pipeline.dummy_texture_bind_group,
+            },
+            None => &textured_mesh_pipeline.dummy_texture_bind_group,
+        };
+        render_pass.set_bind_group(2, texture_bind_group, &[]);
+
+        let Some(mesh_uniform_id) = world.get::<DynamicUniformId<MeshUniform>>(object) else {
+            return RenderResult::Failure("no MeshUniform id");
+        };
+        render_pass.set_bind_group(3, mesh_bind_group, &[**mesh_uniform_id]);
+
+        let Some(render_stats) = world.get_resource::<RenderStats>() else {
+            return RenderResult::Failure("RenderStats resource missing");
+        };
+        render_stats.bind_group_switches(4);
+
+        RenderResult::Success
+    }
+}
+
+/// Counts the mesh drawn by the commands before it in the [`RenderStats`] mesh category.
+pub struct CountTexturedMeshDraw;
+impl RenderCommand for CountTexturedMeshDraw {
+    fn render<'w>(
+        _camera: Entity,
+        _object: Entity,
+        world: &'w World,
+        _render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(render_stats) = world.get_resource::<RenderStats>() else {
+            return RenderResult::Failure("RenderStats resource missing");
+        };
+        render_stats.category_draw(RenderCategory::Mesh, 1);
+
+        RenderResult::Success
+    }
+}
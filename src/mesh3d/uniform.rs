@@ -0,0 +1,99 @@
+use bevy::prelude::{Commands, Entity, Or, Query, Res, ResMut, With};
+use encase::ShaderType;
+
+use crate::render::{
+    blend::AlphaMode,
+    camera::component::ExtractedVisibility,
+    resource::{
+        buffer_pool::BufferPool,
+        component_uniform::ComponentUniforms,
+        renderer::{RenderDevice, RenderQueue},
+        uniform::DynamicUniformId,
+    },
+    texture::texture_arr::TextureIndex,
+};
+
+use super::{
+    bind::MeshPipelineKey, morph::MorphWeightOffset, skin::SkinJointOffset, textured::TexturedMesh,
+};
+
+#[derive(Clone, Default, ShaderType)]
+pub struct MeshUniform {
+    /// Fragments below this alpha are discarded, zero means no masking
+    alpha_cutoff: f32,
+    /// First joint matrix of a skinned mesh in [`SkinJoints`](super::skin::SkinJoints)
+    joint_offset: u32,
+    /// First weight of a morphed mesh in [`MorphTargetWeights`](super::morph::MorphTargetWeights)
+    morph_weight_offset: u32,
+    /// Added to the texture array layer of the vertices
+    texture_index: u32,
+}
+
+impl MeshUniform {
+    pub fn new(alpha_mode: Option<&AlphaMode>) -> Self {
+        Self {
+            alpha_cutoff: alpha_mode.map_or(0.0, AlphaMode::cutoff),
+            joint_offset: 0,
+            morph_weight_offset: 0,
+            texture_index: 0,
+        }
+    }
+
+    pub fn with_joint_offset(mut self, joint_offset: Option<&SkinJointOffset>) -> Self {
+        self.joint_offset = joint_offset.map_or(0, |offset| **offset);
+        self
+    }
+
+    pub fn with_texture_index(mut self, texture_index: Option<&TextureIndex>) -> Self {
+        self.texture_index = texture_index.map_or(0, |index| **index);
+        self
+    }
+
+    pub fn with_morph_weight_offset(mut self, weight_offset: Option<&MorphWeightOffset>) -> Self {
+        self.morph_weight_offset = weight_offset.map_or(0, |offset| **offset);
+        self
+    }
+}
+
+pub fn prepare_mesh_uniforms(
+    mut commands: Commands,
+    mut mesh_uniforms: ResMut<ComponentUniforms<MeshUniform>>,
+    query: Query<
+        (
+            Entity,
+            Option<&AlphaMode>,
+            Option<&SkinJointOffset>,
+            Option<&MorphWeightOffset>,
+            Option<&TextureIndex>,
+            Option<&ExtractedVisibility>,
+        ),
+        Or<(With<MeshPipelineKey>, With<TexturedMesh>)>,
+    >,
+) {
+    let mut spawns: Vec<(Entity, DynamicUniformId<MeshUniform>)> = Vec::new();
+
+    mesh_uniforms.clear();
+    for (entity, alpha_mode, joint_offset, weight_offset, texture_index, computed_visibility) in
+        query.iter()
+    {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        let mesh_uniform = MeshUniform::new(alpha_mode)
+            .with_joint_offset(joint_offset)
+            .with_morph_weight_offset(weight_offset)
+            .with_texture_index(texture_index);
+        spawns.push((entity, mesh_uniforms.push(mesh_uniform).into()));
+    }
+
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn queue_mesh_uniforms(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffer_pool: ResMut<BufferPool>,
+    mut mesh_uniforms: ResMut<ComponentUniforms<MeshUniform>>,
+) {
+    mesh_uniforms.write_buffer_pooled(&render_device, &render_queue, &mut buffer_pool);
+}
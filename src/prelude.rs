@@ -0,0 +1,77 @@
+//!
+//! Types most games use, `use flat::prelude::*;` next to `bevy::prelude::*`.
+//!
+//! Pipelines, bind groups and render internals stay behind their module paths.
+//!
+
+pub use crate::{
+    exit::AppExitCondition,
+    grid::bundle::GroundGridBundle,
+    input::{
+        cursor::{CursorState, WindowCursor},
+        gesture::{DoubleClick, DragEnd, DragStart, Dragging, GestureSettings},
+        keyboard::{KeyBinding, Keys, PhysicalKey},
+        ray::{CursorRay, Ray},
+        FlatInputPlugin,
+    },
+    render::{
+        blend::{AlphaMode, BlendMode},
+        camera::{
+            billboard::Billboard,
+            component::{
+                Camera, CameraBundle, OrthographicProjection, PerspectiveProjection, RenderLayers,
+                RenderTarget, Visibility,
+            },
+            fog::{Fog, FogFalloff},
+            pixel_perfect::PixelPerfect,
+        },
+        color::Color,
+        diagnostic::RenderDiagnosticsPlugin,
+        mesh::Mesh,
+        post::{
+            dof::DepthOfField,
+            fxaa::Fxaa,
+            grading::{ColorGrading, Tonemapping},
+            lens::{ChromaticAberration, FilmGrain, Vignette},
+            FlatPostPlugin,
+        },
+        raster::{CullMode, DepthBias},
+        resource::buffer::{Indices, Vertex, VertexSkinned, VertexTex3},
+        texture::{
+            animated::{AnimatedImage, AnimatedTexture},
+            texture_arr::{ImageArray, ImageArrayHandle, TextureIndex},
+            Image, ImageSampling,
+        },
+        FlatRenderPlugin,
+    },
+    time::{AddFixedSystem, FixedStage, FixedTime, FixedUpdateStage, InterpolatedTransform},
+    trail::{bundle::TrailBundle, FlatTrailPlugin},
+    FlatEngineComplete, FlatEngineConfig, FlatEngineCore,
+};
+
+#[cfg(feature = "mesh3d")]
+pub use crate::mesh3d::{
+    bind::MeshPipelineKey,
+    bundle::{MeshBundle, TexturedMeshBundle},
+    model::{Model, ModelBundle, ModelPart},
+    outline::Outlined,
+    FlatMeshPlugin,
+};
+
+#[cfg(feature = "sprite2d")]
+pub use crate::{
+    shapes::{bundle::ShapeBundle, FlatShapePlugin},
+    sprite::{
+        bundle::SpriteBundle,
+        emissive::{SpriteEmissive, SpriteGlow},
+        light::{Light2d, LightShape2d, Lighting2d, Occluder2d},
+        material::{Material2d, MaterialSpriteBundle, MaterialSpritePlugin},
+        panel::{Anchor, ClipRect, NineSlice, Panel, PanelBundle},
+        widget::{
+            Button, ButtonBundle, Checkbox, CheckboxBundle, Interaction, Slider, SliderBundle,
+            WidgetColors, WidgetEvent,
+        },
+        FlatSpritePlugin, BASE_QUAD_HANDLE,
+    },
+    tilemap::{bundle::TilemapBundle, FlatTilemapPlugin},
+};
@@ -0,0 +1,138 @@
+use bevy::prelude::Component;
+
+/// How the fragments of an entity are combined with the render target.
+///
+/// Part of the pipeline key of every renderer, entities without it use the default of their renderer.
+#[derive(Component, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Overwrites the target, alpha is ignored.
+    Opaque,
+    /// Straight alpha blending.
+    AlphaBlend,
+    /// Adds the color weighted by alpha, for particles and glow.
+    Additive,
+    /// Multiplies the target by the color, weighted by alpha.
+    ///
+    /// Fragment shaders premultiply their output under [`BlendMode::MULTIPLY_DEF`],
+    /// the blend state then gives `dst * mix(1, color, alpha)`.
+    Multiply,
+    /// Alpha blending for colors already multiplied by their alpha.
+    Premultiplied,
+}
+
+impl BlendMode {
+    pub const ALL: &'static [BlendMode] = &[
+        BlendMode::Opaque,
+        BlendMode::AlphaBlend,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+        BlendMode::Premultiplied,
+    ];
+
+    /// Shader def of the fragment shaders premultiplying their output for [`BlendMode::Multiply`].
+    pub const MULTIPLY_DEF: &'static str = "BLEND_MULTIPLY";
+
+    /// Shader defs of the fragment stage drawing with this mode.
+    pub fn shader_defs(&self) -> Vec<String> {
+        match self {
+            BlendMode::Multiply => vec![Self::MULTIPLY_DEF.to_string()],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn blend_state(&self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Opaque => wgpu::BlendState::REPLACE,
+            BlendMode::AlphaBlend => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+            BlendMode::Multiply => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Dst,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+            BlendMode::Premultiplied => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        !matches!(self, BlendMode::Opaque)
+    }
+}
+
+/// Whether an entity is drawn in the transparent pass, missing components count as opaque.
+///
+/// Masked entities write depth like opaque ones, so they stay in the opaque pass.
+pub fn is_transparent(blend_mode: Option<&BlendMode>, alpha_mode: Option<&AlphaMode>) -> bool {
+    let blended = blend_mode.map_or(false, BlendMode::is_transparent);
+    let masked = matches!(alpha_mode, Some(AlphaMode::Mask(_)));
+    blended && !masked
+}
+
+/// How the alpha of the fragments is used, on top of the [`BlendMode`].
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub enum AlphaMode {
+    /// Alpha is passed to the blend state.
+    #[default]
+    Blend,
+    /// Fragments with alpha below the cutoff are discarded, the rest are drawn opaque.
+    ///
+    /// For foliage and fences that should not depend on draw order.
+    Mask(f32),
+}
+
+impl AlphaMode {
+    /// Cutoff passed to the shaders, zero disables masking.
+    pub fn cutoff(&self) -> f32 {
+        match self {
+            AlphaMode::Blend => 0.0,
+            AlphaMode::Mask(cutoff) => cutoff.max(f32::EPSILON),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `src * src_factor + dst * dst_factor` of a color channel.
+    fn blend(component: wgpu::BlendComponent, src: f32, src_alpha: f32, dst: f32) -> f32 {
+        let factor = |factor| match factor {
+            wgpu::BlendFactor::Zero => 0.0,
+            wgpu::BlendFactor::One => 1.0,
+            wgpu::BlendFactor::Dst => dst,
+            wgpu::BlendFactor::SrcAlpha => src_alpha,
+            wgpu::BlendFactor::OneMinusSrcAlpha => 1.0 - src_alpha,
+            factor => unimplemented!("{:?}", factor),
+        };
+        assert_eq!(component.operation, wgpu::BlendOperation::Add);
+        src * factor(component.src_factor) + dst * factor(component.dst_factor)
+    }
+
+    #[test]
+    fn multiply_mixes_the_target_by_alpha() {
+        let color = BlendMode::Multiply.blend_state().color;
+        assert_eq!(color.src_factor, wgpu::BlendFactor::Dst);
+        assert_eq!(color.dst_factor, wgpu::BlendFactor::OneMinusSrcAlpha);
+        assert_eq!(BlendMode::Multiply.shader_defs(), vec![BlendMode::MULTIPLY_DEF]);
+
+        // Shaders output `color * alpha`, the target becomes `dst * mix(1, color, alpha)`
+        let multiply = |src: f32, alpha: f32, dst: f32| blend(color, src * alpha, alpha, dst);
+        assert!((multiply(0.2, 1.0, 0.8) - 0.16).abs() < 1e-6);
+        assert!((multiply(0.2, 0.5, 0.8) - 0.48).abs() < 1e-6);
+        assert!((multiply(0.2, 0.0, 0.8) - 0.8).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::{Component, Entity, GlobalTransform, Mat4, Quat, Query, Res, ResMut, Vec3};
+
+use crate::render::resource::component_uniform::{
+    ComponentUniformSlots, ComponentUniforms, ModelUniform,
+};
+
+use super::component::{Camera, ExtractedVisibility};
+
+///
+/// Turns the model matrix of the entity towards the active camera,
+/// the rotation of the entity itself is ignored.
+///
+/// Meant for quads facing +Z, such as sprites, health bars and impostors in 3D scenes.
+///
+/// The model matrix is shared by all views, billboards face a single camera,
+/// see [`billboard_camera`]. With split-screen or several active cameras they face
+/// that camera in every view, keep one billboard entity per view with `RenderLayers` instead.
+/// With the `push_constants` feature the matrix is pushed per view and they face each camera.
+///
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Billboard {
+    /// Faces the camera plane on all axes.
+    #[default]
+    Full,
+    /// Only rotates around the world Y axis, stays upright.
+    AxisY,
+}
+
+impl Billboard {
+    pub fn rotation(&self, position: Vec3, camera: &GlobalTransform) -> Quat {
+        match self {
+            Billboard::Full => camera.to_scale_rotation_translation().1,
+            Billboard::AxisY => {
+                let to_camera = camera.translation() - position;
+                Quat::from_rotation_y(to_camera.x.atan2(to_camera.z))
+            }
+        }
+    }
+
+    /// Model matrix of `transform` turned towards `camera`, keeps its scale and translation.
+    pub fn model(&self, transform: &GlobalTransform, camera: &GlobalTransform) -> Mat4 {
+        let (scale, _, translation) = transform.to_scale_rotation_translation();
+        let rotation = self.rotation(translation, camera);
+        Mat4::from_scale_rotation_translation(scale, rotation, translation)
+    }
+}
+
+/// Camera the billboards face, the active camera with the lowest entity so the choice is stable.
+pub fn billboard_camera<'a>(
+    cameras: impl Iterator<Item = (Entity, &'a Camera, &'a GlobalTransform)>,
+) -> Option<&'a GlobalTransform> {
+    cameras
+        .filter(|(_, camera, _)| camera.is_active)
+        .min_by_key(|(entity, _, _)| *entity)
+        .map(|(_, _, transform)| transform)
+}
+
+/// Overwrites the [`ModelUniform`] slot of [`Billboard`] entities every frame,
+/// runs after the regular model uniforms are prepared so it replaces their value.
+pub fn prepare_billboard_model_uniforms(
+    mut model_uniforms: ResMut<ComponentUniforms<ModelUniform>>,
+    model_slots: Res<ComponentUniformSlots<ModelUniform>>,
+    cameras: Query<(Entity, &Camera, &GlobalTransform)>,
+    query: Query<(Entity, &Billboard, &GlobalTransform, Option<&ExtractedVisibility>)>,
+) {
+    let Some(camera_transform) = billboard_camera(cameras.iter()) else {
+        return;
+    };
+
+    for (entity, billboard, global_transform, computed_visibility) in query.iter() {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        let Some(offset) = model_slots.get(entity) else {
+            continue;
+        };
+        let model = billboard.model(global_transform, camera_transform);
+        model_uniforms.set(offset, ModelUniform::new(model));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn billboards_face_the_first_active_camera() {
+        let inactive = Camera {
+            is_active: false,
+            ..Default::default()
+        };
+        let (active, other) = (Camera::default(), Camera::default());
+        let transforms = [
+            GlobalTransform::from_xyz(1.0, 0.0, 0.0),
+            GlobalTransform::from_xyz(2.0, 0.0, 0.0),
+            GlobalTransform::from_xyz(3.0, 0.0, 0.0),
+        ];
+        let cameras = [
+            (Entity::from_raw(2), &other, &transforms[2]),
+            (Entity::from_raw(0), &inactive, &transforms[0]),
+            (Entity::from_raw(1), &active, &transforms[1]),
+        ];
+
+        let faced = billboard_camera(cameras.into_iter()).unwrap();
+        assert_eq!(faced.translation(), Vec3::new(2.0, 0.0, 0.0));
+
+        // Upright billboards turn around Y towards that camera only
+        let rotation = Billboard::AxisY.rotation(Vec3::ZERO, faced);
+        assert!((rotation * Vec3::Z).abs_diff_eq(Vec3::X, 1e-5));
+    }
+}
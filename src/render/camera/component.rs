@@ -1,10 +1,16 @@
 use bevy::{
-    prelude::{Bundle, Component, Entity, GlobalTransform, Handle, Transform, Mat4},
+    log::debug,
+    prelude::{Assets, Bundle, Component, Entity, GlobalTransform, Handle, Transform, Mat4},
     window::WindowId,
 };
 use encase::ShaderType;
 
-use crate::render::{texture::Image, view::window::PreparedWindows, RenderAssets, resource::uniform::HandleGpuUniform};
+use crate::render::{
+    color::ColorSpace, extract::ExtractComponent, resource::uniform::HandleGpuUniform,
+    texture::Image, view::window::PreparedWindows, RenderAssets,
+};
+
+use super::fog::{Fog, FogUniform};
 
 #[derive(Bundle, Default)]
 pub struct CameraBundle<P: Projection> {
@@ -16,7 +22,7 @@ pub struct CameraBundle<P: Projection> {
     // pub render_layers: RenderLayers,
 }
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum RenderTarget {
     Image(Handle<Image>),
     Window(WindowId),
@@ -44,24 +50,56 @@ impl RenderTarget {
         }
     }
 
-    pub fn get_view<'a>(
+    /// Cameras are encoded in ascending order, images before windows.
+    pub fn encode_order(&self) -> u8 {
+        match self {
+            RenderTarget::Image(_) => 0,
+            RenderTarget::Window(_) => 1,
+        }
+    }
+
+    /// Texture behind [`RenderTarget::get_view`], to copy from.
+    pub fn get_texture<'a>(
         &self,
         gpu_textures: &'a RenderAssets<Image>,
         windows: &'a PreparedWindows,
-    ) -> &'a wgpu::TextureView {
+    ) -> Option<&'a wgpu::Texture> {
         match self {
-            RenderTarget::Image(handle) => &gpu_textures.get(&handle.id()).unwrap().view,
+            RenderTarget::Image(handle) => Some(&gpu_textures.get(&handle.id())?.texture),
             RenderTarget::Window(id) => {
-                &windows
-                    .get(id)
-                    .unwrap()
-                    .surface_texture
-                    .as_ref()
-                    .unwrap()
-                    .view
+                Some(&windows.get(id)?.surface_texture.as_ref()?.texture.texture)
             }
         }
     }
+
+    /// Physical size of the target.
+    pub fn get_size(
+        &self,
+        images: &Assets<Image>,
+        windows: &PreparedWindows,
+    ) -> Option<(u32, u32)> {
+        match self {
+            RenderTarget::Image(handle) => images.get(handle).map(|image| {
+                let dim = image.dim();
+                (dim.width, dim.heigth)
+            }),
+            RenderTarget::Window(id) => windows
+                .get(id)
+                .map(|window| (window.physical_width, window.physical_height)),
+        }
+    }
+
+    /// View to render into, `None` while the image is not prepared or the window has no surface.
+    pub fn get_view<'a>(
+        &self,
+        gpu_textures: &'a RenderAssets<Image>,
+        windows: &'a PreparedWindows,
+    ) -> Option<&'a wgpu::TextureView> {
+        match self {
+            RenderTarget::Image(handle) => Some(&gpu_textures.get(&handle.id())?.view),
+            RenderTarget::Window(id) => Some(&windows.get(id)?.surface_texture.as_ref()?.view),
+        }
+    }
 }
 
 pub struct CameraMatrices {
@@ -85,6 +123,15 @@ pub struct Camera {
     pub is_active: bool,
 }
 
+impl Camera {
+    pub fn with_render_target(render_target: RenderTarget) -> Self {
+        Self {
+            render_target,
+            ..Default::default()
+        }
+    }
+}
+
 impl Default for Camera {
     fn default() -> Self {
         Self {
@@ -100,6 +147,12 @@ pub trait Projection: Component {
     fn build_projection_matrix(&self) -> Mat4;
 }
 
+///
+/// Box of the view space that is shown, set from the window size on resize.
+///
+/// Resizing spans the logical window size centered on the camera,
+/// one unit per logical pixel with Y up, near and far are kept.
+///
 #[derive(Component)]
 pub struct OrthographicProjection {
     pub left: f32,
@@ -112,8 +165,11 @@ pub struct OrthographicProjection {
 
 impl Projection for OrthographicProjection {
     fn update(&mut self, width: f32, height: f32) {
-        println!("{} {}", width, height);
-        todo!()
+        debug!("OrthographicProjection resized to {} {}", width, height);
+        self.left = -width / 2.0;
+        self.right = width / 2.0;
+        self.bottom = -height / 2.0;
+        self.top = height / 2.0;
     }
 
     fn build_projection_matrix(&self) -> Mat4 {
@@ -157,9 +213,79 @@ impl Projection for PerspectiveProjection {
     }
 }
 
-#[derive(Component)]
-pub struct Visibility {
-    pub visible: bool,
+///
+/// Whether the entity is drawn, `Inherited` follows the parent so hiding the root
+/// of a hierarchy (a UI panel, a model) hides all of it.
+///
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Visibility {
+    /// Drawn even under a hidden parent.
+    Visible,
+    /// Not drawn, neither are children that inherit.
+    Hidden,
+    /// Drawn if the parent is, roots and entities under parents without `Visibility` are drawn.
+    #[default]
+    Inherited,
+}
+
+impl Visibility {
+    /// `Inherited` when `visible`, `Hidden` otherwise.
+    pub fn shown_if(visible: bool) -> Self {
+        if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        }
+    }
+}
+
+/// Whether any camera sees the entity this frame, written by `visibility_system`.
+///
+/// Render systems read the [`ExtractedVisibility`] copied from it.
+#[derive(Component, Clone, Copy, Default)]
+pub struct ComputedVisibility {
+    pub(super) visible: bool,
+}
+
+impl ComputedVisibility {
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// `true` if the entity was checked and no camera sees it.
+    pub fn is_culled(computed: Option<&Self>) -> bool {
+        computed.map_or(false, |computed| !computed.visible)
+    }
+}
+
+/// [`ComputedVisibility`] of the frame being rendered.
+///
+/// Uniforms are only prepared for visible entities, entities without it
+/// (cameras, or ones spawned this frame) always get theirs.
+#[derive(Component, Clone, Copy, Default)]
+pub struct ExtractedVisibility {
+    visible: bool,
+}
+
+impl ExtractedVisibility {
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// `true` if the entity was checked and no camera sees it.
+    pub fn is_culled(extracted: Option<&Self>) -> bool {
+        extracted.map_or(false, |extracted| !extracted.visible)
+    }
+}
+
+impl ExtractComponent for ComputedVisibility {
+    type Out = ExtractedVisibility;
+
+    fn extract_component(&self) -> ExtractedVisibility {
+        ExtractedVisibility {
+            visible: self.visible,
+        }
+    }
 }
 
 #[derive(Component, Default)]
@@ -175,6 +301,10 @@ impl VisibleEntities {
     pub fn clear(&mut self) {
         self.entities.clear();
     }
+
+    pub fn push(&mut self, entity: Entity) {
+        self.entities.push(entity);
+    }
 }
 
 pub type LayerMask = u32; // 32 layers
@@ -182,7 +312,7 @@ pub type Layer = u8; // In runtime range of 0..31
 const DEFAULT_LAYER: Layer = 1;
 const DEFAULT_LAYER_MASK: LayerMask = 1 << DEFAULT_LAYER;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy)]
 pub struct RenderLayers(LayerMask);
 
 impl Default for RenderLayers {
@@ -251,16 +381,73 @@ pub struct CameraUniforms {
     view_proj: Mat4,
     view: Mat4,
     proj: Mat4,
+    fog: FogUniform,
 }
 
-impl HandleGpuUniform for Camera {
-    type GU = CameraUniforms;
+impl CameraUniforms {
+    pub fn with_fog(mut self, fog: &Fog, color_space: ColorSpace) -> Self {
+        self.fog = FogUniform::new(fog, color_space);
+        self
+    }
+}
 
-    fn into_uniform(&self) -> Self::GU {
-        CameraUniforms {
-            view_proj: self.computed.proj * self.computed.view.inverse(), // NOTE: Why inverse
+/// Matrices of the [`Camera`] for the frame being rendered.
+#[derive(Component, Clone, Copy)]
+pub struct ExtractedCamera {
+    pub view: Mat4,
+    pub proj: Mat4,
+}
+
+impl ExtractComponent for Camera {
+    type Out = ExtractedCamera;
+
+    fn extract_component(&self) -> ExtractedCamera {
+        ExtractedCamera {
             view: self.computed.view,
             proj: self.computed.proj,
         }
     }
-}
\ No newline at end of file
+}
+
+impl HandleGpuUniform for ExtractedCamera {
+    type GU = CameraUniforms;
+
+    fn into_uniform(&self, _color_space: ColorSpace) -> Self::GU {
+        CameraUniforms {
+            view_proj: self.proj * self.view.inverse(), // NOTE: Why inverse
+            view: self.view,
+            proj: self.proj,
+            fog: FogUniform::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Vec2, Vec3};
+
+    use super::*;
+
+    #[test]
+    fn orthographic_resize_spans_the_window() {
+        let mut projection = OrthographicProjection {
+            left: -1.0,
+            right: 1.0,
+            bottom: -1.0,
+            top: 1.0,
+            near: 0.1,
+            far: 100.0,
+        };
+        // A scale factor change reports the new logical size
+        projection.update(640.0, 360.0);
+
+        assert_eq!((projection.left, projection.right), (-320.0, 320.0));
+        assert_eq!((projection.bottom, projection.top), (-180.0, 180.0));
+        assert_eq!((projection.near, projection.far), (0.1, 100.0));
+
+        let corner = projection
+            .build_projection_matrix()
+            .project_point3(Vec3::new(320.0, 180.0, -1.0));
+        assert!(corner.truncate().abs_diff_eq(Vec2::ONE, 1e-5));
+    }
+}
@@ -0,0 +1,88 @@
+use bevy::prelude::{Component, Entity, Query, Res, ResMut, Vec4};
+use encase::ShaderType;
+
+use crate::render::{
+    color::{Color, ColorSpace},
+    resource::{
+        component_uniform::{ComponentUniformSlots, ComponentUniforms},
+        uniform::HandleGpuUniform,
+    },
+};
+
+use super::component::{CameraUniforms, ExtractedCamera};
+
+///
+/// Fades geometry seen by the camera into `color` with the distance from the camera.
+///
+/// Applied by the mesh shaders, set `color` to the sky color to hide the far plane.
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Fog {
+    pub color: Color,
+    pub falloff: FogFalloff,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FogFalloff {
+    /// No fog before `start`, full fog after `end`.
+    Linear { start: f32, end: f32 },
+    /// `1 - e^(-density * distance)`
+    Exponential { density: f32 },
+    /// `1 - e^(-(density * distance)^2)`, keeps the near field clearer than `Exponential`.
+    ExponentialSquared { density: f32 },
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            falloff: FogFalloff::Linear {
+                start: 0.0,
+                end: 100.0,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct FogUniform {
+    color: Vec4,
+    /// 0 is no fog, then linear, exponential and exponential squared
+    mode: u32,
+    start: f32,
+    end: f32,
+    density: f32,
+}
+
+impl FogUniform {
+    pub fn new(fog: &Fog, color_space: ColorSpace) -> Self {
+        let (mode, start, end, density) = match fog.falloff {
+            FogFalloff::Linear { start, end } => (1, start, end, 0.0),
+            FogFalloff::Exponential { density } => (2, 0.0, 0.0, density),
+            FogFalloff::ExponentialSquared { density } => (3, 0.0, 0.0, density),
+        };
+        Self {
+            color: fog.color.as_gpu_vec(color_space),
+            mode,
+            start,
+            end,
+            density,
+        }
+    }
+}
+
+/// Overwrites the [`CameraUniforms`] slot of cameras with a [`Fog`] every frame,
+/// runs after the regular camera uniforms are prepared so it replaces their value.
+pub fn prepare_fog_camera_uniforms(
+    mut camera_uniforms: ResMut<ComponentUniforms<CameraUniforms>>,
+    camera_slots: Res<ComponentUniformSlots<CameraUniforms>>,
+    color_space: Res<ColorSpace>,
+    cameras: Query<(Entity, &ExtractedCamera, &Fog)>,
+) {
+    for (entity, camera, fog) in cameras.iter() {
+        if let Some(offset) = camera_slots.get(entity) {
+            let uniform = camera.into_uniform(*color_space);
+            camera_uniforms.set(offset, uniform.with_fog(fog, *color_space));
+        }
+    }
+}
@@ -1,24 +1,56 @@
 use bevy::{
+    hierarchy::Parent,
     prelude::{
-        CoreStage, Entity, EventReader, GlobalTransform, IntoSystemDescriptor, Plugin, Query,
-        SystemLabel, With,
+        Commands, CoreStage, Entity, EventReader, GlobalTransform, IntoSystemDescriptor, Plugin,
+        Query, Res, SystemLabel, With,
     },
-    window::{ModifiesWindows, WindowResized},
+    window::{ModifiesWindows, WindowResized, WindowScaleFactorChanged, Windows},
 };
 
-use self::component::*;
+use crate::render::{
+    extract::{AddExtract, ExtractedTransform},
+    RenderStage,
+};
+
+use self::{
+    billboard::prepare_billboard_model_uniforms, component::*, fog::prepare_fog_camera_uniforms,
+    pixel_perfect::snap_pixel_perfect_cameras,
+};
 
-use super::resource::component_uniform::AddComponentUniform;
+use super::resource::component_uniform::{
+    prepare_stable_component_uniforms, AddComponentUniform,
+};
 
+pub mod billboard;
 pub mod component;
+pub mod fog;
+pub mod pixel_perfect;
 
 pub struct FlatCameraPlugin;
 impl Plugin for FlatCameraPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_projection_systems::<OrthographicProjection>()
             .add_projection_systems::<PerspectiveProjection>()
-            .add_component_uniform::<Camera>()
-            .add_system_to_stage(CoreStage::PostUpdate, visibility_system);
+            .add_extract_component::<Camera>()
+            .add_extract_component::<ComputedVisibility>()
+            .add_stable_component_uniform::<ExtractedCamera>()
+            .add_system_to_stage(CoreStage::PostUpdate, visibility_system)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                snap_pixel_perfect_cameras
+                    .after(update_camera_values::<OrthographicProjection>)
+                    .after(update_camera_values::<PerspectiveProjection>),
+            )
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_billboard_model_uniforms
+                    .after(prepare_stable_component_uniforms::<ExtractedTransform>),
+            )
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_fog_camera_uniforms
+                    .after(prepare_stable_component_uniforms::<ExtractedCamera>),
+            );
     }
 }
 
@@ -43,22 +75,32 @@ impl AddProjectionSystems for bevy::prelude::App {
     }
 }
 
+///
+/// Projections use the logical size of the window,
+/// so a scale factor override (`Window::set_scale_factor_override`) scales what they show
+/// independently of the OS DPI setting.
+///
 pub fn update_projections_on_window_resize<P: Projection>(
-    mut events: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut resized_events: EventReader<WindowResized>,
+    mut scale_factor_events: EventReader<WindowScaleFactorChanged>,
     mut query: Query<(&Camera, &mut P)>,
 ) {
-    for WindowResized {
-        id: window_id,
-        width,
-        height,
-    } in events.iter()
-    {
-        if *width <= 0.0 || *height <= 0.0 {
+    let changed_windows = resized_events
+        .iter()
+        .map(|event| event.id)
+        .chain(scale_factor_events.iter().map(|event| event.id));
+    for window_id in changed_windows {
+        let Some(window) = windows.get(window_id) else {
+            continue;
+        };
+        let (width, height) = (window.width(), window.height());
+        if width <= 0.0 || height <= 0.0 {
             continue;
         }
         for (camera, mut proj) in query.iter_mut() {
-            if camera.render_target.holds_window(*window_id) {
-                proj.update(*width, *height);
+            if camera.render_target.holds_window(window_id) {
+                proj.update(width, height);
             }
         }
     }
@@ -71,15 +113,64 @@ pub fn update_camera_values<P: Projection>(mut query: Query<(&mut Camera, &Globa
     }
 }
 
+/// Whether the entity is drawn once the `Inherited` visibilities up its hierarchy are resolved.
+fn visible_in_hierarchy(
+    entity: Entity,
+    visibilities: &Query<(Option<&Visibility>, Option<&Parent>)>,
+) -> bool {
+    let mut entity = entity;
+    loop {
+        let Ok((visibility, parent)) = visibilities.get(entity) else {
+            return true;
+        };
+        match visibility.copied().unwrap_or_default() {
+            Visibility::Visible => return true,
+            Visibility::Hidden => return false,
+            Visibility::Inherited => match parent {
+                Some(parent) => entity = parent.get(),
+                None => return true,
+            },
+        }
+    }
+}
+
 pub fn visibility_system(
-    entities: Query<(Entity, &Visibility, Option<&RenderLayers>)>,
+    mut commands: Commands,
+    mut entities: Query<
+        (
+            Entity,
+            Option<&RenderLayers>,
+            Option<&mut ComputedVisibility>,
+        ),
+        With<Visibility>,
+    >,
+    hierarchy: Query<(Option<&Visibility>, Option<&Parent>)>,
     mut cameras: Query<(Option<&RenderLayers>, &mut VisibleEntities), With<Camera>>,
 ) {
-    for (entity, visibility, entity_layers) in entities.iter() {
-        if !visibility.visible { continue; }
-        for (camera_layers, mut visible_entities) in cameras.iter_mut() {
-            if layers_intersect(entity_layers, camera_layers) {
-                visible_entities.entities.push(entity);
+    for (_, mut visible_entities) in cameras.iter_mut() {
+        visible_entities.clear();
+    }
+    for (entity, entity_layers, computed_visibility) in entities.iter_mut() {
+        let mut visible = false;
+        if visible_in_hierarchy(entity, &hierarchy) {
+            for (camera_layers, mut visible_entities) in cameras.iter_mut() {
+                if layers_intersect(entity_layers, camera_layers) {
+                    visible_entities.entities.push(entity);
+                    visible = true;
+                }
+            }
+        }
+        match computed_visibility {
+            // Only written on change, it is extracted every time it changes
+            Some(mut computed_visibility) => {
+                if computed_visibility.visible != visible {
+                    computed_visibility.visible = visible;
+                }
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(ComputedVisibility { visible });
             }
         }
     }
@@ -0,0 +1,46 @@
+use bevy::prelude::{Component, Query, Vec4};
+
+use super::component::Camera;
+
+///
+/// Snaps the rendered view of the camera to whole texels, so pixel art does not shimmer
+/// while the camera moves by fractions of a texel.
+///
+/// Only the view is snapped, the camera's `Transform` keeps its exact position.
+/// Meant for cameras looking down the Z axis.
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PixelPerfect {
+    /// Texels of the sprites per world unit.
+    pub pixels_per_unit: f32,
+}
+
+impl Default for PixelPerfect {
+    fn default() -> Self {
+        Self {
+            pixels_per_unit: 1.0,
+        }
+    }
+}
+
+impl PixelPerfect {
+    pub fn snap(&self, value: f32) -> f32 {
+        (value * self.pixels_per_unit).round() / self.pixels_per_unit
+    }
+}
+
+/// Runs after the camera matrices are computed from the `GlobalTransform`.
+pub fn snap_pixel_perfect_cameras(mut cameras: Query<(&mut Camera, &PixelPerfect)>) {
+    for (mut camera, pixel_perfect) in cameras.iter_mut() {
+        if pixel_perfect.pixels_per_unit <= 0.0 {
+            continue;
+        }
+        let translation = camera.computed.view.w_axis;
+        camera.computed.view.w_axis = Vec4::new(
+            pixel_perfect.snap(translation.x),
+            pixel_perfect.snap(translation.y),
+            translation.z,
+            translation.w,
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use bevy::{
+    log::info,
+    prelude::{EventReader, Input, KeyCode, Res, ResMut, Resource},
+};
+
+/// Send to capture the next rendered frame with an attached GPU debugger, e.g. RenderDoc.
+pub struct CaptureNextFrame;
+
+///
+/// Brackets the submission of one frame with `RenderDevice::start_capture`/`stop_capture`
+/// when a [`CaptureNextFrame`] is sent or the hotkey is pressed.
+///
+/// Captures only do something when the app is launched from a GPU debugger.
+///
+#[derive(Resource, Default)]
+pub struct FrameCapture {
+    pub hotkey: Option<KeyCode>,
+    requested: bool,
+}
+
+impl FrameCapture {
+    pub fn with_hotkey(hotkey: KeyCode) -> Self {
+        Self {
+            hotkey: Some(hotkey),
+            requested: false,
+        }
+    }
+
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Takes the request of the frame being rendered.
+    pub(crate) fn take_request(&mut self) -> bool {
+        std::mem::take(&mut self.requested)
+    }
+}
+
+pub fn request_frame_capture(
+    mut frame_capture: ResMut<FrameCapture>,
+    mut capture_events: EventReader<CaptureNextFrame>,
+    keys: Option<Res<Input<KeyCode>>>,
+) {
+    let hotkey_pressed = match (frame_capture.hotkey, keys) {
+        (Some(hotkey), Some(keys)) => keys.just_pressed(hotkey),
+        _ => false,
+    };
+    if capture_events.iter().count() > 0 || hotkey_pressed {
+        info!("Capturing next frame");
+        frame_capture.request();
+    }
+}
@@ -0,0 +1,37 @@
+use bevy::{
+    ecs::system::RemovedComponents,
+    prelude::{App, Component, Entity, ResMut, Resource},
+};
+
+use super::RenderStage;
+
+///
+/// Render state kept across frames per entity, GPU buffers and bind groups of a trail,
+/// bundles recorded for a camera, ...
+///
+/// Systems usually rebuild it only for the entities they query,
+/// the entries of despawned entities would stay forever without a cleanup.
+///
+pub trait EntityRenderState: Resource {
+    fn remove_entity(&mut self, entity: Entity);
+}
+
+pub trait AddEntityCleanup {
+    /// Removes the entries of `R` in `RenderStage::Cleanup` for entities that lost `C` this frame,
+    /// despawned entities included.
+    fn add_entity_cleanup<R: EntityRenderState, C: Component>(&mut self) -> &mut Self;
+}
+impl AddEntityCleanup for App {
+    fn add_entity_cleanup<R: EntityRenderState, C: Component>(&mut self) -> &mut Self {
+        self.add_system_to_stage(RenderStage::Cleanup, cleanup_entity_render_state::<R, C>)
+    }
+}
+
+pub fn cleanup_entity_render_state<R: EntityRenderState, C: Component>(
+    mut state: ResMut<R>,
+    removed: RemovedComponents<C>,
+) {
+    for entity in removed.iter() {
+        state.remove_entity(entity);
+    }
+}
@@ -1,13 +1,186 @@
-use bevy::prelude::{Vec4, Component};
+use std::fmt;
+
+use bevy::prelude::{Component, Resource, Vec4};
 use encase::ShaderType;
 
 use super::resource::uniform::HandleGpuUniform;
 
-#[derive(Component, Clone, Copy)]
+/// sRGB color with straight alpha, components in `0.0..=1.0`.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
 pub struct Color(pub f32, pub f32, pub f32, pub f32);
 
 impl Color {
-    pub const WHITE: Color = Color(0.0, 0.0, 0.0, 1.0);
+    pub const BLACK: Color = Color(0.0, 0.0, 0.0, 1.0);
+    pub const WHITE: Color = Color(1.0, 1.0, 1.0, 1.0);
+    pub const GRAY: Color = Color(0.5, 0.5, 0.5, 1.0);
+    pub const RED: Color = Color(1.0, 0.0, 0.0, 1.0);
+    pub const GREEN: Color = Color(0.0, 1.0, 0.0, 1.0);
+    pub const BLUE: Color = Color(0.0, 0.0, 1.0, 1.0);
+    pub const YELLOW: Color = Color(1.0, 1.0, 0.0, 1.0);
+    pub const CYAN: Color = Color(0.0, 1.0, 1.0, 1.0);
+    pub const MAGENTA: Color = Color(1.0, 0.0, 1.0, 1.0);
+    pub const ORANGE: Color = Color(1.0, 0.65, 0.0, 1.0);
+    pub const PURPLE: Color = Color(0.5, 0.0, 0.5, 1.0);
+    pub const NONE: Color = Color(0.0, 0.0, 0.0, 0.0);
+
+    /// Vertex color leaving the texture unchanged, the sprite and mesh shaders
+    /// add the vertex color to the texture color.
+    pub const NO_TINT: Color = Color(0.0, 0.0, 0.0, 1.0);
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self(r, g, b, 1.0)
+    }
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self(r, g, b, a)
+    }
+
+    pub fn rgb_u8(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba_u8(r, g, b, u8::MAX)
+    }
+
+    pub fn rgba_u8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        )
+    }
+
+    /// Parses `RGB`, `RGBA`, `RRGGBB` or `RRGGBBAA`, with or without a leading `#`.
+    pub fn hex(hex: &str) -> Result<Self, HexColorError> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(HexColorError::Digit(c));
+        }
+        let digit = |i: usize| (hex.as_bytes()[i] as char).to_digit(16).unwrap() as u8;
+        let byte = |i: usize| digit(i) << 4 | digit(i + 1);
+        let short = |i: usize| digit(i) * 0x11;
+
+        match hex.len() {
+            3 => Ok(Self::rgb_u8(short(0), short(1), short(2))),
+            4 => Ok(Self::rgba_u8(short(0), short(1), short(2), short(3))),
+            6 => Ok(Self::rgb_u8(byte(0), byte(2), byte(4))),
+            8 => Ok(Self::rgba_u8(byte(0), byte(2), byte(4), byte(6))),
+            len => Err(HexColorError::Length(len)),
+        }
+    }
+
+    /// `hue` in degrees, `saturation` and `lightness` in `0.0..=1.0`.
+    pub fn hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        Self::hsla(hue, saturation, lightness, 1.0)
+    }
+
+    pub fn hsla(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Self {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Self::from_hue_chroma(hue, chroma, lightness - chroma / 2.0, alpha)
+    }
+
+    /// `hue` in degrees, `saturation` and `value` in `0.0..=1.0`.
+    pub fn hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        Self::hsva(hue, saturation, value, 1.0)
+    }
+
+    pub fn hsva(hue: f32, saturation: f32, value: f32, alpha: f32) -> Self {
+        let chroma = value * saturation;
+        Self::from_hue_chroma(hue, chroma, value - chroma, alpha)
+    }
+
+    fn from_hue_chroma(hue: f32, chroma: f32, min: f32, alpha: f32) -> Self {
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        Self(r + min, g + min, b + min, alpha)
+    }
+
+    /// `[hue, saturation, lightness, alpha]`, hue in degrees.
+    pub fn as_hsla(&self) -> [f32; 4] {
+        let (hue, max, min) = self.hue_max_min();
+        let lightness = (max + min) / 2.0;
+        let saturation = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        };
+        [hue, saturation, lightness, self.3]
+    }
+
+    /// `[hue, saturation, value, alpha]`, hue in degrees.
+    pub fn as_hsva(&self) -> [f32; 4] {
+        let (hue, max, min) = self.hue_max_min();
+        let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+        [hue, saturation, max, self.3]
+    }
+
+    fn hue_max_min(&self) -> (f32, f32, f32) {
+        let Color(r, g, b, _) = *self;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let chroma = max - min;
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+        (hue, max, min)
+    }
+
+    /// Components as written to uniforms and vertex buffers, given in `color_space`.
+    pub fn as_gpu_vec(&self, color_space: ColorSpace) -> Vec4 {
+        match color_space {
+            ColorSpace::Srgb => self.as_linear().as_vec(),
+            ColorSpace::Linear => self.as_vec(),
+        }
+    }
+
+    pub fn as_gpu_arr(&self, color_space: ColorSpace) -> [f32; 4] {
+        self.as_gpu_vec(color_space).to_array()
+    }
+
+    /// Linear RGB components, alpha unchanged.
+    pub fn as_linear(&self) -> Color {
+        Color(
+            srgb_to_linear(self.0),
+            srgb_to_linear(self.1),
+            srgb_to_linear(self.2),
+            self.3,
+        )
+    }
+
+    pub fn as_linear_arr(&self) -> [f32; 4] {
+        self.as_linear().as_arr()
+    }
+
+    /// Color from linear RGB components.
+    pub fn from_linear(linear: Color) -> Color {
+        Color(
+            linear_to_srgb(linear.0),
+            linear_to_srgb(linear.1),
+            linear_to_srgb(linear.2),
+            linear.3,
+        )
+    }
+
+    /// Component-wise interpolation in sRGB, `t` is not clamped.
+    pub fn lerp(&self, other: Color, t: f32) -> Color {
+        Color::from(self.as_vec().lerp(other.as_vec(), t))
+    }
+
+    pub fn with_alpha(&self, alpha: f32) -> Color {
+        Color(self.0, self.1, self.2, alpha)
+    }
 
     pub fn as_vec(&self) -> Vec4 {
         Vec4::new(self.0, self.1, self.2, self.3)
@@ -38,6 +211,71 @@ impl Color {
     }
 }
 
+impl From<Vec4> for Color {
+    fn from(v: Vec4) -> Self {
+        Color(v.x, v.y, v.z, v.w)
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Color(r, g, b, a)
+    }
+}
+
+///
+/// Space the components of every [`Color`] are given in.
+///
+/// Shaders blend and write linear values, the sRGB surface encodes them on output,
+/// so sRGB colors are converted to linear when written to the GPU. This matches the colors
+/// picked in design tools. Set it to `Linear` if the colors are already linear.
+///
+/// Read when uniforms are prepared and when meshes are uploaded,
+/// meshes uploaded before a change keep the previous conversion.
+///
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    Linear,
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexColorError {
+    /// Number of hex digits, expected 3, 4, 6 or 8.
+    Length(usize),
+    Digit(char),
+}
+
+impl fmt::Display for HexColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexColorError::Length(len) => {
+                write!(f, "expected 3, 4, 6 or 8 hex digits, found {}", len)
+            }
+            HexColorError::Digit(c) => write!(f, "invalid hex digit '{}'", c),
+        }
+    }
+}
+
+impl std::error::Error for HexColorError {}
+
 #[derive(Clone, ShaderType)]
 pub struct ColorUniform {
     color: Vec4,
@@ -46,9 +284,52 @@ pub struct ColorUniform {
 impl HandleGpuUniform for Color {
     type GU = ColorUniform;
 
-    fn into_uniform(&self) -> Self::GU {
+    fn into_uniform(&self, color_space: ColorSpace) -> Self::GU {
         ColorUniform {
-            color: self.as_vec(),
+            color: self.as_gpu_vec(color_space),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Color, b: Color) {
+        assert!((a.as_vec() - b.as_vec()).abs().max_element() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn parse_hex() {
+        assert_eq!(Color::hex("#ff0000"), Ok(Color::RED));
+        assert_eq!(Color::hex("0f0"), Ok(Color::GREEN));
+        assert_eq!(Color::hex("0000ff80").unwrap().a(), 128.0 / 255.0);
+        assert_eq!(Color::hex("#12345"), Err(HexColorError::Length(5)));
+        assert_eq!(Color::hex("#gg0000"), Err(HexColorError::Digit('g')));
+    }
+
+    #[test]
+    fn hsl_hsv_round_trip() {
+        assert_close(Color::hsl(0.0, 1.0, 0.5), Color::RED);
+        assert_close(Color::hsv(240.0, 1.0, 1.0), Color::BLUE);
+
+        let color = Color::rgb(0.2, 0.6, 0.4);
+        let [h, s, l, a] = color.as_hsla();
+        assert_close(Color::hsla(h, s, l, a), color);
+        let [h, s, v, a] = color.as_hsva();
+        assert_close(Color::hsva(h, s, v, a), color);
+    }
+
+    #[test]
+    fn linear_round_trip() {
+        let color = Color::rgb(0.2, 0.6, 0.9);
+        assert_close(Color::from_linear(color.as_linear()), color);
+    }
+
+    #[test]
+    fn gpu_components_follow_the_color_space() {
+        let color = Color::rgb(0.2, 0.6, 0.9);
+        assert_eq!(color.as_gpu_vec(ColorSpace::Linear), color.as_vec());
+        assert_eq!(color.as_gpu_vec(ColorSpace::Srgb), color.as_linear().as_vec());
+    }
+}
@@ -0,0 +1,346 @@
+use std::{any::TypeId, marker::PhantomData};
+
+use bevy::{
+    ecs::query::{QueryEntityError, QueryState, ROQueryItem, ReadOnlyWorldQuery},
+    prelude::{
+        Added, App, Commands, Component, CoreStage, Entity, FromWorld, Handle,
+        IntoSystemDescriptor, Mut, Query, Res, Resource, World,
+    },
+    utils::HashMap,
+};
+
+use super::{
+    mesh::{GpuMeshAssembly, Mesh},
+    resource::buffer::MeshVertex,
+    stats::RenderStats,
+    system::{RenderFunctionId, RenderFunctions, RenderResult},
+    RenderAssets, RenderStage,
+};
+
+///
+/// Reusable step of drawing an entity: setting a pipeline, binding a group, drawing a mesh.
+///
+/// Tuples of commands run in order and stop at the first [`RenderResult::Failure`],
+/// a registered command is called like any other render function.
+///
+pub trait RenderCommand: 'static {
+    /// Called on registration, sets up the [`RenderCommandState`]s the command reads.
+    fn init(_app: &mut App) {}
+
+    fn render<'w>(
+        camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult;
+}
+
+macro_rules! impl_render_command_tuple {
+    ($($C:ident),*) => {
+        impl<$($C: RenderCommand),*> RenderCommand for ($($C,)*) {
+            fn init(app: &mut App) {
+                $($C::init(app);)*
+            }
+
+            fn render<'w>(
+                camera: Entity,
+                object: Entity,
+                world: &'w World,
+                render_pass: &mut wgpu::RenderPass<'w>,
+            ) -> RenderResult {
+                $(
+                    if let RenderResult::Failure(reason) =
+                        $C::render(camera, object, world, render_pass)
+                    {
+                        return RenderResult::Failure(reason);
+                    }
+                )*
+                RenderResult::Success
+            }
+        }
+    };
+}
+
+impl_render_command_tuple!(C0);
+impl_render_command_tuple!(C0, C1);
+impl_render_command_tuple!(C0, C1, C2);
+impl_render_command_tuple!(C0, C1, C2, C3);
+impl_render_command_tuple!(C0, C1, C2, C3, C4);
+impl_render_command_tuple!(C0, C1, C2, C3, C4, C5);
+impl_render_command_tuple!(C0, C1, C2, C3, C4, C5, C6);
+impl_render_command_tuple!(C0, C1, C2, C3, C4, C5, C6, C7);
+
+/// Ids of the registered [`RenderCommand`]s, look them up to fill a `RenderFunctionId`.
+#[derive(Resource, Default)]
+pub struct DrawFunctions {
+    ids: HashMap<TypeId, RenderFunctionId>,
+}
+
+impl DrawFunctions {
+    pub fn id<C: RenderCommand>(&self) -> Option<RenderFunctionId> {
+        self.ids.get(&TypeId::of::<C>()).copied()
+    }
+}
+
+///
+/// Draws the entity with the registered command `C`,
+/// its [`RenderFunctionId`] is inserted in `CoreStage::PostUpdate`.
+///
+/// Lets plugins spawn renderable entities without knowing the id their command got.
+///
+#[derive(Component)]
+pub struct RenderWith<C: RenderCommand>(PhantomData<fn() -> C>);
+
+impl<C: RenderCommand> Default for RenderWith<C> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+pub fn assign_render_function_ids<C: RenderCommand>(
+    mut commands: Commands,
+    draw_functions: Res<DrawFunctions>,
+    query: Query<Entity, Added<RenderWith<C>>>,
+) {
+    let Some(id) = draw_functions.id::<C>() else {
+        return;
+    };
+    for entity in query.iter() {
+        commands.entity(entity).insert(id);
+    }
+}
+
+///
+/// Query state cached across frames for render commands,
+/// its archetypes are refreshed at the start of `RenderStage::Render` so entities
+/// spawned or changed since the last frame are matched.
+///
+#[derive(Resource)]
+pub struct RenderCommandState<Q: ReadOnlyWorldQuery + 'static> {
+    state: QueryState<Q>,
+}
+
+impl<Q: ReadOnlyWorldQuery + 'static> FromWorld for RenderCommandState<Q> {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            state: QueryState::new(world),
+        }
+    }
+}
+
+impl<Q: ReadOnlyWorldQuery + 'static> RenderCommandState<Q> {
+    pub fn get<'w>(
+        &self,
+        world: &'w World,
+        entity: Entity,
+    ) -> Result<ROQueryItem<'w, Q>, QueryEntityError> {
+        self.state.get_manual(world, entity)
+    }
+}
+
+pub fn update_render_command_state<Q: ReadOnlyWorldQuery + 'static>(world: &mut World) {
+    world.resource_scope(|world, mut command_state: Mut<RenderCommandState<Q>>| {
+        command_state.state.update_archetypes(world);
+    });
+}
+
+pub trait AddRenderCommand {
+    /// Registers the command under a newly allocated id, see [`DrawFunctions::id`].
+    fn add_render_command<C: RenderCommand>(&mut self) -> &mut Self;
+    /// Registers the command under a fixed id, for bundles with a constant render function.
+    fn add_render_command_with_id<C: RenderCommand>(&mut self, id: usize) -> &mut Self;
+    /// Same as [`add_render_command`](AddRenderCommand::add_render_command), returns the id.
+    fn register_render_command<C: RenderCommand>(&mut self) -> RenderFunctionId;
+    /// Inserts the [`RenderCommandState`] of the query once and refreshes it every frame.
+    fn init_render_command_state<Q: ReadOnlyWorldQuery + 'static>(&mut self) -> &mut Self;
+}
+impl AddRenderCommand for App {
+    fn add_render_command<C: RenderCommand>(&mut self) -> &mut Self {
+        self.register_render_command::<C>();
+        self
+    }
+
+    fn add_render_command_with_id<C: RenderCommand>(&mut self, id: usize) -> &mut Self {
+        C::init(self);
+        self.world
+            .resource_mut::<RenderFunctions>()
+            .add(id.into(), C::render);
+        self.world
+            .resource_mut::<DrawFunctions>()
+            .ids
+            .insert(TypeId::of::<C>(), id.into());
+        self.add_system_to_stage(CoreStage::PostUpdate, assign_render_function_ids::<C>)
+    }
+
+    fn register_render_command<C: RenderCommand>(&mut self) -> RenderFunctionId {
+        if let Some(id) = self.world.resource::<DrawFunctions>().id::<C>() {
+            return id;
+        }
+        C::init(self);
+        let id = self
+            .world
+            .resource_mut::<RenderFunctions>()
+            .add_allocated(C::render);
+        self.world
+            .resource_mut::<DrawFunctions>()
+            .ids
+            .insert(TypeId::of::<C>(), id);
+        self.add_system_to_stage(CoreStage::PostUpdate, assign_render_function_ids::<C>);
+        id
+    }
+
+    fn init_render_command_state<Q: ReadOnlyWorldQuery + 'static>(&mut self) -> &mut Self {
+        if self.world.contains_resource::<RenderCommandState<Q>>() {
+            return self;
+        }
+        self.init_resource::<RenderCommandState<Q>>()
+            .add_system_to_stage(
+                RenderStage::Render,
+                update_render_command_state::<Q>.at_start(),
+            )
+    }
+}
+
+/// Binds the vertex and index buffers of the `Handle<Mesh<V>>` of the entity and draws it.
+pub struct DrawMesh<V: MeshVertex>(PhantomData<V>);
+impl<V: MeshVertex> RenderCommand for DrawMesh<V> {
+    fn init(app: &mut App) {
+        app.init_render_command_state::<&'static Handle<Mesh<V>>>();
+    }
+
+    fn render<'w>(
+        _camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(command_state) =
+            world.get_resource::<RenderCommandState<&'static Handle<Mesh<V>>>>()
+        else {
+            return RenderResult::Failure("RenderCommandState resource missing");
+        };
+        let Ok(mesh_handle) = command_state.get(world, object) else {
+            return RenderResult::Failure("no mesh handle");
+        };
+        let Some(gpu_meshes) = world.get_resource::<RenderAssets<Mesh<V>>>() else {
+            return RenderResult::Failure("RenderAssets<Mesh<V>> resource missing");
+        };
+        let Some(mesh) = gpu_meshes.get(&mesh_handle.id()) else {
+            return RenderResult::Failure("mesh not prepared yet");
+        };
+
+        let Some(render_stats) = world.get_resource::<RenderStats>() else {
+            return RenderResult::Failure("RenderStats resource missing");
+        };
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        let instance_count = 1;
+        match &mesh.assembly {
+            GpuMeshAssembly::Indexed {
+                index_buffer,
+                index_count,
+                index_format,
+            } => {
+                render_pass.set_index_buffer(index_buffer.slice(..), *index_format);
+                render_pass.draw_indexed(0..*index_count as u32, 0, 0..instance_count);
+                render_stats.draw(*index_count as u32, instance_count);
+            }
+            GpuMeshAssembly::NonIndexed { vertex_count } => {
+                render_pass.draw(0..*vertex_count as u32, 0..instance_count);
+                render_stats.draw(*vertex_count as u32, instance_count);
+            }
+        }
+
+        RenderResult::Success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::GlobalTransform;
+
+    use super::*;
+    use crate::render::{
+        camera::component::{Camera, VisibleEntities},
+        phase::{queue_render_phase, Opaque, RenderOrdering, RenderPhase},
+        render_bundle::RenderBundles,
+    };
+
+    #[derive(Component)]
+    struct Marker;
+
+    #[derive(Component)]
+    struct Other;
+
+    struct Noop;
+    impl RenderCommand for Noop {
+        fn render<'w>(
+            _camera: Entity,
+            _object: Entity,
+            _world: &'w World,
+            _render_pass: &mut wgpu::RenderPass<'w>,
+        ) -> RenderResult {
+            RenderResult::Success
+        }
+    }
+
+    #[test]
+    fn render_with_overrides_the_queued_render_function() {
+        let mut app = App::new();
+        app.init_resource::<RenderFunctions>()
+            .init_resource::<DrawFunctions>()
+            .init_resource::<RenderBundles>()
+            .init_resource::<RenderOrdering>()
+            .add_system_to_stage(CoreStage::Last, queue_render_phase::<Opaque>);
+        let id = app.register_render_command::<Noop>();
+
+        let fixed: RenderFunctionId = 0usize.into();
+        let entity = app
+            .world
+            .spawn((
+                fixed,
+                RenderWith::<Noop>::default(),
+                GlobalTransform::default(),
+            ))
+            .id();
+        let mut visible_entities = VisibleEntities::default();
+        visible_entities.push(entity);
+        let camera = app
+            .world
+            .spawn((
+                Camera::default(),
+                visible_entities,
+                RenderPhase::<Opaque>::default(),
+            ))
+            .id();
+
+        app.update();
+
+        assert_eq!(app.world.get::<RenderFunctionId>(entity), Some(&id));
+        let phase = app.world.get::<RenderPhase<Opaque>>(camera).unwrap();
+        let queued: Vec<(Entity, RenderFunctionId)> = phase
+            .items
+            .iter()
+            .map(|item| (item.entity, item.render_function))
+            .collect();
+        assert_eq!(queued, vec![(entity, id)]);
+        assert_ne!(id, fixed);
+    }
+
+    #[test]
+    fn state_matches_archetypes_spawned_after_the_first_frame() {
+        let mut world = World::new();
+        world.init_resource::<RenderCommandState<&'static Marker>>();
+        let first = world.spawn(Marker).id();
+        update_render_command_state::<&'static Marker>(&mut world);
+
+        let later = world.spawn((Marker, Other)).id();
+        let command_state = world.resource::<RenderCommandState<&'static Marker>>();
+        assert!(command_state.get(&world, first).is_ok());
+        assert!(command_state.get(&world, later).is_err());
+
+        update_render_command_state::<&'static Marker>(&mut world);
+        let command_state = world.resource::<RenderCommandState<&'static Marker>>();
+        assert!(command_state.get(&world, first).is_ok());
+        assert!(command_state.get(&world, later).is_ok());
+    }
+}
@@ -0,0 +1,38 @@
+use bevy::prelude::Resource;
+
+///
+/// Replaces the fragment output of the mesh pipelines, to look at broken meshes,
+/// UVs or depth precision without a graphics debugger.
+///
+/// Part of the mesh pipeline keys, changing it queues the pipelines of the new mode.
+///
+#[derive(Resource, Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum DebugViewMode {
+    #[default]
+    None,
+    /// World space face normals mapped from `[-1, 1]` to `[0, 1]`, meshes have no vertex normals.
+    Normals,
+    /// Texture coordinates in red and green, wrapped to `[0, 1]`.
+    Uvs,
+    /// Value written to the depth buffer, white is near.
+    Depth,
+    /// Adds a little light per fragment without depth testing, bright areas are drawn many times.
+    Overdraw,
+}
+
+impl DebugViewMode {
+    /// `#ifdef` enabled in the mesh shaders.
+    pub fn shader_def(&self) -> Option<&'static str> {
+        match self {
+            DebugViewMode::None => None,
+            DebugViewMode::Normals => Some("DEBUG_NORMALS"),
+            DebugViewMode::Uvs => Some("DEBUG_UVS"),
+            DebugViewMode::Depth => Some("DEBUG_DEPTH"),
+            DebugViewMode::Overdraw => Some("DEBUG_OVERDRAW"),
+        }
+    }
+
+    pub fn shader_defs(&self) -> Vec<String> {
+        self.shader_def().into_iter().map(String::from).collect()
+    }
+}
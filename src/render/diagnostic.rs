@@ -0,0 +1,144 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::{App, Plugin, Res, ResMut},
+};
+
+use super::{
+    mesh::Mesh,
+    resource::buffer::{Vertex, VertexTex3},
+    stats::{RenderCategory, RenderStats},
+    texture::{texture_arr::ImageArray, Image},
+    RenderAssets, RenderStage,
+};
+
+///
+/// Registers the frame time and [`RenderStats`] of the renderer as bevy `Diagnostics`,
+/// add `LogDiagnosticsPlugin` to print them.
+///
+/// Includes bevy's `FrameTimeDiagnosticsPlugin` for fps and frame time.
+///
+pub struct RenderDiagnosticsPlugin;
+impl Plugin for RenderDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .add_startup_system(setup_render_diagnostics)
+            .add_system_to_stage(RenderStage::Cleanup, render_diagnostics_system);
+    }
+}
+
+impl RenderDiagnosticsPlugin {
+    pub const DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D01);
+    pub const INSTANCES: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D02);
+    pub const VERTICES: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D03);
+    pub const PIPELINE_SWITCHES: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D04);
+    pub const BIND_GROUP_SWITCHES: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D05);
+    pub const PREPARED_MESHES: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D06);
+    pub const PREPARED_IMAGES: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D07);
+    pub const DRAW_FAILURES: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D08);
+
+    pub const SPRITE_DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D09);
+    pub const SPRITE_ENTITIES_PER_DRAW: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D0A);
+    pub const SHAPE_DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D0B);
+    pub const SHAPE_ENTITIES_PER_DRAW: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D0C);
+    pub const MESH_DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D0D);
+    pub const MESH_ENTITIES_PER_DRAW: DiagnosticId =
+        DiagnosticId::from_u128(0x5B1D8E1C_6A4F_4C39_9D0B_3E1F7A2C4D0E);
+
+    const MAX_HISTORY_LENGTH: usize = 20;
+
+    /// Draw calls and entities per draw diagnostics of the category.
+    pub fn category_diagnostics(category: RenderCategory) -> (DiagnosticId, DiagnosticId) {
+        match category {
+            RenderCategory::Sprite => (Self::SPRITE_DRAW_CALLS, Self::SPRITE_ENTITIES_PER_DRAW),
+            RenderCategory::Shape => (Self::SHAPE_DRAW_CALLS, Self::SHAPE_ENTITIES_PER_DRAW),
+            RenderCategory::Mesh => (Self::MESH_DRAW_CALLS, Self::MESH_ENTITIES_PER_DRAW),
+        }
+    }
+}
+
+fn setup_render_diagnostics(mut diagnostics: ResMut<Diagnostics>) {
+    for (id, name) in [
+        (RenderDiagnosticsPlugin::DRAW_CALLS, "draw_calls"),
+        (RenderDiagnosticsPlugin::INSTANCES, "instances"),
+        (RenderDiagnosticsPlugin::VERTICES, "vertices"),
+        (RenderDiagnosticsPlugin::PIPELINE_SWITCHES, "pipeline_switches"),
+        (RenderDiagnosticsPlugin::BIND_GROUP_SWITCHES, "bind_group_switches"),
+        (RenderDiagnosticsPlugin::PREPARED_MESHES, "prepared_meshes"),
+        (RenderDiagnosticsPlugin::PREPARED_IMAGES, "prepared_images"),
+        (RenderDiagnosticsPlugin::DRAW_FAILURES, "draw_failures"),
+    ] {
+        diagnostics.add(Diagnostic::new(
+            id,
+            name,
+            RenderDiagnosticsPlugin::MAX_HISTORY_LENGTH,
+        ));
+    }
+    for category in RenderCategory::ALL {
+        let (draw_calls, entities_per_draw) =
+            RenderDiagnosticsPlugin::category_diagnostics(category);
+        diagnostics.add(Diagnostic::new(
+            draw_calls,
+            format!("{}_draw_calls", category.name()),
+            RenderDiagnosticsPlugin::MAX_HISTORY_LENGTH,
+        ));
+        diagnostics.add(Diagnostic::new(
+            entities_per_draw,
+            format!("{}_entities_per_draw", category.name()),
+            RenderDiagnosticsPlugin::MAX_HISTORY_LENGTH,
+        ));
+    }
+}
+
+fn render_diagnostics_system(
+    mut diagnostics: ResMut<Diagnostics>,
+    render_stats: Res<RenderStats>,
+    (gpu_meshes, gpu_meshes_tex3): (
+        Res<RenderAssets<Mesh<Vertex>>>,
+        Res<RenderAssets<Mesh<VertexTex3>>>,
+    ),
+    (gpu_images, gpu_image_arrays): (Res<RenderAssets<Image>>, Res<RenderAssets<ImageArray>>),
+) {
+    let stats = render_stats.last_frame();
+    diagnostics.add_measurement(RenderDiagnosticsPlugin::DRAW_CALLS, || {
+        stats.draw_calls as f64
+    });
+    diagnostics.add_measurement(RenderDiagnosticsPlugin::INSTANCES, || {
+        stats.instances as f64
+    });
+    diagnostics.add_measurement(RenderDiagnosticsPlugin::VERTICES, || stats.vertices as f64);
+    diagnostics.add_measurement(RenderDiagnosticsPlugin::PIPELINE_SWITCHES, || {
+        stats.pipeline_switches as f64
+    });
+    diagnostics.add_measurement(RenderDiagnosticsPlugin::BIND_GROUP_SWITCHES, || {
+        stats.bind_group_switches as f64
+    });
+    diagnostics.add_measurement(RenderDiagnosticsPlugin::PREPARED_MESHES, || {
+        (gpu_meshes.len() + gpu_meshes_tex3.len()) as f64
+    });
+    diagnostics.add_measurement(RenderDiagnosticsPlugin::PREPARED_IMAGES, || {
+        (gpu_images.len() + gpu_image_arrays.len()) as f64
+    });
+    diagnostics.add_measurement(RenderDiagnosticsPlugin::DRAW_FAILURES, || {
+        stats.draw_failures as f64
+    });
+    for category in RenderCategory::ALL {
+        let (draw_calls, entities_per_draw) =
+            RenderDiagnosticsPlugin::category_diagnostics(category);
+        let category_stats = stats.category(category);
+        diagnostics.add_measurement(draw_calls, || category_stats.draw_calls as f64);
+        diagnostics.add_measurement(entities_per_draw, || category_stats.entities_per_draw());
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    log::error,
+    prelude::{App, Entity, EventWriter, Res, Resource},
+    window::WindowId,
+};
+
+use super::{resource::shader::ShaderDefError, RenderDevice};
+
+/// Failures the renderer recovered from by skipping work, sent as an event.
+#[derive(Debug, Clone)]
+pub enum RendererError {
+    /// The window could not be drawn this frame.
+    Surface {
+        window: WindowId,
+        error: wgpu::SurfaceError,
+    },
+    /// Error reported by the device outside of an error scope, e.g. after a driver reset.
+    Device(String),
+    /// The entity could not be drawn by the camera this frame,
+    /// usually because it was spawned this frame and some of its GPU data is not ready.
+    Draw {
+        camera: Entity,
+        entity: Entity,
+        reason: &'static str,
+    },
+    /// A shader of the pipeline could not be preprocessed, the pipeline is never created.
+    Shader {
+        pipeline: wgpu::Label<'static>,
+        error: ShaderDefError,
+    },
+}
+
+/// Device errors collected by the `on_uncaptured_error` handler until they are sent as events.
+#[derive(Resource, Default, Clone)]
+pub struct UncapturedDeviceErrors(Arc<Mutex<Vec<String>>>);
+
+/// Replaces wgpu's default handler, which panics, with one forwarding to [`RendererError`].
+pub(crate) fn handle_device_errors(app: &mut App) {
+    let errors = UncapturedDeviceErrors::default();
+    let handler_errors = errors.clone();
+    app.world
+        .get_resource::<RenderDevice>()
+        .unwrap()
+        .on_uncaptured_error(move |error: wgpu::Error| {
+            handler_errors.0.lock().unwrap().push(error.to_string());
+        });
+    app.insert_resource(errors);
+}
+
+pub fn send_device_errors(
+    errors: Res<UncapturedDeviceErrors>,
+    mut renderer_errors: EventWriter<RendererError>,
+) {
+    for error in errors.0.lock().unwrap().drain(..) {
+        error!("Device error: {}", error);
+        renderer_errors.send(RendererError::Device(error));
+    }
+}
@@ -0,0 +1,149 @@
+use bevy::{
+    ecs::system::RemovedComponents,
+    prelude::{App, Changed, Commands, Component, Entity, GlobalTransform, Query, Res, Resource},
+    time::Time,
+};
+
+use super::{
+    color::ColorSpace,
+    resource::{component_uniform::ModelUniform, uniform::HandleGpuUniform},
+    RenderStage,
+};
+
+///
+/// Render state copied out of a main world resource in `RenderStage::Extract`.
+///
+/// Render systems reading the extracted resource instead of the source
+/// do not depend on the simulation systems that write it.
+///
+/// The engine still renders from a single world. Time, transforms, camera matrices and
+/// visibility are extracted ([`ExtractedTime`], [`ExtractedTransform`], `ExtractedCamera`,
+/// `ExtractedVisibility`), the model and camera uniforms and the culling read them.
+/// Feature specific uniforms and the phases still read the main world components.
+///
+pub trait ExtractResource: Resource + Sized {
+    type Source: Resource;
+
+    fn extract_resource(source: &Self::Source) -> Self;
+}
+
+/// Render state copied out of a component, inserted on the same entity
+/// and removed with the source component.
+pub trait ExtractComponent: Component {
+    type Out: Component;
+
+    fn extract_component(&self) -> Self::Out;
+}
+
+pub trait AddExtract {
+    fn add_extract_resource<R: ExtractResource>(&mut self) -> &mut Self;
+    fn add_extract_component<C: ExtractComponent>(&mut self) -> &mut Self;
+}
+impl AddExtract for App {
+    fn add_extract_resource<R: ExtractResource>(&mut self) -> &mut Self {
+        self.add_system_to_stage(RenderStage::Extract, extract_resource::<R>)
+    }
+
+    fn add_extract_component<C: ExtractComponent>(&mut self) -> &mut Self {
+        self.add_system_to_stage(RenderStage::Extract, extract_components::<C>)
+    }
+}
+
+pub fn extract_resource<R: ExtractResource>(mut commands: Commands, source: Res<R::Source>) {
+    if source.is_changed() {
+        commands.insert_resource(R::extract_resource(&source));
+    }
+}
+
+pub fn extract_components<C: ExtractComponent>(
+    mut commands: Commands,
+    removed: RemovedComponents<C>,
+    query: Query<(Entity, &C), Changed<C>>,
+) {
+    for entity in removed.iter() {
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            entity_commands.remove::<C::Out>();
+        }
+    }
+
+    let extracted: Vec<(Entity, C::Out)> = query
+        .iter()
+        .map(|(entity, component)| (entity, component.extract_component()))
+        .collect();
+    commands.insert_or_spawn_batch(extracted);
+}
+
+/// [`Time`] of the frame being rendered.
+#[derive(Resource, Clone, Copy, Default)]
+pub struct ExtractedTime {
+    pub elapsed_seconds: f32,
+    pub delta_seconds: f32,
+}
+
+impl ExtractResource for ExtractedTime {
+    type Source = Time;
+
+    fn extract_resource(time: &Time) -> Self {
+        Self {
+            elapsed_seconds: time.elapsed_seconds(),
+            delta_seconds: time.delta_seconds(),
+        }
+    }
+}
+
+/// [`GlobalTransform`] of the frame being rendered, the source of the model uniforms.
+#[derive(Component, Clone, Copy)]
+pub struct ExtractedTransform(pub GlobalTransform);
+
+impl ExtractComponent for GlobalTransform {
+    type Out = ExtractedTransform;
+
+    fn extract_component(&self) -> ExtractedTransform {
+        ExtractedTransform(*self)
+    }
+}
+
+impl HandleGpuUniform for ExtractedTransform {
+    type GU = ModelUniform;
+
+    fn into_uniform(&self, color_space: ColorSpace) -> Self::GU {
+        self.0.into_uniform(color_space)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component)]
+    struct Source(u32);
+
+    #[derive(Component, Debug, PartialEq)]
+    struct Extracted(u32);
+
+    impl ExtractComponent for Source {
+        type Out = Extracted;
+
+        fn extract_component(&self) -> Extracted {
+            Extracted(self.0)
+        }
+    }
+
+    #[test]
+    fn extracted_components_follow_the_source() {
+        let mut app = App::new();
+        app.add_system(extract_components::<Source>);
+
+        let entity = app.world.spawn(Source(1)).id();
+        app.update();
+        assert_eq!(app.world.get::<Extracted>(entity), Some(&Extracted(1)));
+
+        app.world.get_mut::<Source>(entity).unwrap().0 = 2;
+        app.update();
+        assert_eq!(app.world.get::<Extracted>(entity), Some(&Extracted(2)));
+
+        app.world.entity_mut(entity).remove::<Source>();
+        app.update();
+        assert_eq!(app.world.get::<Extracted>(entity), None);
+    }
+}
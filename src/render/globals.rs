@@ -0,0 +1,97 @@
+use bevy::prelude::{Assets, Commands, Entity, Local, Query, Res, ResMut, Vec2};
+use encase::ShaderType;
+
+use super::{
+    camera::component::{Camera, RenderTarget},
+    extract::ExtractedTime,
+    resource::{
+        buffer_pool::BufferPool,
+        component_uniform::ComponentUniforms,
+        renderer::{RenderDevice, RenderQueue},
+        uniform::{uniform_buffer_layout_entry, DynamicUniformId},
+    },
+    texture::Image,
+    view::window::PreparedWindows,
+};
+
+///
+/// Values shared by every shader, bound next to the camera in the view bind group:
+///
+/// ```wgsl
+/// struct Globals {
+///     time: f32,
+///     delta_time: f32,
+///     frame_count: u32,
+///     resolution: vec2<f32>,
+/// }
+///
+/// @group(1) @binding(1)
+/// var<uniform> globals: Globals;
+/// ```
+///
+/// Pushed once per camera, `resolution` is the physical size of its render target.
+///
+#[derive(Clone, ShaderType)]
+pub struct GlobalsUniform {
+    /// Seconds since startup.
+    time: f32,
+    delta_time: f32,
+    frame_count: u32,
+    resolution: Vec2,
+}
+
+/// Layout entry of the [`GlobalsUniform`] in a view bind group, bound with a dynamic offset.
+pub fn globals_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    uniform_buffer_layout_entry::<GlobalsUniform>(
+        binding,
+        wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        true,
+    )
+}
+
+pub fn prepare_globals_uniforms(
+    mut commands: Commands,
+    time: Res<ExtractedTime>,
+    windows: Res<PreparedWindows>,
+    images: Res<Assets<Image>>,
+    mut globals_uniforms: ResMut<ComponentUniforms<GlobalsUniform>>,
+    mut frame_count: Local<u32>,
+    cameras: Query<(Entity, &Camera)>,
+) {
+    let mut spawns: Vec<(Entity, DynamicUniformId<GlobalsUniform>)> = Vec::new();
+
+    globals_uniforms.clear();
+    for (entity, camera) in cameras.iter() {
+        let resolution = match &camera.render_target {
+            RenderTarget::Window(id) => windows.get(id).map(|window| {
+                Vec2::new(window.physical_width as f32, window.physical_height as f32)
+            }),
+            RenderTarget::Image(handle) => images.get(handle).map(|image| {
+                let dim = image.dim();
+                Vec2::new(dim.width as f32, dim.heigth as f32)
+            }),
+        };
+        let globals = GlobalsUniform {
+            time: time.elapsed_seconds,
+            delta_time: time.delta_seconds,
+            frame_count: *frame_count,
+            resolution: resolution.unwrap_or(Vec2::ZERO),
+        };
+        spawns.push((entity, globals_uniforms.push(globals).into()));
+    }
+    *frame_count = frame_count.wrapping_add(1);
+
+    for (entity, _) in &spawns {
+        commands.entity(*entity).remove::<DynamicUniformId<GlobalsUniform>>();
+    }
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn queue_globals_uniforms(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffer_pool: ResMut<BufferPool>,
+    mut globals_uniforms: ResMut<ComponentUniforms<GlobalsUniform>>,
+) {
+    globals_uniforms.write_buffer_pooled(&render_device, &render_queue, &mut buffer_pool);
+}
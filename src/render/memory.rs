@@ -0,0 +1,259 @@
+use std::ops::{AddAssign, SubAssign};
+
+use bevy::{
+    asset::HandleId,
+    log::warn,
+    prelude::{Handle, Query, Res, ResMut, Resource},
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    camera::component::ExtractedVisibility, resource::buffer_pool::BufferPool, RenderAsset, RenderAssets,
+    TryNextFrame,
+};
+
+/// Estimated GPU memory in bytes, by kind of resource.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GpuMemory {
+    pub textures: u64,
+    pub meshes: u64,
+    pub buffers: u64,
+}
+
+impl GpuMemory {
+    pub fn texture(bytes: u64) -> Self {
+        Self {
+            textures: bytes,
+            ..Default::default()
+        }
+    }
+
+    pub fn mesh(bytes: u64) -> Self {
+        Self {
+            meshes: bytes,
+            ..Default::default()
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.textures + self.meshes + self.buffers
+    }
+}
+
+impl AddAssign for GpuMemory {
+    fn add_assign(&mut self, rhs: Self) {
+        self.textures += rhs.textures;
+        self.meshes += rhs.meshes;
+        self.buffers += rhs.buffers;
+    }
+}
+
+impl SubAssign for GpuMemory {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.textures = self.textures.saturating_sub(rhs.textures);
+        self.meshes = self.meshes.saturating_sub(rhs.meshes);
+        self.buffers = self.buffers.saturating_sub(rhs.buffers);
+    }
+}
+
+struct TrackedAsset {
+    memory: GpuMemory,
+    last_used: u64,
+}
+
+///
+/// Estimated GPU memory of the prepared render assets and the buffers of the [`BufferPool`].
+///
+/// With a `budget`, crossing it logs a warning. With `evict_over_budget` as well,
+/// the prepared assets used least recently are dropped until the usage fits again,
+/// they are prepared again once a visible entity has their `Handle<T>`.
+/// Assets drawn without such a component have to be marked with [`GpuMemoryStats::mark_used`].
+///
+#[derive(Resource, Default)]
+pub struct GpuMemoryStats {
+    pub budget: Option<u64>,
+    pub evict_over_budget: bool,
+    assets: GpuMemory,
+    buffers: u64,
+    tracked: HashMap<HandleId, TrackedAsset>,
+    evicted: HashSet<HandleId>,
+    evicting: Vec<HandleId>,
+    frame: u64,
+    over_budget: bool,
+}
+
+impl GpuMemoryStats {
+    /// Insert it before the `FlatRenderPlugin` to start with a budget.
+    pub fn with_budget(budget: u64, evict_over_budget: bool) -> Self {
+        Self {
+            budget: Some(budget),
+            evict_over_budget,
+            ..Default::default()
+        }
+    }
+
+    pub fn usage(&self) -> GpuMemory {
+        GpuMemory {
+            buffers: self.assets.buffers + self.buffers,
+            ..self.assets
+        }
+    }
+
+    /// Whether the usage was over the budget at the end of the last frame.
+    pub fn over_budget(&self) -> bool {
+        self.over_budget
+    }
+
+    /// Marks the asset used this frame, `true` if it was evicted and has to be prepared again.
+    pub fn mark_used(&mut self, id: HandleId) -> bool {
+        if let Some(tracked) = self.tracked.get_mut(&id) {
+            tracked.last_used = self.frame;
+        }
+        self.evicted.contains(&id)
+    }
+
+    pub(crate) fn track(&mut self, id: HandleId, memory: GpuMemory) {
+        self.evicted.remove(&id);
+        let tracked = TrackedAsset {
+            memory,
+            last_used: self.frame,
+        };
+        if let Some(previous) = self.tracked.insert(id, tracked) {
+            self.assets -= previous.memory;
+        }
+        self.assets += memory;
+    }
+
+    pub(crate) fn untrack(&mut self, id: HandleId) {
+        self.evicted.remove(&id);
+        if let Some(previous) = self.tracked.remove(&id) {
+            self.assets -= previous.memory;
+        }
+    }
+
+    fn evict(&mut self, id: HandleId) {
+        self.untrack(id);
+        self.evicted.insert(id);
+    }
+
+    /// Picks assets not used this frame, least recently used first, until the usage fits `budget`.
+    fn plan_evictions(&mut self, budget: u64) {
+        let mut candidates: Vec<_> = self
+            .tracked
+            .iter()
+            .filter(|(_, tracked)| tracked.last_used < self.frame)
+            .map(|(id, tracked)| (tracked.last_used, *id, tracked.memory.total()))
+            .collect();
+        candidates.sort_by_key(|(last_used, ..)| *last_used);
+
+        let mut usage = self.usage().total();
+        self.evicting.clear();
+        for (_, id, bytes) in candidates {
+            if usage <= budget {
+                break;
+            }
+            usage = usage.saturating_sub(bytes);
+            self.evicting.push(id);
+        }
+    }
+}
+
+/// Marks the assets of visible entities used, evicted ones are queued to be prepared again.
+pub fn track_render_asset_use<T: RenderAsset>(
+    mut memory_stats: ResMut<GpuMemoryStats>,
+    mut try_assets: ResMut<TryNextFrame<T>>,
+    handles: Query<(&Handle<T>, Option<&ExtractedVisibility>)>,
+) {
+    for (handle, computed_visibility) in handles.iter() {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        let handle_id = handle.id();
+        if memory_stats.mark_used(handle_id) && !try_assets.contains(&handle_id) {
+            try_assets.push(handle_id);
+        }
+    }
+}
+
+/// Warns when the budget is crossed and picks the assets to evict over it.
+pub fn update_gpu_memory_stats(
+    mut memory_stats: ResMut<GpuMemoryStats>,
+    buffer_pool: Res<BufferPool>,
+) {
+    memory_stats.buffers = buffer_pool.allocated_bytes();
+    memory_stats.evicting.clear();
+
+    if let Some(budget) = memory_stats.budget {
+        let usage = memory_stats.usage();
+        let over_budget = usage.total() > budget;
+        if over_budget && !memory_stats.over_budget {
+            warn!(
+                "Estimated GPU memory of {} bytes is over the budget of {} bytes: {:?}",
+                usage.total(),
+                budget,
+                usage
+            );
+        }
+        memory_stats.over_budget = over_budget;
+        if over_budget && memory_stats.evict_over_budget {
+            memory_stats.plan_evictions(budget);
+        }
+    } else {
+        memory_stats.over_budget = false;
+    }
+
+    memory_stats.frame += 1;
+}
+
+pub fn evict_render_assets<T: RenderAsset>(
+    mut memory_stats: ResMut<GpuMemoryStats>,
+    mut render_assets: ResMut<RenderAssets<T>>,
+) {
+    let evicted: Vec<HandleId> = memory_stats
+        .evicting
+        .iter()
+        .copied()
+        .filter(|handle_id| render_assets.remove(handle_id).is_some())
+        .collect();
+    for handle_id in evicted {
+        memory_stats.evict(handle_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::texture::Image;
+
+    #[test]
+    fn evicts_least_recently_used_over_budget() {
+        let (old, recent, current) = (
+            HandleId::random::<Image>(),
+            HandleId::random::<Image>(),
+            HandleId::random::<Image>(),
+        );
+        let mut stats = GpuMemoryStats::with_budget(250, true);
+        stats.track(old, GpuMemory::texture(100));
+        stats.frame = 1;
+        stats.track(recent, GpuMemory::mesh(100));
+        stats.frame = 2;
+        stats.track(current, GpuMemory::texture(100));
+        assert_eq!(stats.usage().total(), 300);
+
+        stats.plan_evictions(250);
+        assert_eq!(stats.evicting, vec![old]);
+
+        stats.evict(old);
+        assert_eq!(
+            stats.usage(),
+            GpuMemory {
+                textures: 100,
+                meshes: 100,
+                buffers: 0
+            }
+        );
+        assert!(stats.mark_used(old));
+        stats.track(old, GpuMemory::texture(100));
+        assert!(!stats.mark_used(old));
+    }
+}
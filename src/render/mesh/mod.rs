@@ -1,15 +1,22 @@
-use bevy::{prelude::Component, reflect::TypeUuid};
+use std::borrow::Cow;
+
+use bevy::{
+    prelude::{App, Component},
+    reflect::TypeUuid,
+};
+
+use self::morph::{GpuMorphTargets, MorphTarget};
 
 use super::{
+    color::{Color, ColorSpace},
+    memory::GpuMemory,
     resource::buffer::{Indices, MeshVertex},
-    RenderAsset, RenderDevice, RenderQueue,
+    AddRenderAsset, RenderAsset, RenderAssets, RenderDevice, RenderQueue,
 };
 
+pub mod morph;
 pub mod primitive;
-
-pub struct Model<V: MeshVertex> {
-    pub meshes: Vec<Mesh<V>>,
-}
+pub mod topology;
 
 pub struct MeshRaw<V> {
     pub primitive_topology: wgpu::PrimitiveTopology,
@@ -23,6 +30,7 @@ pub struct Mesh<V: MeshVertex> {
     primitive_topology: wgpu::PrimitiveTopology,
     vertices: Vec<V>,
     indices: Option<Indices>,
+    morph_targets: Vec<MorphTarget>,
 }
 
 impl<V: MeshVertex> Mesh<V> {
@@ -31,6 +39,7 @@ impl<V: MeshVertex> Mesh<V> {
             primitive_topology,
             vertices: Default::default(),
             indices: None,
+            morph_targets: Vec::new(),
         }
     }
 
@@ -43,6 +52,7 @@ impl<V: MeshVertex> Mesh<V> {
             primitive_topology,
             vertices,
             indices,
+            morph_targets: Vec::new(),
         }
     }
 
@@ -97,6 +107,20 @@ impl<V: MeshVertex> Mesh<V> {
         bytemuck::cast_slice(&self.vertices)
     }
 
+    /// Vertices as uploaded, their colors are given in `color_space`.
+    pub fn get_gpu_vertices(&self, color_space: ColorSpace) -> Cow<[V]> {
+        if color_space == ColorSpace::Linear {
+            return Cow::Borrowed(&self.vertices);
+        }
+        let mut vertices = self.vertices.clone();
+        for vertex in vertices.iter_mut() {
+            if let Some(color) = vertex.color_mut() {
+                *color = Color::from(*color).as_gpu_arr(color_space);
+            }
+        }
+        Cow::Owned(vertices)
+    }
+
     pub fn get_vertex_buffer_layout(&self) -> wgpu::VertexBufferLayout<'static> {
         // TODO: lifetime
         V::layout()
@@ -109,6 +133,12 @@ impl<V: MeshVertex> Mesh<V> {
     pub fn vertex_count(&self) -> usize {
         self.vertices.len()
     }
+
+    /// Bytes of the vertex and index buffers the mesh is prepared into.
+    pub fn buffer_bytes(&self) -> u64 {
+        let index_bytes = self.get_index_buffer_bytes().map_or(0, <[u8]>::len);
+        (self.get_vertex_buffer_bytes().len() + index_bytes) as u64
+    }
 }
 
 impl<V: MeshVertex> AsRef<Self> for Mesh<V> {
@@ -132,37 +162,39 @@ impl<V: MeshVertex> BatchMesh<V> {
         }
     }
 
+    ///
+    /// Appends the mesh, converting it to the index requirement of the batch:
+    /// non-indexed meshes get sequential indices in an indexed batch,
+    /// indexed meshes are expanded into their vertices in a non-indexed batch.
+    ///
     pub fn add(&mut self, mesh: Mesh<V>) {
-        let (vertices, indices) = (mesh.vertices, mesh.indices);
-        let offset = vertices.len() as u32;
+        let (mut vertices, indices) = (mesh.vertices, mesh.indices);
+        let base = self.inner_mesh.vertex_count() as u32;
 
+        if !self.indexed {
+            if let Some(indices) = indices {
+                vertices = indices.iter().map(|ind| vertices[ind]).collect();
+            }
+            self.inner_mesh.push_vertices(vertices);
+            return;
+        }
+
+        let mut indices = indices.unwrap_or_else(|| Indices::sequential(vertices.len()));
+        let wide = base as usize + vertices.len() > u16::MAX as usize + 1;
+        if wide {
+            indices.promote();
+        }
+        indices.shift(base);
         self.inner_mesh.push_vertices(vertices);
 
         match self.inner_mesh.get_indices_mut() {
             Some(inner_indices) => {
-                match indices {
-                    Some(mut indices) => {
-                        indices.shift(offset);
-                        inner_indices.extend(indices);
-                    }
-                    // TODO: OR: may convert non-indexed into indexed
-                    // by triplet indexing
-                    None => panic!("Index requirements does not match"),
-                }
-            }
-            None => {
-                match (self.indexed, indices) {
-                    (true, Some(mut indices)) => {
-                        indices.shift(offset);
-                        self.inner_mesh.set_indices(indices);
-                    }
-                    (false, None) => {
-                        // Normal Case
-                    }
-                    // TODO: OR: may produce garbage gracefully
-                    _ => panic!("Index requirements does not match"),
+                if wide {
+                    inner_indices.promote();
                 }
+                inner_indices.extend(indices);
             }
+            None => self.inner_mesh.set_indices(indices),
         }
     }
 
@@ -196,10 +228,11 @@ pub struct GpuMesh {
     pub vertex_buffer: wgpu::Buffer,
     pub assembly: GpuMeshAssembly,
     pub primitive_topology: wgpu::PrimitiveTopology,
+    pub morph_targets: Option<GpuMorphTargets>,
 }
 
 impl GpuMesh {
-    pub fn from_mesh<V, M>(render_device: &RenderDevice, mesh: M) -> GpuMesh
+    pub fn from_mesh<V, M>(render_device: &RenderDevice, mesh: M, color_space: ColorSpace) -> GpuMesh
     where
         V: MeshVertex,
         M: AsRef<Mesh<V>>,
@@ -209,7 +242,7 @@ impl GpuMesh {
             vertex_buffer_layout: mesh.get_vertex_buffer_layout(),
             vertex_buffer: render_device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
-                contents: &mesh.get_vertex_buffer_bytes(),
+                contents: bytemuck::cast_slice(&mesh.get_gpu_vertices(color_space)),
                 usage: wgpu::BufferUsages::VERTEX,
             }),
             assembly: match mesh.get_index_buffer_bytes() {
@@ -227,6 +260,7 @@ impl GpuMesh {
                 },
             },
             primitive_topology: mesh.get_primitive_topology(),
+            morph_targets: GpuMorphTargets::from_mesh(render_device, mesh),
         }
     }
 }
@@ -234,16 +268,52 @@ impl GpuMesh {
 impl<V: MeshVertex> RenderAsset for Mesh<V> {
     type PreparedAsset = GpuMesh;
 
-    fn prepare(&self, render_device: &RenderDevice, _queue: &RenderQueue) -> Option<Self::PreparedAsset> {
-        Some(GpuMesh::from_mesh(render_device, self))
+    fn gpu_memory(&self) -> GpuMemory {
+        GpuMemory::mesh(self.buffer_bytes())
+    }
+
+    fn prepare(
+        &self,
+        render_device: &RenderDevice,
+        _queue: &RenderQueue,
+        color_space: ColorSpace,
+    ) -> Option<Self::PreparedAsset> {
+        Some(GpuMesh::from_mesh(render_device, self, color_space))
     }
 }
 
 impl<V: MeshVertex> RenderAsset for BatchMesh<V> {
     type PreparedAsset = GpuMesh;
 
-    fn prepare(&self, render_device: &RenderDevice, _queue: &RenderQueue) -> Option<Self::PreparedAsset> {
-        Some(GpuMesh::from_mesh(render_device, self))
+    fn gpu_memory(&self) -> GpuMemory {
+        GpuMemory::mesh(self.as_ref().buffer_bytes())
+    }
+
+    fn prepare(
+        &self,
+        render_device: &RenderDevice,
+        _queue: &RenderQueue,
+        color_space: ColorSpace,
+    ) -> Option<Self::PreparedAsset> {
+        Some(GpuMesh::from_mesh(render_device, self, color_space))
     }
 }
 
+
+pub trait AddMeshVertex {
+    ///
+    /// Registers `Assets<Mesh<V>>`, `RenderAssets<Mesh<V>>` and the system preparing them,
+    /// so plugins can introduce their own vertex layouts.
+    ///
+    /// Does nothing if `V` is already registered, plugins sharing a vertex type can all call it.
+    ///
+    fn add_mesh_vertex<V: MeshVertex>(&mut self) -> &mut Self;
+}
+impl AddMeshVertex for App {
+    fn add_mesh_vertex<V: MeshVertex>(&mut self) -> &mut Self {
+        if self.world.contains_resource::<RenderAssets<Mesh<V>>>() {
+            return self;
+        }
+        self.add_render_asset::<Mesh<V>>()
+    }
+}
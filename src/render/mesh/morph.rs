@@ -0,0 +1,100 @@
+use bevy::prelude::Vec3;
+use encase::ShaderType;
+
+use crate::render::{resource::buffer::MeshVertex, RenderDevice};
+
+use super::Mesh;
+
+///
+/// Blend shape of a [`Mesh`], one delta per vertex of the mesh.
+///
+/// The vertex shader adds the deltas scaled by the target's weight,
+/// see [`MorphWeights`](crate::mesh3d::morph::MorphWeights).
+///
+#[derive(Clone, Default)]
+pub struct MorphTarget {
+    pub positions: Vec<[f32; 3]>,
+    /// Left empty when the target does not move the normals
+    pub normals: Vec<[f32; 3]>,
+}
+
+#[derive(Clone, Copy, Default, ShaderType)]
+pub struct MorphDelta {
+    position: Vec3,
+    normal: Vec3,
+}
+
+/// Deltas of every target, target major: `deltas[target * vertex_count + vertex]`.
+#[derive(Default, ShaderType)]
+pub struct MorphTargetData {
+    vertex_count: u32,
+    target_count: u32,
+    #[size(runtime)]
+    deltas: Vec<MorphDelta>,
+}
+
+impl MorphTargetData {
+    pub fn new<V: MeshVertex>(mesh: &Mesh<V>) -> Self {
+        let vertex_count = mesh.vertex_count();
+        let mut deltas = Vec::with_capacity(vertex_count * mesh.morph_targets.len());
+        for target in &mesh.morph_targets {
+            deltas.extend((0..vertex_count).map(|i| MorphDelta {
+                position: target.positions[i].into(),
+                normal: target.normals.get(i).copied().unwrap_or_default().into(),
+            }));
+        }
+        Self {
+            vertex_count: vertex_count as u32,
+            target_count: mesh.morph_targets.len() as u32,
+            deltas,
+        }
+    }
+}
+
+pub struct GpuMorphTargets {
+    pub buffer: wgpu::Buffer,
+    pub target_count: u32,
+}
+
+impl GpuMorphTargets {
+    pub fn from_mesh<V: MeshVertex>(render_device: &RenderDevice, mesh: &Mesh<V>) -> Option<Self> {
+        if mesh.morph_targets.is_empty() {
+            return None;
+        }
+        let data = MorphTargetData::new(mesh);
+        let mut scratch = encase::StorageBuffer::new(Vec::new());
+        scratch.write(&data).unwrap();
+        Some(Self {
+            buffer: render_device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Morph Target Buffer"),
+                contents: scratch.as_ref(),
+                usage: wgpu::BufferUsages::STORAGE,
+            }),
+            target_count: data.target_count,
+        })
+    }
+}
+
+impl<V: MeshVertex> Mesh<V> {
+    /// Panics if the target does not have a delta for every vertex.
+    pub fn add_morph_target(&mut self, target: MorphTarget) {
+        assert_eq!(
+            target.positions.len(),
+            self.vertex_count(),
+            "morph target must have a position delta per vertex"
+        );
+        assert!(
+            target.normals.is_empty() || target.normals.len() == self.vertex_count(),
+            "morph target must have a normal delta per vertex or none"
+        );
+        self.morph_targets.push(target);
+    }
+
+    pub fn get_morph_targets(&self) -> &[MorphTarget] {
+        &self.morph_targets
+    }
+
+    pub fn clear_morph_targets(&mut self) {
+        self.morph_targets.clear();
+    }
+}
@@ -56,7 +56,7 @@ pub fn create_unit_cube(facing: FaceDirection) -> Mesh<Vertex> {
         .map(|(i, vp)| Vertex {
             position: vp.clone(),
             uv: UNIT_CUBE_UVS[i % 4],
-            color: Color::WHITE.as_arr(),
+            color: Color::NO_TINT.as_arr(),
         })
         .collect();
 
@@ -21,7 +21,7 @@ pub fn create_unit_square() -> Mesh<Vertex> {
     for ind in UNIT_SQUARE_INDICES {
         let position = UNIT_SQUARE_POSITIONS[*ind as usize];
         let uv = UNIT_SQUARE_UVS[*ind as usize];
-        let color = Color::WHITE.as_arr();
+        let color = Color::NO_TINT.as_arr();
 
         vertices.push(Vertex {
             position,
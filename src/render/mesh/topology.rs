@@ -0,0 +1,153 @@
+use bevy::utils::{HashMap, HashSet};
+
+use crate::render::resource::buffer::{Indices, MeshVertex};
+
+use super::Mesh;
+
+///
+/// Conversions for meshes imported from tools with other conventions,
+/// the pipelines expect Ccw triangle lists and cull back faces.
+///
+impl<V: MeshVertex> Mesh<V> {
+    /// Vertex indices of each triangle, strips are unrolled keeping their winding.
+    ///
+    /// `None` if the mesh is not a triangle list or strip.
+    pub fn triangles(&self) -> Option<Vec<[usize; 3]>> {
+        let indices: Vec<usize> = match &self.indices {
+            Some(indices) => indices.iter().collect(),
+            None => (0..self.vertices.len()).collect(),
+        };
+        let triangles = match self.primitive_topology {
+            wgpu::PrimitiveTopology::TriangleList => indices
+                .chunks_exact(3)
+                .map(|tri| [tri[0], tri[1], tri[2]])
+                .collect(),
+            wgpu::PrimitiveTopology::TriangleStrip => indices
+                .windows(3)
+                .enumerate()
+                .map(|(i, tri)| match i % 2 {
+                    0 => [tri[0], tri[1], tri[2]],
+                    _ => [tri[1], tri[0], tri[2]],
+                })
+                .collect(),
+            _ => return None,
+        };
+        Some(triangles)
+    }
+
+    /// LineList of the unique triangle edges, sharing the vertices of the mesh.
+    ///
+    /// `None` if the mesh has no triangles.
+    pub fn to_wireframe(&self) -> Option<Mesh<V>> {
+        let mut edges: HashSet<(usize, usize)> = HashSet::new();
+        let mut lines: Vec<u32> = Vec::new();
+        for [a, b, c] in self.triangles()? {
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                if edges.insert((from.min(to), from.max(to))) {
+                    lines.extend([from as u32, to as u32]);
+                }
+            }
+        }
+        let mut wireframe = Mesh::new_with(
+            wgpu::PrimitiveTopology::LineList,
+            self.vertices.clone(),
+            Some(lines.into()),
+        );
+        wireframe.morph_targets = self.morph_targets.clone();
+        Some(wireframe)
+    }
+
+    /// Merges identical vertices, leaving an indexed mesh with the same primitives.
+    pub fn weld(&mut self) {
+        let mut unique: Vec<V> = Vec::new();
+        let mut lookup: HashMap<&[u8], u32> = HashMap::new();
+        let remap: Vec<u32> = self
+            .vertices
+            .iter()
+            .map(|vertex| {
+                *lookup
+                    .entry(bytemuck::bytes_of(vertex))
+                    .or_insert_with(|| {
+                        unique.push(*vertex);
+                        unique.len() as u32 - 1
+                    })
+            })
+            .collect();
+
+        // Welded vertices keep the deltas of their first occurrence
+        if !self.morph_targets.is_empty() {
+            let mut first = vec![usize::MAX; unique.len()];
+            for (vertex, unique_index) in remap.iter().enumerate().rev() {
+                first[*unique_index as usize] = vertex;
+            }
+            for target in &mut self.morph_targets {
+                target.positions = first.iter().map(|i| target.positions[*i]).collect();
+                if !target.normals.is_empty() {
+                    target.normals = first.iter().map(|i| target.normals[*i]).collect();
+                }
+            }
+        }
+
+        let indices: Vec<u32> = match &self.indices {
+            Some(indices) => indices.iter().map(|ind| remap[ind]).collect(),
+            None => remap,
+        };
+        self.vertices = unique;
+        self.indices = Some(indices.into());
+    }
+
+    /// Reverses the winding of every triangle, a strip becomes an indexed list.
+    /// Line and point meshes have no winding and are left as they are.
+    ///
+    /// Normals are not part of [`MeshVertex`], flip them with [`Mesh::map_vertices`].
+    pub fn flip_winding(&mut self) {
+        let Some(triangles) = self.triangles() else {
+            return;
+        };
+        let flipped: Vec<u32> = triangles
+            .into_iter()
+            .flat_map(|[a, b, c]| [a as u32, c as u32, b as u32])
+            .collect();
+        self.primitive_topology = wgpu::PrimitiveTopology::TriangleList;
+        self.indices = Some(match self.indices {
+            Some(Indices::U16(_)) => Indices::U16(flipped.iter().map(|i| *i as u16).collect()),
+            _ => Indices::U32(flipped),
+        });
+    }
+
+    pub fn map_vertices(&mut self, f: impl FnMut(&mut V)) {
+        self.vertices.iter_mut().for_each(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::resource::buffer::Vertex;
+
+    use super::*;
+
+    fn vertices(count: usize) -> Vec<Vertex> {
+        (0..count)
+            .map(|i| Vertex {
+                position: [i as f32, 0.0, 0.0],
+                uv: [0.0; 2],
+                color: [1.0; 4],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn line_meshes_have_no_triangles() {
+        let mut lines = Mesh::new_with(wgpu::PrimitiveTopology::LineList, vertices(4), None);
+        assert!(lines.triangles().is_none());
+        assert!(lines.to_wireframe().is_none());
+
+        lines.flip_winding();
+        assert_eq!(lines.primitive_topology, wgpu::PrimitiveTopology::LineList);
+        assert!(lines.indices.is_none());
+
+        let mut strip = Mesh::new_with(wgpu::PrimitiveTopology::TriangleStrip, vertices(4), None);
+        strip.flip_winding();
+        assert_eq!(strip.triangles(), Some(vec![[0, 2, 1], [2, 3, 1]]));
+    }
+}
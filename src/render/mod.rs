@@ -1,5 +1,6 @@
 use bevy::{
     asset::{Asset, HandleId},
+    log::trace,
     prelude::{
         AddAsset, App, AssetEvent, Assets, CoreStage, Deref, DerefMut, EventReader,
         GlobalTransform, Handle, IntoSystemDescriptor, Plugin, Res, ResMut, Resource, StageLabel,
@@ -12,31 +13,71 @@ use bevy::{
 use crate::util::NewTypePhantom;
 
 use self::{
-    camera::FlatCameraPlugin,
-    color::Color,
-    mesh::Mesh,
+    camera::{component::Camera, FlatCameraPlugin},
+    capture::{request_frame_capture, CaptureNextFrame, FrameCapture},
+    cleanup::AddEntityCleanup,
+    color::{Color, ColorSpace},
+    command::DrawFunctions,
+    debug_view::DebugViewMode,
+    error::{handle_device_errors, send_device_errors, RendererError},
+    extract::{AddExtract, ExtractedTime, ExtractedTransform},
+    globals::{prepare_globals_uniforms, queue_globals_uniforms, GlobalsUniform},
+    memory::{
+        evict_render_assets, track_render_asset_use, update_gpu_memory_stats, GpuMemory,
+        GpuMemoryStats,
+    },
+    mesh::AddMeshVertex,
+    pass::CameraPasses,
+    phase::{AddRenderPhase, Opaque, RenderOrdering, Transparent},
+    render_bundle::RenderBundles,
     resource::{
-        buffer::{Vertex, VertexTex3},
-        component_uniform::AddComponentUniform,
+        buffer::{Vertex, VertexSkinned, VertexTex3},
+        buffer_pool::{recycle_transient_buffers, BufferPool},
+        component_uniform::{AddComponentUniform, ComponentUniforms},
         pipeline::{compile_shaders_into_pipelines, PipelineCache},
-        renderer::{RenderAdapter, RenderDevice, RenderInstance, RenderQueue},
+        renderer::{RenderAdapter, RenderAdapterChoice, RenderDevice, RenderInstance, RenderQueue},
         shader::{Shader, ShaderLoader},
+        specialized_pipeline::{update_pipeline_warmup_status, PipelineWarmupStatus},
     },
+    stats::RenderStats,
     system::{render_system, RenderFunctions, RenderNode},
-    texture::{Image, ImageLoader, ImageJustLoader, texture_arr::{create_image_arr_from_images, ImageArray}, DepthTextures},
-    view::window::FlatViewPlugin,
+    texture::{animated::{animate_textures, AnimatedImage, AnimatedImageLoader}, Image, ImageLoader, ImageJustLoader, texture_arr::{create_image_arr_from_images, ImageArray}, video::stream_video_textures, DepthTextures},
+    view::{
+        grab::GrabTextureLayout,
+        upscale::UpscalePipeline,
+        window::{prepare_windows, FlatViewPlugin},
+    },
 };
+#[cfg(feature = "shader_cache")]
+use self::resource::shader_cache::{ShaderCache, ShaderCacheDir};
 
+pub mod blend;
 pub mod camera;
+pub mod capture;
+pub mod cleanup;
 pub mod color;
+pub mod command;
+pub mod debug_view;
+pub mod diagnostic;
+pub mod error;
+pub mod extract;
+pub mod globals;
+pub mod memory;
 pub mod mesh;
+pub mod pass;
+pub mod phase;
+pub mod post;
+pub mod raster;
+pub mod render_bundle;
 pub mod resource;
+pub mod stats;
 pub mod system;
 pub mod texture;
 pub mod view;
 
 #[derive(StageLabel)]
 pub enum RenderStage {
+    Extract, // Copy render state out of the simulation: Time -> ExtractedTime
     Prepare, // Prepare Resources and Entities for the rendering context: Image -> GpuTexture
     Create,  // Create resources directly for rendering: GpuTexture -> BindGroup
     Render,  // Render
@@ -48,6 +89,11 @@ impl Plugin for FlatRenderPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_stage_after(
             CoreStage::PostUpdate,
+            RenderStage::Extract,
+            SystemStage::parallel(),
+        )
+        .add_stage_after(
+            RenderStage::Extract,
             RenderStage::Prepare,
             SystemStage::parallel(),
         )
@@ -68,26 +114,112 @@ impl Plugin for FlatRenderPlugin {
         );
 
         app.init_resource::<RenderFunctions>()
+            .init_resource::<CameraPasses>()
+            .init_resource::<DrawFunctions>()
+            .init_resource::<RenderBundles>()
+            .init_resource::<RenderStats>()
+            .init_resource::<GpuMemoryStats>()
             .init_resource::<RenderNode>()
             .init_resource::<PipelineCache>()
+            .init_resource::<BufferPool>()
             .init_resource::<DepthTextures>()
-            .init_asset_loader::<ShaderLoader>()
+            .init_resource::<FrameCapture>()
+            .init_resource::<ExtractedTime>()
+            .init_resource::<ColorSpace>()
+            .init_resource::<DebugViewMode>()
+            .init_resource::<RenderOrdering>()
+            .init_resource::<PipelineWarmupStatus>()
+            .add_event::<CaptureNextFrame>()
+            .add_event::<RendererError>();
+
+        // Before the plugins, their resources are created with the device limits
+        create_wgpu_resources(app);
+        handle_device_errors(app);
+
+        app.init_asset_loader::<ShaderLoader>()
             .init_asset_loader::<ImageLoader>()
             .init_asset_loader::<ImageJustLoader>()
+            .init_asset_loader::<AnimatedImageLoader>()
             // .init_asset_loader::<MeshLoader>()
             .add_asset::<Shader>()
             .add_render_asset::<Image>()
             .add_render_asset::<ImageArray>()
-            .add_render_asset::<Mesh<Vertex>>()
-            .add_render_asset::<Mesh<VertexTex3>>()
+            .add_asset::<AnimatedImage>()
+            // Every built-in vertex type is registered here, before the plugins using them.
+            // A custom vertex type needs `app.add_mesh_vertex::<MyVertex>()`.
+            .add_mesh_vertex::<Vertex>()
+            .add_mesh_vertex::<VertexTex3>()
+            .add_mesh_vertex::<VertexSkinned>()
             .add_component_uniform::<Color>()
-            .add_component_uniform::<GlobalTransform>()
+            .add_extract_component::<GlobalTransform>()
+            .add_stable_component_uniform::<ExtractedTransform>()
+            .init_resource::<ComponentUniforms<GlobalsUniform>>()
+            .add_render_phase::<Opaque>()
+            .add_render_phase::<Transparent>()
+            .add_extract_resource::<ExtractedTime>()
+            .add_entity_cleanup::<RenderBundles, Camera>()
+            .add_system_to_stage(CoreStage::PreUpdate, request_frame_capture)
+            .add_system_to_stage(CoreStage::PostUpdate, animate_textures)
+            .add_system_to_stage(CoreStage::PreUpdate, send_device_errors)
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_globals_uniforms.after(prepare_windows),
+            )
+            .add_system_to_stage(RenderStage::Create, queue_globals_uniforms)
             .add_system_to_stage(RenderStage::Create, create_image_arr_from_images)
-            .add_system_to_stage(RenderStage::Prepare, compile_shaders_into_pipelines);
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                stream_video_textures.after(prepare_render_assets::<Image>),
+            )
+            .add_system_to_stage(RenderStage::Prepare, compile_shaders_into_pipelines)
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                update_pipeline_warmup_status.after(compile_shaders_into_pipelines),
+            )
+            .add_system_to_stage(RenderStage::Cleanup, recycle_transient_buffers)
+            .add_system_to_stage(RenderStage::Cleanup, update_gpu_memory_stats);
 
         app.add_plugin(FlatCameraPlugin).add_plugin(FlatViewPlugin);
 
-        create_wgpu_resources(app);
+        app.init_resource::<UpscalePipeline>()
+            .init_resource::<GrabTextureLayout>();
+    }
+}
+
+/// Device features the engine pipelines depend on.
+pub fn required_features() -> wgpu::Features {
+    let features = wgpu::Features::empty() | wgpu::Features::TEXTURE_BINDING_ARRAY;
+    if cfg!(feature = "push_constants") {
+        features | wgpu::Features::PUSH_CONSTANTS
+    } else {
+        features
+    }
+}
+
+///
+/// Limits requested from the device, with the offset alignments of the adapter
+/// so dynamic uniforms are packed as tightly as it allows.
+///
+pub fn required_limits(adapter_limits: &wgpu::Limits) -> wgpu::Limits {
+    let limits = if cfg!(target_arch = "wasm32") {
+        wgpu::Limits::downlevel_webgl2_defaults()
+    } else {
+        wgpu::Limits::default()
+    };
+    let limits = wgpu::Limits {
+        min_uniform_buffer_offset_alignment: adapter_limits.min_uniform_buffer_offset_alignment,
+        min_storage_buffer_offset_alignment: adapter_limits.min_storage_buffer_offset_alignment,
+        // Sizes the bindless sprite texture array
+        max_sampled_textures_per_shader_stage: adapter_limits.max_sampled_textures_per_shader_stage,
+        ..limits
+    };
+    if cfg!(feature = "push_constants") {
+        wgpu::Limits {
+            max_push_constant_size: 128,
+            ..limits
+        }
+    } else {
+        limits
     }
 }
 
@@ -95,16 +227,12 @@ impl Plugin for FlatRenderPlugin {
 /// Creates wgpu Instance, Device and Queue as World Resources.
 ///
 /// Creates wpgu Surface for initial primary window.
+/// Headless apps have none, the adapter is then chosen without a compatible surface.
 ///
 pub fn create_wgpu_resources(app: &mut App) {
     let backends = wgpu::Backends::all();
     let power_preference = wgpu::PowerPreference::HighPerformance;
-    let features = wgpu::Features::empty() | wgpu::Features::TEXTURE_BINDING_ARRAY;
-    let limits = if cfg!(target_arch = "wasm32") {
-        wgpu::Limits::downlevel_webgl2_defaults()
-    } else {
-        wgpu::Limits::default()
-    };
+    let features = required_features();
 
     let windows = app.world.resource::<Windows>();
     let instance = wgpu::Instance::new(backends);
@@ -117,13 +245,29 @@ pub fn create_wgpu_resources(app: &mut App) {
             instance.create_surface(&handle)
         });
 
-    let adapter =
-        futures_lite::future::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference,
-            compatible_surface: surface.as_ref(),
-            ..Default::default()
-        }))
-        .unwrap();
+    let adapter = match app.world.get_resource::<RenderAdapterChoice>() {
+        Some(choice) => instance
+            .enumerate_adapters(choice.backend.into())
+            .find(|adapter| adapter.get_info().name == choice.name)
+            .unwrap_or_else(|| panic!("No adapter {:?}", choice)),
+        None => futures_lite::future::block_on(instance.request_adapter(
+            &wgpu::RequestAdapterOptions {
+                power_preference,
+                compatible_surface: surface.as_ref(),
+                ..Default::default()
+            },
+        ))
+        .unwrap(),
+    };
+    let limits = required_limits(&adapter.limits());
+
+    // Requested only when available, the shader cache falls back to WGSL without it
+    #[cfg(feature = "shader_cache")]
+    let features = if app.world.contains_resource::<ShaderCacheDir>() {
+        features | (adapter.features() & wgpu::Features::SPIRV_SHADER_PASSTHROUGH)
+    } else {
+        features
+    };
 
     let (device, queue) = futures_lite::future::block_on(adapter.request_device(
         &wgpu::DeviceDescriptor {
@@ -135,6 +279,12 @@ pub fn create_wgpu_resources(app: &mut App) {
     ))
     .unwrap();
 
+    #[cfg(feature = "shader_cache")]
+    if let Some(ShaderCacheDir(dir)) = app.world.get_resource::<ShaderCacheDir>().cloned() {
+        let shader_cache = ShaderCache::new(&dir, &adapter.get_info(), device.features());
+        app.world.resource_mut::<PipelineCache>().shader_cache = shader_cache;
+    }
+
     app.insert_resource(RenderInstance(instance))
         .insert_resource(RenderAdapter(adapter))
         .insert_resource(RenderQueue(queue))
@@ -149,7 +299,15 @@ impl AddRenderAsset for App {
         self.add_asset::<T>()
             .init_resource::<RenderAssets<T>>()
             .init_resource::<TryNextFrame<T>>()
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                track_render_asset_use::<T>.before(prepare_render_assets::<T>),
+            )
             .add_system_to_stage(RenderStage::Prepare, prepare_render_assets::<T>)
+            .add_system_to_stage(
+                RenderStage::Cleanup,
+                evict_render_assets::<T>.after(update_gpu_memory_stats),
+            )
     }
 }
 
@@ -159,10 +317,16 @@ pub trait RenderAsset: Asset {
     fn should_prepare(&self) -> bool {
         true
     }
+    /// Estimated GPU memory of the prepared asset, counted in [`GpuMemoryStats`].
+    fn gpu_memory(&self) -> GpuMemory {
+        GpuMemory::default()
+    }
+    /// Colors of the asset are given in `color_space`, see [`ColorSpace`].
     fn prepare(
         &self,
         render_device: &RenderDevice,
         queue: &RenderQueue,
+        color_space: ColorSpace,
     ) -> Option<Self::PreparedAsset>;
 }
 
@@ -180,17 +344,20 @@ pub type TryNextFrame<T> = NewTypePhantom<Vec<HandleId>, T>;
 pub fn prepare_render_assets<T: RenderAsset>(
     render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
+    color_space: Res<ColorSpace>,
     assets: Res<Assets<T>>,
     mut try_assets: ResMut<TryNextFrame<T>>, // NOTE: Infinite growth
     mut render_assets: ResMut<RenderAssets<T>>,
+    mut memory_stats: ResMut<GpuMemoryStats>,
     mut asset_events: EventReader<AssetEvent<T>>,
 ) {
     let try_assets_take = std::mem::replace(&mut try_assets.0, Vec::new());
     for handle_id in try_assets_take {
         if let Some(asset) = assets.get(&Handle::weak(handle_id)) {
-            match asset.prepare(&render_device, &render_queue) {
+            match asset.prepare(&render_device, &render_queue, *color_space) {
                 Some(render_asset) => {
                     render_assets.insert(handle_id, render_asset);
+                    memory_stats.track(handle_id, asset.gpu_memory());
                 }
                 None => {
                     if asset.should_prepare() {
@@ -202,14 +369,15 @@ pub fn prepare_render_assets<T: RenderAsset>(
     }
 
     for event in asset_events.iter() {
-        dbg!(event);
+        trace!("{:?}", event);
         match event {
             AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
                 let handle_id = handle.id();
                 if let Some(asset) = assets.get(handle) {
-                    match asset.prepare(&render_device, &render_queue) {
+                    match asset.prepare(&render_device, &render_queue, *color_space) {
                         Some(render_asset) => {
                             render_assets.insert(handle_id, render_asset);
+                            memory_stats.track(handle_id, asset.gpu_memory());
                         }
                         None => {
                             if asset.should_prepare() {
@@ -221,7 +389,25 @@ pub fn prepare_render_assets<T: RenderAsset>(
             }
             AssetEvent::Removed { handle } => {
                 render_assets.remove(&handle.id());
+                memory_stats.untrack(handle.id());
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_limits_keep_the_adapter_alignment() {
+        let adapter_limits = wgpu::Limits {
+            min_uniform_buffer_offset_alignment: 64,
+            min_storage_buffer_offset_alignment: 32,
+            ..Default::default()
+        };
+        let limits = required_limits(&adapter_limits);
+        assert_eq!(limits.min_uniform_buffer_offset_alignment, 64);
+        assert_eq!(limits.min_storage_buffer_offset_alignment, 32);
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::prelude::{App, Entity, Resource, World};
+
+///
+/// Attachments of the camera a [`CameraPassFunction`] runs for.
+///
+/// Passes load and store them, clearing would discard what the camera drew.
+///
+pub struct CameraAttachments<'a> {
+    pub color: &'a wgpu::TextureView,
+    /// `None` while the target texture is not available to copy from
+    pub color_texture: Option<&'a wgpu::Texture>,
+    pub depth: Option<&'a wgpu::TextureView>,
+    pub size: (u32, u32),
+}
+
+/// Encodes a pass of the camera, passes run for every camera, check its components to skip one.
+pub type CameraPassFunction = for<'w> fn(
+    /*camera*/ Entity,
+    &'w World,
+    &CameraAttachments<'w>,
+    &mut wgpu::CommandEncoder,
+);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CameraPassStage {
+    /// After the target is cleared, before the opaque draws
+    BeforeMain,
+    /// After the transparent draws
+    AfterMain,
+}
+
+/// Passes of every camera around its main pass, run in the order they are added.
+#[derive(Resource, Default)]
+pub struct CameraPasses {
+    before_main: Vec<CameraPassFunction>,
+    after_main: Vec<CameraPassFunction>,
+}
+
+impl CameraPasses {
+    pub fn add(&mut self, stage: CameraPassStage, pass: CameraPassFunction) {
+        match stage {
+            CameraPassStage::BeforeMain => self.before_main.push(pass),
+            CameraPassStage::AfterMain => self.after_main.push(pass),
+        }
+    }
+
+    pub fn get(&self, stage: CameraPassStage) -> &[CameraPassFunction] {
+        match stage {
+            CameraPassStage::BeforeMain => &self.before_main,
+            CameraPassStage::AfterMain => &self.after_main,
+        }
+    }
+}
+
+pub trait AddCameraPass {
+    fn add_camera_pass(&mut self, stage: CameraPassStage, pass: CameraPassFunction) -> &mut Self;
+}
+impl AddCameraPass for App {
+    fn add_camera_pass(&mut self, stage: CameraPassStage, pass: CameraPassFunction) -> &mut Self {
+        self.world
+            .get_resource_mut::<CameraPasses>()
+            .unwrap()
+            .add(stage, pass);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first(_: Entity, _: &World, _: &CameraAttachments, _: &mut wgpu::CommandEncoder) {}
+    fn second(_: Entity, _: &World, _: &CameraAttachments, _: &mut wgpu::CommandEncoder) {}
+
+    #[test]
+    fn passes_keep_stage_and_order() {
+        let mut app = App::new();
+        app.init_resource::<CameraPasses>()
+            .add_camera_pass(CameraPassStage::AfterMain, first)
+            .add_camera_pass(CameraPassStage::AfterMain, second)
+            .add_camera_pass(CameraPassStage::BeforeMain, second);
+
+        let passes = app.world.resource::<CameraPasses>();
+        let after: Vec<usize> = passes
+            .get(CameraPassStage::AfterMain)
+            .iter()
+            .map(|pass| *pass as usize)
+            .collect();
+        assert_eq!(after, vec![first as usize, second as usize]);
+        assert_eq!(passes.get(CameraPassStage::BeforeMain).len(), 1);
+    }
+}
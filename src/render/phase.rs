@@ -0,0 +1,280 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::{
+    App, Commands, Component, CoreStage, Entity, GlobalTransform, IntoSystemDescriptor, Mat4,
+    Query, Res, Resource, SystemLabel, Vec3, With, Without,
+};
+
+use super::{
+    blend::{is_transparent, AlphaMode, BlendMode},
+    camera::{
+        component::{Camera, VisibleEntities},
+        visibility_system,
+    },
+    render_bundle::RenderBundles,
+    system::RenderFunctionId,
+    RenderStage,
+};
+
+///
+/// Makes the order of draws the same on every run of the same scene, for screenshot tests.
+///
+/// Draws at the same distance are ordered by entity instead of query order, cameras
+/// sharing a target by entity, and bindless sprite texture slots by the entity using them.
+/// Entities must be spawned in the same order for the order to repeat.
+///
+#[derive(Resource, Clone, Copy, Default, Debug)]
+pub struct RenderOrdering {
+    pub deterministic: bool,
+}
+
+/// Draw call collected into a [`RenderPhase`].
+#[derive(Clone, Copy, Debug)]
+pub struct PhaseItem {
+    pub entity: Entity,
+    pub render_function: RenderFunctionId,
+    /// Distance from the camera along its forward axis.
+    pub distance: f32,
+}
+
+/// Group of draws of a camera that are sorted together and executed in order.
+pub trait Phase: Send + Sync + 'static {
+    /// Whether the entity belongs to this phase.
+    fn accepts(blend_mode: Option<&BlendMode>, alpha_mode: Option<&AlphaMode>) -> bool;
+
+    fn sort(items: &mut [PhaseItem]);
+}
+
+/// Opaque and masked entities, front-to-back to reduce overdraw.
+pub struct Opaque;
+impl Phase for Opaque {
+    fn accepts(blend_mode: Option<&BlendMode>, alpha_mode: Option<&AlphaMode>) -> bool {
+        !is_transparent(blend_mode, alpha_mode)
+    }
+
+    fn sort(items: &mut [PhaseItem]) {
+        items.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    }
+}
+
+/// Blended entities, back-to-front so they composite correctly.
+pub struct Transparent;
+impl Phase for Transparent {
+    fn accepts(blend_mode: Option<&BlendMode>, alpha_mode: Option<&AlphaMode>) -> bool {
+        is_transparent(blend_mode, alpha_mode)
+    }
+
+    fn sort(items: &mut [PhaseItem]) {
+        items.sort_by(|a, b| b.distance.total_cmp(&a.distance));
+    }
+}
+
+/// Sorted draws of a camera for the phase `P`, rebuilt every frame.
+#[derive(Component)]
+pub struct RenderPhase<P: Phase> {
+    pub items: Vec<PhaseItem>,
+    _marker: PhantomData<P>,
+}
+
+impl<P: Phase> Default for RenderPhase<P> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<P: Phase> RenderPhase<P> {
+    pub fn iter(&self) -> std::slice::Iter<PhaseItem> {
+        self.items.iter()
+    }
+}
+
+/// Phases are queued at the end of [`RenderStage::Create`],
+/// after renderers recorded their [`RenderBundles`].
+#[derive(SystemLabel)]
+pub struct QueueRenderPhases;
+
+pub trait AddRenderPhase {
+    fn add_render_phase<P: Phase>(&mut self) -> &mut Self;
+}
+impl AddRenderPhase for App {
+    fn add_render_phase<P: Phase>(&mut self) -> &mut Self {
+        self.add_system_to_stage(
+            CoreStage::PostUpdate,
+            insert_render_phases::<P>.before(visibility_system),
+        )
+        .add_system_to_stage(
+            RenderStage::Create,
+            queue_render_phase::<P>.label(QueueRenderPhases),
+        )
+    }
+}
+
+pub fn insert_render_phases<P: Phase>(
+    mut commands: Commands,
+    cameras: Query<Entity, (With<Camera>, Without<RenderPhase<P>>)>,
+) {
+    for camera in cameras.iter() {
+        commands.entity(camera).insert(RenderPhase::<P>::default());
+    }
+}
+
+/// Sorts the items with `P`, ties are in entity order if `deterministic`.
+pub fn sort_phase_items<P: Phase>(items: &mut [PhaseItem], deterministic: bool) {
+    if deterministic {
+        // The phase sorts are stable, items at the same distance keep this order
+        items.sort_unstable_by_key(|item| item.entity);
+    }
+    P::sort(items);
+}
+
+///
+/// Distance of `position` in front of the camera along its forward axis.
+///
+/// `view_from_world` is the inverse of `camera.computed.view`, the camera's world transform.
+///
+pub fn view_distance(view_from_world: &Mat4, position: Vec3) -> f32 {
+    -view_from_world.transform_point3(position).z
+}
+
+pub fn queue_render_phase<P: Phase>(
+    render_bundles: Res<RenderBundles>,
+    render_ordering: Res<RenderOrdering>,
+    mut cameras: Query<(Entity, &Camera, &VisibleEntities, &mut RenderPhase<P>)>,
+    entities: Query<(
+        &RenderFunctionId,
+        Option<&GlobalTransform>,
+        Option<&BlendMode>,
+        Option<&AlphaMode>,
+    )>,
+) {
+    for (camera_entity, camera, visible_entities, mut render_phase) in cameras.iter_mut() {
+        render_phase.items.clear();
+        let view_from_world = camera.computed.view.inverse();
+
+        for entity in visible_entities.iter() {
+            if render_bundles.contains(camera_entity, *entity) {
+                continue;
+            }
+            let Ok(entity_data) = entities.get(*entity) else {
+                continue;
+            };
+            let (render_function, transform, blend_mode, alpha_mode) = entity_data;
+            if !P::accepts(blend_mode, alpha_mode) {
+                continue;
+            }
+            let distance = transform.map_or(0.0, |transform| {
+                view_distance(&view_from_world, transform.translation())
+            });
+            render_phase.items.push(PhaseItem {
+                entity: *entity,
+                render_function: *render_function,
+                distance,
+            });
+        }
+
+        sort_phase_items::<P>(&mut render_phase.items, render_ordering.deterministic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Quat, Transform};
+
+    use super::*;
+
+    #[test]
+    fn ties_are_ordered_by_entity() {
+        let item = |index: u32, distance: f32| PhaseItem {
+            entity: Entity::from_raw(index),
+            render_function: 0usize.into(),
+            distance,
+        };
+        let mut a = vec![item(3, 1.0), item(1, 1.0), item(2, 0.0), item(0, 1.0)];
+        let mut b = vec![item(0, 1.0), item(2, 0.0), item(3, 1.0), item(1, 1.0)];
+        sort_phase_items::<Opaque>(&mut a, true);
+        sort_phase_items::<Opaque>(&mut b, true);
+
+        let entities = |items: &[PhaseItem]| -> Vec<u32> {
+            items.iter().map(|item| item.entity.index()).collect()
+        };
+        assert_eq!(entities(&a), vec![2, 0, 1, 3]);
+        assert_eq!(entities(&a), entities(&b));
+
+        sort_phase_items::<Transparent>(&mut a, true);
+        assert_eq!(entities(&a), vec![0, 1, 3, 2]);
+    }
+
+    #[test]
+    fn offset_camera_sorts_by_its_own_distance() {
+        // Camera at z = 10 looking down -z, entity 0 is the closest to it
+        let camera_world = Mat4::from_translation(Vec3::new(0.0, 0.0, 10.0));
+        let view_from_world = camera_world.inverse();
+        let mut items: Vec<PhaseItem> = [8.0, 2.0, -5.0]
+            .into_iter()
+            .enumerate()
+            .map(|(index, z)| PhaseItem {
+                entity: Entity::from_raw(index as u32),
+                render_function: 0usize.into(),
+                distance: view_distance(&view_from_world, Vec3::new(0.0, 0.0, z)),
+            })
+            .collect();
+        assert_eq!(items[0].distance, 2.0);
+
+        let entities = |items: &[PhaseItem]| -> Vec<u32> {
+            items.iter().map(|item| item.entity.index()).collect()
+        };
+        sort_phase_items::<Opaque>(&mut items, false);
+        assert_eq!(entities(&items), vec![0, 1, 2]);
+        sort_phase_items::<Transparent>(&mut items, false);
+        assert_eq!(entities(&items), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn queued_in_front_to_back_order_of_a_moved_camera() {
+        let mut app = App::new();
+        app.init_resource::<RenderBundles>()
+            .init_resource::<RenderOrdering>()
+            .add_system(queue_render_phase::<Opaque>);
+
+        // Distances 5, 15 and 10 along the camera's forward axis, +x
+        let entities: Vec<Entity> = [-5.0, 5.0, 0.0]
+            .into_iter()
+            .map(|x| {
+                let transform = Transform::from_xyz(x, 1.0, 3.0);
+                let render_function: RenderFunctionId = 0usize.into();
+                app.world
+                    .spawn((render_function, GlobalTransform::from(transform)))
+                    .id()
+            })
+            .collect();
+
+        let mut camera = Camera::default();
+        camera.computed.view = Transform::from_xyz(-10.0, 1.0, 3.0)
+            .with_rotation(Quat::from_rotation_y(-std::f32::consts::FRAC_PI_2))
+            .compute_matrix();
+        let mut visible_entities = VisibleEntities::default();
+        for entity in entities.iter().rev() {
+            visible_entities.push(*entity);
+        }
+        let camera = app
+            .world
+            .spawn((camera, visible_entities, RenderPhase::<Opaque>::default()))
+            .id();
+
+        app.update();
+
+        let phase = app.world.get::<RenderPhase<Opaque>>(camera).unwrap();
+        let queued: Vec<(Entity, f32)> = phase
+            .items
+            .iter()
+            .map(|item| (item.entity, item.distance.round()))
+            .collect();
+        assert_eq!(
+            queued,
+            vec![(entities[0], 5.0), (entities[2], 10.0), (entities[1], 15.0)]
+        );
+    }
+}
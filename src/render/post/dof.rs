@@ -0,0 +1,257 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{Commands, Component, Entity, FromWorld, Mat4, Query, Res, ResMut, Resource, World},
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        camera::component::Camera,
+        pass::CameraAttachments,
+        resource::{
+            buffer_pool::BufferPool,
+            component_uniform::ComponentUniforms,
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, RenderPipelineId, VertexState,
+            },
+            renderer::{RenderDevice, RenderQueue},
+            shader::Shader,
+            uniform::DynamicUniformId,
+        },
+        view::grab::GrabTextureLayout,
+    },
+    util::EngineDefault,
+};
+
+use super::{
+    begin_post_pass, copy_to_post_texture, PostDepthBindGroups, PostDepthLayout,
+    DEPTH_OF_FIELD_SHADER_HANDLE,
+};
+
+///
+/// Blurs what the camera sees away from the focal distance, like a camera lens.
+///
+/// Every pixel gathers its neighbors within its blur radius, read from the depth
+/// the camera drew. Transparent draws do not write depth, they are blurred
+/// like what is behind them.
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DepthOfField {
+    /// Distance from the camera that is sharp, in world units
+    pub focal_distance: f32,
+    /// Blur strength, at 1 the blur is half of `max_blur_radius` at twice the focal distance
+    pub aperture: f32,
+    /// Largest blur radius in pixels
+    pub max_blur_radius: f32,
+}
+
+impl Default for DepthOfField {
+    fn default() -> Self {
+        Self {
+            focal_distance: 10.0,
+            aperture: 1.0,
+            max_blur_radius: 8.0,
+        }
+    }
+}
+
+impl DepthOfField {
+    /// Blur radius in pixels at `distance` from the camera, what the shader does.
+    pub fn blur_radius(&self, distance: f32) -> f32 {
+        let distance = distance.max(1e-4);
+        let coc = self.aperture * (distance - self.focal_distance).abs() / distance;
+        coc.clamp(0.0, 1.0) * self.max_blur_radius
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct DepthOfFieldUniform {
+    /// View space from clip space, turns the depth back into a distance
+    inverse_proj: Mat4,
+    focal_distance: f32,
+    aperture: f32,
+    max_blur_radius: f32,
+}
+
+/// Pushes the uniform of every camera with a [`DepthOfField`], it needs the projection too.
+pub fn prepare_depth_of_field_uniforms(
+    mut commands: Commands,
+    mut dof_uniforms: ResMut<ComponentUniforms<DepthOfFieldUniform>>,
+    cameras: Query<(Entity, &Camera, &DepthOfField)>,
+) {
+    let mut spawns: Vec<(Entity, DynamicUniformId<DepthOfFieldUniform>)> = Vec::new();
+
+    dof_uniforms.clear();
+    for (entity, camera, dof) in cameras.iter() {
+        let uniform = DepthOfFieldUniform {
+            inverse_proj: camera.computed.proj.inverse(),
+            focal_distance: dof.focal_distance,
+            aperture: dof.aperture.max(0.0),
+            max_blur_radius: dof.max_blur_radius.max(0.0),
+        };
+        spawns.push((entity, dof_uniforms.push(uniform).into()));
+    }
+
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn queue_depth_of_field_uniforms(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffer_pool: ResMut<BufferPool>,
+    mut dof_uniforms: ResMut<ComponentUniforms<DepthOfFieldUniform>>,
+) {
+    dof_uniforms.write_buffer_pooled(&render_device, &render_queue, &mut buffer_pool);
+}
+
+#[derive(Resource)]
+pub struct DepthOfFieldPipeline {
+    pub dof_layout: BindGroupLayout,
+    pub pipeline_id: RenderPipelineId,
+}
+
+impl FromWorld for DepthOfFieldPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<GrabTextureLayout>,
+            Res<PostDepthLayout>,
+            ResMut<PipelineCache>,
+        )> = SystemState::new(world);
+        let (render_device, grab_layout, depth_layout, mut pipeline_cache) = state.get_mut(world);
+
+        let dof_layout: BindGroupLayout = render_device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("depth_of_field_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(DepthOfFieldUniform::min_size()),
+                    },
+                    count: None,
+                }],
+            })
+            .into();
+
+        let pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("depth_of_field_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![
+                    grab_layout.layout.clone(),
+                    dof_layout.clone(),
+                    depth_layout.layout.clone(),
+                ],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: DEPTH_OF_FIELD_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: DEPTH_OF_FIELD_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            dof_layout,
+            pipeline_id,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct DepthOfFieldBindGroups {
+    pub dof_bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn create_depth_of_field_bind_groups(
+    render_device: Res<RenderDevice>,
+    dof_pipeline: Res<DepthOfFieldPipeline>,
+    dof_uniforms: Res<ComponentUniforms<DepthOfFieldUniform>>,
+    mut dof_bind_groups: ResMut<DepthOfFieldBindGroups>,
+) {
+    let Some(dof_binding) = dof_uniforms.binding() else {
+        return;
+    };
+    dof_bind_groups.dof_bind_group =
+        Some(render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &dof_pipeline.dof_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: dof_binding,
+            }],
+        }));
+}
+
+/// Camera pass blurring the target of cameras with a [`DepthOfField`].
+pub fn encode_depth_of_field<'w>(
+    camera: Entity,
+    world: &'w World,
+    attachments: &CameraAttachments<'w>,
+    command_encoder: &mut wgpu::CommandEncoder,
+) {
+    if !world.entity(camera).contains::<DepthOfField>() {
+        return;
+    }
+    let dof_bind_groups = world.get_resource::<DepthOfFieldBindGroups>().unwrap();
+    let depth_bind_groups = world.get_resource::<PostDepthBindGroups>().unwrap();
+    let (Some(dof_bind_group), Some(depth_bind_group), Some(uniform_id)) = (
+        dof_bind_groups.dof_bind_group.as_ref(),
+        depth_bind_groups.get(&camera),
+        world.get::<DynamicUniformId<DepthOfFieldUniform>>(camera),
+    ) else {
+        return;
+    };
+    let dof_pipeline = world.get_resource::<DepthOfFieldPipeline>().unwrap();
+    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
+    let Some(render_pipeline) = pipeline_cache.get(&dof_pipeline.pipeline_id) else {
+        return;
+    };
+    let Some(source) = copy_to_post_texture(camera, world, attachments, command_encoder) else {
+        return;
+    };
+
+    let mut render_pass = begin_post_pass("depth_of_field_pass", attachments, command_encoder);
+    render_pass.set_pipeline(render_pipeline);
+    render_pass.set_bind_group(0, &source.bind_group, &[]);
+    render_pass.set_bind_group(1, dof_bind_group, &[**uniform_id]);
+    render_pass.set_bind_group(2, depth_bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sharp_at_focal_distance() {
+        let dof = DepthOfField {
+            focal_distance: 10.0,
+            aperture: 1.0,
+            max_blur_radius: 8.0,
+        };
+        assert_eq!(dof.blur_radius(10.0), 0.0);
+        assert_eq!(dof.blur_radius(20.0), 4.0);
+        assert_eq!(dof.blur_radius(5.0), 8.0);
+        assert!(dof.blur_radius(1e6) <= 8.0);
+    }
+}
@@ -0,0 +1,81 @@
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        uv: vec2<f32>,
+}
+
+// One triangle covering the target
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+
+    return out;
+}
+
+struct DepthOfField {
+    inverse_proj: mat4x4<f32>,
+    focal_distance: f32,
+    aperture: f32,
+    max_blur_radius: f32,
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(1) @binding(0)
+var<uniform> dof: DepthOfField;
+
+@group(2) @binding(0)
+var t_depth: texture_depth_2d;
+
+let TAPS: i32 = 32;
+let GOLDEN_ANGLE: f32 = 2.39996323;
+
+fn view_distance(uv: vec2<f32>, size: vec2<f32>) -> f32 {
+    let coords = clamp(vec2<i32>(uv * size), vec2<i32>(0), vec2<i32>(size) - 1);
+    let depth = textureLoad(t_depth, coords, 0);
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let view = dof.inverse_proj * ndc;
+    return -view.z / view.w;
+}
+
+fn blur_radius(distance: f32) -> f32 {
+    let d = max(distance, 0.0001);
+    let coc = dof.aperture * abs(d - dof.focal_distance) / d;
+    return clamp(coc, 0.0, 1.0) * dof.max_blur_radius;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t_source));
+    let texel = 1.0 / size;
+    let center = textureSampleLevel(t_source, s_source, in.uv, 0.0);
+
+    let radius = blur_radius(view_distance(in.uv, size));
+    if radius < 0.5 {
+        return center;
+    }
+
+    // Spiral of taps filling the disk of the blur radius evenly
+    var sum = center.rgb;
+    var weight = 1.0;
+    for (var i = 0; i < TAPS; i = i + 1) {
+        let tap_radius = sqrt((f32(i) + 0.5) / f32(TAPS)) * radius;
+        let angle = f32(i) * GOLDEN_ANGLE;
+        let uv = in.uv + vec2<f32>(cos(angle), sin(angle)) * tap_radius * texel;
+
+        // Taps whose own blur does not reach the pixel are sharp, they do not bleed into it
+        let tap_weight = clamp(blur_radius(view_distance(uv, size)) - tap_radius + 1.0, 0.0, 1.0);
+        sum = sum + textureSampleLevel(t_source, s_source, uv, 0.0).rgb * tap_weight;
+        weight = weight + tap_weight;
+    }
+
+    return vec4<f32>(sum / weight, center.a);
+}
@@ -0,0 +1,212 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{Component, Entity, FromWorld, Res, ResMut, Resource, World},
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        color::ColorSpace,
+        pass::CameraAttachments,
+        resource::{
+            component_uniform::ComponentUniforms,
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, RenderPipelineId, VertexState,
+            },
+            renderer::RenderDevice,
+            shader::Shader,
+            uniform::{DynamicUniformId, HandleGpuUniform},
+        },
+        view::grab::GrabTextureLayout,
+    },
+    util::EngineDefault,
+};
+
+use super::{begin_post_pass, copy_to_post_texture, FXAA_SHADER_HANDLE};
+
+///
+/// Smooths the aliased edges of what the camera renders, by blurring along the edges
+/// found in the brightness of the final image.
+///
+/// Much cheaper than rendering more samples and works on WebGL,
+/// but it also softens textures and thin details. Runs first of the post passes,
+/// on the colors the camera drew.
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Fxaa {
+    /// Smallest brightness contrast, relative to the brightest neighbor, that is an edge
+    pub edge_threshold: f32,
+    /// Contrast below which dark areas are left alone
+    pub edge_threshold_min: f32,
+    /// Blur of edges thinner than a pixel, 0 is off and 1 the softest
+    pub subpixel_blending: f32,
+}
+
+impl Default for Fxaa {
+    fn default() -> Self {
+        Self::HIGH
+    }
+}
+
+impl Fxaa {
+    pub const LOW: Self = Self {
+        edge_threshold: 0.25,
+        edge_threshold_min: 0.0833,
+        subpixel_blending: 0.5,
+    };
+    pub const HIGH: Self = Self {
+        edge_threshold: 0.166,
+        edge_threshold_min: 0.0833,
+        subpixel_blending: 0.75,
+    };
+    pub const ULTRA: Self = Self {
+        edge_threshold: 0.125,
+        edge_threshold_min: 0.0625,
+        subpixel_blending: 1.0,
+    };
+}
+
+#[derive(Clone, ShaderType)]
+pub struct FxaaUniform {
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    subpixel_blending: f32,
+}
+
+impl HandleGpuUniform for Fxaa {
+    type GU = FxaaUniform;
+
+    fn into_uniform(&self, _color_space: ColorSpace) -> Self::GU {
+        FxaaUniform {
+            edge_threshold: self.edge_threshold.max(0.0),
+            edge_threshold_min: self.edge_threshold_min.max(0.0),
+            subpixel_blending: self.subpixel_blending.clamp(0.0, 1.0),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct FxaaPipeline {
+    pub fxaa_layout: BindGroupLayout,
+    pub pipeline_id: RenderPipelineId,
+}
+
+impl FromWorld for FxaaPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<GrabTextureLayout>,
+            ResMut<PipelineCache>,
+        )> = SystemState::new(world);
+        let (render_device, grab_layout, mut pipeline_cache) = state.get_mut(world);
+
+        let fxaa_layout: BindGroupLayout = render_device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("fxaa_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(FxaaUniform::min_size()),
+                    },
+                    count: None,
+                }],
+            })
+            .into();
+
+        let pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("fxaa_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![grab_layout.layout.clone(), fxaa_layout.clone()],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: FXAA_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: FXAA_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            fxaa_layout,
+            pipeline_id,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct FxaaBindGroups {
+    pub fxaa_bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn create_fxaa_bind_groups(
+    render_device: Res<RenderDevice>,
+    fxaa_pipeline: Res<FxaaPipeline>,
+    fxaa_uniforms: Res<ComponentUniforms<FxaaUniform>>,
+    mut fxaa_bind_groups: ResMut<FxaaBindGroups>,
+) {
+    let Some(fxaa_binding) = fxaa_uniforms.binding() else {
+        return;
+    };
+    fxaa_bind_groups.fxaa_bind_group =
+        Some(render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &fxaa_pipeline.fxaa_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: fxaa_binding,
+            }],
+        }));
+}
+
+/// Camera pass anti-aliasing the target of cameras with [`Fxaa`].
+pub fn encode_fxaa<'w>(
+    camera: Entity,
+    world: &'w World,
+    attachments: &CameraAttachments<'w>,
+    command_encoder: &mut wgpu::CommandEncoder,
+) {
+    if !world.entity(camera).contains::<Fxaa>() {
+        return;
+    }
+    let fxaa_bind_groups = world.get_resource::<FxaaBindGroups>().unwrap();
+    let (Some(fxaa_bind_group), Some(uniform_id)) = (
+        fxaa_bind_groups.fxaa_bind_group.as_ref(),
+        world.get::<DynamicUniformId<FxaaUniform>>(camera),
+    ) else {
+        return;
+    };
+    let fxaa_pipeline = world.get_resource::<FxaaPipeline>().unwrap();
+    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
+    let Some(render_pipeline) = pipeline_cache.get(&fxaa_pipeline.pipeline_id) else {
+        return;
+    };
+    let Some(source) = copy_to_post_texture(camera, world, attachments, command_encoder) else {
+        return;
+    };
+
+    let mut render_pass = begin_post_pass("fxaa_pass", attachments, command_encoder);
+    render_pass.set_pipeline(render_pipeline);
+    render_pass.set_bind_group(0, &source.bind_group, &[]);
+    render_pass.set_bind_group(1, fxaa_bind_group, &[**uniform_id]);
+    render_pass.draw(0..3, 0..1);
+}
@@ -0,0 +1,165 @@
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        uv: vec2<f32>,
+}
+
+// One triangle covering the target
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+
+    return out;
+}
+
+struct Fxaa {
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    subpixel_blending: f32,
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(1) @binding(0)
+var<uniform> fxaa: Fxaa;
+
+let SEARCH_STEPS: i32 = 10;
+
+// Perceptual brightness, edges are found as the eye sees them and not in linear light
+fn luma(color: vec3<f32>) -> f32 {
+    return sqrt(dot(color, vec3<f32>(0.2126, 0.7152, 0.0722)));
+}
+
+fn sample_luma(uv: vec2<f32>) -> f32 {
+    return luma(textureSampleLevel(t_source, s_source, uv, 0.0).rgb);
+}
+
+// Distance the edge search moves at each step, longer further away
+fn search_step(i: i32) -> f32 {
+    if i < 2 {
+        return 1.0;
+    }
+    if i < 6 {
+        return 1.5;
+    }
+    if i < 8 {
+        return 2.0;
+    }
+    return 4.0;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+    let center = textureSampleLevel(t_source, s_source, in.uv, 0.0);
+
+    let luma_m = luma(center.rgb);
+    let luma_n = sample_luma(in.uv + vec2<f32>(0.0, -texel.y));
+    let luma_s = sample_luma(in.uv + vec2<f32>(0.0, texel.y));
+    let luma_w = sample_luma(in.uv + vec2<f32>(-texel.x, 0.0));
+    let luma_e = sample_luma(in.uv + vec2<f32>(texel.x, 0.0));
+
+    let luma_min = min(luma_m, min(min(luma_n, luma_s), min(luma_w, luma_e)));
+    let luma_max = max(luma_m, max(max(luma_n, luma_s), max(luma_w, luma_e)));
+    let range = luma_max - luma_min;
+    if range < max(fxaa.edge_threshold_min, luma_max * fxaa.edge_threshold) {
+        return center;
+    }
+
+    let luma_nw = sample_luma(in.uv + vec2<f32>(-texel.x, -texel.y));
+    let luma_ne = sample_luma(in.uv + vec2<f32>(texel.x, -texel.y));
+    let luma_sw = sample_luma(in.uv + vec2<f32>(-texel.x, texel.y));
+    let luma_se = sample_luma(in.uv + vec2<f32>(texel.x, texel.y));
+
+    let edge_horizontal = abs(luma_nw + luma_sw - 2.0 * luma_w)
+        + 2.0 * abs(luma_n + luma_s - 2.0 * luma_m)
+        + abs(luma_ne + luma_se - 2.0 * luma_e);
+    let edge_vertical = abs(luma_nw + luma_ne - 2.0 * luma_n)
+        + 2.0 * abs(luma_w + luma_e - 2.0 * luma_m)
+        + abs(luma_sw + luma_se - 2.0 * luma_s);
+    let is_horizontal = edge_horizontal >= edge_vertical;
+
+    // The side of the edge with the larger contrast
+    let luma_1 = select(luma_w, luma_n, is_horizontal);
+    let luma_2 = select(luma_e, luma_s, is_horizontal);
+    let gradient_1 = luma_1 - luma_m;
+    let gradient_2 = luma_2 - luma_m;
+    let is_1_steepest = abs(gradient_1) >= abs(gradient_2);
+    let gradient_scaled = 0.25 * max(abs(gradient_1), abs(gradient_2));
+
+    var step_length = select(texel.x, texel.y, is_horizontal);
+    var luma_local_average = 0.5 * (luma_2 + luma_m);
+    if is_1_steepest {
+        step_length = -step_length;
+        luma_local_average = 0.5 * (luma_1 + luma_m);
+    }
+
+    // Halfway between the pixel and its neighbor across the edge
+    var edge_uv = in.uv;
+    if is_horizontal {
+        edge_uv.y = edge_uv.y + step_length * 0.5;
+    } else {
+        edge_uv.x = edge_uv.x + step_length * 0.5;
+    }
+
+    // Walks along the edge both ways until the contrast changes
+    let offset = select(vec2<f32>(0.0, texel.y), vec2<f32>(texel.x, 0.0), is_horizontal);
+    var uv_1 = edge_uv;
+    var uv_2 = edge_uv;
+    var luma_end_1 = 0.0;
+    var luma_end_2 = 0.0;
+    var reached_1 = false;
+    var reached_2 = false;
+    for (var i = 0; i < SEARCH_STEPS; i = i + 1) {
+        if !reached_1 {
+            uv_1 = uv_1 - offset * search_step(i);
+            luma_end_1 = sample_luma(uv_1) - luma_local_average;
+            reached_1 = abs(luma_end_1) >= gradient_scaled;
+        }
+        if !reached_2 {
+            uv_2 = uv_2 + offset * search_step(i);
+            luma_end_2 = sample_luma(uv_2) - luma_local_average;
+            reached_2 = abs(luma_end_2) >= gradient_scaled;
+        }
+        if reached_1 && reached_2 {
+            break;
+        }
+    }
+
+    let distance_1 = select(in.uv.y - uv_1.y, in.uv.x - uv_1.x, is_horizontal);
+    let distance_2 = select(uv_2.y - in.uv.y, uv_2.x - in.uv.x, is_horizontal);
+    let is_direction_1 = distance_1 < distance_2;
+    let distance_final = min(distance_1, distance_2);
+    let edge_thickness = distance_1 + distance_2;
+
+    // Only blends when the end of the edge closest to the pixel goes the right way
+    let luma_end = select(luma_end_2, luma_end_1, is_direction_1);
+    let is_center_smaller = luma_m < luma_local_average;
+    var pixel_offset = 0.0;
+    if (luma_end < 0.0) != is_center_smaller {
+        pixel_offset = 0.5 - distance_final / edge_thickness;
+    }
+
+    let luma_average = (2.0 * (luma_n + luma_s + luma_w + luma_e)
+        + luma_nw + luma_ne + luma_sw + luma_se) / 12.0;
+    let subpixel_1 = clamp(abs(luma_average - luma_m) / range, 0.0, 1.0);
+    let subpixel_2 = (-2.0 * subpixel_1 + 3.0) * subpixel_1 * subpixel_1;
+    let subpixel_offset = subpixel_2 * subpixel_2 * fxaa.subpixel_blending;
+    let final_offset = max(pixel_offset, subpixel_offset);
+
+    var final_uv = in.uv;
+    if is_horizontal {
+        final_uv.y = final_uv.y + final_offset * step_length;
+    } else {
+        final_uv.x = final_uv.x + final_offset * step_length;
+    }
+    return textureSampleLevel(t_source, s_source, final_uv, 0.0);
+}
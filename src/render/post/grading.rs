@@ -0,0 +1,352 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{
+        Assets, Component, Deref, DerefMut, Entity, FromWorld, Handle, Query, Res, ResMut,
+        Resource, Vec3, World,
+    },
+    utils::HashMap,
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        cleanup::EntityRenderState,
+        color::ColorSpace,
+        pass::CameraAttachments,
+        resource::{
+            component_uniform::ComponentUniforms,
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, RenderPipelineId, VertexState,
+            },
+            renderer::{RenderDevice, RenderQueue},
+            shader::Shader,
+            uniform::{DynamicUniformId, HandleGpuUniform},
+        },
+        texture::{GpuTexture, Image, PixelFormat, RawImage},
+        view::grab::GrabTextureLayout,
+        RenderAssets,
+    },
+    util::EngineDefault,
+};
+
+use super::{begin_post_pass, copy_to_post_texture, COLOR_GRADING_SHADER_HANDLE};
+
+///
+/// Sets the look of what the camera renders, applied by the last pass of the camera
+/// in linear color: exposure, tonemapping, contrast, saturation, then the LUT.
+///
+/// The default changes nothing.
+///
+#[derive(Component, Clone, Debug)]
+pub struct ColorGrading {
+    /// Exposure value, every +1 doubles the brightness
+    pub exposure: f32,
+    pub tonemapping: Tonemapping,
+    /// Around middle grey, 1 is unchanged
+    pub contrast: f32,
+    /// 0 is greyscale, 1 is unchanged
+    pub saturation: f32,
+    /// 3D lookup table of `N` slices of `N x N` side by side, `N * N` wide and `N` high.
+    /// Red grows to the right in a slice, green downwards and blue from slice to slice,
+    /// indexed by the sRGB encoded color. Ignored while not loaded or not of that shape.
+    pub lut: Option<Handle<Image>>,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            tonemapping: Tonemapping::None,
+            contrast: 1.0,
+            saturation: 1.0,
+            lut: None,
+        }
+    }
+}
+
+/// Maps the unbounded brightness after the exposure into the range of the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tonemapping {
+    /// Clamps, brighter than white saturates
+    #[default]
+    None,
+    /// `c / (1 + c)`
+    Reinhard,
+    /// Fitted ACES filmic curve, more contrast and a soft shoulder
+    Aces,
+}
+
+const MIDDLE_GREY: f32 = 0.18;
+const LUMA: Vec3 = Vec3::new(0.2126, 0.7152, 0.0722);
+
+impl ColorGrading {
+    /// The grading before the LUT, what the shader does.
+    pub fn grade(&self, color: Vec3) -> Vec3 {
+        let color = color * 2f32.powf(self.exposure);
+        let color = match self.tonemapping {
+            Tonemapping::None => color,
+            Tonemapping::Reinhard => color / (Vec3::ONE + color),
+            Tonemapping::Aces => {
+                (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14)
+            }
+        };
+        let color = (color.max(Vec3::ZERO) / MIDDLE_GREY).powf(self.contrast) * MIDDLE_GREY;
+        let luma = Vec3::splat(color.dot(LUMA));
+        luma.lerp(color, self.saturation).max(Vec3::ZERO)
+    }
+}
+
+/// Size `N` of a LUT strip, `None` if the image is not `N * N` wide and `N` high.
+pub fn lut_size(width: u32, height: u32) -> Option<u32> {
+    (height >= 2 && width == height * height).then_some(height)
+}
+
+#[derive(Clone, ShaderType)]
+pub struct ColorGradingUniform {
+    exposure: f32,
+    /// 0 is none, then Reinhard and ACES
+    tonemapping: u32,
+    contrast: f32,
+    saturation: f32,
+}
+
+impl HandleGpuUniform for ColorGrading {
+    type GU = ColorGradingUniform;
+
+    fn into_uniform(&self, _color_space: ColorSpace) -> Self::GU {
+        ColorGradingUniform {
+            exposure: 2f32.powf(self.exposure),
+            tonemapping: match self.tonemapping {
+                Tonemapping::None => 0,
+                Tonemapping::Reinhard => 1,
+                Tonemapping::Aces => 2,
+            },
+            contrast: self.contrast,
+            saturation: self.saturation,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ColorGradingPipeline {
+    /// Uniform at binding 0, the LUT and its sampler at 1 and 2
+    pub grading_layout: BindGroupLayout,
+    pub lut_sampler: wgpu::Sampler,
+    /// 1 x 1, the shader skips the LUT of a single texel
+    pub dummy_lut: GpuTexture,
+    pub pipeline_id: RenderPipelineId,
+}
+
+impl FromWorld for ColorGradingPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<RenderQueue>,
+            Res<GrabTextureLayout>,
+            ResMut<PipelineCache>,
+        )> = SystemState::new(world);
+        let (render_device, render_queue, grab_layout, mut pipeline_cache) = state.get_mut(world);
+
+        let grading_layout: BindGroupLayout = render_device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("color_grading_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: Some(ColorGradingUniform::min_size()),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            })
+            .into();
+
+        let pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("color_grading_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![grab_layout.layout.clone(), grading_layout.clone()],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: COLOR_GRADING_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: COLOR_GRADING_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // Linear between the texels of a slice, the shader blends the two nearest slices
+        let lut_sampler = render_device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let dummy_lut = GpuTexture::from_raw_image(
+            &render_device,
+            &render_queue,
+            &RawImage::new(&[255u8; 4], (1, 1), PixelFormat::RGBA8),
+            Some("dummy_lut"),
+        )
+        .unwrap();
+
+        Self {
+            grading_layout,
+            lut_sampler,
+            dummy_lut,
+            pipeline_id,
+        }
+    }
+}
+
+/// Grading bind group of every camera with a [`ColorGrading`], rebuilt every frame.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ColorGradingBindGroups(pub HashMap<Entity, wgpu::BindGroup>);
+
+impl EntityRenderState for ColorGradingBindGroups {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(&entity);
+    }
+}
+
+pub fn create_color_grading_bind_groups(
+    render_device: Res<RenderDevice>,
+    grading_pipeline: Res<ColorGradingPipeline>,
+    grading_uniforms: Res<ComponentUniforms<ColorGradingUniform>>,
+    images: Res<Assets<Image>>,
+    gpu_textures: Res<RenderAssets<Image>>,
+    mut bind_groups: ResMut<ColorGradingBindGroups>,
+    cameras: Query<(Entity, &ColorGrading)>,
+) {
+    bind_groups.clear();
+    let Some(grading_binding) = grading_uniforms.binding() else {
+        return;
+    };
+    for (entity, grading) in cameras.iter() {
+        let lut = grading
+            .lut
+            .as_ref()
+            .filter(|lut| {
+                images.get(lut).map_or(false, |image| {
+                    let dim = image.dim();
+                    lut_size(dim.width, dim.heigth).is_some()
+                })
+            })
+            .and_then(|lut| gpu_textures.get(&lut.id()))
+            .unwrap_or(&grading_pipeline.dummy_lut);
+
+        let bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("color_grading_bind_group"),
+            layout: &grading_pipeline.grading_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: grading_binding.clone(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&lut.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&grading_pipeline.lut_sampler),
+                },
+            ],
+        });
+        bind_groups.insert(entity, bind_group);
+    }
+}
+
+/// Camera pass grading the target of cameras with a [`ColorGrading`].
+pub fn encode_color_grading<'w>(
+    camera: Entity,
+    world: &'w World,
+    attachments: &CameraAttachments<'w>,
+    command_encoder: &mut wgpu::CommandEncoder,
+) {
+    let bind_groups = world.get_resource::<ColorGradingBindGroups>().unwrap();
+    let (Some(bind_group), Some(uniform_id)) = (
+        bind_groups.get(&camera),
+        world.get::<DynamicUniformId<ColorGradingUniform>>(camera),
+    ) else {
+        return;
+    };
+    let grading_pipeline = world.get_resource::<ColorGradingPipeline>().unwrap();
+    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
+    let Some(render_pipeline) = pipeline_cache.get(&grading_pipeline.pipeline_id) else {
+        return;
+    };
+    let Some(source) = copy_to_post_texture(camera, world, attachments, command_encoder) else {
+        return;
+    };
+
+    let mut render_pass = begin_post_pass("color_grading_pass", attachments, command_encoder);
+    render_pass.set_pipeline(render_pipeline);
+    render_pass.set_bind_group(0, &source.bind_group, &[]);
+    render_pass.set_bind_group(1, bind_group, &[**uniform_id]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_grading_keeps_colors() {
+        let color = Vec3::new(0.8, 0.3, 0.05);
+        let graded = ColorGrading::default().grade(color);
+        assert!((graded - color).abs().max_element() < 1e-5);
+
+        let grading = ColorGrading {
+            exposure: 1.0,
+            saturation: 0.0,
+            ..Default::default()
+        };
+        let graded = grading.grade(color);
+        assert!((graded.x - graded.y).abs() < 1e-5 && (graded.y - graded.z).abs() < 1e-5);
+        assert!((graded.x - 2.0 * color.dot(LUMA)).abs() < 1e-5);
+
+        assert_eq!(lut_size(256, 16), Some(16));
+        assert_eq!(lut_size(256, 15), None);
+        assert_eq!(lut_size(1, 1), None);
+    }
+}
@@ -0,0 +1,86 @@
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        uv: vec2<f32>,
+}
+
+// One triangle covering the target
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+
+    return out;
+}
+
+struct ColorGrading {
+    exposure: f32,
+    tonemapping: u32,
+    contrast: f32,
+    saturation: f32,
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(1) @binding(0)
+var<uniform> grading: ColorGrading;
+@group(1) @binding(1)
+var t_lut: texture_2d<f32>;
+@group(1) @binding(2)
+var s_lut: sampler;
+
+let MIDDLE_GREY: f32 = 0.18;
+
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+    if grading.tonemapping == 1u {
+        return color / (1.0 + color);
+    }
+    if grading.tonemapping == 2u {
+        return (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14);
+    }
+    return color;
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+// Blends the two slices around blue, the sampler filters red and green
+fn sample_lut(color: vec3<f32>, size: f32) -> vec3<f32> {
+    let encoded = clamp(linear_to_srgb(color), vec3<f32>(0.0), vec3<f32>(1.0));
+    let slice = encoded.b * (size - 1.0);
+    let slice_low = floor(slice);
+    let slice_high = min(slice_low + 1.0, size - 1.0);
+    let texel = encoded.rg * (size - 1.0) + 0.5;
+    let y = texel.y / size;
+    // Sampling the sRGB texture decodes it, the result is linear again
+    let low = textureSample(t_lut, s_lut, vec2<f32>((slice_low * size + texel.x) / (size * size), y));
+    let high = textureSample(t_lut, s_lut, vec2<f32>((slice_high * size + texel.x) / (size * size), y));
+    return mix(low.rgb, high.rgb, slice - slice_low);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let source = textureSample(t_source, s_source, in.uv);
+
+    var color = tonemap(source.rgb * grading.exposure);
+    color = pow(max(color, vec3<f32>(0.0)) / MIDDLE_GREY, vec3<f32>(grading.contrast)) * MIDDLE_GREY;
+    let luma = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    color = max(mix(vec3<f32>(luma), color, grading.saturation), vec3<f32>(0.0));
+
+    let lut_size = f32(textureDimensions(t_lut).y);
+    if lut_size > 1.0 {
+        color = sample_lut(color, lut_size);
+    }
+
+    return vec4<f32>(color, source.a);
+}
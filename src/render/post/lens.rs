@@ -0,0 +1,318 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{
+        Commands, Component, Entity, FromWorld, Or, Query, Res, ResMut, Resource, Vec4, With,
+        Without, World,
+    },
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        extract::ExtractedTime,
+        pass::CameraAttachments,
+        resource::{
+            buffer_pool::BufferPool,
+            component_uniform::ComponentUniforms,
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, RenderPipelineId, VertexState,
+            },
+            renderer::{RenderDevice, RenderQueue},
+            shader::Shader,
+            uniform::DynamicUniformId,
+        },
+        view::grab::GrabTextureLayout,
+    },
+    util::EngineDefault,
+};
+
+use super::{begin_post_pass, copy_to_post_texture, LENS_EFFECTS_SHADER_HANDLE};
+
+/// Darkens the corners of what the camera renders.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Vignette {
+    /// Darkening at the corners, 0 is none and 1 is black
+    pub intensity: f32,
+    /// Distance from the center the darkening starts at, 1 is the corners
+    pub radius: f32,
+    /// Distance over which it fades in
+    pub smoothness: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            intensity: 0.4,
+            radius: 0.5,
+            smoothness: 0.5,
+        }
+    }
+}
+
+/// Splits red and blue away from the center towards the edges, like a cheap lens.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ChromaticAberration {
+    /// Offset of red and blue at the edges, as a fraction of the target width
+    pub intensity: f32,
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        Self { intensity: 0.004 }
+    }
+}
+
+/// Noise over what the camera renders, a new pattern every frame.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FilmGrain {
+    /// Largest change of brightness
+    pub intensity: f32,
+    /// Size of a grain in pixels
+    pub size: f32,
+}
+
+impl Default for FilmGrain {
+    fn default() -> Self {
+        Self {
+            intensity: 0.05,
+            size: 1.5,
+        }
+    }
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct LensEffectsUniform {
+    /// Intensity, radius and smoothness of the vignette
+    vignette: Vec4,
+    aberration: f32,
+    grain_intensity: f32,
+    grain_size: f32,
+    time: f32,
+}
+
+impl LensEffectsUniform {
+    /// Effects the camera does not have are zero, the shader skips them.
+    pub fn new(
+        vignette: Option<&Vignette>,
+        aberration: Option<&ChromaticAberration>,
+        grain: Option<&FilmGrain>,
+        time: f32,
+    ) -> Self {
+        Self {
+            vignette: vignette.map_or(Vec4::ZERO, |vignette| {
+                Vec4::new(
+                    vignette.intensity.clamp(0.0, 1.0),
+                    vignette.radius,
+                    vignette.smoothness.max(1e-4),
+                    0.0,
+                )
+            }),
+            aberration: aberration.map_or(0.0, |aberration| aberration.intensity),
+            grain_intensity: grain.map_or(0.0, |grain| grain.intensity),
+            grain_size: grain.map_or(1.0, |grain| grain.size.max(1.0)),
+            time,
+        }
+    }
+}
+
+type LensEffectsFilter = Or<(With<Vignette>, With<ChromaticAberration>, With<FilmGrain>)>;
+
+/// Inserted on the cameras with any of the lens effects, removed once they have none.
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct LensEffects;
+
+/// Combines the lens effects of every camera into one uniform, they are drawn in one pass.
+pub fn prepare_lens_effects_uniforms(
+    mut commands: Commands,
+    time: Res<ExtractedTime>,
+    mut lens_uniforms: ResMut<ComponentUniforms<LensEffectsUniform>>,
+    cameras: Query<
+        (
+            Entity,
+            Option<&Vignette>,
+            Option<&ChromaticAberration>,
+            Option<&FilmGrain>,
+        ),
+        LensEffectsFilter,
+    >,
+    removed: Query<
+        Entity,
+        (
+            With<LensEffects>,
+            Without<Vignette>,
+            Without<ChromaticAberration>,
+            Without<FilmGrain>,
+        ),
+    >,
+) {
+    for entity in removed.iter() {
+        commands.entity(entity).remove::<LensEffects>();
+    }
+
+    let mut spawns: Vec<(Entity, LensEffects, DynamicUniformId<LensEffectsUniform>)> = Vec::new();
+
+    lens_uniforms.clear();
+    for (entity, vignette, aberration, grain) in cameras.iter() {
+        let uniform = LensEffectsUniform::new(vignette, aberration, grain, time.elapsed_seconds);
+        spawns.push((entity, LensEffects, lens_uniforms.push(uniform).into()));
+    }
+
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn queue_lens_effects_uniforms(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffer_pool: ResMut<BufferPool>,
+    mut lens_uniforms: ResMut<ComponentUniforms<LensEffectsUniform>>,
+) {
+    lens_uniforms.write_buffer_pooled(&render_device, &render_queue, &mut buffer_pool);
+}
+
+#[derive(Resource)]
+pub struct LensEffectsPipeline {
+    pub lens_layout: BindGroupLayout,
+    pub pipeline_id: RenderPipelineId,
+}
+
+impl FromWorld for LensEffectsPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<GrabTextureLayout>,
+            ResMut<PipelineCache>,
+        )> = SystemState::new(world);
+        let (render_device, grab_layout, mut pipeline_cache) = state.get_mut(world);
+
+        let lens_layout: BindGroupLayout = render_device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("lens_effects_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(LensEffectsUniform::min_size()),
+                    },
+                    count: None,
+                }],
+            })
+            .into();
+
+        let pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("lens_effects_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![grab_layout.layout.clone(), lens_layout.clone()],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: LENS_EFFECTS_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: LENS_EFFECTS_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            lens_layout,
+            pipeline_id,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LensEffectsBindGroups {
+    pub lens_bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn create_lens_effects_bind_groups(
+    render_device: Res<RenderDevice>,
+    lens_pipeline: Res<LensEffectsPipeline>,
+    lens_uniforms: Res<ComponentUniforms<LensEffectsUniform>>,
+    mut lens_bind_groups: ResMut<LensEffectsBindGroups>,
+) {
+    let Some(lens_binding) = lens_uniforms.binding() else {
+        return;
+    };
+    lens_bind_groups.lens_bind_group =
+        Some(render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &lens_pipeline.lens_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: lens_binding,
+            }],
+        }));
+}
+
+/// Camera pass drawing the [`Vignette`], [`ChromaticAberration`] and [`FilmGrain`] of the camera.
+pub fn encode_lens_effects<'w>(
+    camera: Entity,
+    world: &'w World,
+    attachments: &CameraAttachments<'w>,
+    command_encoder: &mut wgpu::CommandEncoder,
+) {
+    if !world.entity(camera).contains::<LensEffects>() {
+        return;
+    }
+    let lens_bind_groups = world.get_resource::<LensEffectsBindGroups>().unwrap();
+    let (Some(lens_bind_group), Some(uniform_id)) = (
+        lens_bind_groups.lens_bind_group.as_ref(),
+        world.get::<DynamicUniformId<LensEffectsUniform>>(camera),
+    ) else {
+        return;
+    };
+    let lens_pipeline = world.get_resource::<LensEffectsPipeline>().unwrap();
+    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
+    let Some(render_pipeline) = pipeline_cache.get(&lens_pipeline.pipeline_id) else {
+        return;
+    };
+    let Some(source) = copy_to_post_texture(camera, world, attachments, command_encoder) else {
+        return;
+    };
+
+    let mut render_pass = begin_post_pass("lens_effects_pass", attachments, command_encoder);
+    render_pass.set_pipeline(render_pipeline);
+    render_pass.set_bind_group(0, &source.bind_group, &[]);
+    render_pass.set_bind_group(1, lens_bind_group, &[**uniform_id]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_effects_are_zero() {
+        let uniform = LensEffectsUniform::new(None, None, None, 1.0);
+        assert_eq!(uniform.vignette, Vec4::ZERO);
+        assert_eq!(uniform.aberration, 0.0);
+        assert_eq!(uniform.grain_intensity, 0.0);
+
+        let vignette = Vignette {
+            intensity: 2.0,
+            smoothness: 0.0,
+            ..Default::default()
+        };
+        let uniform = LensEffectsUniform::new(Some(&vignette), None, None, 1.0);
+        assert_eq!(uniform.vignette.x, 1.0);
+        assert!(uniform.vignette.z > 0.0);
+    }
+}
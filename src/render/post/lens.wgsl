@@ -0,0 +1,73 @@
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        uv: vec2<f32>,
+}
+
+// One triangle covering the target
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+
+    return out;
+}
+
+struct LensEffects {
+    // intensity, radius, smoothness
+    vignette: vec4<f32>,
+    aberration: f32,
+    grain_intensity: f32,
+    grain_size: f32,
+    time: f32,
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(1) @binding(0)
+var<uniform> lens: LensEffects;
+
+fn hash(p: vec3<f32>) -> f32 {
+    let q = fract(p * 0.1031);
+    let r = q + dot(q, q.zyx + 31.32);
+    return fract((r.x + r.y) * r.z);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t_source));
+    // -1 to 1 from the center, corrected so the vignette is round
+    let centered = in.uv * 2.0 - 1.0;
+    let aspect = vec2<f32>(size.x / size.y, 1.0);
+
+    var color = textureSample(t_source, s_source, in.uv);
+
+    // Red and blue pushed apart along the direction from the center, more towards the edges
+    let offset = centered * lens.aberration;
+    let red = textureSample(t_source, s_source, in.uv + offset).r;
+    let blue = textureSample(t_source, s_source, in.uv - offset).b;
+    if lens.aberration != 0.0 {
+        color = vec4<f32>(red, color.g, blue, color.a);
+    }
+
+    if lens.vignette.x > 0.0 {
+        let distance = length(centered * aspect) / length(aspect);
+        let darkening = smoothstep(lens.vignette.y, lens.vignette.y + lens.vignette.z, distance);
+        color = vec4<f32>(color.rgb * (1.0 - darkening * lens.vignette.x), color.a);
+    }
+
+    if lens.grain_intensity > 0.0 {
+        let cell = floor(in.uv * size / lens.grain_size);
+        let noise = hash(vec3<f32>(cell, fract(lens.time) * 1000.0)) * 2.0 - 1.0;
+        color = vec4<f32>(max(color.rgb + noise * lens.grain_intensity, vec3<f32>(0.0)), color.a);
+    }
+
+    return color;
+}
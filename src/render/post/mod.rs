@@ -0,0 +1,295 @@
+//!
+//! Post-processing passes, full screen passes run on the color target of a camera
+//! after everything else it draws.
+//!
+//! Every pass copies the target into the [`PostTextures`] of the camera and draws
+//! a full screen triangle sampling the copy back into the target. Effects are
+//! camera components, cameras without them skip the pass.
+//!
+//! Passes that need the depth of the scene bind the depth texture of the camera
+//! with [`PostDepthLayout`], see [`PostDepthBindGroups`].
+//!
+
+use bevy::{
+    asset::load_internal_asset,
+    ecs::system::SystemState,
+    prelude::{
+        App, Assets, Component, Deref, DerefMut, Entity, FromWorld, HandleUntyped,
+        IntoSystemDescriptor, Plugin, Query, Res, ResMut, Resource, With, World,
+    },
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+
+use crate::render::{
+    camera::component::Camera,
+    cleanup::{AddEntityCleanup, EntityRenderState},
+    pass::{AddCameraPass, CameraAttachments, CameraPassStage},
+    resource::{
+        component_uniform::{queue_component_uniforms, AddComponentUniform, ComponentUniforms},
+        pipeline::BindGroupLayout,
+        renderer::RenderDevice,
+        shader::Shader,
+    },
+    texture::{DepthTextures, Image},
+    view::{
+        grab::{GrabTexture, GrabTextureLayout},
+        window::PreparedWindows,
+    },
+    RenderStage,
+};
+
+use self::{
+    dof::{
+        create_depth_of_field_bind_groups, encode_depth_of_field, prepare_depth_of_field_uniforms,
+        queue_depth_of_field_uniforms, DepthOfField, DepthOfFieldBindGroups, DepthOfFieldPipeline,
+        DepthOfFieldUniform,
+    },
+    fxaa::{create_fxaa_bind_groups, encode_fxaa, Fxaa, FxaaBindGroups, FxaaPipeline},
+    grading::{
+        create_color_grading_bind_groups, encode_color_grading, ColorGrading,
+        ColorGradingBindGroups, ColorGradingPipeline,
+    },
+    lens::{
+        create_lens_effects_bind_groups, encode_lens_effects, prepare_lens_effects_uniforms,
+        queue_lens_effects_uniforms, LensEffects, LensEffectsBindGroups, LensEffectsPipeline,
+        LensEffectsUniform,
+    },
+};
+
+pub mod dof;
+pub mod fxaa;
+pub mod grading;
+pub mod lens;
+
+pub const COLOR_GRADING_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 85678909876445673);
+pub const DEPTH_OF_FIELD_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 85678909876445676);
+pub const FXAA_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 85678909876445675);
+pub const LENS_EFFECTS_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 85678909876445674);
+
+///
+/// Add it after the plugins adding other camera passes,
+/// post passes run last so they process the outlines and overlays too.
+///
+pub struct FlatPostPlugin;
+impl Plugin for FlatPostPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            COLOR_GRADING_SHADER_HANDLE,
+            "grading.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            DEPTH_OF_FIELD_SHADER_HANDLE,
+            "dof.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(app, FXAA_SHADER_HANDLE, "fxaa.wgsl", Shader::from_wgsl);
+        load_internal_asset!(
+            app,
+            LENS_EFFECTS_SHADER_HANDLE,
+            "lens.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.init_resource::<PostTextures>()
+            .init_resource::<PostDepthLayout>()
+            .init_resource::<PostDepthBindGroups>()
+            .init_resource::<DepthOfFieldPipeline>()
+            .init_resource::<DepthOfFieldBindGroups>()
+            .init_resource::<ComponentUniforms<DepthOfFieldUniform>>()
+            .init_resource::<ColorGradingPipeline>()
+            .init_resource::<ColorGradingBindGroups>()
+            .init_resource::<FxaaPipeline>()
+            .init_resource::<FxaaBindGroups>()
+            .init_resource::<LensEffectsPipeline>()
+            .init_resource::<LensEffectsBindGroups>()
+            .init_resource::<ComponentUniforms<LensEffectsUniform>>()
+            .add_entity_cleanup::<PostTextures, Camera>()
+            .add_entity_cleanup::<PostDepthBindGroups, Camera>()
+            .add_entity_cleanup::<ColorGradingBindGroups, ColorGrading>()
+            .add_component_uniform::<ColorGrading>()
+            .add_component_uniform::<Fxaa>()
+            .add_system_to_stage(RenderStage::Prepare, prepare_depth_of_field_uniforms)
+            .add_system_to_stage(RenderStage::Create, queue_depth_of_field_uniforms)
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_depth_of_field_bind_groups.after(queue_depth_of_field_uniforms),
+            )
+            .add_system_to_stage(RenderStage::Create, prepare_post_textures::<DepthOfField>)
+            .add_system_to_stage(
+                RenderStage::Create,
+                prepare_post_depth_bind_groups::<DepthOfField>,
+            )
+            .add_system_to_stage(RenderStage::Prepare, prepare_lens_effects_uniforms)
+            .add_system_to_stage(RenderStage::Create, queue_lens_effects_uniforms)
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_lens_effects_bind_groups.after(queue_lens_effects_uniforms),
+            )
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_fxaa_bind_groups.after(queue_component_uniforms::<Fxaa>),
+            )
+            .add_system_to_stage(RenderStage::Create, prepare_post_textures::<Fxaa>)
+            .add_system_to_stage(RenderStage::Create, prepare_post_textures::<ColorGrading>)
+            .add_system_to_stage(RenderStage::Create, prepare_post_textures::<LensEffects>)
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_color_grading_bind_groups.after(queue_component_uniforms::<ColorGrading>),
+            )
+            // Depth of field first, then anti-aliasing on the edges left sharp and not on the grain.
+            // Grading is the final pass, effects are added before it
+            .add_camera_pass(CameraPassStage::AfterMain, encode_depth_of_field)
+            .add_camera_pass(CameraPassStage::AfterMain, encode_fxaa)
+            .add_camera_pass(CameraPassStage::AfterMain, encode_lens_effects)
+            .add_camera_pass(CameraPassStage::AfterMain, encode_color_grading);
+    }
+}
+
+/// Copies of the camera targets post passes sample, in the engine default texture format.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct PostTextures(pub HashMap<Entity, GrabTexture>);
+
+impl EntityRenderState for PostTextures {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(&entity);
+    }
+}
+
+/// Creates the post textures of the cameras with the effect `C`, again when the target is resized.
+pub fn prepare_post_textures<C: Component>(
+    render_device: Res<RenderDevice>,
+    layout: Res<GrabTextureLayout>,
+    images: Res<Assets<Image>>,
+    windows: Res<PreparedWindows>,
+    mut post_textures: ResMut<PostTextures>,
+    cameras: Query<(Entity, &Camera), With<C>>,
+) {
+    for (entity, camera) in cameras.iter() {
+        let Some(size) = camera.render_target.get_size(&images, &windows) else {
+            continue;
+        };
+        if size.0 == 0 || size.1 == 0 {
+            continue;
+        }
+        if post_textures.get(&entity).map(|post| post.size) == Some(size) {
+            continue;
+        }
+        post_textures.insert(entity, GrabTexture::create(&render_device, &layout, size));
+    }
+}
+
+/// Layout of the depth bind group of post passes, the depth texture at binding 0.
+/// It is read with `textureLoad`, depth textures can not be filtered.
+#[derive(Resource)]
+pub struct PostDepthLayout {
+    pub layout: BindGroupLayout,
+}
+
+impl FromWorld for PostDepthLayout {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<Res<RenderDevice>> = SystemState::new(world);
+        let render_device = state.get(world);
+
+        let layout = render_device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("post_depth_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            })
+            .into();
+
+        Self { layout }
+    }
+}
+
+/// Depth bind groups of the cameras with a post pass reading depth, rebuilt every frame.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct PostDepthBindGroups(pub HashMap<Entity, wgpu::BindGroup>);
+
+impl EntityRenderState for PostDepthBindGroups {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(&entity);
+    }
+}
+
+/// Binds the depth texture of the cameras with the effect `C`, the target is resized in Prepare.
+pub fn prepare_post_depth_bind_groups<C: Component>(
+    render_device: Res<RenderDevice>,
+    layout: Res<PostDepthLayout>,
+    depth_textures: Res<DepthTextures>,
+    mut depth_bind_groups: ResMut<PostDepthBindGroups>,
+    cameras: Query<(Entity, &Camera), With<C>>,
+) {
+    for (entity, camera) in cameras.iter() {
+        let Some(depth_texture) = depth_textures.get(&camera.render_target) else {
+            depth_bind_groups.remove(&entity);
+            continue;
+        };
+        let bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("post_depth_bind_group"),
+            layout: &layout.layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&depth_texture.view),
+            }],
+        });
+        depth_bind_groups.insert(entity, bind_group);
+    }
+}
+
+/// Copies the target of the camera into its post texture, `None` if either is not available.
+pub fn copy_to_post_texture<'w>(
+    camera: Entity,
+    world: &'w World,
+    attachments: &CameraAttachments<'w>,
+    command_encoder: &mut wgpu::CommandEncoder,
+) -> Option<&'w GrabTexture> {
+    let post_textures = world.get_resource::<PostTextures>().unwrap();
+    let post_texture = post_textures.get(&camera)?;
+    let target_texture = attachments.color_texture?;
+    if post_texture.size != attachments.size {
+        return None;
+    }
+    command_encoder.copy_texture_to_texture(
+        target_texture.as_image_copy(),
+        post_texture.texture.texture.as_image_copy(),
+        post_texture.copy_size(),
+    );
+    Some(post_texture)
+}
+
+/// Pass over the whole target of the camera, the triangle drawn covers every pixel.
+pub fn begin_post_pass<'a>(
+    label: &'static str,
+    attachments: &CameraAttachments<'a>,
+    command_encoder: &'a mut wgpu::CommandEncoder,
+) -> wgpu::RenderPass<'a> {
+    command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: attachments.color,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    })
+}
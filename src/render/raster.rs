@@ -0,0 +1,167 @@
+use std::hash::{Hash, Hasher};
+
+use bevy::prelude::{Component, Entity, World};
+
+use super::blend::{AlphaMode, BlendMode};
+
+/// Which faces of an entity are culled and which winding is the front.
+///
+/// Entities without it cull back faces with Ccw winding, flip it for meshes
+/// imported with the opposite winding instead of rebuilding them.
+#[derive(Component, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct CullMode {
+    /// Culled face, `None` draws both.
+    pub face: Option<wgpu::Face>,
+    pub front_face: wgpu::FrontFace,
+}
+
+impl Default for CullMode {
+    fn default() -> Self {
+        Self::BACK
+    }
+}
+
+impl CullMode {
+    pub const BACK: CullMode = CullMode {
+        face: Some(wgpu::Face::Back),
+        front_face: wgpu::FrontFace::Ccw,
+    };
+    pub const FRONT: CullMode = CullMode {
+        face: Some(wgpu::Face::Front),
+        front_face: wgpu::FrontFace::Ccw,
+    };
+    pub const NONE: CullMode = CullMode {
+        face: None,
+        front_face: wgpu::FrontFace::Ccw,
+    };
+
+    pub fn with_front_face(mut self, front_face: wgpu::FrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+}
+
+///
+/// Offsets the depth of an entity so it wins the depth test against coplanar geometry,
+/// for decals and overlays drawn over a surface. The depth compare is `Less`,
+/// negative values move the entity towards the camera.
+///
+/// Depth in polygon offset units is `constant + slope_scale * max_depth_slope`,
+/// clamped to `clamp` unless it is zero.
+///
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct DepthBias {
+    pub constant: i32,
+    pub slope_scale: f32,
+    pub clamp: f32,
+}
+
+impl DepthBias {
+    pub fn new(constant: i32, slope_scale: f32) -> Self {
+        Self {
+            constant,
+            slope_scale,
+            clamp: 0.0,
+        }
+    }
+
+    pub fn state(&self) -> wgpu::DepthBiasState {
+        wgpu::DepthBiasState {
+            constant: self.constant,
+            slope_scale: self.slope_scale,
+            clamp: self.clamp,
+        }
+    }
+}
+
+// Pipeline key part, the floats are compared and hashed by their bits
+impl PartialEq for DepthBias {
+    fn eq(&self, other: &Self) -> bool {
+        self.constant == other.constant
+            && self.slope_scale.to_bits() == other.slope_scale.to_bits()
+            && self.clamp.to_bits() == other.clamp.to_bits()
+    }
+}
+impl Eq for DepthBias {}
+impl Hash for DepthBias {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.constant.hash(state);
+        self.slope_scale.to_bits().hash(state);
+        self.clamp.to_bits().hash(state);
+    }
+}
+
+/// Rasterizer part of the pipeline keys, built from the components of the entity.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct RasterKey {
+    pub cull_mode: CullMode,
+    pub depth_bias: DepthBias,
+    /// [`AlphaMode::Mask`], the fragments left are opaque and write depth.
+    pub masked: bool,
+}
+
+impl RasterKey {
+    pub fn new(
+        cull_mode: Option<&CullMode>,
+        depth_bias: Option<&DepthBias>,
+        alpha_mode: Option<&AlphaMode>,
+    ) -> Self {
+        Self {
+            cull_mode: cull_mode.copied().unwrap_or_default(),
+            depth_bias: depth_bias.copied().unwrap_or_default(),
+            masked: matches!(alpha_mode, Some(AlphaMode::Mask(_))),
+        }
+    }
+
+    pub fn of(world: &World, entity: Entity) -> Self {
+        Self::new(
+            world.get::<CullMode>(entity),
+            world.get::<DepthBias>(entity),
+            world.get::<AlphaMode>(entity),
+        )
+    }
+
+    /// Opaque and masked entities write depth, blended ones only test against it.
+    pub fn depth_write_enabled(&self, blend_mode: BlendMode) -> bool {
+        self.masked || !blend_mode.is_transparent()
+    }
+
+    pub fn primitive_state(&self, topology: wgpu::PrimitiveTopology) -> wgpu::PrimitiveState {
+        wgpu::PrimitiveState {
+            front_face: self.cull_mode.front_face,
+            cull_mode: self.cull_mode.face,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+            topology,
+            strip_index_format: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_bias_eq_agrees_with_hash() {
+        let zero = DepthBias::new(0, 0.0);
+        let negative_zero = DepthBias::new(0, -0.0);
+        assert_ne!(zero, negative_zero);
+
+        let nan = DepthBias::new(0, f32::NAN);
+        assert_eq!(nan, nan);
+        assert_eq!(DepthBias::new(2, 1.5), DepthBias::new(2, 1.5));
+    }
+
+    #[test]
+    fn masked_entities_write_depth() {
+        let blended = RasterKey::new(None, None, Some(&AlphaMode::Blend));
+        let masked = RasterKey::new(None, None, Some(&AlphaMode::Mask(0.5)));
+        assert_ne!(blended, masked);
+
+        assert!(!blended.depth_write_enabled(BlendMode::AlphaBlend));
+        assert!(masked.depth_write_enabled(BlendMode::AlphaBlend));
+        assert!(RasterKey::default().depth_write_enabled(BlendMode::Opaque));
+    }
+}
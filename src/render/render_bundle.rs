@@ -0,0 +1,80 @@
+use bevy::{
+    prelude::{Component, Entity, Resource},
+    utils::{HashMap, HashSet},
+};
+
+use super::{cleanup::EntityRenderState, stats::FrameRenderStats};
+
+/// Marks an entity that does not change from frame to frame.
+///
+/// Renderers that support it pre-record such entities into a `wgpu::RenderBundle` per camera
+/// and replay it instead of encoding their draws again.
+/// Transparent entities are still drawn through the phases, they need sorting.
+#[derive(Component, Clone, Copy, Default)]
+pub struct StaticGeometry;
+
+/// Pre-recorded draws of a camera and the entities they cover.
+pub struct RecordedBundle {
+    pub bundle: wgpu::RenderBundle,
+    pub entities: HashSet<Entity>,
+    /// Commands recorded into the bundle, counted each time it is replayed.
+    pub stats: FrameRenderStats,
+}
+
+/// Bundles replayed at the start of the main pass of each camera, before the phases.
+///
+/// Each renderer keeps its own bundle per camera under a `source` name,
+/// entities covered by a bundle are left out of the render phases of that camera.
+#[derive(Resource, Default)]
+pub struct RenderBundles {
+    cameras: HashMap<Entity, HashMap<&'static str, RecordedBundle>>,
+}
+
+impl RenderBundles {
+    pub fn get(&self, camera: Entity, source: &'static str) -> Option<&RecordedBundle> {
+        self.cameras.get(&camera)?.get(source)
+    }
+
+    pub fn insert(&mut self, camera: Entity, source: &'static str, bundle: RecordedBundle) {
+        self.cameras.entry(camera).or_default().insert(source, bundle);
+    }
+
+    pub fn remove(&mut self, camera: Entity, source: &'static str) {
+        if let Some(bundles) = self.cameras.get_mut(&camera) {
+            bundles.remove(source);
+        }
+    }
+
+    pub fn camera_bundles(&self, camera: Entity) -> impl Iterator<Item = &wgpu::RenderBundle> {
+        self.cameras
+            .get(&camera)
+            .into_iter()
+            .flat_map(|bundles| bundles.values().map(|recorded| &recorded.bundle))
+    }
+
+    pub fn camera_stats(&self, camera: Entity) -> FrameRenderStats {
+        let mut stats = FrameRenderStats::default();
+        for recorded in self.cameras.get(&camera).into_iter().flat_map(|b| b.values()) {
+            stats += recorded.stats;
+        }
+        stats
+    }
+
+    pub fn remove_camera(&mut self, camera: Entity) {
+        self.cameras.remove(&camera);
+    }
+
+    pub fn contains(&self, camera: Entity, entity: Entity) -> bool {
+        self.cameras.get(&camera).map_or(false, |bundles| {
+            bundles
+                .values()
+                .any(|recorded| recorded.entities.contains(&entity))
+        })
+    }
+}
+
+impl EntityRenderState for RenderBundles {
+    fn remove_entity(&mut self, camera: Entity) {
+        self.remove_camera(camera);
+    }
+}
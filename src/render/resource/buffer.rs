@@ -17,6 +17,29 @@ impl Indices {
 }
 
 impl Indices {
+    /// `0..count`, as `U16` if every index fits.
+    pub fn sequential(count: usize) -> Self {
+        if count <= u16::MAX as usize + 1 {
+            Indices::U16((0..count as u32).map(|i| i as u16).collect())
+        } else {
+            Indices::U32((0..count as u32).collect())
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self {
+            Indices::U16(vec) => Box::new(vec.iter().map(|i| *i as usize)),
+            Indices::U32(vec) => Box::new(vec.iter().map(|i| *i as usize)),
+        }
+    }
+
+    /// Widens `U16` indices to `U32`.
+    pub fn promote(&mut self) {
+        if let Indices::U16(vec) = self {
+            *self = Indices::U32(vec.iter().map(|i| *i as u32).collect());
+        }
+    }
+
     pub fn shift(&mut self, offset: u32) {
         match self {
             Indices::U16(vec) => {
@@ -75,6 +98,11 @@ impl From<Vec<u32>> for Indices {
 pub trait MeshVertex: TypeUuid + Sized + C + Pod + Zeroable + Send + Sync + 'static {
     const ATTRIBUTES: &'static [wgpu::VertexAttribute];
 
+    /// Color converted from the `ColorSpace` when the mesh is uploaded, `None` without one.
+    fn color_mut(&mut self) -> Option<&mut [f32; 4]> {
+        None
+    }
+
     fn size() -> u64 {
         std::mem::size_of::<Self>() as u64
     }
@@ -130,6 +158,10 @@ impl MeshVertex for Vertex {
         1 => Float32x2,
         2 => Float32x4,
     ];
+
+    fn color_mut(&mut self) -> Option<&mut [f32; 4]> {
+        Some(&mut self.color)
+    }
 }
 
 impl FromRawVertex for Vertex {
@@ -162,6 +194,36 @@ impl MeshVertex for VertexTex3 {
         1 => Float32x3,
         2 => Float32x4,
     ];
+
+    fn color_mut(&mut self) -> Option<&mut [f32; 4]> {
+        Some(&mut self.color)
+    }
+}
+
+/// [`VertexTex3`] bound to up to four joints of a skin, weights sum to one.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, TypeUuid, C, Pod, Zeroable)]
+#[uuid = "5B0C3D8E-7F41-4A2B-9E6D-1C8A4F2B7D93"]
+pub struct VertexSkinned {
+    pub position: [f32; 3],
+    pub uv: [f32; 3],
+    pub color: [f32; 4],
+    pub joint_indices: [u32; 4],
+    pub joint_weights: [f32; 4],
+}
+
+impl MeshVertex for VertexSkinned {
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Float32x4,
+        3 => Uint32x4,
+        4 => Float32x4,
+    ];
+
+    fn color_mut(&mut self) -> Option<&mut [f32; 4]> {
+        Some(&mut self.color)
+    }
 }
 
 // pub struct Instance {
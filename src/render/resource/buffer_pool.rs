@@ -0,0 +1,138 @@
+use bevy::{
+    prelude::{ResMut, Resource},
+    utils::HashMap,
+};
+
+use super::renderer::RenderDevice;
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+struct PoolKey {
+    usage: wgpu::BufferUsages,
+    size: u64,
+}
+
+/// Buffer taken from a [`BufferPool`], `size` is its size class and may exceed the requested size.
+pub struct PooledBuffer {
+    buffer: wgpu::Buffer,
+    usage: wgpu::BufferUsages,
+    size: u64,
+}
+
+impl PooledBuffer {
+    #[inline]
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+///
+/// GPU buffers grouped by usage and size class (powers of two, at least [`BufferPool::MIN_SIZE`]).
+///
+/// Buffers given back with [`release`](BufferPool::release) are reused by the next
+/// [`acquire`](BufferPool::acquire) of the same class instead of allocating a new one.
+/// Frame-transient buffers from [`acquire_transient`](BufferPool::acquire_transient) are
+/// given back automatically in `RenderStage::Cleanup`.
+///
+#[derive(Resource, Default)]
+pub struct BufferPool {
+    free: HashMap<PoolKey, Vec<wgpu::Buffer>>,
+    transient: Vec<PooledBuffer>,
+    allocated_bytes: u64,
+    allocations: u64,
+}
+
+impl BufferPool {
+    pub const MIN_SIZE: u64 = 256;
+
+    pub fn size_class(size: u64) -> u64 {
+        size.max(Self::MIN_SIZE).next_power_of_two()
+    }
+
+    pub fn acquire(
+        &mut self,
+        device: &RenderDevice,
+        usage: wgpu::BufferUsages,
+        size: u64,
+    ) -> PooledBuffer {
+        let key = PoolKey {
+            usage,
+            size: Self::size_class(size),
+        };
+        let buffer = match self.free.get_mut(&key).and_then(Vec::pop) {
+            Some(buffer) => buffer,
+            None => {
+                self.allocated_bytes += key.size;
+                self.allocations += 1;
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("pooled_buffer"),
+                    size: key.size,
+                    usage,
+                    mapped_at_creation: false,
+                })
+            }
+        };
+        PooledBuffer {
+            buffer,
+            usage,
+            size: key.size,
+        }
+    }
+
+    pub fn release(&mut self, pooled: PooledBuffer) {
+        let key = PoolKey {
+            usage: pooled.usage,
+            size: pooled.size,
+        };
+        self.free.entry(key).or_default().push(pooled.buffer);
+    }
+
+    /// Buffer valid until the end of the current frame.
+    pub fn acquire_transient(
+        &mut self,
+        device: &RenderDevice,
+        usage: wgpu::BufferUsages,
+        size: u64,
+    ) -> &wgpu::Buffer {
+        let pooled = self.acquire(device, usage, size);
+        self.transient.push(pooled);
+        &self.transient.last().unwrap().buffer
+    }
+
+    /// Total size of the buffers allocated by the pool, in use or free.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.allocated_bytes
+    }
+
+    /// Number of buffers the pool had to allocate since creation.
+    pub fn allocations(&self) -> u64 {
+        self.allocations
+    }
+
+    pub fn free_bytes(&self) -> u64 {
+        self.free
+            .iter()
+            .map(|(key, buffers)| key.size * buffers.len() as u64)
+            .sum()
+    }
+
+    /// Drops every free buffer.
+    pub fn trim(&mut self) {
+        self.allocated_bytes -= self.free_bytes();
+        self.free.clear();
+    }
+
+    pub(crate) fn recycle_transient(&mut self) {
+        for pooled in std::mem::take(&mut self.transient) {
+            self.release(pooled);
+        }
+    }
+}
+
+pub fn recycle_transient_buffers(mut buffer_pool: ResMut<BufferPool>) {
+    buffer_pool.recycle_transient();
+}
@@ -1,12 +1,19 @@
-use bevy::prelude::{
-    App, Commands, Component, Deref, DerefMut, Entity, GlobalTransform, Mat4, Query, Res, ResMut,
-    Resource,
+use std::marker::PhantomData;
+
+use bevy::{
+    ecs::system::RemovedComponents,
+    prelude::{
+        App, ChangeTrackers, Commands, Component, Deref, DerefMut, Entity, FromWorld, GlobalTransform,
+        Mat4, Query, Res, ResMut, Resource, World,
+    },
+    utils::HashMap,
 };
 use encase::{private::WriteInto, ShaderType};
 
-use crate::render::RenderStage;
+use crate::render::{camera::component::ExtractedVisibility, color::ColorSpace, RenderStage};
 
 use super::{
+    buffer_pool::BufferPool,
     renderer::{RenderDevice, RenderQueue},
     uniform::{DynamicUniformBuffer, DynamicUniformId, HandleGpuUniform},
 };
@@ -15,14 +22,25 @@ use super::{
 pub struct ComponentUniforms<T: ShaderType + WriteInto + Send + Sync + 'static>(
     pub DynamicUniformBuffer<T>,
 );
-impl<T: ShaderType + WriteInto + Send + Sync + 'static> Default for ComponentUniforms<T> {
-    fn default() -> Self {
-        Self(Default::default())
+// Packed with the device alignment once the device exists, the default one is valid on all
+impl<T: ShaderType + WriteInto + Send + Sync + 'static> FromWorld for ComponentUniforms<T> {
+    fn from_world(world: &mut World) -> Self {
+        match world.get_resource::<RenderDevice>() {
+            Some(render_device) => Self(DynamicUniformBuffer::for_device(render_device)),
+            None => Self(Default::default()),
+        }
     }
 }
 
 pub trait AddComponentUniform {
     fn add_component_uniform<H: HandleGpuUniform + Component>(&mut self) -> &mut Self;
+
+    /// Keeps the uniform of every entity at the same offset across frames
+    /// and only uploads the ones whose component changed, instead of rebuilding the buffer.
+    ///
+    /// Suits components that rarely change, culled entities give up their slot
+    /// and are uploaded again once a camera sees them.
+    fn add_stable_component_uniform<H: HandleGpuUniform + Component>(&mut self) -> &mut Self;
 }
 impl AddComponentUniform for App {
     fn add_component_uniform<H: HandleGpuUniform + Component>(&mut self) -> &mut Self {
@@ -30,21 +48,32 @@ impl AddComponentUniform for App {
             .add_system_to_stage(RenderStage::Prepare, prepare_component_uniforms::<H>)
             .add_system_to_stage(RenderStage::Create, queue_component_uniforms::<H>)
     }
+
+    fn add_stable_component_uniform<H: HandleGpuUniform + Component>(&mut self) -> &mut Self {
+        self.init_resource::<ComponentUniforms<H::GU>>()
+            .init_resource::<ComponentUniformSlots<H::GU>>()
+            .add_system_to_stage(RenderStage::Prepare, prepare_stable_component_uniforms::<H>)
+            .add_system_to_stage(RenderStage::Create, queue_stable_component_uniforms::<H>)
+    }
 }
 
 pub fn prepare_component_uniforms<H: HandleGpuUniform + Component>(
     mut commands: Commands,
     mut component_uniforms: ResMut<ComponentUniforms<H::GU>>,
-    query: Query<(Entity, &H)>,
+    color_space: Res<ColorSpace>,
+    query: Query<(Entity, &H, Option<&ExtractedVisibility>)>,
 ) {
     let mut spawns: Vec<(Entity, DynamicUniformId<H::GU>)> = Vec::new();
 
     component_uniforms.clear();
-    for (entity, uniform_handle) in query.iter() {
+    for (entity, uniform_handle, computed_visibility) in query.iter() {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
         spawns.push((
             entity,
             component_uniforms
-                .push(uniform_handle.into_uniform())
+                .push(uniform_handle.into_uniform(*color_space))
                 .into(),
         ));
     }
@@ -58,9 +87,90 @@ pub fn prepare_component_uniforms<H: HandleGpuUniform + Component>(
 pub fn queue_component_uniforms<H: HandleGpuUniform + Component>(
     render_device: Res<RenderDevice>,
     render_queue: Res<RenderQueue>,
+    mut buffer_pool: ResMut<BufferPool>,
     mut component_uniforms: ResMut<ComponentUniforms<H::GU>>,
 ) {
-    component_uniforms.write_buffer(&render_device, &render_queue);
+    component_uniforms.write_buffer_pooled(&render_device, &render_queue, &mut buffer_pool);
+}
+
+/// Offsets of the entities in a stable [`ComponentUniforms`], freed ones are reused.
+#[derive(Resource)]
+pub struct ComponentUniformSlots<T: ShaderType> {
+    offsets: HashMap<Entity, u32>,
+    free: Vec<u32>,
+    marker: PhantomData<T>,
+}
+impl<T: ShaderType> ComponentUniformSlots<T> {
+    /// Offset of the value of `entity`, to overwrite it with `ComponentUniforms::set`.
+    pub fn get(&self, entity: Entity) -> Option<u32> {
+        self.offsets.get(&entity).copied()
+    }
+}
+impl<T: ShaderType> Default for ComponentUniformSlots<T> {
+    fn default() -> Self {
+        Self {
+            offsets: HashMap::new(),
+            free: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+pub fn prepare_stable_component_uniforms<H: HandleGpuUniform + Component>(
+    mut commands: Commands,
+    mut component_uniforms: ResMut<ComponentUniforms<H::GU>>,
+    mut slots: ResMut<ComponentUniformSlots<H::GU>>,
+    color_space: Res<ColorSpace>,
+    removed: RemovedComponents<H>,
+    query: Query<(Entity, &H, ChangeTrackers<H>, Option<&ExtractedVisibility>)>,
+) {
+    for entity in removed.iter() {
+        if let Some(offset) = slots.offsets.remove(&entity) {
+            slots.free.push(offset);
+            // The slot goes to another entity, despawned entities need nothing
+            if let Some(mut entity_commands) = commands.get_entity(entity) {
+                entity_commands.remove::<DynamicUniformId<H::GU>>();
+            }
+        }
+    }
+
+    let mut spawns: Vec<(Entity, DynamicUniformId<H::GU>)> = Vec::new();
+
+    for (entity, uniform_handle, tracker, computed_visibility) in query.iter() {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            if let Some(offset) = slots.offsets.remove(&entity) {
+                slots.free.push(offset);
+                commands.entity(entity).remove::<DynamicUniformId<H::GU>>();
+            }
+            continue;
+        }
+        if let Some(offset) = slots.offsets.get(&entity) {
+            if tracker.is_changed() || color_space.is_changed() {
+                component_uniforms.set(*offset, uniform_handle.into_uniform(*color_space));
+            }
+            continue;
+        }
+        let uniform = uniform_handle.into_uniform(*color_space);
+        let offset = match slots.free.pop() {
+            Some(offset) => {
+                component_uniforms.set(offset, uniform);
+                offset
+            }
+            None => component_uniforms.push(uniform),
+        };
+        slots.offsets.insert(entity, offset);
+        spawns.push((entity, offset.into()));
+    }
+
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn queue_stable_component_uniforms<H: HandleGpuUniform + Component>(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut component_uniforms: ResMut<ComponentUniforms<H::GU>>,
+) {
+    component_uniforms.write_buffer_changed(&render_device, &render_queue);
 }
 
 #[derive(Clone, ShaderType)]
@@ -68,12 +178,90 @@ pub struct ModelUniform {
     model: Mat4,
 }
 
+impl ModelUniform {
+    pub fn new(model: Mat4) -> Self {
+        Self { model }
+    }
+}
+
 impl HandleGpuUniform for GlobalTransform {
     type GU = ModelUniform;
 
-    fn into_uniform(&self) -> Self::GU {
+    fn into_uniform(&self, _color_space: ColorSpace) -> Self::GU {
         ModelUniform {
             model: self.compute_matrix(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_uniforms_only_rewrite_changed_slots() {
+        let mut app = App::new();
+        app.init_resource::<ComponentUniforms<ModelUniform>>()
+            .init_resource::<ComponentUniformSlots<ModelUniform>>()
+            .init_resource::<ColorSpace>()
+            .add_system(prepare_stable_component_uniforms::<GlobalTransform>);
+
+        let a = app.world.spawn(GlobalTransform::IDENTITY).id();
+        let b = app.world.spawn(GlobalTransform::IDENTITY).id();
+        app.update();
+
+        let slots = app.world.resource::<ComponentUniformSlots<ModelUniform>>();
+        let (offset_a, offset_b) = (slots.get(a).unwrap(), slots.get(b).unwrap());
+        assert_ne!(offset_a, offset_b);
+        let uniforms = app.world.resource::<ComponentUniforms<ModelUniform>>();
+        assert!(uniforms.dirty_ranges().is_empty());
+
+        *app.world.get_mut::<GlobalTransform>(b).unwrap() =
+            GlobalTransform::from_xyz(1.0, 0.0, 0.0);
+        app.update();
+
+        let uniforms = app.world.resource::<ComponentUniforms<ModelUniform>>();
+        let size = ModelUniform::min_size().get();
+        assert_eq!(uniforms.dirty_ranges(), &[(offset_b as u64, size)]);
+
+        // The freed slot loses its id and goes to the next entity
+        app.world.entity_mut(a).remove::<GlobalTransform>();
+        app.update();
+        assert!(app.world.get::<DynamicUniformId<ModelUniform>>(a).is_none());
+
+        let c = app.world.spawn(GlobalTransform::IDENTITY).id();
+        app.update();
+        let slots = app.world.resource::<ComponentUniformSlots<ModelUniform>>();
+        assert_eq!(slots.get(c), Some(offset_a));
+    }
+
+    #[test]
+    fn culled_entities_give_up_their_stable_slot() {
+        let mut app = App::new();
+        app.init_resource::<ComponentUniforms<ModelUniform>>()
+            .init_resource::<ComponentUniformSlots<ModelUniform>>()
+            .init_resource::<ColorSpace>()
+            .add_system(prepare_stable_component_uniforms::<GlobalTransform>);
+
+        let a = app.world.spawn(GlobalTransform::IDENTITY).id();
+        app.update();
+        let offset = app
+            .world
+            .resource::<ComponentUniformSlots<ModelUniform>>()
+            .get(a)
+            .unwrap();
+
+        // Default is checked and not visible
+        app.world.entity_mut(a).insert(ExtractedVisibility::default());
+        app.update();
+        let slots = app.world.resource::<ComponentUniformSlots<ModelUniform>>();
+        assert_eq!(slots.get(a), None);
+        assert!(app.world.get::<DynamicUniformId<ModelUniform>>(a).is_none());
+
+        app.world.entity_mut(a).remove::<ExtractedVisibility>();
+        app.update();
+        let slots = app.world.resource::<ComponentUniformSlots<ModelUniform>>();
+        assert_eq!(slots.get(a), Some(offset));
+        assert!(app.world.get::<DynamicUniformId<ModelUniform>>(a).is_some());
+    }
+}
@@ -1,7 +1,12 @@
 pub mod buffer;
+pub mod buffer_pool;
 pub mod component_uniform;
 pub mod pipeline;
+pub mod push_constant;
 pub mod renderer;
 pub mod shader;
+#[cfg(feature = "shader_cache")]
+pub mod shader_cache;
+pub mod storage;
 pub mod uniform;
 pub mod specialized_pipeline;
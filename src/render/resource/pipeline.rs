@@ -1,13 +1,16 @@
 use std::{num::NonZeroU32, sync::Arc, ops::Deref};
 
 use bevy::{
-    prelude::{Assets, Component, Handle, Res, ResMut, Resource},
+    log::error,
+    prelude::{Assets, Component, EventWriter, Handle, Res, ResMut, Resource},
     utils::HashMap,
 };
 
-use crate::render::RenderDevice;
+use crate::render::{error::RendererError, RenderDevice};
 
-use super::shader::Shader;
+#[cfg(feature = "shader_cache")]
+use super::shader_cache::ShaderCache;
+use super::shader::{Shader, ShaderDefError};
 
 #[derive(Component, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RenderPipelineId(usize);
@@ -17,6 +20,8 @@ pub struct PipelineCache {
     id_to_ind: HashMap<RenderPipelineId, usize>,
     pipelines: Vec<wgpu::RenderPipeline>,
     waiting: Vec<(RenderPipelineId, RenderPipelineDescriptor)>,
+    #[cfg(feature = "shader_cache")]
+    pub shader_cache: Option<ShaderCache>,
 }
 
 impl PipelineCache {
@@ -30,6 +35,19 @@ impl PipelineCache {
         self.pipelines.get(*self.id_to_ind.get(&id)?)
     }
 
+    fn compile_shader(
+        &self,
+        render_device: &RenderDevice,
+        shader: &Shader,
+        shader_defs: &[String],
+    ) -> Result<wgpu::ShaderModule, ShaderDefError> {
+        #[cfg(feature = "shader_cache")]
+        if let Some(shader_cache) = &self.shader_cache {
+            return shader_cache.compile(render_device, shader, shader_defs);
+        }
+        shader.compile(render_device, shader_defs)
+    }
+
     fn create(
         &mut self,
         render_device: &RenderDevice,
@@ -76,11 +94,18 @@ impl PipelineCache {
         self.id_to_ind.insert(id, self.pipelines.len() - 1);
     }
 
+    ///
+    /// Creates the pipelines whose shaders are loaded, the others keep waiting.
+    ///
+    /// Pipelines with a shader that fails to preprocess are dropped and returned
+    /// as [`RendererError::Shader`].
+    ///
     pub fn create_available_in_waiting(
         &mut self,
         render_device: &RenderDevice,
         shaders: &Assets<Shader>,
-    ) {
+    ) -> Vec<RendererError> {
+        let mut failures = Vec::new();
         let waiting_take = std::mem::replace(&mut self.waiting, Vec::new());
         for (id, desc) in waiting_take {
             let Some(vertex_shader) = shaders.get(&desc.vertex.shader) else {
@@ -89,7 +114,9 @@ impl PipelineCache {
             };
             let (vf_same, fragment_shader) = match &desc.fragment {
                 Some(fragment_state) => {
-                    if fragment_state.shader.eq(&desc.vertex.shader) {
+                    if fragment_state.shader.eq(&desc.vertex.shader)
+                        && fragment_state.shader_defs == desc.vertex.shader_defs
+                    {
                         (true, None)
                     } else {
                         let Some(fragment_shader) = shaders.get(&fragment_state.shader) else {
@@ -102,8 +129,27 @@ impl PipelineCache {
                 None => (false, None),
             };
 
-            let vs_module = vertex_shader.compile(render_device);
-            let fs_module = fragment_shader.map(|s| s.compile(render_device));
+            let modules = self
+                .compile_shader(render_device, vertex_shader, &desc.vertex.shader_defs)
+                .and_then(|vs_module| {
+                    let fs_module = fragment_shader
+                        .map(|s| {
+                            let shader_defs = &desc.fragment.as_ref().unwrap().shader_defs;
+                            self.compile_shader(render_device, s, shader_defs)
+                        })
+                        .transpose()?;
+                    Ok((vs_module, fs_module))
+                });
+            let (vs_module, fs_module) = match modules {
+                Ok(modules) => modules,
+                Err(error) => {
+                    failures.push(RendererError::Shader {
+                        pipeline: desc.label,
+                        error,
+                    });
+                    continue;
+                }
+            };
 
             self.create(
                 render_device,
@@ -117,6 +163,7 @@ impl PipelineCache {
                 },
             );
         }
+        failures
     }
 }
 
@@ -124,8 +171,14 @@ pub fn compile_shaders_into_pipelines(
     render_device: Res<RenderDevice>,
     mut pipeline_cache: ResMut<PipelineCache>,
     shaders: Res<Assets<Shader>>,
+    mut renderer_errors: EventWriter<RendererError>,
 ) {
-    pipeline_cache.create_available_in_waiting(&render_device, &shaders)
+    for failure in pipeline_cache.create_available_in_waiting(&render_device, &shaders) {
+        if let RendererError::Shader { pipeline, error } = &failure {
+            error!("Pipeline {:?} skipped: {}", pipeline, error);
+        }
+        renderer_errors.send(failure);
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -152,6 +205,8 @@ pub struct RenderPipelineDescriptor {
 #[derive(Clone, Debug)]
 pub struct VertexState {
     pub shader: Handle<Shader>,
+    /// Enabled `#ifdef` blocks of the shader, see [`Shader::preprocess`].
+    pub shader_defs: Vec<String>,
     pub entry_point: &'static str,
     pub buffers: Vec<wgpu::VertexBufferLayout<'static>>,
 }
@@ -159,6 +214,7 @@ pub struct VertexState {
 #[derive(Clone, Debug)]
 pub struct FragmentState {
     pub shader: Handle<Shader>,
+    pub shader_defs: Vec<String>,
     pub entry_point: &'static str,
     pub targets: Vec<Option<wgpu::ColorTargetState>>,
 }
@@ -0,0 +1,86 @@
+use bevy::prelude::{Entity, GlobalTransform, Mat4, World};
+
+use crate::render::camera::billboard::Billboard;
+
+///
+/// Model matrix passed to the vertex stage as a push constant,
+/// replaces the dynamic offset into the model uniform buffer for every draw.
+///
+/// Pipelines keep an empty bind group layout at the model group so the other groups keep their index.
+/// Shaders declare `model` as a push constant under [`MODEL_PUSH_CONSTANT_DEF`].
+///
+pub const MODEL_PUSH_CONSTANT_RANGE: wgpu::PushConstantRange = wgpu::PushConstantRange {
+    stages: wgpu::ShaderStages::VERTEX,
+    range: 0..64,
+};
+
+/// Shader def set when `model` is read from push constants instead of `@group(0) @binding(0)`.
+pub const MODEL_PUSH_CONSTANT_DEF: &str = "MODEL_PUSH_CONSTANT";
+
+/// Shader defs of the pipelines reading `model`, empty without the `push_constants` feature.
+pub fn model_shader_defs() -> Vec<String> {
+    if cfg!(feature = "push_constants") {
+        vec![MODEL_PUSH_CONSTANT_DEF.to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+///
+/// Pushes the model matrix of `object`, [`Billboard`] entities are turned towards `camera`.
+///
+/// Unlike the shared model uniform the matrix is pushed per view,
+/// so billboards face the camera of the view being drawn.
+///
+pub fn set_model_push_constant(
+    camera: Entity,
+    object: Entity,
+    world: &World,
+    render_pass: &mut wgpu::RenderPass,
+) {
+    let model = match world.get::<GlobalTransform>(object) {
+        Some(transform) => match (
+            world.get::<Billboard>(object),
+            world.get::<GlobalTransform>(camera),
+        ) {
+            (Some(billboard), Some(camera_transform)) => {
+                billboard.model(transform, camera_transform)
+            }
+            _ => transform.compute_matrix(),
+        },
+        None => Mat4::IDENTITY,
+    };
+    render_pass.set_push_constants(
+        MODEL_PUSH_CONSTANT_RANGE.stages,
+        0,
+        bytemuck::cast_slice(&model.to_cols_array()),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::resource::shader::Shader;
+
+    use super::*;
+
+    #[test]
+    fn sprite_shaders_declare_model_per_def() {
+        let sources = [
+            include_str!("../../sprite/sprite.wgsl"),
+            include_str!("../../sprite/sprite_bindless.wgsl"),
+        ];
+        for source in sources {
+            let shader = Shader::from_wgsl(source);
+
+            let pushed = shader
+                .preprocess(&[MODEL_PUSH_CONSTANT_DEF.to_string()])
+                .unwrap();
+            assert!(pushed.contains("var<push_constant> model: Model;"));
+            assert!(!pushed.contains("var<uniform> model: Model;"));
+
+            let bound = shader.preprocess(&[]).unwrap();
+            assert!(bound.contains("var<uniform> model: Model;"));
+            assert!(!bound.contains("var<push_constant> model: Model;"));
+        }
+    }
+}
@@ -10,6 +10,14 @@ pub struct RenderInstance(pub wgpu::Instance);
 #[derive(Resource, Deref)]
 pub struct RenderAdapter(pub wgpu::Adapter);
 
+/// Adapter to create the device on, set through `FlatEngineConfig::with_adapter`.
+/// Without it the high performance adapter compatible with the window is used.
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct RenderAdapterChoice {
+    pub name: String,
+    pub backend: wgpu::Backend,
+}
+
 #[derive(Resource, Deref)]
 pub struct RenderQueue(pub wgpu::Queue);
 
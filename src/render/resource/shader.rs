@@ -1,3 +1,5 @@
+use std::fmt;
+
 use bevy::{reflect::TypeUuid, asset::{AssetLoader, LoadedAsset}};
 
 use crate::render::RenderDevice;
@@ -18,14 +20,75 @@ impl Shader {
         }
     }
 
-    pub fn compile(&self, render_device: &RenderDevice) -> wgpu::ShaderModule {
-        render_device.create_shader_module(wgpu::ShaderModuleDescriptor {
+    pub fn compile(
+        &self,
+        render_device: &RenderDevice,
+        shader_defs: &[String],
+    ) -> Result<wgpu::ShaderModule, ShaderDefError> {
+        let source = self.preprocess(shader_defs)?;
+        Ok(render_device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(self.raw.as_str().into()),
-        })
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        }))
+    }
+
+    ///
+    /// Keeps the lines enabled by `shader_defs`, blocks are opened with
+    /// `#ifdef NAME` or `#ifndef NAME`, optionally split by `#else`, and closed with `#endif`.
+    ///
+    /// Fails on unbalanced blocks.
+    ///
+    pub fn preprocess(&self, shader_defs: &[String]) -> Result<String, ShaderDefError> {
+        // Whether each open block is enabled, lines are kept when all of them are
+        let mut scopes: Vec<bool> = Vec::new();
+        let mut out = String::with_capacity(self.raw.len());
+        for (index, line) in self.raw.lines().enumerate() {
+            let trimmed = line.trim();
+            if let Some(def) = trimmed.strip_prefix("#ifdef ") {
+                scopes.push(shader_defs.iter().any(|d| d == def.trim()));
+            } else if let Some(def) = trimmed.strip_prefix("#ifndef ") {
+                scopes.push(!shader_defs.iter().any(|d| d == def.trim()));
+            } else if trimmed == "#else" {
+                let scope = scopes
+                    .last_mut()
+                    .ok_or(ShaderDefError::UnmatchedElse { line: index + 1 })?;
+                *scope = !*scope;
+            } else if trimmed == "#endif" {
+                scopes
+                    .pop()
+                    .ok_or(ShaderDefError::UnmatchedEndif { line: index + 1 })?;
+            } else if scopes.iter().all(|enabled| *enabled) {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        if !scopes.is_empty() {
+            return Err(ShaderDefError::UnclosedIfdef);
+        }
+        Ok(out)
     }
 }
 
+/// Unbalanced `#ifdef` blocks found by [`Shader::preprocess`], lines start from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderDefError {
+    UnmatchedElse { line: usize },
+    UnmatchedEndif { line: usize },
+    UnclosedIfdef,
+}
+
+impl fmt::Display for ShaderDefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnmatchedElse { line } => write!(f, "#else without #ifdef at line {}", line),
+            Self::UnmatchedEndif { line } => write!(f, "#endif without #ifdef at line {}", line),
+            Self::UnclosedIfdef => write!(f, "#ifdef without #endif"),
+        }
+    }
+}
+
+impl std::error::Error for ShaderDefError {}
+
 #[derive(Default)]
 pub struct ShaderLoader;
 impl AssetLoader for ShaderLoader {
@@ -48,3 +111,37 @@ impl AssetLoader for ShaderLoader {
         &["wgsl"]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preprocess_shader_defs() {
+        let shader = Shader::from_wgsl(
+            "a\n#ifdef X\nb\n#ifndef Y\nc\n#else\nd\n#endif\n#else\ne\n#endif\nf\n",
+        );
+        assert_eq!(shader.preprocess(&[]).unwrap(), "a\ne\nf\n");
+        assert_eq!(shader.preprocess(&["X".to_string()]).unwrap(), "a\nb\nc\nf\n");
+        assert_eq!(
+            shader
+                .preprocess(&["X".to_string(), "Y".to_string()])
+                .unwrap(),
+            "a\nb\nd\nf\n"
+        );
+    }
+
+    #[test]
+    fn preprocess_rejects_unbalanced_blocks() {
+        let preprocess = |source| Shader::from_wgsl(source).preprocess(&[]);
+        assert_eq!(
+            preprocess("a\n#else\n"),
+            Err(ShaderDefError::UnmatchedElse { line: 2 })
+        );
+        assert_eq!(
+            preprocess("#endif\n"),
+            Err(ShaderDefError::UnmatchedEndif { line: 1 })
+        );
+        assert_eq!(preprocess("#ifdef X\na\n"), Err(ShaderDefError::UnclosedIfdef));
+    }
+}
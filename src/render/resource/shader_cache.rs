@@ -0,0 +1,127 @@
+//!
+//! Compiled shader modules persisted to disk, so later runs skip parsing and validating the WGSL.
+//!
+//! Shaders are translated to SPIR-V by naga once and stored under a directory per adapter,
+//! keyed by the preprocessed source. The SPIR-V is only used on adapters with
+//! `Features::SPIRV_SHADER_PASSTHROUGH` (Vulkan), other backends compile the WGSL as usual.
+//!
+//! wgpu 0.14 has no pipeline cache to persist, drivers keep their own.
+//!
+
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use bevy::{log::warn, prelude::Resource};
+
+use crate::render::RenderDevice;
+
+use super::shader::{Shader, ShaderDefError};
+
+/// Bumped when the SPIR-V written for the same source changes, e.g. with a naga update.
+const CACHE_VERSION: u32 = 1;
+
+/// Directory of the cache, set through `FlatEngineConfig::with_shader_cache`.
+#[derive(Resource, Clone, Debug)]
+pub struct ShaderCacheDir(pub PathBuf);
+
+pub struct ShaderCache {
+    dir: PathBuf,
+}
+
+impl ShaderCache {
+    ///
+    /// Cache of `adapter` inside `dir`, `None` when the adapter can not use SPIR-V.
+    ///
+    /// The directory is created when the first module is written.
+    ///
+    pub fn new(dir: &Path, adapter: &wgpu::AdapterInfo, features: wgpu::Features) -> Option<Self> {
+        if !features.contains(wgpu::Features::SPIRV_SHADER_PASSTHROUGH) {
+            return None;
+        }
+        let adapter_key = format!(
+            "{}-{:?}-{:x}-{:x}",
+            adapter.name, adapter.backend, adapter.vendor, adapter.device
+        );
+        Some(Self {
+            dir: dir.join(format!("{:016x}", fnv1a(adapter_key.as_bytes()))),
+        })
+    }
+
+    /// Module of the preprocessed `shader`, from disk or translated and stored.
+    pub fn compile(
+        &self,
+        render_device: &RenderDevice,
+        shader: &Shader,
+        shader_defs: &[String],
+    ) -> Result<wgpu::ShaderModule, ShaderDefError> {
+        let source = shader.preprocess(shader_defs)?;
+        let path = self.dir.join(format!(
+            "v{}-{:016x}.spv",
+            CACHE_VERSION,
+            fnv1a(source.as_bytes())
+        ));
+
+        let words = match std::fs::read(&path) {
+            Ok(bytes) => Some(wgpu::util::make_spirv_raw(&bytes).into_owned()),
+            Err(_) => translate(&source).map(|words| {
+                self.write(&path, &words);
+                words
+            }),
+        };
+
+        Ok(match words {
+            // SAFE: written by naga from a source that passed validation
+            Some(words) => unsafe {
+                render_device.create_shader_module_spirv(&wgpu::ShaderModuleDescriptorSpirV {
+                    label: None,
+                    source: Cow::Owned(words),
+                })
+            },
+            // Left to wgpu, which reports the errors
+            None => render_device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            }),
+        })
+    }
+
+    fn write(&self, path: &Path, words: &[u32]) {
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let result = std::fs::create_dir_all(&self.dir).and_then(|_| std::fs::write(path, bytes));
+        if let Err(err) = result {
+            warn!("Failed to write shader cache {:?}: {}", path, err);
+        }
+    }
+}
+
+/// SPIR-V of the WGSL `source`, `None` when it does not parse or validate.
+fn translate(source: &str) -> Option<Vec<u32>> {
+    let module = naga::front::wgsl::parse_str(source).ok()?;
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .ok()?;
+    naga::back::spv::write_vec(&module, &info, &Default::default(), None).ok()
+}
+
+/// Stable across runs and Rust versions, unlike the std hasher.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_is_stable() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
@@ -1,8 +1,15 @@
-use bevy::{prelude::Resource, utils::HashMap};
+use bevy::{
+    prelude::{App, IntoSystemDescriptor, Res, ResMut, Resource},
+    utils::HashMap,
+};
 use std::hash::Hash;
 
+use crate::render::RenderStage;
+
 use super::{
-    pipeline::{RenderPipelineDescriptor, RenderPipelineId},
+    pipeline::{
+        compile_shaders_into_pipelines, PipelineCache, RenderPipelineDescriptor, RenderPipelineId,
+    },
     renderer::RenderDevice,
 };
 
@@ -17,6 +24,25 @@ pub struct Specialized<P: PipelineSpecialize> {
     pub pipelines: HashMap<P::Key, RenderPipelineId>,
 }
 
+impl<P: PipelineSpecialize> Specialized<P>
+where
+    P::Key: Clone,
+{
+    /// Pipeline of `key`, queued into the cache the first time it is requested.
+    pub fn specialize(
+        &mut self,
+        pipeline_cache: &mut PipelineCache,
+        pipeline: &P,
+        render_device: &RenderDevice,
+        key: P::Key,
+    ) -> RenderPipelineId {
+        *self
+            .pipelines
+            .entry(key.clone())
+            .or_insert_with(|| pipeline_cache.queue(pipeline.specialize(render_device, key)))
+    }
+}
+
 impl<P: PipelineSpecialize> Default for Specialized<P> {
     fn default() -> Self {
         Self {
@@ -24,3 +50,133 @@ impl<P: PipelineSpecialize> Default for Specialized<P> {
         }
     }
 }
+
+///
+/// Keys of `P` to compile before anything is drawn with them, e.g. during a loading screen,
+/// so a new variant showing up mid-game does not stall the frame it appears in.
+///
+/// Drained every frame, [`PipelineWarmupStatus`] tells when the pipelines are compiled.
+///
+#[derive(Resource)]
+pub struct PipelineWarmup<P: PipelineSpecialize> {
+    keys: Vec<P::Key>,
+}
+
+impl<P: PipelineSpecialize> PipelineWarmup<P> {
+    pub fn push(&mut self, key: P::Key) {
+        self.keys.push(key);
+    }
+
+    pub fn extend(&mut self, keys: impl IntoIterator<Item = P::Key>) {
+        self.keys.extend(keys);
+    }
+}
+
+impl<P: PipelineSpecialize> Default for PipelineWarmup<P> {
+    fn default() -> Self {
+        Self {
+            keys: Default::default(),
+        }
+    }
+}
+
+/// Pipelines requested through a [`PipelineWarmup`] that are not compiled yet.
+#[derive(Resource, Default, Debug)]
+pub struct PipelineWarmupStatus {
+    pending: Vec<RenderPipelineId>,
+    requested: usize,
+}
+
+impl PipelineWarmupStatus {
+    pub fn is_ready(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Compiled fraction of everything requested so far, 1.0 when nothing was.
+    pub fn progress(&self) -> f32 {
+        if self.requested == 0 {
+            return 1.0;
+        }
+        1.0 - self.pending.len() as f32 / self.requested as f32
+    }
+
+    fn track(&mut self, id: RenderPipelineId) {
+        if !self.pending.contains(&id) {
+            self.pending.push(id);
+            self.requested += 1;
+        }
+    }
+
+    fn retain_pending(&mut self, is_ready: impl Fn(&RenderPipelineId) -> bool) {
+        self.pending.retain(|id| !is_ready(id));
+        if self.pending.is_empty() {
+            self.requested = 0;
+        }
+    }
+}
+
+pub fn warm_up_pipelines<P: PipelineSpecialize + Resource>(
+    render_device: Res<RenderDevice>,
+    pipeline: Res<P>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    mut specialized: ResMut<Specialized<P>>,
+    mut warmup: ResMut<PipelineWarmup<P>>,
+    mut status: ResMut<PipelineWarmupStatus>,
+) where
+    P::Key: Clone + Send + Sync + 'static,
+{
+    for key in warmup.keys.drain(..) {
+        let id = specialized.specialize(&mut pipeline_cache, &pipeline, &render_device, key);
+        if pipeline_cache.get(&id).is_none() {
+            status.track(id);
+        }
+    }
+}
+
+pub fn update_pipeline_warmup_status(
+    pipeline_cache: Res<PipelineCache>,
+    mut status: ResMut<PipelineWarmupStatus>,
+) {
+    if !status.is_ready() {
+        status.retain_pending(|id| pipeline_cache.get(id).is_some());
+    }
+}
+
+///
+/// Updates `app` until the warm-up is compiled, at most `max_updates` times,
+/// for a blocking load instead of polling [`PipelineWarmupStatus`].
+///
+/// Returns whether the pipelines are ready, shaders still loading from files need updates.
+///
+pub fn block_on_pipeline_warmup(app: &mut App, max_updates: u32) -> bool {
+    for _ in 0..max_updates {
+        app.update();
+        if app.world.resource::<PipelineWarmupStatus>().is_ready() {
+            return true;
+        }
+    }
+    false
+}
+
+pub trait AddPipelineWarmup {
+    fn add_pipeline_warmup<P: PipelineSpecialize + Resource>(&mut self) -> &mut Self
+    where
+        P::Key: Clone + Send + Sync + 'static;
+}
+
+impl AddPipelineWarmup for App {
+    fn add_pipeline_warmup<P: PipelineSpecialize + Resource>(&mut self) -> &mut Self
+    where
+        P::Key: Clone + Send + Sync + 'static,
+    {
+        self.init_resource::<PipelineWarmup<P>>().add_system_to_stage(
+            RenderStage::Prepare,
+            warm_up_pipelines::<P>.before(compile_shaders_into_pipelines),
+        )
+    }
+}
+
@@ -0,0 +1,269 @@
+// DISCLAIMER: COPIED FROM BEVY
+
+use encase::{
+    internal::WriteInto, DynamicStorageBuffer as DynamicStorageBufferWrapper, ShaderType,
+    StorageBuffer as StorageBufferWrapper,
+};
+
+use crate::render::{RenderDevice, RenderQueue};
+
+/// Stores data to be transferred to the GPU and made accessible to shaders as a storage buffer.
+///
+/// Storage buffers can be made available to shaders in some combination of read/write mode, and can store large amounts
+/// of data. Note however that WebGL2 does not support storage buffers, so consider alternative options in this case.
+///
+/// Storage buffers can store runtime-sized arrays, but only if they are the last field in a structure.
+///
+/// The contained data is stored in system RAM. [`write_buffer`](StorageBuffer::write_buffer) queues
+/// copying of the data from system RAM to VRAM. Storage buffers must conform to [std430 alignment/padding requirements],
+/// which is automatically enforced by this structure.
+///
+/// Other options for storing GPU-accessible data are:
+/// * [`DynamicStorageBuffer`]
+/// * [`UniformBuffer`](super::uniform::UniformBuffer)
+/// * [`DynamicUniformBuffer`](super::uniform::DynamicUniformBuffer)
+///
+/// [std430 alignment/padding requirements]: https://www.w3.org/TR/WGSL/#address-spaces-storage
+pub struct StorageBuffer<T: ShaderType> {
+    value: T,
+    scratch: StorageBufferWrapper<Vec<u8>>,
+    buffer: Option<wgpu::Buffer>,
+    capacity: usize,
+    label: Option<String>,
+    label_changed: bool,
+    generation: u64,
+}
+
+impl<T: ShaderType> From<T> for StorageBuffer<T> {
+    fn from(value: T) -> Self {
+        Self {
+            value,
+            scratch: StorageBufferWrapper::new(Vec::new()),
+            buffer: None,
+            capacity: 0,
+            label: None,
+            label_changed: false,
+            generation: 0,
+        }
+    }
+}
+
+impl<T: ShaderType + Default> Default for StorageBuffer<T> {
+    fn default() -> Self {
+        Self::from(T::default())
+    }
+}
+
+impl<T: ShaderType + WriteInto> StorageBuffer<T> {
+    #[inline]
+    pub fn buffer(&self) -> Option<&wgpu::Buffer> {
+        self.buffer.as_ref()
+    }
+
+    #[inline]
+    pub fn binding(&self) -> Option<wgpu::BindingResource> {
+        Some(wgpu::BindingResource::Buffer(
+            self.buffer()?.as_entire_buffer_binding(),
+        ))
+    }
+
+    /// Incremented every time the GPU-side buffer is recreated,
+    /// bind groups kept across frames have to be recreated when it changes.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn set(&mut self, value: T) {
+        self.value = value;
+    }
+
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    pub fn set_label(&mut self, label: Option<&str>) {
+        let label = label.map(str::to_string);
+
+        if label != self.label {
+            self.label_changed = true;
+        }
+
+        self.label = label;
+    }
+
+    pub fn get_label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Queues writing of data from system RAM to VRAM using the [`RenderDevice`]
+    /// and the provided [`RenderQueue`].
+    ///
+    /// If there is no GPU-side buffer allocated to hold the data currently stored, or if a GPU-side buffer previously
+    /// allocated does not have enough capacity, a new GPU-side buffer is created.
+    pub fn write_buffer(&mut self, device: &RenderDevice, queue: &RenderQueue) {
+        self.scratch.as_mut().clear();
+        self.scratch.write(&self.value).unwrap();
+
+        let size = self.scratch.as_ref().len();
+
+        if self.capacity < size || self.label_changed {
+            self.buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: self.label.as_deref(),
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+                contents: self.scratch.as_ref(),
+            }));
+            self.capacity = size;
+            self.label_changed = false;
+            self.generation += 1;
+        } else if let Some(buffer) = &self.buffer {
+            queue.write_buffer(buffer, 0, self.scratch.as_ref());
+        }
+    }
+}
+
+/// Stores data to be transferred to the GPU and made accessible to shaders as a dynamic storage buffer.
+///
+/// Dynamic storage buffers can be made available to shaders in some combination of read/write mode, and can store large
+/// amounts of data. Note however that WebGL2 does not support storage buffers, so consider alternative options in this case.
+/// Dynamic storage buffers support multiple separate bindings at dynamic byte offsets and so have a
+/// [`push`](DynamicStorageBuffer::push) method.
+///
+/// The contained data is stored in system RAM. [`write_buffer`](DynamicStorageBuffer::write_buffer)
+/// queues copying of the data from system RAM to VRAM. The data within a storage buffer binding must conform to
+/// [std430 alignment/padding requirements]. `DynamicStorageBuffer` does not yet support this, so there is no
+/// std430 padding between the values.
+///
+/// Other options for storing GPU-accessible data are:
+/// * [`StorageBuffer`]
+/// * [`UniformBuffer`](super::uniform::UniformBuffer)
+/// * [`DynamicUniformBuffer`](super::uniform::DynamicUniformBuffer)
+///
+/// [std430 alignment/padding requirements]: https://www.w3.org/TR/WGSL/#address-spaces-storage
+pub struct DynamicStorageBuffer<T: ShaderType> {
+    values: Vec<T>,
+    scratch: DynamicStorageBufferWrapper<Vec<u8>>,
+    buffer: Option<wgpu::Buffer>,
+    capacity: usize,
+    label: Option<String>,
+    label_changed: bool,
+    generation: u64,
+}
+
+impl<T: ShaderType> Default for DynamicStorageBuffer<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            scratch: DynamicStorageBufferWrapper::new(Vec::new()),
+            buffer: None,
+            capacity: 0,
+            label: None,
+            label_changed: false,
+            generation: 0,
+        }
+    }
+}
+
+impl<T: ShaderType + WriteInto> DynamicStorageBuffer<T> {
+    #[inline]
+    pub fn buffer(&self) -> Option<&wgpu::Buffer> {
+        self.buffer.as_ref()
+    }
+
+    #[inline]
+    pub fn binding(&self) -> Option<wgpu::BindingResource> {
+        Some(wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: self.buffer()?,
+            offset: 0,
+            size: Some(T::min_size()),
+        }))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Incremented every time the GPU-side buffer is recreated,
+    /// bind groups kept across frames have to be recreated when it changes.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    #[inline]
+    pub fn push(&mut self, value: T) -> u32 {
+        let offset = self.scratch.write(&value).unwrap() as u32;
+        self.values.push(value);
+        offset
+    }
+
+    pub fn set_label(&mut self, label: Option<&str>) {
+        let label = label.map(str::to_string);
+
+        if label != self.label {
+            self.label_changed = true;
+        }
+
+        self.label = label;
+    }
+
+    pub fn get_label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    #[inline]
+    pub fn write_buffer(&mut self, device: &RenderDevice, queue: &RenderQueue) {
+        let size = self.scratch.as_ref().len();
+
+        if self.capacity < size || self.label_changed {
+            self.buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: self.label.as_deref(),
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+                contents: self.scratch.as_ref(),
+            }));
+            self.capacity = size;
+            self.label_changed = false;
+            self.generation += 1;
+        } else if let Some(buffer) = &self.buffer {
+            queue.write_buffer(buffer, 0, self.scratch.as_ref());
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.scratch.as_mut().clear();
+        self.scratch.set_offset(0);
+    }
+}
+
+// -- Bevy Copy End --
+
+/// Layout entry of a storage buffer holding `T`, `read_only` unless written from a compute shader.
+pub fn storage_buffer_layout_entry<T: ShaderType>(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    read_only: bool,
+    has_dynamic_offset: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset,
+            min_binding_size: Some(T::min_size()),
+        },
+        count: None,
+    }
+}
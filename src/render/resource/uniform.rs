@@ -8,7 +8,9 @@ use encase::{
     UniformBuffer as UniformBufferWrapper,
 };
 
-use crate::render::{RenderDevice, RenderQueue};
+use crate::render::{color::ColorSpace, RenderDevice, RenderQueue};
+
+use super::buffer_pool::{BufferPool, PooledBuffer};
 
 /// Stores data to be transferred to the GPU and made accessible to shaders as a uniform buffer.
 ///
@@ -145,28 +147,64 @@ pub struct DynamicUniformBuffer<T: ShaderType> {
     values: Vec<T>,
     scratch: DynamicUniformBufferWrapper<Vec<u8>>,
     buffer: Option<wgpu::Buffer>,
+    pooled: Option<PooledBuffer>,
     capacity: usize,
     label: Option<String>,
     label_changed: bool,
+    generation: u64,
+    alignment: u64,
+    uploaded_len: usize,
+    dirty: Vec<(u64, u64)>,
 }
 
 impl<T: ShaderType> Default for DynamicUniformBuffer<T> {
     fn default() -> Self {
+        Self::with_alignment(Self::DEFAULT_ALIGNMENT)
+    }
+}
+
+impl<T: ShaderType> DynamicUniformBuffer<T> {
+    /// Largest `min_uniform_buffer_offset_alignment` a device may report,
+    /// offsets aligned to it are valid on every device.
+    pub const DEFAULT_ALIGNMENT: u64 = 256;
+
+    /// Buffer with a fixed stride between values, `alignment` has to be a multiple of
+    /// the device `min_uniform_buffer_offset_alignment`.
+    pub fn with_alignment(alignment: u64) -> Self {
         Self {
             values: Vec::new(),
-            scratch: DynamicUniformBufferWrapper::new(Vec::new()),
+            scratch: DynamicUniformBufferWrapper::new_with_alignment(Vec::new(), alignment),
             buffer: None,
+            pooled: None,
             capacity: 0,
             label: None,
             label_changed: false,
+            generation: 0,
+            alignment,
+            uploaded_len: 0,
+            dirty: Vec::new(),
         }
     }
+
+    /// Buffer packed as tightly as the device `min_uniform_buffer_offset_alignment` allows.
+    pub fn for_device(device: &RenderDevice) -> Self {
+        Self::with_alignment(device.limits().min_uniform_buffer_offset_alignment as u64)
+    }
+
+    /// Alignment of the offsets returned by [`push`](Self::push), fixed at creation.
+    #[inline]
+    pub fn alignment(&self) -> u64 {
+        self.alignment
+    }
 }
 
 impl<T: ShaderType + WriteInto> DynamicUniformBuffer<T> {
     #[inline]
     pub fn buffer(&self) -> Option<&wgpu::Buffer> {
-        self.buffer.as_ref()
+        match &self.pooled {
+            Some(pooled) => Some(pooled.buffer()),
+            None => self.buffer.as_ref(),
+        }
     }
 
     #[inline]
@@ -188,6 +226,13 @@ impl<T: ShaderType + WriteInto> DynamicUniformBuffer<T> {
         self.values.is_empty()
     }
 
+    /// Incremented every time the GPU-side buffer is recreated,
+    /// bind groups kept across frames have to be recreated when it changes.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
     /// Push data into the `DynamicUniformBuffer`'s internal vector (residing on system RAM).
     #[inline]
     pub fn push(&mut self, value: T) -> u32 {
@@ -196,6 +241,18 @@ impl<T: ShaderType + WriteInto> DynamicUniformBuffer<T> {
         offset
     }
 
+    /// Overwrites the value at `offset`, returned by an earlier [`push`](Self::push).
+    /// [`write_buffer_changed`](Self::write_buffer_changed) only uploads the overwritten range.
+    pub fn set(&mut self, offset: u32, value: T) {
+        let next_offset = self.scratch.as_ref().len() as u64;
+        let next_offset = (next_offset + self.alignment - 1) / self.alignment * self.alignment;
+
+        self.scratch.set_offset(offset as u64);
+        self.scratch.write(&value).unwrap();
+        self.scratch.set_offset(next_offset);
+        self.dirty.push((offset as u64, T::min_size().get()));
+    }
+
     pub fn set_label(&mut self, label: Option<&str>) {
         let label = label.map(str::to_string);
 
@@ -225,16 +282,76 @@ impl<T: ShaderType + WriteInto> DynamicUniformBuffer<T> {
                 usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
                 contents: self.scratch.as_ref(),
             }));
+            self.pooled = None;
             self.capacity = size;
             self.label_changed = false;
-        } else if let Some(buffer) = &self.buffer {
+            self.generation += 1;
+        } else if let Some(buffer) = self.buffer() {
             queue.write_buffer(buffer, 0, self.scratch.as_ref());
         }
     }
 
+    /// Same as [`write_buffer`](Self::write_buffer) but grows into a buffer taken from `pool`,
+    /// the outgrown one is given back to it. Pooled buffers are unlabeled.
+    pub fn write_buffer_pooled(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        pool: &mut BufferPool,
+    ) {
+        let size = self.scratch.as_ref().len();
+
+        if self.capacity < size {
+            if let Some(pooled) = self.pooled.take() {
+                pool.release(pooled);
+            }
+            let pooled = pool.acquire(
+                device,
+                wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+                size as u64,
+            );
+            self.buffer = None;
+            self.capacity = pooled.size() as usize;
+            self.pooled = Some(pooled);
+            self.generation += 1;
+        }
+        if let Some(buffer) = self.buffer() {
+            queue.write_buffer(buffer, 0, self.scratch.as_ref());
+        }
+    }
+
+    /// Ranges overwritten by [`set`](Self::set) since the last upload, as `(offset, size)`.
+    pub fn dirty_ranges(&self) -> &[(u64, u64)] {
+        &self.dirty
+    }
+
+    /// Uploads only the values pushed or [`set`](Self::set) since the last upload,
+    /// the whole buffer is written when it has to grow. Meant for buffers that are not
+    /// [`clear`](Self::clear)ed every frame.
+    pub fn write_buffer_changed(&mut self, device: &RenderDevice, queue: &RenderQueue) {
+        let size = self.scratch.as_ref().len();
+
+        if self.capacity < size || self.label_changed {
+            self.write_buffer(device, queue);
+        } else if let Some(buffer) = self.buffer() {
+            let bytes = self.scratch.as_ref();
+            for (offset, len) in &self.dirty {
+                let range = *offset as usize..(*offset + *len) as usize;
+                queue.write_buffer(buffer, *offset, &bytes[range]);
+            }
+            if self.uploaded_len < size {
+                queue.write_buffer(buffer, self.uploaded_len as u64, &bytes[self.uploaded_len..]);
+            }
+        }
+        self.dirty.clear();
+        self.uploaded_len = size;
+    }
+
     #[inline]
     pub fn clear(&mut self) {
         self.values.clear();
+        self.uploaded_len = 0;
+        self.dirty.clear();
         self.scratch.as_mut().clear();
         self.scratch.set_offset(0);
     }
@@ -242,6 +359,24 @@ impl<T: ShaderType + WriteInto> DynamicUniformBuffer<T> {
 
 // -- Bevy Copy End --
 
+/// Layout entry of a uniform buffer holding `T`, see also `storage_buffer_layout_entry`.
+pub fn uniform_buffer_layout_entry<T: ShaderType>(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    has_dynamic_offset: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset,
+            min_binding_size: Some(T::min_size()),
+        },
+        count: None,
+    }
+}
+
 #[derive(Component)]
 pub struct DynamicUniformId<T: ShaderType>(pub u32, PhantomData<T>);
 impl<T: ShaderType> Deref for DynamicUniformId<T> {
@@ -265,18 +400,19 @@ impl<T: ShaderType> From<u32> for DynamicUniformId<T> {
 pub trait HandleGpuUniform {
     type GU: ShaderType + WriteInto + Send + Sync + 'static;
 
-    fn generate_uniform(&self) -> Self::GU
+    fn generate_uniform(&self, color_space: ColorSpace) -> Self::GU
     where
         Self::GU: Default,
     {
         let mut gpu_uniform = Self::GU::default();
-        self.update_uniform(&mut gpu_uniform);
+        self.update_uniform(&mut gpu_uniform, color_space);
         gpu_uniform
     }
     
-    fn update_uniform(&self, gpu_uniform: &mut Self::GU) {
-        *gpu_uniform = self.into_uniform();
+    fn update_uniform(&self, gpu_uniform: &mut Self::GU, color_space: ColorSpace) {
+        *gpu_uniform = self.into_uniform(color_space);
     }
 
-    fn into_uniform(&self) -> Self::GU;
+    /// Colors are written in `color_space`, the value of the [`ColorSpace`] resource.
+    fn into_uniform(&self, color_space: ColorSpace) -> Self::GU;
 }
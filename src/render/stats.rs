@@ -0,0 +1,238 @@
+use std::{
+    ops::AddAssign,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use bevy::{prelude::Resource, utils::HashSet};
+
+/// Kind of entity a draw renders, for the breakdowns of [`FrameRenderStats::categories`].
+/// Text is drawn as sprite meshes and counted with the sprites.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RenderCategory {
+    Sprite,
+    Shape,
+    Mesh,
+}
+
+impl RenderCategory {
+    pub const COUNT: usize = 3;
+    pub const ALL: [RenderCategory; Self::COUNT] = [Self::Sprite, Self::Shape, Self::Mesh];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderCategory::Sprite => "sprite",
+            RenderCategory::Shape => "shape",
+            RenderCategory::Mesh => "mesh",
+        }
+    }
+}
+
+/// Draws of a [`RenderCategory`] and the entities they rendered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CategoryRenderStats {
+    pub draw_calls: u64,
+    pub entities: u64,
+}
+
+impl CategoryRenderStats {
+    /// Above 1 when batching merges entities into a draw, 0 without draws.
+    pub fn entities_per_draw(&self) -> f64 {
+        if self.draw_calls == 0 {
+            return 0.0;
+        }
+        self.entities as f64 / self.draw_calls as f64
+    }
+}
+
+impl AddAssign for CategoryRenderStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.draw_calls += rhs.draw_calls;
+        self.entities += rhs.entities;
+    }
+}
+
+/// Counts of the GPU commands encoded in a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameRenderStats {
+    pub draw_calls: u64,
+    pub instances: u64,
+    /// Vertices submitted, indices for indexed draws.
+    pub vertices: u64,
+    pub pipeline_switches: u64,
+    pub bind_group_switches: u64,
+    /// Draws skipped with a `RenderResult::Failure`.
+    pub draw_failures: u64,
+    /// Indexed by [`RenderCategory`], draws outside them (grid, trails, camera passes)
+    /// are only in the totals.
+    pub categories: [CategoryRenderStats; RenderCategory::COUNT],
+}
+
+impl FrameRenderStats {
+    pub fn draw(&mut self, vertices: u32, instances: u32) {
+        self.draw_calls += 1;
+        self.instances += instances as u64;
+        self.vertices += vertices as u64 * instances as u64;
+    }
+
+    /// Counts a draw of `entities` entities of the category, next to [`FrameRenderStats::draw`].
+    pub fn category_draw(&mut self, category: RenderCategory, entities: u32) {
+        self.categories[category as usize] += CategoryRenderStats {
+            draw_calls: 1,
+            entities: entities as u64,
+        };
+    }
+
+    pub fn category(&self, category: RenderCategory) -> CategoryRenderStats {
+        self.categories[category as usize]
+    }
+
+    /// Entities per draw over every category, how well batching works in the scene.
+    pub fn entities_per_draw(&self) -> f64 {
+        let mut total = CategoryRenderStats::default();
+        for category in self.categories {
+            total += category;
+        }
+        total.entities_per_draw()
+    }
+}
+
+impl AddAssign for FrameRenderStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.draw_calls += rhs.draw_calls;
+        self.instances += rhs.instances;
+        self.vertices += rhs.vertices;
+        self.pipeline_switches += rhs.pipeline_switches;
+        self.bind_group_switches += rhs.bind_group_switches;
+        self.draw_failures += rhs.draw_failures;
+        for (category, rhs) in self.categories.iter_mut().zip(rhs.categories) {
+            *category += rhs;
+        }
+    }
+}
+
+///
+/// Statistics of the commands encoded by the render system.
+///
+/// Render functions record into it through `&World` while cameras are encoded in parallel,
+/// [`RenderStats::last_frame`] holds the totals of the last rendered frame.
+///
+#[derive(Resource, Default)]
+pub struct RenderStats {
+    draw_calls: AtomicU64,
+    instances: AtomicU64,
+    vertices: AtomicU64,
+    pipeline_switches: AtomicU64,
+    bind_group_switches: AtomicU64,
+    draw_failures: AtomicU64,
+    category_draw_calls: [AtomicU64; RenderCategory::COUNT],
+    category_entities: [AtomicU64; RenderCategory::COUNT],
+    last_frame: FrameRenderStats,
+    logged_failure_reasons: HashSet<&'static str>,
+}
+
+impl RenderStats {
+    pub fn draw(&self, vertices: u32, instances: u32) {
+        self.draw_calls.fetch_add(1, Ordering::Relaxed);
+        self.instances.fetch_add(instances as u64, Ordering::Relaxed);
+        self.vertices
+            .fetch_add(vertices as u64 * instances as u64, Ordering::Relaxed);
+    }
+
+    /// Counts a draw of `entities` entities of the category, next to [`RenderStats::draw`].
+    pub fn category_draw(&self, category: RenderCategory, entities: u32) {
+        self.category_draw_calls[category as usize].fetch_add(1, Ordering::Relaxed);
+        self.category_entities[category as usize].fetch_add(entities as u64, Ordering::Relaxed);
+    }
+
+    pub fn pipeline_switch(&self) {
+        self.pipeline_switches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn bind_group_switches(&self, count: u32) {
+        self.bind_group_switches
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn draw_failure(&self) {
+        self.draw_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Adds commands encoded elsewhere, e.g. replayed from a render bundle.
+    pub fn add(&self, stats: FrameRenderStats) {
+        self.draw_calls.fetch_add(stats.draw_calls, Ordering::Relaxed);
+        self.instances.fetch_add(stats.instances, Ordering::Relaxed);
+        self.vertices.fetch_add(stats.vertices, Ordering::Relaxed);
+        self.pipeline_switches
+            .fetch_add(stats.pipeline_switches, Ordering::Relaxed);
+        self.bind_group_switches
+            .fetch_add(stats.bind_group_switches, Ordering::Relaxed);
+        self.draw_failures
+            .fetch_add(stats.draw_failures, Ordering::Relaxed);
+        for (i, category) in stats.categories.iter().enumerate() {
+            self.category_draw_calls[i].fetch_add(category.draw_calls, Ordering::Relaxed);
+            self.category_entities[i].fetch_add(category.entities, Ordering::Relaxed);
+        }
+    }
+
+    pub fn last_frame(&self) -> FrameRenderStats {
+        self.last_frame
+    }
+
+    /// Moves the counts of the frame just encoded into [`RenderStats::last_frame`].
+    pub(crate) fn finish_frame(&mut self) -> FrameRenderStats {
+        self.last_frame = FrameRenderStats {
+            draw_calls: std::mem::take(self.draw_calls.get_mut()),
+            instances: std::mem::take(self.instances.get_mut()),
+            vertices: std::mem::take(self.vertices.get_mut()),
+            pipeline_switches: std::mem::take(self.pipeline_switches.get_mut()),
+            bind_group_switches: std::mem::take(self.bind_group_switches.get_mut()),
+            draw_failures: std::mem::take(self.draw_failures.get_mut()),
+            categories: std::array::from_fn(|i| CategoryRenderStats {
+                draw_calls: std::mem::take(self.category_draw_calls[i].get_mut()),
+                entities: std::mem::take(self.category_entities[i].get_mut()),
+            }),
+        };
+        self.last_frame
+    }
+
+    /// `true` the first time a draw fails with this reason, to log each reason once.
+    pub(crate) fn first_failure(&mut self, reason: &'static str) -> bool {
+        self.logged_failure_reasons.insert(reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batched_draws_count_their_entities() {
+        let stats = RenderStats::default();
+        stats.draw(6, 1);
+        stats.category_draw(RenderCategory::Sprite, 1);
+        stats.draw(600, 1);
+        stats.category_draw(RenderCategory::Sprite, 100);
+        stats.draw(36, 1);
+        stats.category_draw(RenderCategory::Mesh, 1);
+
+        let mut bundle_stats = FrameRenderStats::default();
+        bundle_stats.draw(36, 1);
+        bundle_stats.category_draw(RenderCategory::Mesh, 1);
+        stats.add(bundle_stats);
+
+        let mut stats = stats;
+        let frame = stats.finish_frame();
+        assert_eq!(frame.draw_calls, 4);
+        assert_eq!(
+            frame.category(RenderCategory::Sprite).entities_per_draw(),
+            50.5
+        );
+        assert_eq!(frame.category(RenderCategory::Mesh).draw_calls, 2);
+        assert_eq!(
+            frame.category(RenderCategory::Shape).entities_per_draw(),
+            0.0
+        );
+        assert_eq!(frame.entities_per_draw(), 103.0 / 4.0);
+        assert_eq!(stats.finish_frame(), FrameRenderStats::default());
+    }
+}
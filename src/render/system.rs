@@ -1,23 +1,37 @@
 use core::panic;
+use std::sync::Mutex;
 
 use bevy::{
     ecs::system::lifetimeless::Read,
+    log::{debug, info_span, trace, warn},
     prelude::{
-        App, Component, Entity, FromWorld, GlobalTransform, Handle, Mut, QueryState, Resource,
-        Transform, With, World,
+        App, Assets, Component, Entity, Events, FromWorld, GlobalTransform, Handle, Mut,
+        QueryState, Resource, Transform, With, World,
     },
+    tasks::ComputeTaskPool,
     utils::HashMap,
     window::WindowId,
 };
+#[cfg(feature = "window")]
 use winit::window::Window;
 
 use super::{
     camera::component::*,
+    capture::FrameCapture,
     color::Color,
+    error::RendererError,
     mesh::Mesh,
+    pass::{CameraAttachments, CameraPassStage, CameraPasses},
+    phase::{Opaque, PhaseItem, RenderOrdering, RenderPhase, Transparent},
+    render_bundle::RenderBundles,
     resource::buffer::MeshVertex,
-    texture::{DepthTextures, Image},
-    view::window::PreparedWindows,
+    stats::RenderStats,
+    texture::{DepthTexture, DepthTextures, Image},
+    view::{
+        grab::GrabTextures,
+        upscale::{encode_upscales, PreparedUpscales},
+        window::PreparedWindows,
+    },
     RenderAssets, RenderDevice, RenderInstance, RenderQueue,
 };
 
@@ -31,24 +45,73 @@ pub struct MeshBundle<V: MeshVertex> {
                                            // pub pipeline_id: CachedRenderPipelineId,
 }
 
+///
+/// Every system is already wrapped in a span by bevy's `trace` feature,
+/// the spans here break the render system down into update, encode, submit and present.
+/// Build with the `trace_tracy` feature to stream them to Tracy.
+///
 pub fn render_system(world: &mut World) {
+    let capture = world
+        .get_resource_mut::<FrameCapture>()
+        .unwrap()
+        .take_request();
+    if capture {
+        world.get_resource::<RenderDevice>().unwrap().start_capture();
+    }
+
     world.resource_scope(|world: &mut World, mut render_node: Mut<RenderNode>| {
+        let _span = info_span!("render_node_update").entered();
         render_node.update(&world);
     });
 
     let render_node = world.get_resource::<RenderNode>().unwrap();
-    render_node.run(&world);
+    let failures = render_node.run(&world);
+
+    // Failures repeat every frame until the data is ready, log only what is new
+    let mut render_stats = world.get_resource_mut::<RenderStats>().unwrap();
+    let failed_last_frame = render_stats.last_frame().draw_failures;
+    for failure in &failures {
+        if let RendererError::Draw { entity, reason, .. } = failure {
+            if render_stats.first_failure(reason) {
+                warn!("Draw of {:?} failed: {}", entity, reason);
+            }
+        }
+    }
+    let frame_stats = render_stats.finish_frame();
+    if failed_last_frame == 0 && frame_stats.draw_failures > 0 {
+        debug!("{} draws failed this frame", frame_stats.draw_failures);
+    } else if failed_last_frame > 0 && frame_stats.draw_failures == 0 {
+        debug!("All draws succeeded again");
+    }
+    trace!("{:?}", frame_stats);
+
+    world
+        .get_resource_mut::<Events<RendererError>>()
+        .unwrap()
+        .extend(failures);
 
     world.resource_scope(|_world: &mut World, mut windows: Mut<PreparedWindows>| {
+        let _span = info_span!("present").entered();
         for window in windows.values_mut() {
-            window.surface_texture.take().unwrap().texture.present();
+            if let Some(surface_texture) = window.surface_texture.take() {
+                surface_texture.texture.present();
+            }
         }
     });
+
+    if capture {
+        world.get_resource::<RenderDevice>().unwrap().stop_capture();
+    }
 }
 
 #[derive(Resource)]
 pub struct RenderNode {
-    cameras: QueryState<(Entity, Read<Camera>, Read<VisibleEntities>)>,
+    cameras: QueryState<(
+        Entity,
+        Read<Camera>,
+        Read<RenderPhase<Opaque>>,
+        Read<RenderPhase<Transparent>>,
+    )>,
     entities: QueryState<(Entity,), (With<Visibility>,)>,
 }
 
@@ -71,74 +134,160 @@ impl RenderNode {
         self.entities.update_archetypes(world);
     }
 
-    pub fn run(&self, world: &World) {
+    /// Returns the draws that failed, as [`RendererError::Draw`].
+    pub fn run(&self, world: &World) -> Vec<RendererError> {
         let render_device = world.get_resource::<RenderDevice>().unwrap();
         let render_queue = world.get_resource::<RenderQueue>().unwrap();
 
+        let images = world.get_resource::<Assets<Image>>().unwrap();
         let gpu_textures = world.get_resource::<RenderAssets<Image>>().unwrap();
         let windows = world.get_resource::<PreparedWindows>().unwrap();
 
-        let mut command_encoder = render_device.create_command_encoder(&Default::default());
-
-        let render_functions = world.get_resource::<RenderFunctions>().unwrap();
-        let cameras = self.cameras.iter_manual(world);
+        // Cameras rendering into images first, their images are sampled by the other cameras
+        let mut cameras: Vec<_> = self.cameras.iter_manual(world).collect();
+        if world.get_resource::<RenderOrdering>().unwrap().deterministic {
+            cameras.sort_by_key(|(entity, camera, _, _)| {
+                (camera.render_target.encode_order(), *entity)
+            });
+        } else {
+            cameras.sort_by_key(|(_, camera, _, _)| camera.render_target.encode_order());
+        }
 
         let depth_textures = world.get_resource::<DepthTextures>().unwrap();
+        let render_bundles = world.get_resource::<RenderBundles>().unwrap();
+        let render_stats = world.get_resource::<RenderStats>().unwrap();
+        let grab_textures = world.get_resource::<GrabTextures>().unwrap();
+        let camera_passes = world.get_resource::<CameraPasses>().unwrap();
 
         let mut camera_windows: Vec<WindowId> = Vec::new();
+        let failures: Mutex<Vec<RendererError>> = Mutex::new(Vec::new());
+        let failures_ref = &failures;
+
+        // Each camera is encoded into its own CommandEncoder on the compute task pool,
+        // command buffers come back in spawn order so cameras sharing a target keep their order
+        // and images are rendered before the cameras displaying them
+        let camera_command_buffers = ComputeTaskPool::get().scope(|scope| {
+            for (camera_entity, camera, opaque_phase, transparent_phase) in cameras {
+                if let Some(id) = camera.render_target.get_window() {
+                    camera_windows.push(id);
+                }
 
-        for (camera_entity, camera, visible_entities) in cameras {
-            if let Some(id) = camera.render_target.get_window() {
-                camera_windows.push(id);
-            }
-
-            let render_target_view = camera.render_target.get_view(&gpu_textures, &windows);
+                scope.spawn(async move {
+                    let _span = info_span!("encode_camera", camera = ?camera_entity).entered();
+
+                    let mut command_encoder =
+                        render_device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("camera_command_encoder"),
+                        });
+                    let Some(render_target_view) =
+                        camera.render_target.get_view(&gpu_textures, &windows)
+                    else {
+                        return None;
+                    };
+
+                    let depth_texture = depth_textures.get(&camera.render_target);
+                    let attachments = CameraAttachments {
+                        color: render_target_view,
+                        color_texture: camera.render_target.get_texture(&gpu_textures, &windows),
+                        depth: depth_texture.map(|dt| &dt.view),
+                        size: camera
+                            .render_target
+                            .get_size(&images, &windows)
+                            .unwrap_or_default(),
+                    };
+                    let clear = wgpu::LoadOp::Clear(wgpu::Color {
+                        // Magenta
+                        r: 1.0,
+                        g: 0.0,
+                        b: 1.0,
+                        a: 1.0,
+                    });
+
+                    // The target is cleared on its own when passes run before the main pass
+                    let before_main = camera_passes.get(CameraPassStage::BeforeMain);
+                    let main_load = if before_main.is_empty() {
+                        clear
+                    } else {
+                        drop(begin_camera_pass(
+                            &mut command_encoder,
+                            render_target_view,
+                            depth_texture,
+                            clear,
+                        ));
+                        for pass in before_main {
+                            (pass)(camera_entity, world, &attachments, &mut command_encoder);
+                        }
+                        wgpu::LoadOp::Load
+                    };
+
+                    let mut render_pass = begin_camera_pass(
+                        &mut command_encoder,
+                        render_target_view,
+                        depth_texture,
+                        main_load,
+                    );
+
+                    render_pass.execute_bundles(render_bundles.camera_bundles(camera_entity));
+                    render_stats.add(render_bundles.camera_stats(camera_entity));
+
+                    // Opaque front-to-back, then transparent back-to-front with depth write off
+                    render_phase_items(
+                        camera_entity,
+                        &opaque_phase.items,
+                        world,
+                        &mut render_pass,
+                        failures_ref,
+                    );
+
+                    // The pass is split to copy what the opaque pass drew for the transparent draws
+                    let grab = grab_textures
+                        .get(&camera_entity)
+                        .zip(attachments.color_texture);
+                    if let Some((grab_texture, target_texture)) = grab {
+                        drop(render_pass);
+                        command_encoder.copy_texture_to_texture(
+                            target_texture.as_image_copy(),
+                            grab_texture.texture.texture.as_image_copy(),
+                            grab_texture.copy_size(),
+                        );
+                        render_pass = begin_camera_pass(
+                            &mut command_encoder,
+                            render_target_view,
+                            depth_texture,
+                            wgpu::LoadOp::Load,
+                        );
+                    }
 
-            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &render_target_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            // Magenta
-                            r: 1.0,
-                            g: 0.0,
-                            b: 1.0,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: depth_textures.get(&camera.render_target).map(|dt| {
-                    wgpu::RenderPassDepthStencilAttachment {
-                        view: &dt.view,
-                        depth_ops: Some(wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(1.0),
-                            store: true,
-                        }),
-                        stencil_ops: None,
+                    render_phase_items(
+                        camera_entity,
+                        &transparent_phase.items,
+                        world,
+                        &mut render_pass,
+                        failures_ref,
+                    );
+                    drop(render_pass);
+
+                    for pass in camera_passes.get(CameraPassStage::AfterMain) {
+                        (pass)(camera_entity, world, &attachments, &mut command_encoder);
                     }
-                }),
-            });
 
-            for entity in visible_entities.iter() {
-                if let Some(render_function_id) = world.get::<RenderFunctionId>(*entity) {
-                    let render = render_functions.get(render_function_id).unwrap();
-                    let _render_result = (render)(camera_entity, *entity, world, &mut render_pass);
-                    // match render_result {
-                    //     RenderResult::Success => info!("RenderResult::Success"),
-                    //     RenderResult::Failure => warn!("RenderResult::Failure"),
-                    // }
-                }
+                    Some(command_encoder.finish())
+                });
             }
-        }
+        });
+
+        let mut command_encoder = render_device.create_command_encoder(&Default::default());
+
+        // Encoded after the cameras, which render the upscaled images
+        encode_upscales(world, &mut command_encoder);
+        let prepared_upscales = world.get_resource::<PreparedUpscales>().unwrap();
 
-        for window in windows
-            .values()
-            .filter(|window| !camera_windows.contains(&window.id))
-        {
-            let surface_data = &window.surface_texture.as_ref().unwrap();
+        for window in windows.values().filter(|window| {
+            !camera_windows.contains(&window.id) && !prepared_upscales.covers(window.id)
+        }) {
+            let Some(surface_data) = window.surface_texture.as_ref() else {
+                continue;
+            };
             let _render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -153,7 +302,74 @@ impl RenderNode {
             });
         }
 
-        render_queue.submit([command_encoder.finish()]);
+        let _span = info_span!("submit").entered();
+        render_queue.submit(
+            camera_command_buffers
+                .into_iter()
+                .flatten()
+                .chain(std::iter::once(command_encoder.finish())),
+        );
+
+        failures.into_inner().unwrap()
+    }
+}
+
+fn begin_camera_pass<'a>(
+    command_encoder: &'a mut wgpu::CommandEncoder,
+    view: &'a wgpu::TextureView,
+    depth_texture: Option<&'a DepthTexture>,
+    load: wgpu::LoadOp<wgpu::Color>,
+) -> wgpu::RenderPass<'a> {
+    let depth_load = match load {
+        wgpu::LoadOp::Clear(_) => wgpu::LoadOp::Clear(1.0),
+        wgpu::LoadOp::Load => wgpu::LoadOp::Load,
+    };
+    command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations { load, store: true },
+        })],
+        depth_stencil_attachment: depth_texture.map(|dt| wgpu::RenderPassDepthStencilAttachment {
+            view: &dt.view,
+            depth_ops: Some(wgpu::Operations {
+                load: depth_load,
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+    })
+}
+
+fn render_phase_items<'w>(
+    camera_entity: Entity,
+    items: &[PhaseItem],
+    world: &'w World,
+    render_pass: &mut wgpu::RenderPass<'w>,
+    failures: &Mutex<Vec<RendererError>>,
+) {
+    let render_functions = world.get_resource::<RenderFunctions>().unwrap();
+    let render_stats = world.get_resource::<RenderStats>().unwrap();
+    for item in items {
+        let Some(render) = render_functions.get(&item.render_function) else {
+            render_stats.draw_failure();
+            failures.lock().unwrap().push(RendererError::Draw {
+                camera: camera_entity,
+                entity: item.entity,
+                reason: "render function not registered",
+            });
+            continue;
+        };
+        let render_result = (render)(camera_entity, item.entity, world, render_pass);
+        if let RenderResult::Failure(reason) = render_result {
+            render_stats.draw_failure();
+            failures.lock().unwrap().push(RendererError::Draw {
+                camera: camera_entity,
+                entity: item.entity,
+                reason,
+            });
+        }
     }
 }
 
@@ -172,7 +388,8 @@ impl AddRenderFunction for App {
 
 pub enum RenderResult {
     Success,
-    Failure,
+    /// The entity was skipped, sent as a [`RendererError::Draw`] with the reason.
+    Failure(&'static str),
 }
 
 pub type RenderFunction = for<'w> fn(
@@ -182,8 +399,8 @@ pub type RenderFunction = for<'w> fn(
     &mut wgpu::RenderPass<'w>,
 ) -> RenderResult;
 
-// TODO: entity has to register a RenderFunctionId
-//       how does it find the id
+/// Render function of the entity, fixed ids below [`ALLOCATED_RENDER_FUNCTION_START`]
+/// or allocated ones, see `RenderWith` to get them from the command type.
 #[derive(Component, Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RenderFunctionId(usize);
 
@@ -193,10 +410,14 @@ impl From<usize> for RenderFunctionId {
     }
 }
 
+/// First id handed out by [`RenderFunctions::add_allocated`], fixed ids stay below it.
+pub const ALLOCATED_RENDER_FUNCTION_START: usize = 1 << 16;
+
 #[derive(Resource)]
 pub struct RenderFunctions {
     id_to_ind: HashMap<RenderFunctionId, usize>,
     functions: Vec<RenderFunction>,
+    allocated: usize,
 }
 
 impl Default for RenderFunctions {
@@ -204,17 +425,41 @@ impl Default for RenderFunctions {
         Self {
             id_to_ind: HashMap::new(),
             functions: Vec::new(),
+            allocated: 0,
         }
     }
 }
 
 impl RenderFunctions {
     pub fn add(&mut self, id: RenderFunctionId, render: RenderFunction) {
+        if self.try_add(id, render).is_err() {
+            panic!("Attempted adding multiple render functions with the same id: {:?}", id);
+        }
+    }
+
+    /// Fails with the id if it is taken, prefer allocated ids over fixed ones to avoid it.
+    pub fn try_add(
+        &mut self,
+        id: RenderFunctionId,
+        render: RenderFunction,
+    ) -> Result<(), RenderFunctionId> {
         if self.id_to_ind.contains_key(&id) {
-            panic!("Attempted adding multiple render functions with the same id");
+            return Err(id);
         }
         self.functions.push(render);
         self.id_to_ind.insert(id, self.functions.len() - 1);
+        Ok(())
+    }
+
+    pub fn contains(&self, id: &RenderFunctionId) -> bool {
+        self.id_to_ind.contains_key(id)
+    }
+
+    pub fn add_allocated(&mut self, render: RenderFunction) -> RenderFunctionId {
+        let id = RenderFunctionId(ALLOCATED_RENDER_FUNCTION_START + self.allocated);
+        self.allocated += 1;
+        self.add(id, render);
+        id
     }
 
     pub fn get(&self, index: &RenderFunctionId) -> Option<&RenderFunction> {
@@ -222,13 +467,16 @@ impl RenderFunctions {
     }
 }
 
+#[cfg(feature = "window")]
 fn unimpl_create<T>() -> T {
     unimplemented!()
 }
+#[cfg(feature = "window")]
 fn unimpl_from_world<'w, T>(_world: &'w World) -> &'w T {
     unimplemented!()
 }
 
+#[cfg(feature = "window")]
 pub fn render_note(world: &World) {
     let window = unimpl_create::<Window>();
 
@@ -0,0 +1,195 @@
+use std::io::Cursor;
+
+use anyhow::*;
+use bevy::{
+    asset::{AssetLoader, LoadedAsset},
+    log::warn,
+    prelude::{Assets, Component, Handle, Query, Res},
+    reflect::TypeUuid,
+    tasks::AsyncComputeTaskPool,
+    time::Time,
+};
+use image::{codecs::gif::GifDecoder, codecs::png::PngDecoder, AnimationDecoder, Frame};
+
+use super::{
+    texture_arr::{ImageArray, ImageArrayHandle, TextureIndex},
+    ImageDim, PixelFormat,
+};
+
+///
+/// Frames of an animated GIF or APNG, one layer of `array` each,
+/// shown for the matching entry of `frame_durations` in seconds.
+///
+#[derive(TypeUuid)]
+#[uuid = "C4F1A7D2-3B58-4E69-8A0C-5D2E7B91F346"]
+pub struct AnimatedImage {
+    pub array: Handle<ImageArray>,
+    pub frame_durations: Vec<f32>,
+}
+
+impl AnimatedImage {
+    pub fn duration(&self) -> f32 {
+        self.frame_durations.iter().sum()
+    }
+
+    /// Frame shown `time` seconds into the animation, looping.
+    pub fn frame_at(&self, time: f32) -> u32 {
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return 0;
+        }
+        let mut time = time.rem_euclid(duration);
+        for (frame, frame_duration) in self.frame_durations.iter().enumerate() {
+            if time < *frame_duration {
+                return frame as u32;
+            }
+            time -= frame_duration;
+        }
+        self.frame_durations.len() as u32 - 1
+    }
+}
+
+/// Layers of a texture array every adapter supports, longer animations are cut.
+const MAX_FRAMES: usize = 256;
+
+/// Composited frames of a GIF, or of a PNG as APNG, in RGBA8.
+fn decode_frames(bytes: &[u8], gif: bool) -> Result<Vec<Frame>> {
+    let frames = if gif {
+        GifDecoder::new(Cursor::new(bytes))?.into_frames()
+    } else {
+        PngDecoder::new(Cursor::new(bytes))?.apng().into_frames()
+    };
+    Ok(frames.collect_frames()?)
+}
+
+///
+/// Loads `.gif` and `.apng` files as an [`AnimatedImage`],
+/// its [`ImageArray`] is the labeled asset `array`.
+///
+#[derive(Default)]
+pub struct AnimatedImageLoader;
+impl AssetLoader for AnimatedImageLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::asset::BoxedFuture<'a, Result<(), Error>> {
+        Box::pin(async {
+            let gif = load_context
+                .path()
+                .extension()
+                .map_or(false, |extension| extension == "gif");
+            let bytes = bytes.to_vec();
+            let mut frames = AsyncComputeTaskPool::get()
+                .spawn(async move { decode_frames(&bytes, gif) })
+                .await?;
+            if frames.is_empty() {
+                bail!("{:?} has no frames", load_context.path());
+            }
+            if frames.len() > MAX_FRAMES {
+                warn!(
+                    "{:?} has {} frames, only the first {} are kept",
+                    load_context.path(),
+                    frames.len(),
+                    MAX_FRAMES
+                );
+                frames.truncate(MAX_FRAMES);
+            }
+
+            let (width, heigth) = frames[0].buffer().dimensions();
+            let dim = ImageDim {
+                width,
+                heigth,
+                pixel: PixelFormat::RGBA8,
+            };
+            let mut array = ImageArray::new(dim);
+            let mut frame_durations = Vec::with_capacity(frames.len());
+            for frame in &frames {
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                frame_durations.push(numer as f32 / denom.max(1) as f32 / 1000.0);
+                array.add(frame.buffer(), dim);
+            }
+
+            let array = load_context.set_labeled_asset("array", LoadedAsset::new(array));
+            load_context.set_default_asset(LoadedAsset::new(AnimatedImage {
+                array,
+                frame_durations,
+            }));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gif", "apng"]
+    }
+}
+
+///
+/// Plays an [`AnimatedImage`] on a mesh drawn with an [`ImageArrayHandle`],
+/// the [`TextureIndex`] of the entity follows the current frame.
+///
+#[derive(Component, Clone)]
+pub struct AnimatedTexture {
+    pub animation: Handle<AnimatedImage>,
+    pub playing: bool,
+    pub speed: f32,
+    pub elapsed: f32,
+}
+
+impl AnimatedTexture {
+    pub fn new(animation: Handle<AnimatedImage>) -> Self {
+        Self {
+            animation,
+            playing: true,
+            speed: 1.0,
+            elapsed: 0.0,
+        }
+    }
+}
+
+pub fn animate_textures(
+    time: Res<Time>,
+    animations: Res<Assets<AnimatedImage>>,
+    mut query: Query<(
+        &mut AnimatedTexture,
+        &mut TextureIndex,
+        Option<&mut ImageArrayHandle>,
+    )>,
+) {
+    for (mut animated, mut texture_index, image_array) in query.iter_mut() {
+        let Some(animation) = animations.get(&animated.animation) else {
+            continue;
+        };
+        if let Some(mut image_array) = image_array {
+            if image_array.image_arr.as_ref() != Some(&animation.array) {
+                image_array.image_arr = Some(animation.array.clone());
+            }
+        }
+        if animated.playing {
+            animated.elapsed += time.delta_seconds() * animated.speed;
+        }
+        let frame = animation.frame_at(animated.elapsed);
+        if texture_index.0 != frame {
+            *texture_index = TextureIndex(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_at_follows_durations_and_loops() {
+        let animation = AnimatedImage {
+            array: Handle::default(),
+            frame_durations: vec![0.1, 0.3, 0.1],
+        };
+        assert_eq!(animation.frame_at(0.05), 0);
+        assert_eq!(animation.frame_at(0.2), 1);
+        assert_eq!(animation.frame_at(0.45), 2);
+        assert_eq!(animation.frame_at(0.55), 0);
+        assert_eq!(animation.frame_at(-0.05), 2);
+    }
+}
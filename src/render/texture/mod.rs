@@ -1,22 +1,79 @@
+use std::borrow::Cow;
+
 use anyhow::*;
 use bevy::asset::{AssetLoader, LoadedAsset};
-use bevy::prelude::{Deref, DerefMut, Resource};
+use bevy::prelude::{Deref, DerefMut, FromWorld, Resource, World};
 use bevy::reflect::TypeUuid;
+use bevy::tasks::AsyncComputeTaskPool;
 use bevy::utils::HashMap;
 use image::{DynamicImage, GenericImageView};
 
-use super::{camera, RenderAsset, RenderDevice, RenderQueue};
+use crate::util::EngineDefault;
+
+use super::{
+    camera, color::ColorSpace, memory::GpuMemory, RenderAsset, RenderDevice, RenderQueue,
+};
 
+pub mod animated;
 pub mod texture_arr;
+pub mod video;
 
 #[derive(TypeUuid)]
 #[uuid = "3F897E85-62CE-4B2C-A957-FCF0CCE649FD"]
 pub struct Image {
     pub img: DynamicImage,
     pub prepare: bool,
+    /// Prepared as a blank texture cameras can render into, see [`Image::render_target`].
+    pub render_target: bool,
+    pub sampling: ImageSampling,
+}
+
+///
+/// How magnified texels are filtered, minified ones are always sampled nearest.
+///
+/// As a resource, the sampling of the images loaded by [`ImageLoader`],
+/// set it through [`FlatEngineConfig::pixel_art`](crate::FlatEngineConfig::pixel_art).
+///
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageSampling {
+    #[default]
+    Linear,
+    /// Texels stay sharp squares, for pixel art.
+    Nearest,
+}
+
+impl ImageSampling {
+    pub fn mag_filter(&self) -> wgpu::FilterMode {
+        match self {
+            ImageSampling::Linear => wgpu::FilterMode::Linear,
+            ImageSampling::Nearest => wgpu::FilterMode::Nearest,
+        }
+    }
+
+    pub fn create_sampler(&self, device: &RenderDevice) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: self.mag_filter(),
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        })
+    }
 }
 
 impl Image {
+    /// Image to use as a `RenderTarget::Image`, in the engine default texture format.
+    pub fn render_target(width: u32, height: u32) -> Self {
+        Self {
+            img: DynamicImage::new_rgba8(width, height),
+            prepare: true,
+            render_target: true,
+            sampling: ImageSampling::Linear,
+        }
+    }
+
     pub fn dim(&self) -> ImageDim {
         let dimensions = self.img.dimensions();
         ImageDim {
@@ -27,8 +84,37 @@ impl Image {
     }
 }
 
-#[derive(Default)]
-pub struct ImageLoader;
+/// Decodes `bytes` and converts the pixels to RGBA8, the format images are prepared in.
+pub fn decode_rgba8(bytes: &[u8]) -> Result<DynamicImage> {
+    let img = image::load_from_memory(bytes)?;
+    Ok(DynamicImage::ImageRgba8(img.into_rgba8()))
+}
+
+///
+/// [`decode_rgba8`] on the [`AsyncComputeTaskPool`], large decodes do not hold up asset IO.
+///
+/// The image is only handed to the asset server once its pixels are converted,
+/// preparing it then uploads them without another conversion on the render thread.
+///
+pub async fn decode_rgba8_async(bytes: &[u8]) -> Result<DynamicImage> {
+    let bytes = bytes.to_vec();
+    AsyncComputeTaskPool::get()
+        .spawn(async move { decode_rgba8(&bytes) })
+        .await
+}
+
+pub struct ImageLoader {
+    sampling: ImageSampling,
+}
+
+impl FromWorld for ImageLoader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            sampling: world.get_resource().copied().unwrap_or_default(),
+        }
+    }
+}
+
 impl AssetLoader for ImageLoader {
     fn load<'a>(
         &'a self,
@@ -36,8 +122,13 @@ impl AssetLoader for ImageLoader {
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::asset::BoxedFuture<'a, Result<(), Error>> {
         Box::pin(async {
-            let img = image::load_from_memory(bytes)?;
-            load_context.set_default_asset(LoadedAsset::new(Image { img, prepare: true }));
+            let img = decode_rgba8_async(bytes).await?;
+            load_context.set_default_asset(LoadedAsset::new(Image {
+                img,
+                prepare: true,
+                render_target: false,
+                sampling: self.sampling,
+            }));
 
             Ok(())
         })
@@ -57,10 +148,12 @@ impl AssetLoader for ImageJustLoader {
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::asset::BoxedFuture<'a, Result<(), Error>> {
         Box::pin(async {
-            let img = image::load_from_memory(bytes)?;
+            let img = decode_rgba8_async(bytes).await?;
             load_context.set_default_asset(LoadedAsset::new(Image {
                 img,
                 prepare: false,
+                render_target: false,
+                sampling: ImageSampling::Linear,
             }));
 
             Ok(())
@@ -75,15 +168,36 @@ impl AssetLoader for ImageJustLoader {
 impl RenderAsset for Image {
     type PreparedAsset = GpuTexture;
 
-    fn prepare(&self, device: &RenderDevice, queue: &RenderQueue) -> Option<Self::PreparedAsset> {
+    fn gpu_memory(&self) -> GpuMemory {
+        GpuMemory::texture(self.dim().total_bytes() as u64)
+    }
+
+    fn prepare(
+        &self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        _color_space: ColorSpace,
+    ) -> Option<Self::PreparedAsset> {
         if !self.prepare {
             return None;
         }
+        if self.render_target {
+            let (width, height) = self.img.dimensions();
+            return Some(GpuTexture::create_render_target(device, width, height, None));
+        }
 
-        let rgba = self.img.to_rgba8(); // TODO: extend support
+        // Loaded images are already RGBA8, only generated ones are converted here
+        let rgba = match self.img.as_rgba8() {
+            Some(rgba) => Cow::Borrowed(rgba),
+            None => Cow::Owned(self.img.to_rgba8()), // TODO: extend support
+        };
         let dim = self.img.dimensions();
         let raw_img = RawImage::new(&rgba, dim, PixelFormat::RGBA8); // TODO: extend support
-        Some(GpuTexture::from_raw_image(device, queue, &raw_img, None).unwrap())
+        let mut gpu_texture = GpuTexture::from_raw_image(device, queue, &raw_img, None).unwrap();
+        if self.sampling != ImageSampling::Linear {
+            gpu_texture.sampler = self.sampling.create_sampler(device);
+        }
+        Some(gpu_texture)
     }
 }
 
@@ -331,16 +445,73 @@ impl GpuTexture {
         //     ];
     }
 
+    /// Texture cameras render into and that can be sampled or copied afterwards.
+    pub fn create_render_target(
+        render_device: &RenderDevice,
+        width: u32,
+        height: u32,
+        label: Option<&str>,
+    ) -> Self {
+        let texture = render_device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::engine_default(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = render_device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
     pub fn create_depth_texture(
         render_device: &RenderDevice,
         config: &wgpu::SurfaceConfiguration,
         label: Option<&str>,
         depth_format: wgpu::TextureFormat,
+    ) -> Self {
+        Self::create_depth_texture_sized(
+            render_device,
+            config.width,
+            config.height,
+            label,
+            depth_format,
+        )
+    }
+
+    pub fn create_depth_texture_sized(
+        render_device: &RenderDevice,
+        width: u32,
+        height: u32,
+        label: Option<&str>,
+        depth_format: wgpu::TextureFormat,
     ) -> Self {
         let size = wgpu::Extent3d {
             // 2.
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         };
         let desc = wgpu::TextureDescriptor {
@@ -387,6 +558,16 @@ impl DepthTexture {
     pub fn create(render_device: &RenderDevice, config: &wgpu::SurfaceConfiguration) -> Self {
         Self(GpuTexture::create_depth_texture(render_device, config, None, Self::DEPTH_FORMAT))
     }
+
+    pub fn create_sized(render_device: &RenderDevice, width: u32, height: u32) -> Self {
+        Self(GpuTexture::create_depth_texture_sized(
+            render_device,
+            width,
+            height,
+            None,
+            Self::DEPTH_FORMAT,
+        ))
+    }
 }
 
 #[derive(Resource, Default, Deref, DerefMut)]
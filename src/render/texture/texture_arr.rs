@@ -1,9 +1,12 @@
 use bevy::{
-    prelude::{Assets, Component, Handle, Query, ResMut},
+    log::{debug, error},
+    prelude::{Assets, Component, Deref, Handle, Query, ResMut},
     reflect::TypeUuid,
 };
 
 use crate::render::{
+    color::ColorSpace,
+    memory::GpuMemory,
     resource::renderer::{RenderDevice, RenderQueue},
     RenderAsset,
 };
@@ -79,11 +82,20 @@ impl FromIterator<Image> for ImageArray {
 impl RenderAsset for ImageArray {
     type PreparedAsset = GpuTexture;
 
-    fn prepare(&self, device: &RenderDevice, queue: &RenderQueue) -> Option<Self::PreparedAsset> {
+    fn gpu_memory(&self) -> GpuMemory {
+        GpuMemory::texture(self.data.len() as u64)
+    }
+
+    fn prepare(
+        &self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+        _color_space: ColorSpace,
+    ) -> Option<Self::PreparedAsset> {
         match GpuTexture::create_texture_array(device, queue, &self.data, self.dim, self.count) {
             Ok(e) => Some(e),
             Err(err) => {
-                dbg!(err);
+                error!("Failed to create texture array: {:?}", err);
                 None
             }
         }
@@ -106,6 +118,13 @@ impl ImageArrayHandle {
     }
 }
 
+///
+/// Layer offset added to the `uv.z` layer of the vertices,
+/// lets entities sharing a mesh and an [`ImageArray`] sample different layers.
+///
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Deref)]
+pub struct TextureIndex(pub u32);
+
 pub fn create_image_arr_from_images(
     mut image_assets: ResMut<Assets<Image>>,
     mut image_arr_assets: ResMut<Assets<ImageArray>>,
@@ -130,7 +149,7 @@ pub fn create_image_arr_from_images(
 
             image_arr.image_arr = Some(image_arr_assets.add(image_array));
             image_arr.images.clear();
-            println!("ImageArray created");
+            debug!("ImageArray created");
         }
     }
 }
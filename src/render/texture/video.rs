@@ -0,0 +1,292 @@
+use bevy::prelude::{Assets, Component, Handle, Query, Res};
+use image::{DynamicImage, RgbaImage};
+
+use crate::render::{extract::ExtractedTime, resource::renderer::RenderQueue, RenderAssets};
+
+use super::{Image, ImageSampling};
+
+/// Pixels of a frame changed by [`VideoDecoder::decode_next`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TextureRegion {
+    pub fn full(width: u32, height: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    /// Smallest region covering both.
+    pub fn union(&self, other: &Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Self {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+///
+/// Source of the frames of a [`VideoTexture`], implement it over a video library
+/// for cutscenes, [`FrameSequence`] plays frames already in memory.
+///
+pub trait VideoDecoder: Send + Sync + 'static {
+    /// Size of the frames, constant for the whole video.
+    fn size(&self) -> (u32, u32);
+
+    fn frames_per_second(&self) -> f32;
+
+    ///
+    /// Decodes the next frame over the previous one in `frame`, tightly packed RGBA8 rows.
+    ///
+    /// Returns the region that changed, `None` at the end of the video.
+    ///
+    fn decode_next(&mut self, frame: &mut [u8]) -> Option<TextureRegion>;
+
+    /// Goes back to the first frame.
+    fn rewind(&mut self);
+}
+
+///
+/// Plays a video into `image`, a texture sprites and meshes can use like any other.
+///
+/// Frames are decoded as the time of the frame reaches them and only the regions
+/// they change are written into the prepared texture.
+///
+#[derive(Component)]
+pub struct VideoTexture {
+    pub image: Handle<Image>,
+    pub playing: bool,
+    pub looping: bool,
+    decoder: Box<dyn VideoDecoder>,
+    frame: Vec<u8>,
+    /// Seconds since the last decoded frame
+    elapsed: f32,
+    /// Changed pixels not written into the texture yet
+    pending: Option<TextureRegion>,
+}
+
+impl VideoTexture {
+    /// Creates the image the video plays into, the first frame is decoded right away.
+    pub fn new(images: &mut Assets<Image>, decoder: impl VideoDecoder) -> Self {
+        let (width, height) = decoder.size();
+        let image = images.add(Image {
+            img: DynamicImage::new_rgba8(width, height),
+            prepare: true,
+            render_target: false,
+            sampling: ImageSampling::Linear,
+        });
+        Self::with_image(image, decoder)
+    }
+
+    /// Plays into an existing RGBA8 `image` of the size of the video.
+    pub fn with_image(image: Handle<Image>, decoder: impl VideoDecoder) -> Self {
+        let (width, height) = decoder.size();
+        let mut video = Self {
+            image,
+            playing: true,
+            looping: false,
+            decoder: Box::new(decoder),
+            frame: vec![0; (width * height * 4) as usize],
+            elapsed: 0.0,
+            pending: None,
+        };
+        video.decode_next();
+        video
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.decoder.size()
+    }
+
+    /// Plays from the first frame again.
+    pub fn restart(&mut self) {
+        self.decoder.rewind();
+        self.elapsed = 0.0;
+        self.playing = true;
+        self.decode_next();
+    }
+
+    /// Advances the video by `delta` seconds, decoding the frames it passes.
+    pub fn advance(&mut self, delta: f32) {
+        if !self.playing {
+            return;
+        }
+        let frame_time = 1.0 / self.decoder.frames_per_second();
+        self.elapsed += delta;
+        while self.playing && self.elapsed >= frame_time {
+            self.elapsed -= frame_time;
+            self.decode_next();
+        }
+    }
+
+    fn decode_next(&mut self) {
+        let mut region = self.decoder.decode_next(&mut self.frame);
+        if region.is_none() && self.looping {
+            self.decoder.rewind();
+            region = self.decoder.decode_next(&mut self.frame);
+        }
+        let Some(region) = region else {
+            self.playing = false;
+            return;
+        };
+        self.pending = Some(match self.pending {
+            Some(pending) => pending.union(&region),
+            None => region,
+        });
+    }
+
+    fn write_pending(&mut self, texture: &wgpu::Texture, queue: &RenderQueue) {
+        let Some(region) = self.pending.take() else {
+            return;
+        };
+        let (width, _) = self.size();
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: region.x,
+                    y: region.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.frame,
+            wgpu::ImageDataLayout {
+                offset: ((region.y * width + region.x) * 4) as u64,
+                bytes_per_row: std::num::NonZeroU32::new(width * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: region.width,
+                height: region.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+/// Advances the videos and writes the regions their new frames changed.
+pub fn stream_video_textures(
+    time: Res<ExtractedTime>,
+    render_queue: Res<RenderQueue>,
+    gpu_images: Res<RenderAssets<Image>>,
+    mut videos: Query<&mut VideoTexture>,
+) {
+    for mut video in videos.iter_mut() {
+        video.advance(time.delta_seconds);
+        // Kept pending until the image is prepared
+        if let Some(gpu_image) = gpu_images.get(&video.image.id()) {
+            video.write_pending(&gpu_image.texture, &render_queue);
+        }
+    }
+}
+
+/// Frames already decoded in memory, e.g. a short animated billboard.
+pub struct FrameSequence {
+    pub frames: Vec<RgbaImage>,
+    pub frames_per_second: f32,
+    next: usize,
+}
+
+impl FrameSequence {
+    /// Every frame has the size of the first one.
+    pub fn new(frames: Vec<RgbaImage>, frames_per_second: f32) -> Self {
+        assert!(!frames.is_empty(), "FrameSequence needs at least one frame");
+        Self {
+            frames,
+            frames_per_second,
+            next: 0,
+        }
+    }
+}
+
+impl VideoDecoder for FrameSequence {
+    fn size(&self) -> (u32, u32) {
+        self.frames[0].dimensions()
+    }
+
+    fn frames_per_second(&self) -> f32 {
+        self.frames_per_second
+    }
+
+    fn decode_next(&mut self, frame: &mut [u8]) -> Option<TextureRegion> {
+        let next = self.frames.get(self.next)?;
+        frame.copy_from_slice(next.as_raw());
+        self.next += 1;
+        let (width, height) = self.size();
+        Some(TextureRegion::full(width, height))
+    }
+
+    fn rewind(&mut self) {
+        self.next = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_union_covers_both() {
+        let a = TextureRegion {
+            x: 2,
+            y: 4,
+            width: 4,
+            height: 2,
+        };
+        let b = TextureRegion {
+            x: 1,
+            y: 5,
+            width: 2,
+            height: 4,
+        };
+        assert_eq!(
+            a.union(&b),
+            TextureRegion {
+                x: 1,
+                y: 4,
+                width: 5,
+                height: 5
+            }
+        );
+    }
+
+    #[test]
+    fn frames_advance_with_time_and_loop() {
+        let frames = (0..3)
+            .map(|i| RgbaImage::from_pixel(1, 1, image::Rgba([i, 0, 0, 255])))
+            .collect();
+        let decoder = FrameSequence::new(frames, 10.0);
+        let mut video = VideoTexture::with_image(Handle::default(), decoder).looping();
+        assert_eq!(video.frame[0], 0);
+
+        video.advance(0.25);
+        assert_eq!(video.frame[0], 2);
+        video.advance(0.1);
+        assert_eq!(video.frame[0], 0);
+        assert!(video.playing);
+
+        video.looping = false;
+        video.advance(0.3);
+        assert_eq!(video.frame[0], 2);
+        assert!(!video.playing);
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{
+        Assets, Component, Deref, DerefMut, Entity, FromWorld, Query, Res, ResMut, Resource, With,
+        World,
+    },
+    utils::HashMap,
+};
+
+use crate::{
+    render::{
+        camera::component::Camera,
+        cleanup::EntityRenderState,
+        resource::{pipeline::BindGroupLayout, renderer::RenderDevice},
+        texture::{GpuTexture, Image},
+    },
+    util::EngineDefault,
+};
+
+use super::window::PreparedWindows;
+
+///
+/// Copies the color target of the camera into a texture after the opaque pass,
+/// transparent draws can then sample what is behind them: refraction, distortion, frosted glass, ...
+///
+/// The render pass of the camera is split in two, add it only to cameras that need it.
+/// The copy is bound with [`GrabTextureLayout`], see [`GrabTextures`].
+/// It is in the engine default texture format, like the targets it is copied from.
+///
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct CopyTargetToTexture;
+
+/// Layout of the grab texture bind group, a texture at binding 0 and its sampler at binding 1.
+#[derive(Resource)]
+pub struct GrabTextureLayout {
+    pub layout: BindGroupLayout,
+}
+
+impl FromWorld for GrabTextureLayout {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<Res<RenderDevice>> = SystemState::new(world);
+        let render_device = state.get(world);
+
+        let layout = render_device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("grab_texture_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            })
+            .into();
+
+        Self { layout }
+    }
+}
+
+pub struct GrabTexture {
+    pub texture: GpuTexture,
+    pub size: (u32, u32),
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl GrabTexture {
+    pub fn create(
+        render_device: &RenderDevice,
+        layout: &GrabTextureLayout,
+        size: (u32, u32),
+    ) -> Self {
+        let texture = render_device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("grab_texture"),
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::engine_default(),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = render_device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("grab_texture_bind_group"),
+            layout: &layout.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Self {
+            texture: GpuTexture {
+                texture,
+                view,
+                sampler,
+            },
+            size,
+            bind_group,
+        }
+    }
+
+    pub fn copy_size(&self) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: self.size.0,
+            height: self.size.1,
+            depth_or_array_layers: 1,
+        }
+    }
+}
+
+/// Grab textures of the cameras with [`CopyTargetToTexture`], filled while the camera renders.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct GrabTextures(pub HashMap<Entity, GrabTexture>);
+
+impl EntityRenderState for GrabTextures {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(&entity);
+    }
+}
+
+/// Creates the grab textures at the size of the camera targets, again when the target is resized.
+pub fn prepare_grab_textures(
+    render_device: Res<RenderDevice>,
+    layout: Res<GrabTextureLayout>,
+    images: Res<Assets<Image>>,
+    windows: Res<PreparedWindows>,
+    mut grab_textures: ResMut<GrabTextures>,
+    cameras: Query<(Entity, &Camera), With<CopyTargetToTexture>>,
+) {
+    for (entity, camera) in cameras.iter() {
+        let Some(size) = camera.render_target.get_size(&images, &windows) else {
+            continue;
+        };
+        if size.0 == 0 || size.1 == 0 {
+            continue;
+        }
+        if grab_textures.get(&entity).map(|grab| grab.size) == Some(size) {
+            continue;
+        }
+        grab_textures.insert(entity, GrabTexture::create(&render_device, &layout, size));
+    }
+}
@@ -0,0 +1,90 @@
+//!
+//! Cameras rendering into an image, displayed by sprites or meshes, e.g. a minimap or a portal.
+//!
+//! ```ignore
+//! let image = images.add(Image::render_target(256, 256));
+//! commands.spawn(CameraBundle {
+//!     camera: Camera::with_render_target(RenderTarget::Image(image.clone())),
+//!     projection: PerspectiveProjection::default(), // aspect of the image, not resized with windows
+//!     ..Default::default()
+//! });
+//! commands.spawn(SpriteBundle {
+//!     texture: image,
+//!     ..Default::default()
+//! });
+//! ```
+//!
+//! - [`Image::render_target`] textures can be both render attachments and sampled.
+//! - Cameras rendering into images are encoded before the cameras rendering into windows,
+//!   so the image shows the frame being rendered. Images displayed by other image cameras are not ordered.
+//! - Replacing or resizing the image through `Assets<Image>` prepares a new texture,
+//!   the depth texture and the texture bind groups of sprites and meshes follow.
+//! - A camera can not sample the image it renders into, keep the displaying entity
+//!   out of the image camera with `RenderLayers`.
+//!
+
+use bevy::{
+    asset::HandleId,
+    prelude::{Assets, Local, Query, Res, ResMut},
+    utils::HashMap,
+};
+
+use crate::render::{
+    camera::component::{Camera, RenderTarget},
+    texture::{DepthTexture, DepthTextures, Image},
+    RenderAssets, RenderDevice,
+};
+
+/// Creates depth textures for cameras rendering into an image,
+/// windows get theirs in `configure_surfaces`.
+pub fn prepare_image_target_depth_textures(
+    render_device: Res<RenderDevice>,
+    images: Res<Assets<Image>>,
+    gpu_textures: Res<RenderAssets<Image>>,
+    cameras: Query<&Camera>,
+    mut depth_textures: ResMut<DepthTextures>,
+    mut depth_sizes: Local<HashMap<HandleId, (u32, u32)>>,
+) {
+    for camera in cameras.iter() {
+        let RenderTarget::Image(handle) = &camera.render_target else {
+            continue;
+        };
+        let Some(image) = images.get(handle) else {
+            continue;
+        };
+        if !gpu_textures.contains_key(&handle.id()) {
+            continue;
+        }
+        let size = (image.dim().width, image.dim().heigth);
+        if depth_sizes.get(&handle.id()) == Some(&size) {
+            continue;
+        }
+
+        depth_textures.insert(
+            RenderTarget::Image(handle.clone_weak()),
+            DepthTexture::create_sized(&render_device, size.0, size.1),
+        );
+        depth_sizes.insert(handle.id(), size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::Handle, window::WindowId};
+
+    use super::*;
+
+    #[test]
+    fn image_targets_are_encoded_first() {
+        let image = RenderTarget::Image(Handle::weak(HandleId::random::<Image>()));
+        let mut targets = vec![
+            RenderTarget::Window(WindowId::primary()),
+            image.clone(),
+            RenderTarget::Window(WindowId::new()),
+        ];
+        targets.sort_by_key(RenderTarget::encode_order);
+
+        assert_eq!(targets[0], image);
+        assert_eq!(targets[1], RenderTarget::Window(WindowId::primary()));
+    }
+}
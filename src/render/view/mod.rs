@@ -1,2 +1,5 @@
+pub mod grab;
 
+pub mod image_target;
+pub mod upscale;
 pub mod window;
\ No newline at end of file
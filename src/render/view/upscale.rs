@@ -0,0 +1,267 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{
+        Assets, Component, FromWorld, Handle, HandleUntyped, Query, Res, ResMut, Resource, World,
+    },
+    reflect::TypeUuid,
+    window::WindowId,
+};
+
+use crate::{
+    render::{
+        resource::{
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, RenderPipelineId, VertexState,
+            },
+            renderer::RenderDevice,
+            shader::Shader,
+        },
+        texture::{Image, ImageSampling},
+        RenderAssets,
+    },
+    util::EngineDefault,
+};
+
+use super::window::PreparedWindows;
+
+pub const UPSCALE_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 75678909876445673);
+
+///
+/// Draws a low resolution image over the whole window, scaled by the largest integer
+/// factor that fits and centered between black bars.
+///
+/// Render the world into `image` with a camera targeting `RenderTarget::Image(image)`,
+/// the window then needs no camera of its own.
+///
+#[derive(Component, Clone)]
+pub struct UpscaleToWindow {
+    pub image: Handle<Image>,
+    pub window: WindowId,
+}
+
+impl UpscaleToWindow {
+    /// Creates the `width` x `height` image to render into, sampled nearest.
+    pub fn new(images: &mut Assets<Image>, width: u32, height: u32, window: WindowId) -> Self {
+        let mut image = Image::render_target(width, height);
+        image.sampling = ImageSampling::Nearest;
+        Self {
+            image: images.add(image),
+            window,
+        }
+    }
+}
+
+/// `[x, y, width, height]` of `source` scaled into `target`, integer scaled when it fits.
+pub fn integer_viewport(source: (u32, u32), target: (u32, u32)) -> [f32; 4] {
+    let fit = (target.0 as f32 / source.0 as f32).min(target.1 as f32 / source.1 as f32);
+    // Smaller windows can not fit the image even once, it is shrunk instead
+    let scale = if fit >= 1.0 { fit.floor() } else { fit };
+    let (width, height) = (source.0 as f32 * scale, source.1 as f32 * scale);
+    [
+        ((target.0 as f32 - width) / 2.0).floor(),
+        ((target.1 as f32 - height) / 2.0).floor(),
+        width,
+        height,
+    ]
+}
+
+#[derive(Resource)]
+pub struct UpscalePipeline {
+    pub texture_layout: BindGroupLayout,
+    pub sampler: wgpu::Sampler,
+    pub pipeline_id: RenderPipelineId,
+}
+
+impl FromWorld for UpscalePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(Res<RenderDevice>, ResMut<PipelineCache>)> =
+            SystemState::new(world);
+        let (render_device, mut pipeline_cache) = state.get_mut(world);
+
+        let texture_layout: BindGroupLayout = render_device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("upscale_texture_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            })
+            .into();
+
+        let pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("upscale_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![texture_layout.clone()],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: UPSCALE_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: UPSCALE_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            texture_layout,
+            sampler: ImageSampling::Nearest.create_sampler(&render_device),
+            pipeline_id,
+        }
+    }
+}
+
+pub struct PreparedUpscale {
+    pub window: WindowId,
+    pub viewport: [f32; 4],
+    pub bind_group: wgpu::BindGroup,
+}
+
+/// Upscales of the frame, drawn by the render node after every camera.
+#[derive(Resource, Default)]
+pub struct PreparedUpscales(pub Vec<PreparedUpscale>);
+
+impl PreparedUpscales {
+    pub fn covers(&self, window: WindowId) -> bool {
+        self.0.iter().any(|upscale| upscale.window == window)
+    }
+}
+
+pub fn prepare_upscales(
+    render_device: Res<RenderDevice>,
+    upscale_pipeline: Res<UpscalePipeline>,
+    images: Res<Assets<Image>>,
+    gpu_textures: Res<RenderAssets<Image>>,
+    windows: Res<PreparedWindows>,
+    mut prepared_upscales: ResMut<PreparedUpscales>,
+    upscales: Query<&UpscaleToWindow>,
+) {
+    prepared_upscales.0.clear();
+    for upscale in upscales.iter() {
+        let (Some(image), Some(gpu_texture), Some(window)) = (
+            images.get(&upscale.image),
+            gpu_textures.get(&upscale.image.id()),
+            windows.get(&upscale.window),
+        ) else {
+            continue;
+        };
+
+        let dim = image.dim();
+        let viewport = integer_viewport(
+            (dim.width, dim.heigth),
+            (window.physical_width, window.physical_height),
+        );
+        let bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("upscale_bind_group"),
+            layout: &upscale_pipeline.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&gpu_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&upscale_pipeline.sampler),
+                },
+            ],
+        });
+        prepared_upscales.0.push(PreparedUpscale {
+            window: upscale.window,
+            viewport,
+            bind_group,
+        });
+    }
+}
+
+/// Clears the windows of the upscales to black and draws the images into their viewports.
+pub fn encode_upscales(world: &World, command_encoder: &mut wgpu::CommandEncoder) {
+    let prepared_upscales = world.get_resource::<PreparedUpscales>().unwrap();
+    let upscale_pipeline = world.get_resource::<UpscalePipeline>().unwrap();
+    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
+    let windows = world.get_resource::<PreparedWindows>().unwrap();
+
+    let Some(render_pipeline) = pipeline_cache.get(&upscale_pipeline.pipeline_id) else {
+        return;
+    };
+    for upscale in &prepared_upscales.0 {
+        let Some(surface_data) = windows
+            .get(&upscale.window)
+            .and_then(|window| window.surface_texture.as_ref())
+        else {
+            continue;
+        };
+
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("upscale_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &surface_data.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        let [x, y, width, height] = upscale.viewport;
+        render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        render_pass.set_pipeline(render_pipeline);
+        render_pass.set_bind_group(0, &upscale.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_scale_with_bars() {
+        assert_eq!(
+            integer_viewport((320, 180), (1920, 1080)),
+            [0.0, 0.0, 1920.0, 1080.0]
+        );
+        assert_eq!(
+            integer_viewport((320, 180), (1280, 800)),
+            [0.0, 40.0, 1280.0, 720.0]
+        );
+        assert_eq!(
+            integer_viewport((320, 180), (1000, 1000)),
+            [20.0, 230.0, 960.0, 540.0]
+        );
+        assert_eq!(
+            integer_viewport((320, 180), (160, 180)),
+            [0.0, 45.0, 160.0, 90.0]
+        );
+    }
+}
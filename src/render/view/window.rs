@@ -1,22 +1,48 @@
 use bevy::{
-    prelude::{Deref, DerefMut, Plugin, Res, ResMut, Resource},
+    asset::load_internal_asset,
+    log::{info, warn},
+    prelude::{Deref, DerefMut, EventReader, EventWriter, Plugin, Res, ResMut, Resource},
     utils::HashMap,
-    window::{RawHandleWrapper, WindowId, Windows},
+    window::{RawHandleWrapper, WindowClosed, WindowId, Windows},
 };
 
 use crate::render::{
     camera,
+    cleanup::AddEntityCleanup,
+    error::RendererError,
     texture::{self, DepthTextures},
+    resource::shader::Shader,
+    view::{
+        grab::{prepare_grab_textures, CopyTargetToTexture, GrabTextures},
+        image_target::prepare_image_target_depth_textures,
+        upscale::{prepare_upscales, PreparedUpscales, UPSCALE_SHADER_HANDLE},
+    },
     RenderAdapter, RenderDevice, RenderInstance, RenderStage,
 };
 
 pub struct FlatViewPlugin;
 impl Plugin for FlatViewPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
+        load_internal_asset!(
+            app,
+            UPSCALE_SHADER_HANDLE,
+            "upscale.wgsl",
+            Shader::from_wgsl
+        );
+
+        // UpscalePipeline and GrabTextureLayout are initialized by FlatRenderPlugin
+        // once the device exists
         app.init_resource::<WindowSurfaces>()
             .init_resource::<PreparedWindows>()
+            .init_resource::<PreparedUpscales>()
+            .init_resource::<GrabTextures>()
+            .add_entity_cleanup::<GrabTextures, CopyTargetToTexture>()
             .add_system_to_stage(RenderStage::Prepare, prepare_windows)
-            .add_system_to_stage(RenderStage::Create, configure_surfaces);
+            .add_system_to_stage(RenderStage::Prepare, remove_closed_windows)
+            .add_system_to_stage(RenderStage::Create, configure_surfaces)
+            .add_system_to_stage(RenderStage::Create, prepare_image_target_depth_textures)
+            .add_system_to_stage(RenderStage::Create, prepare_upscales)
+            .add_system_to_stage(RenderStage::Create, prepare_grab_textures);
     }
 }
 
@@ -40,6 +66,8 @@ pub struct PreparedWindow {
     pub surface_texture_format: Option<wgpu::TextureFormat>,
     pub size_changed: bool,
     pub present_mode_changed: bool,
+    /// The last frame was skipped because no surface texture could be acquired.
+    pub surface_skipped: bool,
 }
 
 #[derive(Resource, Default, Deref, DerefMut)]
@@ -80,6 +108,7 @@ pub fn prepare_windows(windows: Res<Windows>, mut prepared_windows: ResMut<Prepa
                 surface_texture_format: None,
                 size_changed: false,
                 present_mode_changed: false,
+                surface_skipped: false,
             });
 
         prep_window.surface_texture = None;
@@ -93,6 +122,20 @@ pub fn prepare_windows(windows: Res<Windows>, mut prepared_windows: ResMut<Prepa
     }
 }
 
+/// Drops the surface and depth texture of closed windows.
+pub fn remove_closed_windows(
+    mut closed_events: EventReader<WindowClosed>,
+    mut prepared_windows: ResMut<PreparedWindows>,
+    mut surfaces: ResMut<WindowSurfaces>,
+    mut depth_textures: ResMut<DepthTextures>,
+) {
+    for WindowClosed { id } in closed_events.iter() {
+        prepared_windows.remove(id);
+        surfaces.remove(id);
+        depth_textures.remove(&camera::component::RenderTarget::Window(*id));
+    }
+}
+
 pub fn configure_surfaces(
     render_instance: Res<RenderInstance>,
     render_adapter: Res<RenderAdapter>,
@@ -100,8 +143,14 @@ pub fn configure_surfaces(
     mut windows: ResMut<PreparedWindows>,
     mut surfaces: ResMut<WindowSurfaces>,
     mut depth_textures: ResMut<DepthTextures>,
+    mut renderer_errors: EventWriter<RendererError>,
 ) {
     for window in windows.values_mut() {
+        // Minimized windows have a zero sized surface, they are skipped until restored
+        if window.physical_width == 0 || window.physical_height == 0 {
+            continue;
+        }
+
         let is_new_surface = !surfaces.contains_key(&window.id);
         let (surface, format) = surfaces.entry(window.id).or_insert_with(|| unsafe {
             let surface =
@@ -115,7 +164,8 @@ pub fn configure_surfaces(
         });
 
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            // Copied from by cameras with `CopyTargetToTexture`
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
             format: format.clone(),
             width: window.physical_width,
             height: window.physical_height,
@@ -124,68 +174,65 @@ pub fn configure_surfaces(
         };
 
         if is_new_surface || window.size_changed || window.present_mode_changed {
-            surface.configure(render_device.inner(), &config);
-            let surface_texture = surface
-                .get_current_texture()
-                .expect("Could not get surface texture");
-            let surface_view = surface_texture
-                .texture
-                .create_view(&wgpu::TextureViewDescriptor::default());
-            window.surface_texture = Some(SurfaceTextureData {
-                view: surface_view,
-                texture: surface_texture,
-            });
+            configure_surface(&render_device, surface, &config, window.id, &mut depth_textures);
+        }
 
-            // TODO: support RenderTarget::Image
-            // NOTE: creates depth texture for all windows
-            match depth_textures.get_mut(&camera::component::RenderTarget::Window(window.id)) {
-                Some(dt) => {
-                    *dt = texture::DepthTexture::create(&render_device, &config);
-                }
-                None => {
-                    depth_textures.insert(
-                        camera::component::RenderTarget::Window(window.id),
-                        texture::DepthTexture::create(
-                            &render_device,
-                            &config,
-                        ),
-                    );
-                }
+        let surface_texture = match surface.get_current_texture() {
+            Ok(st) => Ok(st),
+            Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                configure_surface(
+                    &render_device,
+                    surface,
+                    &config,
+                    window.id,
+                    &mut depth_textures,
+                );
+                surface.get_current_texture()
             }
-        } else {
-            match surface.get_current_texture() {
-                Ok(st) => {
-                    window.surface_texture = Some(SurfaceTextureData {
-                        view: st
-                            .texture
-                            .create_view(&wgpu::TextureViewDescriptor::default()),
-                        texture: st,
-                    });
+            Err(error) => Err(error),
+        };
+
+        // Timeout, OutOfMemory or a surface that stays lost: skip the window this frame
+        window.surface_texture = match surface_texture {
+            Ok(st) => {
+                if window.surface_skipped {
+                    info!("Window {:?} is rendering again", window.id);
+                    window.surface_skipped = false;
                 }
-                Err(wgpu::SurfaceError::Outdated) => {
-                    surface.configure(render_device.inner(), &config);
-                    let surface_texture = surface
-                        .get_current_texture()
-                        .expect("Could not get surface texture");
-                    let surface_view = surface_texture
+                Some(SurfaceTextureData {
+                    view: st
                         .texture
-                        .create_view(&wgpu::TextureViewDescriptor::default());
-                    window.surface_texture = Some(SurfaceTextureData {
-                        view: surface_view,
-                        texture: surface_texture,
-                    });
-
-                    // TODO: support RenderTarget::Image
-                    if let Some(dt) =
-                        depth_textures.get_mut(&camera::component::RenderTarget::Window(window.id))
-                    {
-                        *dt = texture::DepthTexture::create(&render_device, &config);
-                    }
-                }
-                Err(_) => {
-                    panic!("Could not get surface texture");
+                        .create_view(&wgpu::TextureViewDescriptor::default()),
+                    texture: st,
+                })
+            }
+            Err(error) => {
+                if !window.surface_skipped {
+                    warn!("Skipping frames of window {:?}: {}", window.id, error);
+                    window.surface_skipped = true;
                 }
+                renderer_errors.send(RendererError::Surface {
+                    window: window.id,
+                    error,
+                });
+                None
             }
-        }
+        };
     }
 }
+
+fn configure_surface(
+    render_device: &RenderDevice,
+    surface: &wgpu::Surface,
+    config: &wgpu::SurfaceConfiguration,
+    window_id: WindowId,
+    depth_textures: &mut DepthTextures,
+) {
+    surface.configure(render_device.inner(), config);
+
+    // NOTE: creates depth texture for all windows
+    depth_textures.insert(
+        camera::component::RenderTarget::Window(window_id),
+        texture::DepthTexture::create(render_device, config),
+    );
+}
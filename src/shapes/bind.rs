@@ -0,0 +1,279 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{Changed, Component, FromWorld, Or, Query, Res, ResMut, Resource, World},
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        blend::{AlphaMode, BlendMode},
+        camera::component::CameraUniforms,
+        raster::{CullMode, DepthBias, RasterKey},
+        resource::{
+            buffer::{MeshVertex, Vertex},
+            component_uniform::{ComponentUniforms, ModelUniform},
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, VertexState,
+            },
+            renderer::RenderDevice,
+            shader::Shader,
+            specialized_pipeline::{PipelineSpecialize, Specialized},
+        },
+        texture,
+    },
+    util::EngineDefault,
+};
+
+use super::{ShapeStyleUniform, SDF_SHAPE_DEFAULT_BLEND_MODE, SDF_SHAPE_SHADER_HANDLE};
+
+/// Shape evaluated by the signed distance function in the fragment shader.
+///
+/// Every shape is inscribed into the unit quad.
+#[derive(Component, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum ShapeKind {
+    Circle,
+    Triangle,
+    Rectangle,
+}
+
+impl ShapeKind {
+    pub const ALL: &'static [ShapeKind] =
+        &[ShapeKind::Circle, ShapeKind::Triangle, ShapeKind::Rectangle];
+
+    fn fragment_entry_point(&self) -> &'static str {
+        match self {
+            ShapeKind::Circle => "fs_circle",
+            ShapeKind::Triangle => "fs_triangle",
+            ShapeKind::Rectangle => "fs_rectangle",
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct SdfShapePipeline {
+    pub model_layout: BindGroupLayout,
+    pub view_layout: BindGroupLayout,
+    pub style_layout: BindGroupLayout,
+}
+
+impl FromWorld for SdfShapePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            ResMut<PipelineCache>,
+            ResMut<Specialized<Self>>,
+        )> = SystemState::new(world);
+        let (render_device, mut pipeline_cache, mut specialized_self) = state.get_mut(world);
+
+        let model_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(ModelUniform::min_size()),
+                    },
+                    count: None,
+                }],
+                label: Some("sdf_shape_model_layout"),
+            });
+
+        let view_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(CameraUniforms::min_size()),
+                    },
+                    count: None,
+                }],
+                label: Some("sdf_shape_view_layout"),
+            });
+
+        let style_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(ShapeStyleUniform::min_size()),
+                    },
+                    count: None,
+                }],
+                label: Some("sdf_shape_style_layout"),
+            });
+
+        let sdf_shape_pipeline = SdfShapePipeline {
+            model_layout,
+            view_layout,
+            style_layout,
+        };
+
+        for shape_kind in ShapeKind::ALL {
+            for blend_mode in BlendMode::ALL {
+                let key = (*shape_kind, *blend_mode, RasterKey::default());
+                let id = pipeline_cache.queue(sdf_shape_pipeline.specialize(&render_device, key));
+                specialized_self.pipelines.insert(key, id);
+            }
+        }
+
+        sdf_shape_pipeline
+    }
+}
+
+/// Queues the pipelines of shapes with a [`CullMode`], [`DepthBias`]
+/// or masked [`AlphaMode`], the default ones are queued up front.
+pub fn specialize_sdf_shape_pipelines(
+    render_device: Res<RenderDevice>,
+    sdf_shape_pipeline: Res<SdfShapePipeline>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    mut specialized_sdf_shape_pipeline: ResMut<Specialized<SdfShapePipeline>>,
+    query: Query<
+        (
+            &ShapeKind,
+            Option<&BlendMode>,
+            Option<&CullMode>,
+            Option<&DepthBias>,
+            Option<&AlphaMode>,
+        ),
+        Or<(
+            Changed<ShapeKind>,
+            Changed<BlendMode>,
+            Changed<CullMode>,
+            Changed<DepthBias>,
+            Changed<AlphaMode>,
+        )>,
+    >,
+) {
+    for (shape_kind, blend_mode, cull_mode, depth_bias, alpha_mode) in query.iter() {
+        let key = (
+            *shape_kind,
+            blend_mode.copied().unwrap_or(SDF_SHAPE_DEFAULT_BLEND_MODE),
+            RasterKey::new(cull_mode, depth_bias, alpha_mode),
+        );
+        specialized_sdf_shape_pipeline.specialize(
+            &mut pipeline_cache,
+            &sdf_shape_pipeline,
+            &render_device,
+            key,
+        );
+    }
+}
+
+impl PipelineSpecialize for SdfShapePipeline {
+    type Key = (ShapeKind, BlendMode, RasterKey);
+
+    fn specialize(
+        &self,
+        _render_device: &RenderDevice,
+        (shape_kind, blend_mode, raster_key): Self::Key,
+    ) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: None,
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![
+                    self.model_layout.clone(),
+                    self.view_layout.clone(),
+                    self.style_layout.clone(),
+                ],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: SDF_SHAPE_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: vec![Vertex::layout()],
+            },
+            fragment: Some(FragmentState {
+                shader: SDF_SHAPE_SHADER_HANDLE.typed(),
+                shader_defs: blend_mode.shader_defs(),
+                entry_point: shape_kind.fragment_entry_point(),
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(blend_mode.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: raster_key.primitive_state(wgpu::PrimitiveTopology::TriangleList),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::DepthTexture::DEPTH_FORMAT,
+                depth_write_enabled: raster_key.depth_write_enabled(blend_mode),
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: raster_key.depth_bias.state(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        }
+    }
+}
+
+/// Bind groups shared by every [`ShapeKind`], created once per frame.
+#[derive(Default, Resource)]
+pub struct SdfShapeBindGroups {
+    pub model_bind_group: Option<wgpu::BindGroup>,
+    pub view_bind_group: Option<wgpu::BindGroup>,
+    pub style_bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn create_sdf_shape_bind_groups(
+    mut sdf_shape_bind_groups: ResMut<SdfShapeBindGroups>,
+    render_device: Res<RenderDevice>,
+    sdf_shape_pipeline: Res<SdfShapePipeline>,
+    model_uniforms: Res<ComponentUniforms<ModelUniform>>,
+    view_uniforms: Res<ComponentUniforms<CameraUniforms>>,
+    style_uniforms: Res<ComponentUniforms<ShapeStyleUniform>>,
+) {
+    let Some(model_binding) = model_uniforms.binding() else {
+        return;
+    };
+    let model_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &sdf_shape_pipeline.model_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: model_binding,
+        }],
+    });
+
+    let Some(view_binding) = view_uniforms.binding() else {
+        return;
+    };
+    let view_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &sdf_shape_pipeline.view_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: view_binding,
+        }],
+    });
+
+    let Some(style_binding) = style_uniforms.binding() else {
+        return;
+    };
+    let style_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &sdf_shape_pipeline.style_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: style_binding,
+        }],
+    });
+
+    sdf_shape_bind_groups.model_bind_group = Some(model_bind_group);
+    sdf_shape_bind_groups.view_bind_group = Some(view_bind_group);
+    sdf_shape_bind_groups.style_bind_group = Some(style_bind_group);
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::{Bundle, GlobalTransform, Handle, Transform};
+
+use crate::{
+    render::{
+        blend::BlendMode, camera::component::Visibility, mesh::Mesh, resource::buffer::Vertex,
+        system::RenderFunctionId,
+    },
+    sprite::BASE_QUAD_HANDLE,
+};
+
+use super::{
+    bind::ShapeKind, ShapeStyle, SDF_SHAPE_DEFAULT_BLEND_MODE, SDF_SHAPE_RENDER_FUNCTION,
+};
+
+/// Shape inscribed into the unit quad, scale the transform to set its size.
+#[derive(Bundle)]
+pub struct ShapeBundle {
+    pub global_transform: GlobalTransform,
+    pub transform: Transform,
+    pub mesh: Handle<Mesh<Vertex>>,
+    pub kind: ShapeKind,
+    pub style: ShapeStyle,
+    pub blend_mode: BlendMode,
+    pub visibility: Visibility,
+    pub render_function: RenderFunctionId,
+}
+
+impl ShapeBundle {
+    pub fn new(kind: ShapeKind) -> Self {
+        Self {
+            global_transform: GlobalTransform::default(),
+            transform: Transform::default(),
+            mesh: BASE_QUAD_HANDLE.typed(),
+            kind,
+            style: ShapeStyle::default(),
+            blend_mode: SDF_SHAPE_DEFAULT_BLEND_MODE,
+            visibility: Visibility::Inherited,
+            render_function: SDF_SHAPE_RENDER_FUNCTION.into(),
+        }
+    }
+
+    pub fn circle() -> Self {
+        Self::new(ShapeKind::Circle)
+    }
+
+    pub fn triangle() -> Self {
+        Self::new(ShapeKind::Triangle)
+    }
+
+    pub fn rectangle() -> Self {
+        Self::new(ShapeKind::Rectangle)
+    }
+}
+
+impl Default for ShapeBundle {
+    fn default() -> Self {
+        Self::circle()
+    }
+}
@@ -1 +1,229 @@
-pub mod skybox;
\ No newline at end of file
+use bevy::{
+    asset::load_internal_asset,
+    prelude::{Component, Entity, Handle, HandleUntyped, Plugin, Vec4, World},
+    reflect::TypeUuid,
+};
+use encase::ShaderType;
+
+use crate::render::{
+    blend::BlendMode,
+    camera::component::CameraUniforms,
+    color::{Color, ColorSpace},
+    mesh::{GpuMeshAssembly, Mesh},
+    raster::RasterKey,
+    resource::{
+        buffer::Vertex,
+        component_uniform::{AddComponentUniform, ModelUniform},
+        pipeline::PipelineCache,
+        shader::Shader,
+        specialized_pipeline::{AddPipelineWarmup, Specialized},
+        uniform::{DynamicUniformId, HandleGpuUniform},
+    },
+    stats::{RenderCategory, RenderStats},
+    system::{AddRenderFunction, RenderResult},
+    RenderAssets, RenderStage,
+};
+
+use self::bind::{
+    create_sdf_shape_bind_groups, specialize_sdf_shape_pipelines, SdfShapeBindGroups,
+    SdfShapePipeline, ShapeKind,
+};
+
+pub mod bind;
+pub mod bundle;
+pub mod skybox;
+
+const SDF_SHAPE_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 35678909876445673);
+
+pub struct FlatShapePlugin;
+impl Plugin for FlatShapePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        load_internal_asset!(
+            app,
+            SDF_SHAPE_SHADER_HANDLE,
+            "sdf_shape.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_component_uniform::<ShapeStyle>()
+            .init_resource::<Specialized<SdfShapePipeline>>()
+            .init_resource::<SdfShapePipeline>()
+            .add_pipeline_warmup::<SdfShapePipeline>()
+            .init_resource::<SdfShapeBindGroups>()
+            .add_render_function(SDF_SHAPE_RENDER_FUNCTION, render_sdf_shape)
+            .add_system_to_stage(RenderStage::Prepare, specialize_sdf_shape_pipelines)
+            .add_system_to_stage(RenderStage::Create, create_sdf_shape_bind_groups);
+    }
+}
+
+/// Fill and stroke options of an SDF shape.
+///
+/// `stroke_width` is measured in normalized shape space,
+/// where the distance from the center to the edge of the shape is `1.0`.
+#[derive(Component, Clone, Copy)]
+pub struct ShapeStyle {
+    pub fill: bool,
+    pub fill_color: Color,
+    pub stroke_width: f32,
+    pub stroke_color: Color,
+}
+
+impl Default for ShapeStyle {
+    fn default() -> Self {
+        Self::filled(Color::WHITE)
+    }
+}
+
+impl ShapeStyle {
+    pub fn filled(color: Color) -> Self {
+        Self {
+            fill: true,
+            fill_color: color,
+            stroke_width: 0.0,
+            stroke_color: color,
+        }
+    }
+
+    pub fn outlined(stroke_width: f32, stroke_color: Color) -> Self {
+        Self {
+            fill: false,
+            fill_color: stroke_color,
+            stroke_width,
+            stroke_color,
+        }
+    }
+
+    pub fn with_stroke(mut self, stroke_width: f32, stroke_color: Color) -> Self {
+        self.stroke_width = stroke_width;
+        self.stroke_color = stroke_color;
+        self
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct ShapeStyleUniform {
+    fill_color: Vec4,
+    stroke_color: Vec4,
+    stroke_width: f32,
+    fill: u32,
+}
+
+impl HandleGpuUniform for ShapeStyle {
+    type GU = ShapeStyleUniform;
+
+    fn into_uniform(&self, color_space: ColorSpace) -> Self::GU {
+        ShapeStyleUniform {
+            fill_color: self.fill_color.as_gpu_vec(color_space),
+            stroke_color: self.stroke_color.as_gpu_vec(color_space),
+            stroke_width: self.stroke_width.max(0.0),
+            fill: self.fill as u32,
+        }
+    }
+}
+
+pub const SDF_SHAPE_RENDER_FUNCTION: usize = 3;
+/// Used for shapes without a [`BlendMode`].
+pub const SDF_SHAPE_DEFAULT_BLEND_MODE: BlendMode = BlendMode::AlphaBlend;
+fn render_sdf_shape<'w>(
+    camera: Entity,
+    object: Entity,
+    world: &'w World,
+    render_pass: &mut wgpu::RenderPass<'w>,
+) -> RenderResult {
+    // -- Set Pipeline --
+    let Some(specialized_sdf_shape_pipeline) =
+        world.get_resource::<Specialized<SdfShapePipeline>>()
+    else {
+        return RenderResult::Failure("Specialized<SdfShapePipeline> resource missing");
+    };
+    let Some(pipeline_cache) = world.get_resource::<PipelineCache>() else {
+        return RenderResult::Failure("PipelineCache resource missing");
+    };
+
+    let Some(shape_kind) = world.get::<ShapeKind>(object) else {
+        return RenderResult::Failure("no ShapeKind");
+    };
+    let blend_mode = world
+        .get::<BlendMode>(object)
+        .copied()
+        .unwrap_or(SDF_SHAPE_DEFAULT_BLEND_MODE);
+    let raster_key = RasterKey::of(world, object);
+    let pipeline_key = (*shape_kind, blend_mode, raster_key);
+    let Some(pipeline_id) = specialized_sdf_shape_pipeline.pipelines.get(&pipeline_key) else {
+        return RenderResult::Failure("pipeline not specialized");
+    };
+    let Some(render_pipeline) = pipeline_cache.get(pipeline_id) else {
+        return RenderResult::Failure("pipeline not compiled yet");
+    };
+    render_pass.set_pipeline(render_pipeline);
+    let Some(render_stats) = world.get_resource::<RenderStats>() else {
+        return RenderResult::Failure("RenderStats resource missing");
+    };
+    render_stats.pipeline_switch();
+    // -- -- -- -------- -- -- --
+
+    // -- Get Mesh --
+    let Some(mesh_handle) = world.get::<Handle<Mesh<Vertex>>>(object) else {
+        return RenderResult::Failure("no mesh handle");
+    };
+    let Some(gpu_meshes) = world.get_resource::<RenderAssets<Mesh<Vertex>>>() else {
+        return RenderResult::Failure("RenderAssets<Mesh<Vertex>> resource missing");
+    };
+    let Some(mesh) = gpu_meshes.get(&mesh_handle.id()) else {
+        return RenderResult::Failure("mesh not prepared yet");
+    };
+    // -- -- -- -------- -- -- --
+
+    // -- Bind Model, View, Style BindGroups --
+    let Some(sdf_shape_bind_groups) = world.get_resource::<SdfShapeBindGroups>() else {
+        return RenderResult::Failure("SdfShapeBindGroups resource missing");
+    };
+    let (Some(model_bind_group), Some(view_bind_group), Some(style_bind_group)) = (
+        sdf_shape_bind_groups.model_bind_group.as_ref(),
+        sdf_shape_bind_groups.view_bind_group.as_ref(),
+        sdf_shape_bind_groups.style_bind_group.as_ref(),
+    ) else {
+        return RenderResult::Failure("bind groups not created");
+    };
+
+    let Some(model_uniform_id) = world.get::<DynamicUniformId<ModelUniform>>(object) else {
+        return RenderResult::Failure("no ModelUniform id");
+    };
+    render_pass.set_bind_group(0, model_bind_group, &[**model_uniform_id]);
+
+    let Some(view_uniform_id) = world.get::<DynamicUniformId<CameraUniforms>>(camera) else {
+        return RenderResult::Failure("no CameraUniforms id");
+    };
+    render_pass.set_bind_group(1, view_bind_group, &[**view_uniform_id]);
+
+    let Some(style_uniform_id) = world.get::<DynamicUniformId<ShapeStyleUniform>>(object) else {
+        return RenderResult::Failure("no ShapeStyleUniform id");
+    };
+    render_pass.set_bind_group(2, style_bind_group, &[**style_uniform_id]);
+    render_stats.bind_group_switches(3);
+    // -- -- -- -------- -- -- --
+
+    // -- Set Mesh Buffers --
+    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+    let instance_count = 1;
+    match &mesh.assembly {
+        GpuMeshAssembly::Indexed {
+            index_buffer,
+            index_count,
+            index_format,
+        } => {
+            render_pass.set_index_buffer(index_buffer.slice(..), *index_format);
+            render_pass.draw_indexed(0..*index_count as u32, 0, 0..instance_count);
+            render_stats.draw(*index_count as u32, instance_count);
+        }
+        GpuMeshAssembly::NonIndexed { vertex_count } => {
+            render_pass.draw(0..*vertex_count as u32, 0..instance_count);
+            render_stats.draw(*vertex_count as u32, instance_count);
+        }
+    }
+    render_stats.category_draw(RenderCategory::Shape, 1);
+    // -- -- -- -------- -- -- --
+
+    RenderResult::Success
+}
@@ -0,0 +1,118 @@
+
+// -- Vertex -----
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+}
+
+struct Model {
+    model: mat4x4<f32>,
+}
+
+struct VertexInput {
+    @location(0)    position: vec3<f32>,
+    @location(1)    uv: vec2<f32>,
+    @location(2)    color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> model: Model;
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@vertex
+fn vs_main(
+    vertex: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.clip_position = camera.view_proj * model.model * vec4<f32>(vertex.position, 1.0);
+    out.uv = vertex.uv;
+
+    return out;
+}
+
+// -- Fragment -----
+
+struct ShapeStyle {
+    fill_color: vec4<f32>,
+    stroke_color: vec4<f32>,
+    stroke_width: f32,
+    fill: u32,
+}
+
+@group(2) @binding(0)
+var<uniform> style: ShapeStyle;
+
+// Normalized shape space: origin at the quad center, +y up, quad edges at 1.0
+fn shape_space(uv: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+}
+
+fn sd_circle(p: vec2<f32>) -> f32 {
+    return length(p) - 1.0;
+}
+
+// Equilateral triangle with unit half-width, centered vertically
+fn sd_triangle(p_in: vec2<f32>) -> f32 {
+    let k = sqrt(3.0);
+    var p = p_in + vec2<f32>(0.0, 0.5 / k);
+    p.x = abs(p.x) - 1.0;
+    p.y = p.y + 1.0 / k;
+    if (p.x + k * p.y > 0.0) {
+        p = vec2<f32>(p.x - k * p.y, -k * p.x - p.y) / 2.0;
+    }
+    p.x = p.x - clamp(p.x, -2.0, 0.0);
+    return -length(p) * sign(p.y);
+}
+
+fn sd_rectangle(p: vec2<f32>) -> f32 {
+    let q = abs(p) - vec2<f32>(1.0);
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0);
+}
+
+fn shade(d: f32) -> vec4<f32> {
+    let aa = fwidth(d);
+
+    let outer = 1.0 - smoothstep(-aa, aa, d);
+    let inner = 1.0 - smoothstep(-aa, aa, d + style.stroke_width);
+
+    let fill_alpha = f32(style.fill) * inner * style.fill_color.a;
+    let stroke_alpha = (outer - inner) * style.stroke_color.a;
+
+    let alpha = fill_alpha + stroke_alpha;
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    let rgb = (style.fill_color.rgb * fill_alpha + style.stroke_color.rgb * stroke_alpha) / alpha;
+    var color = vec4<f32>(rgb, alpha);
+#ifdef BLEND_MULTIPLY
+    // The Multiply blend state expects premultiplied alpha
+    color = vec4<f32>(color.rgb * color.a, color.a);
+#endif
+    return color;
+}
+
+@fragment
+fn fs_circle(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(sd_circle(shape_space(in.uv)));
+}
+
+@fragment
+fn fs_triangle(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(sd_triangle(shape_space(in.uv)));
+}
+
+@fragment
+fn fs_rectangle(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(sd_rectangle(shape_space(in.uv)));
+}
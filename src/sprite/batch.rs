@@ -0,0 +1,497 @@
+use bevy::{
+    asset::HandleId,
+    prelude::{
+        Assets, Entity, GlobalTransform, Handle, Mat4, Query, Res, ResMut, Resource, With,
+        Without, World,
+    },
+    utils::HashMap,
+};
+
+use crate::render::{
+    blend::{AlphaMode, BlendMode},
+    camera::{
+        billboard::Billboard,
+        component::{Camera, CameraUniforms},
+    },
+    color::{Color, ColorSpace},
+    globals::GlobalsUniform,
+    mesh::Mesh,
+    phase::{Opaque, PhaseItem, RenderPhase, Transparent},
+    raster::{CullMode, DepthBias, RasterKey},
+    resource::{
+        buffer::{Indices, Vertex},
+        buffer_pool::{BufferPool, PooledBuffer},
+        component_uniform::ModelUniform,
+        pipeline::PipelineCache,
+        push_constant::MODEL_PUSH_CONSTANT_RANGE,
+        renderer::{RenderDevice, RenderQueue},
+        specialized_pipeline::Specialized,
+        uniform::{DynamicUniformBuffer, DynamicUniformId},
+    },
+    stats::{RenderCategory, RenderStats},
+    system::{RenderFunctionId, RenderResult},
+    texture::Image,
+};
+
+use super::{
+    bind::{SpriteBindGroups, SpritePipeline, TextureBindGroups},
+    uniform::{SpriteRect, SpriteTiling, SpriteUniform},
+    SPRITE_DEFAULT_BLEND_MODE, SPRITE_RENDER_FUNCTION,
+};
+
+pub const SPRITE_BATCH_RENDER_FUNCTION: usize = 6;
+
+///
+/// Merges sprites sharing blend mode, texture and alpha cutoff into a single draw.
+///
+/// The vertices of the batched sprites are transformed on the CPU into one vertex buffer,
+/// the batch is drawn with an identity model matrix in place of its first sprite.
+/// Opaque sprites are batched regardless of their order, they are depth tested.
+/// Transparent sprites are only batched with their neighbours in the sorted phase.
+///
+/// Sprites with a [`SpriteRect`], [`SpriteTiling`] or [`Billboard`] need their own uniforms
+/// and are drawn one by one.
+///
+#[derive(Resource, Clone, Copy)]
+pub struct SpriteBatching {
+    pub enabled: bool,
+    /// Runs with fewer sprites are drawn one by one.
+    pub min_batch_size: usize,
+}
+
+impl Default for SpriteBatching {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_batch_size: 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+struct SpriteBatchKey {
+    blend_mode: BlendMode,
+    raster_key: RasterKey,
+    image: HandleId,
+    alpha_cutoff: u32,
+}
+
+pub struct SpriteBatch {
+    pub blend_mode: BlendMode,
+    pub raster_key: RasterKey,
+    pub image: HandleId,
+    /// Vertex range in [`SpriteBatches::vertex_buffer`].
+    pub vertices: std::ops::Range<u32>,
+    pub sprite_uniform_id: DynamicUniformId<SpriteUniform>,
+    pub sprite_count: usize,
+}
+
+/// Batches of the frame, keyed by camera and the entity drawing the batch.
+#[derive(Resource, Default)]
+pub struct SpriteBatches {
+    batches: HashMap<(Entity, Entity), SpriteBatch>,
+    vertices: Vec<Vertex>,
+    vertex_buffer: Option<PooledBuffer>,
+    model_uniforms: DynamicUniformBuffer<ModelUniform>,
+    sprite_uniforms: DynamicUniformBuffer<SpriteUniform>,
+    model_bind_group: Option<wgpu::BindGroup>,
+    sprite_bind_group: Option<wgpu::BindGroup>,
+}
+
+impl SpriteBatches {
+    pub fn get(&self, camera: Entity, entity: Entity) -> Option<&SpriteBatch> {
+        self.batches.get(&(camera, entity))
+    }
+
+    pub fn vertex_buffer(&self) -> Option<&wgpu::Buffer> {
+        self.vertex_buffer.as_ref().map(PooledBuffer::buffer)
+    }
+
+    pub fn len(&self) -> usize {
+        self.batches.len()
+    }
+}
+
+/// Sprites merged into a batch, in phase order.
+struct BatchRun {
+    item_index: usize,
+    key: SpriteBatchKey,
+    entities: Vec<Entity>,
+}
+
+/// Replaces runs of batchable items with a single [`SPRITE_BATCH_RENDER_FUNCTION`] item.
+fn batch_phase_items(
+    items: &mut Vec<PhaseItem>,
+    ordered: bool,
+    batch_key: impl Fn(&PhaseItem) -> Option<SpriteBatchKey>,
+) -> Vec<BatchRun> {
+    let mut batched = Vec::with_capacity(items.len());
+    let mut runs: Vec<BatchRun> = Vec::new();
+    let mut run_by_key: HashMap<SpriteBatchKey, usize> = HashMap::new();
+
+    for item in items.drain(..) {
+        let Some(key) = batch_key(&item) else {
+            batched.push(item);
+            continue;
+        };
+        let open_run = if ordered {
+            runs.last_mut()
+                .filter(|run| run.key == key && run.item_index + 1 == batched.len())
+        } else {
+            run_by_key.get(&key).map(|ind| &mut runs[*ind])
+        };
+        match open_run {
+            Some(run) => run.entities.push(item.entity),
+            None => {
+                run_by_key.insert(key, runs.len());
+                runs.push(BatchRun {
+                    item_index: batched.len(),
+                    key,
+                    entities: vec![item.entity],
+                });
+                batched.push(PhaseItem {
+                    render_function: SPRITE_BATCH_RENDER_FUNCTION.into(),
+                    ..item
+                });
+            }
+        }
+    }
+
+    *items = batched;
+    runs
+}
+
+pub fn queue_sprite_batches(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    settings: Res<SpriteBatching>,
+    sprite_pipeline: Res<SpritePipeline>,
+    meshes: Res<Assets<Mesh<Vertex>>>,
+    color_space: Res<ColorSpace>,
+    mut buffer_pool: ResMut<BufferPool>,
+    mut sprite_batches: ResMut<SpriteBatches>,
+    mut cameras: Query<
+        (Entity, &mut RenderPhase<Opaque>, &mut RenderPhase<Transparent>),
+        With<Camera>,
+    >,
+    sprites: Query<
+        (
+            &RenderFunctionId,
+            &Handle<Mesh<Vertex>>,
+            &Handle<Image>,
+            &GlobalTransform,
+            Option<&BlendMode>,
+            Option<&AlphaMode>,
+            Option<&CullMode>,
+            Option<&DepthBias>,
+        ),
+        (Without<SpriteRect>, Without<SpriteTiling>, Without<Billboard>),
+    >,
+) {
+    let sprite_batches = &mut *sprite_batches;
+    sprite_batches.batches.clear();
+    sprite_batches.vertices.clear();
+    sprite_batches.sprite_uniforms.clear();
+    if !settings.enabled {
+        return;
+    }
+
+    let batch_key = |item: &PhaseItem| -> Option<SpriteBatchKey> {
+        if item.render_function != SPRITE_RENDER_FUNCTION.into() {
+            return None;
+        }
+        let (_, mesh_handle, image_handle, _, blend_mode, alpha_mode, cull_mode, depth_bias) =
+            sprites.get(item.entity).ok()?;
+        let mesh = meshes.get(mesh_handle)?;
+        if mesh.get_primitive_topology() != wgpu::PrimitiveTopology::TriangleList {
+            return None;
+        }
+        Some(SpriteBatchKey {
+            blend_mode: blend_mode.copied().unwrap_or(SPRITE_DEFAULT_BLEND_MODE),
+            raster_key: RasterKey::new(cull_mode, depth_bias, alpha_mode),
+            image: image_handle.id(),
+            alpha_cutoff: alpha_mode.map_or(0.0, AlphaMode::cutoff).to_bits(),
+        })
+    };
+
+    for (camera_entity, mut opaque_phase, mut transparent_phase) in cameras.iter_mut() {
+        let opaque_runs = batch_phase_items(&mut opaque_phase.items, false, &batch_key);
+        let transparent_runs = batch_phase_items(&mut transparent_phase.items, true, &batch_key);
+
+        for (phase_items, runs) in [
+            (&mut opaque_phase.items, opaque_runs),
+            (&mut transparent_phase.items, transparent_runs),
+        ] {
+            // Runs too small to batch are put back in place, shifting the items after them
+            let mut shift = 0;
+            for run in runs {
+                let item_index = run.item_index + shift;
+                if run.entities.len() < settings.min_batch_size.max(1) {
+                    for (ind, entity) in run.entities.iter().enumerate() {
+                        let item = PhaseItem {
+                            entity: *entity,
+                            render_function: SPRITE_RENDER_FUNCTION.into(),
+                            ..phase_items[item_index]
+                        };
+                        if ind == 0 {
+                            phase_items[item_index] = item;
+                        } else {
+                            phase_items.insert(item_index + ind, item);
+                        }
+                    }
+                    shift += run.entities.len() - 1;
+                    continue;
+                }
+
+                let start = sprite_batches.vertices.len() as u32;
+                for entity in &run.entities {
+                    let (_, mesh_handle, _, global_transform, ..) =
+                        sprites.get(*entity).unwrap();
+                    let mesh = meshes.get(mesh_handle).unwrap();
+                    push_transformed_vertices(
+                        &mut sprite_batches.vertices,
+                        mesh,
+                        global_transform.compute_matrix(),
+                        *color_space,
+                    );
+                }
+                let end = sprite_batches.vertices.len() as u32;
+
+                let sprite_uniform_id = sprite_batches
+                    .sprite_uniforms
+                    .push(SpriteUniform::masked(f32::from_bits(run.key.alpha_cutoff)))
+                    .into();
+                sprite_batches.batches.insert(
+                    (camera_entity, run.entities[0]),
+                    SpriteBatch {
+                        blend_mode: run.key.blend_mode,
+                        raster_key: run.key.raster_key,
+                        image: run.key.image,
+                        vertices: start..end,
+                        sprite_uniform_id,
+                        sprite_count: run.entities.len(),
+                    },
+                );
+            }
+        }
+    }
+
+    write_batch_buffers(
+        &render_device,
+        &render_queue,
+        &sprite_pipeline,
+        &mut buffer_pool,
+        sprite_batches,
+    );
+}
+
+/// Expands the mesh into a triangle list in world space, colors converted as on upload.
+fn push_transformed_vertices(
+    vertices: &mut Vec<Vertex>,
+    mesh: &Mesh<Vertex>,
+    model: Mat4,
+    color_space: ColorSpace,
+) {
+    let transform = |vertex: &Vertex| Vertex {
+        position: model.transform_point3(vertex.position.into()).into(),
+        color: Color::from(vertex.color).as_gpu_arr(color_space),
+        ..*vertex
+    };
+    let mesh_vertices = mesh.get_vertices();
+    match mesh.get_indices() {
+        Some(Indices::U16(indices)) => vertices.extend(
+            indices
+                .iter()
+                .map(|ind| transform(&mesh_vertices[*ind as usize])),
+        ),
+        Some(Indices::U32(indices)) => vertices.extend(
+            indices
+                .iter()
+                .map(|ind| transform(&mesh_vertices[*ind as usize])),
+        ),
+        None => vertices.extend(mesh_vertices.iter().map(transform)),
+    }
+}
+
+fn write_batch_buffers(
+    render_device: &RenderDevice,
+    render_queue: &RenderQueue,
+    sprite_pipeline: &SpritePipeline,
+    buffer_pool: &mut BufferPool,
+    sprite_batches: &mut SpriteBatches,
+) {
+    if sprite_batches.batches.is_empty() {
+        return;
+    }
+
+    let size = (sprite_batches.vertices.len() * std::mem::size_of::<Vertex>()) as u64;
+    if sprite_batches
+        .vertex_buffer
+        .as_ref()
+        .map_or(true, |pooled| pooled.size() < size)
+    {
+        if let Some(pooled) = sprite_batches.vertex_buffer.take() {
+            buffer_pool.release(pooled);
+        }
+        sprite_batches.vertex_buffer = Some(buffer_pool.acquire(
+            render_device,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            size,
+        ));
+    }
+    render_queue.write_buffer(
+        sprite_batches.vertex_buffer().unwrap(),
+        0,
+        bytemuck::cast_slice(&sprite_batches.vertices),
+    );
+
+    if sprite_batches.model_uniforms.is_empty() {
+        sprite_batches
+            .model_uniforms
+            .push(ModelUniform::new(Mat4::IDENTITY));
+    }
+    sprite_batches
+        .model_uniforms
+        .write_buffer(render_device, render_queue);
+    sprite_batches
+        .sprite_uniforms
+        .write_buffer_pooled(render_device, render_queue, buffer_pool);
+
+    sprite_batches.model_bind_group = if cfg!(feature = "push_constants") {
+        Some(render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &sprite_pipeline.model_layout,
+            entries: &[],
+        }))
+    } else {
+        sprite_batches.model_uniforms.binding().map(|model_binding| {
+            render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("sprite_batch_model_bind_group"),
+                layout: &sprite_pipeline.model_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: model_binding,
+                }],
+            })
+        })
+    };
+    sprite_batches.sprite_bind_group =
+        sprite_batches
+            .sprite_uniforms
+            .binding()
+            .map(|sprite_binding| {
+                render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("sprite_batch_bind_group"),
+                    layout: &sprite_pipeline.sprite_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: sprite_binding,
+                    }],
+                })
+            });
+}
+
+pub fn render_sprite_batch<'w>(
+    camera: Entity,
+    object: Entity,
+    world: &'w World,
+    render_pass: &mut wgpu::RenderPass<'w>,
+) -> RenderResult {
+    let Some(sprite_batches) = world.get_resource::<SpriteBatches>() else {
+        return RenderResult::Failure("SpriteBatches resource missing");
+    };
+    let Some(batch) = sprite_batches.get(camera, object) else {
+        return RenderResult::Failure("sprite batch not queued");
+    };
+
+    // -- Pipeline --
+    let Some(specialized_sprite_pipeline) =
+        world.get_resource::<Specialized<SpritePipeline>>()
+    else {
+        return RenderResult::Failure("Specialized<SpritePipeline> resource missing");
+    };
+    let Some(pipeline_cache) = world.get_resource::<PipelineCache>() else {
+        return RenderResult::Failure("PipelineCache resource missing");
+    };
+    let Some(pipeline_id) = specialized_sprite_pipeline
+        .pipelines
+        .get(&(batch.blend_mode, batch.raster_key))
+    else {
+        return RenderResult::Failure("pipeline not specialized");
+    };
+    let Some(render_pipeline) = pipeline_cache.get(pipeline_id) else {
+        return RenderResult::Failure("pipeline not compiled yet");
+    };
+    render_pass.set_pipeline(render_pipeline);
+
+    // -- Bind Groups --
+    let Some(sprite_pipeline) = world.get_resource::<SpritePipeline>() else {
+        return RenderResult::Failure("SpritePipeline resource missing");
+    };
+    let Some(sprite_bind_groups) = world.get_resource::<SpriteBindGroups>() else {
+        return RenderResult::Failure("SpriteBindGroups resource missing");
+    };
+    let Some(texture_bind_groups) = world.get_resource::<TextureBindGroups>() else {
+        return RenderResult::Failure("TextureBindGroups resource missing");
+    };
+    let (Some(model_bind_group), Some(view_bind_group), Some(sprite_bind_group)) = (
+        sprite_batches.model_bind_group.as_ref(),
+        sprite_bind_groups.view_bind_group.as_ref(),
+        sprite_batches.sprite_bind_group.as_ref(),
+    ) else {
+        return RenderResult::Failure("bind groups not created");
+    };
+
+    if cfg!(feature = "push_constants") {
+        render_pass.set_bind_group(0, model_bind_group, &[]);
+        set_identity_push_constant(render_pass);
+    } else {
+        render_pass.set_bind_group(0, model_bind_group, &[0]);
+    }
+
+    let Some(view_uniform_id) = world.get::<DynamicUniformId<CameraUniforms>>(camera) else {
+        return RenderResult::Failure("no CameraUniforms id");
+    };
+    let Some(globals_uniform_id) = world.get::<DynamicUniformId<GlobalsUniform>>(camera) else {
+        return RenderResult::Failure("no GlobalsUniform id");
+    };
+    render_pass.set_bind_group(1, view_bind_group, &[**view_uniform_id, **globals_uniform_id]);
+
+    let texture_bind_group = texture_bind_groups
+        .get(&batch.image)
+        .unwrap_or(&sprite_pipeline.dummy_texture_bind_group);
+    render_pass.set_bind_group(2, texture_bind_group, &[]);
+
+    render_pass.set_bind_group(3, sprite_bind_group, &[*batch.sprite_uniform_id]);
+
+    // -- Draw --
+    let Some(vertex_buffer) = sprite_batches.vertex_buffer() else {
+        return RenderResult::Failure("sprite batch buffer not written");
+    };
+    let vertex_size = std::mem::size_of::<Vertex>() as u64;
+    render_pass.set_vertex_buffer(
+        0,
+        vertex_buffer.slice(
+            batch.vertices.start as u64 * vertex_size..batch.vertices.end as u64 * vertex_size,
+        ),
+    );
+    render_pass.draw(0..batch.vertices.len() as u32, 0..1);
+
+    let Some(render_stats) = world.get_resource::<RenderStats>() else {
+        return RenderResult::Failure("RenderStats resource missing");
+    };
+    render_stats.pipeline_switch();
+    render_stats.bind_group_switches(4);
+    render_stats.draw(batch.vertices.len() as u32, 1);
+    render_stats.category_draw(RenderCategory::Sprite, batch.sprite_count as u32);
+
+    RenderResult::Success
+}
+
+fn set_identity_push_constant(render_pass: &mut wgpu::RenderPass) {
+    render_pass.set_push_constants(
+        MODEL_PUSH_CONSTANT_RANGE.stages,
+        0,
+        bytemuck::cast_slice(&Mat4::IDENTITY.to_cols_array()),
+    );
+}
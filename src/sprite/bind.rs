@@ -1,64 +1,86 @@
 use bevy::{
     asset::HandleId,
     ecs::system::SystemState,
-    prelude::{FromWorld, Res, ResMut, Resource, World, Deref, DerefMut},
+    prelude::{
+        AssetEvent, Changed, Deref, DerefMut, EventReader, FromWorld, Handle, Or, Query, Res,
+        ResMut, Resource, World,
+    },
     utils::HashMap,
 };
 use encase::ShaderType;
 
 use crate::{render::{
-    resource::{pipeline::{BindGroupLayout, PipelineCache, RenderPipelineDescriptor, PipelineLayoutDescriptor, VertexState, FragmentState, RenderPipelineId}, shader::Shader, buffer::{Vertex, MeshVertex}, renderer::{RenderDevice, RenderQueue}, component_uniform::{ComponentUniforms, ModelUniform}},
+    blend::{AlphaMode, BlendMode},
+    resource::{pipeline::{BindGroupLayout, PipelineCache, RenderPipelineDescriptor, PipelineLayoutDescriptor, VertexState, FragmentState}, shader::Shader, specialized_pipeline::{PipelineSpecialize, Specialized}, buffer::{Vertex, MeshVertex}, renderer::{RenderDevice, RenderQueue}, component_uniform::{ComponentUniforms, ModelUniform}, push_constant::{MODEL_PUSH_CONSTANT_RANGE, model_shader_defs}},
     texture::{GpuTexture, Image, PixelFormat, RawImage, self},
-    RenderAssets, camera::component::CameraUniforms,
+    RenderAssets, camera::component::CameraUniforms, globals::{globals_layout_entry, GlobalsUniform},
+    raster::{CullMode, DepthBias, RasterKey},
+    system::RenderFunctionId,
 }, util::EngineDefault};
 
-use super::SPRITE_SHADER_HANDLE;
+use super::{
+    bindless::{BindlessSpritePipeline, BINDLESS_SPRITE_RENDER_FUNCTION},
+    uniform::SpriteUniform,
+    SPRITE_DEFAULT_BLEND_MODE, SPRITE_RENDER_FUNCTION, SPRITE_SHADER_HANDLE,
+};
 
 #[derive(Resource)]
 pub struct SpritePipeline {
-    pub pipeline_id: RenderPipelineId,
     pub model_layout: BindGroupLayout,
     pub view_layout: BindGroupLayout,
     pub texture_layout: BindGroupLayout,
+    pub sprite_layout: BindGroupLayout,
     pub dummy_texture: GpuTexture,
     pub dummy_texture_bind_group: wgpu::BindGroup,
 }
 
 impl FromWorld for SpritePipeline {
     fn from_world(world: &mut World) -> Self {
-        let mut state: SystemState<(Res<RenderDevice>, Res<RenderQueue>, ResMut<PipelineCache>)> =
-            SystemState::new(world);
-        let (render_device, render_queue, mut pipeline_cache) =
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<RenderQueue>,
+            ResMut<PipelineCache>,
+            ResMut<Specialized<Self>>,
+        )> = SystemState::new(world);
+        let (render_device, render_queue, mut pipeline_cache, mut specialized_self) =
             state.get_mut(world);
 
+        // Empty with push constants, the model matrix is pushed per draw
         let model_layout =
             render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: true,
-                        // min_binding_size: None,
-                        min_binding_size: Some(ModelUniform::min_size()),
-                    },
-                    count: None,
-                }],
+                entries: if cfg!(feature = "push_constants") {
+                    &[]
+                } else {
+                    &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            // min_binding_size: None,
+                            min_binding_size: Some(ModelUniform::min_size()),
+                        },
+                        count: None,
+                    }]
+                },
                 label: Some("sprite_model_layout"),
             });
 
         let view_layout =
             render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: true,
-                        min_binding_size: Some(CameraUniforms::min_size()),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: Some(CameraUniforms::min_size()),
+                        },
+                        count: None,
                     },
-                    count: None,
-                }],
+                    globals_layout_entry(1),
+                ],
                 label: Some("sprite_view_layout"),
             });
 
@@ -85,6 +107,21 @@ impl FromWorld for SpritePipeline {
                 ],
             });
 
+        let sprite_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(SpriteUniform::min_size()),
+                    },
+                    count: None,
+                }],
+                label: Some("sprite_uniform_layout"),
+            });
+
         let dummy_texture = {
             let texture = GpuTexture::from_raw_image(
                 &render_device,
@@ -112,58 +149,136 @@ impl FromWorld for SpritePipeline {
                 ],
             });
 
-        let pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
-            label: None,
-            layout: PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts: vec![model_layout.clone(), view_layout.clone(), texture_layout.clone()],
-                push_constant_ranges: Vec::new(),
-            },
-            vertex: VertexState {
-                shader: SPRITE_SHADER_HANDLE.typed(),
-                entry_point: Shader::VS_ENTRY_DEFAULT,
-                buffers: vec![Vertex::layout()],
-            },
-            fragment: Some(FragmentState {
-                shader: SPRITE_SHADER_HANDLE.typed(),
-                entry_point: Shader::FS_ENTRY_DEFAULT,
-                targets: vec![Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::engine_default(),
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::DepthTexture::DEPTH_FORMAT, // wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less, // 1.
-                stencil: wgpu::StencilState::default(),     // 2.
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
-
-        SpritePipeline {
-            pipeline_id,
+        let sprite_pipeline = SpritePipeline {
             model_layout,
             view_layout,
             texture_layout,
+            sprite_layout,
             dummy_texture,
             dummy_texture_bind_group,
+        };
+
+        for blend_mode in BlendMode::ALL {
+            let key = (*blend_mode, RasterKey::default());
+            let id = pipeline_cache.queue(sprite_pipeline.specialize(&render_device, key));
+            specialized_self.pipelines.insert(key, id);
+        }
+
+        sprite_pipeline
+    }
+}
+
+impl PipelineSpecialize for SpritePipeline {
+    type Key = (BlendMode, RasterKey);
+
+    fn specialize(&self, _render_device: &RenderDevice, key: Self::Key) -> RenderPipelineDescriptor {
+        sprite_pipeline_descriptor(
+            vec![
+                self.model_layout.clone(),
+                self.view_layout.clone(),
+                self.texture_layout.clone(),
+                self.sprite_layout.clone(),
+            ],
+            SPRITE_SHADER_HANDLE.typed(),
+            key,
+        )
+    }
+}
+
+/// Descriptor shared by the sprite pipelines, they only differ in bind group layouts and shader.
+pub fn sprite_pipeline_descriptor(
+    bind_group_layouts: Vec<BindGroupLayout>,
+    shader: Handle<Shader>,
+    (key, raster_key): (BlendMode, RasterKey),
+) -> RenderPipelineDescriptor {
+    RenderPipelineDescriptor {
+        label: None,
+        layout: PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts,
+            push_constant_ranges: if cfg!(feature = "push_constants") {
+                vec![MODEL_PUSH_CONSTANT_RANGE]
+            } else {
+                Vec::new()
+            },
+        },
+        vertex: VertexState {
+            shader: shader.clone(),
+            shader_defs: model_shader_defs(),
+            entry_point: Shader::VS_ENTRY_DEFAULT,
+            buffers: vec![Vertex::layout()],
+        },
+        fragment: Some(FragmentState {
+            shader,
+            shader_defs: [model_shader_defs(), key.shader_defs()].concat(),
+            entry_point: Shader::FS_ENTRY_DEFAULT,
+            targets: vec![Some(wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::engine_default(),
+                blend: Some(key.blend_state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: raster_key.primitive_state(wgpu::PrimitiveTopology::TriangleList),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DepthTexture::DEPTH_FORMAT, // wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: raster_key.depth_write_enabled(key),
+            depth_compare: wgpu::CompareFunction::Less, // 1.
+            stencil: wgpu::StencilState::default(),     // 2.
+            bias: raster_key.depth_bias.state(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    }
+}
+
+/// Queues the pipelines of sprites with a [`CullMode`], [`DepthBias`]
+/// or masked [`AlphaMode`], the default ones are queued up front.
+pub fn specialize_sprite_pipelines(
+    render_device: Res<RenderDevice>,
+    sprite_pipeline: Res<SpritePipeline>,
+    bindless_sprite_pipeline: Res<BindlessSpritePipeline>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    mut specialized_sprite_pipeline: ResMut<Specialized<SpritePipeline>>,
+    mut specialized_bindless_sprite_pipeline: ResMut<Specialized<BindlessSpritePipeline>>,
+    query: Query<
+        (
+            &RenderFunctionId,
+            Option<&BlendMode>,
+            Option<&CullMode>,
+            Option<&DepthBias>,
+            Option<&AlphaMode>,
+        ),
+        Or<(
+            Changed<CullMode>,
+            Changed<DepthBias>,
+            Changed<BlendMode>,
+            Changed<AlphaMode>,
+        )>,
+    >,
+) {
+    for (render_function, blend_mode, cull_mode, depth_bias, alpha_mode) in query.iter() {
+        let key = (
+            blend_mode.copied().unwrap_or(SPRITE_DEFAULT_BLEND_MODE),
+            RasterKey::new(cull_mode, depth_bias, alpha_mode),
+        );
+        if *render_function == BINDLESS_SPRITE_RENDER_FUNCTION.into() {
+            specialized_bindless_sprite_pipeline.specialize(
+                &mut pipeline_cache,
+                &bindless_sprite_pipeline,
+                &render_device,
+                key,
+            );
+        } else if *render_function == SPRITE_RENDER_FUNCTION.into() {
+            specialized_sprite_pipeline.specialize(
+                &mut pipeline_cache,
+                &sprite_pipeline,
+                &render_device,
+                key,
+            );
         }
     }
 }
@@ -172,6 +287,7 @@ impl FromWorld for SpritePipeline {
 pub struct SpriteBindGroups {
     pub model_bind_group: Option<wgpu::BindGroup>,
     pub view_bind_group: Option<wgpu::BindGroup>,
+    pub sprite_bind_group: Option<wgpu::BindGroup>,
 }
 
 pub fn create_sprite_bind_groups(
@@ -180,37 +296,68 @@ pub fn create_sprite_bind_groups(
     sprite_pipeline: Res<SpritePipeline>,
     model_uniforms: Res<ComponentUniforms<ModelUniform>>,
     view_uniforms: Res<ComponentUniforms<CameraUniforms>>,
+    globals_uniforms: Res<ComponentUniforms<GlobalsUniform>>,
+    sprite_uniforms: Res<ComponentUniforms<SpriteUniform>>,
 ) {
-    let Some(model_binding) = model_uniforms.binding() else {
+    let model_bind_group = if cfg!(feature = "push_constants") {
+        render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &sprite_pipeline.model_layout,
+            entries: &[],
+        })
+    } else {
+        let Some(model_binding) = model_uniforms.binding() else {
+            return;
+        };
+        render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &sprite_pipeline.model_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: model_binding,
+                },
+            ],
+        })
+    };
+
+    let (Some(view_binding), Some(globals_binding)) =
+        (view_uniforms.binding(), globals_uniforms.binding())
+    else {
         return;
     };
-    let model_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+    let view_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: None,
-        layout: &sprite_pipeline.model_layout,
+        layout: &sprite_pipeline.view_layout,
         entries: &[
             wgpu::BindGroupEntry {
                 binding: 0,
-                resource: model_binding,
+                resource: view_binding,
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: globals_binding,
             },
         ],
     });
 
-    let Some(view_binding) = view_uniforms.binding() else {
+    let Some(sprite_binding) = sprite_uniforms.binding() else {
         return;
     };
-    let view_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+    let sprite_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: None,
-        layout: &sprite_pipeline.view_layout,
+        layout: &sprite_pipeline.sprite_layout,
         entries: &[
             wgpu::BindGroupEntry {
                 binding: 0,
-                resource: view_binding,
+                resource: sprite_binding,
             },
         ],
     });
 
     sprite_bind_groups.model_bind_group = Some(model_bind_group);
     sprite_bind_groups.view_bind_group = Some(view_bind_group);
+    sprite_bind_groups.sprite_bind_group = Some(sprite_bind_group);
 }
 
 
@@ -222,7 +369,20 @@ pub fn create_texture_bind_groups(
     sprite_pipeline: Res<SpritePipeline>,
     mut texture_bind_groups: ResMut<TextureBindGroups>,
     render_images: Res<RenderAssets<Image>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
 ) {
+    // Modified images are prepared again, e.g. a resized render target, the old view is stale
+    for event in image_events.iter() {
+        match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => {
+                texture_bind_groups.remove(&handle.id());
+            }
+            AssetEvent::Created { .. } => {}
+        }
+    }
+    // Images evicted over the GPU memory budget are freed with their bind group
+    texture_bind_groups.retain(|handle_id, _| render_images.contains_key(handle_id));
+
     for (handle_id, gpu_image) in render_images.iter() {
         texture_bind_groups.entry(*handle_id).or_insert_with(|| {
             render_device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -0,0 +1,341 @@
+use bevy::{
+    asset::HandleId,
+    ecs::system::SystemState,
+    log::warn,
+    prelude::{Entity, FromWorld, Handle, Query, Res, ResMut, Resource, World},
+    utils::HashMap,
+};
+
+use crate::render::{
+    blend::BlendMode,
+    command::{DrawMesh, RenderCommand},
+    phase::RenderOrdering,
+    raster::RasterKey,
+    resource::{
+        buffer::Vertex,
+        pipeline::{BindGroupLayout, PipelineCache, RenderPipelineDescriptor},
+        renderer::RenderDevice,
+        specialized_pipeline::{PipelineSpecialize, Specialized},
+    },
+    stats::RenderStats,
+    system::{RenderFunctionId, RenderResult},
+    texture::Image,
+    RenderAssets,
+};
+
+use super::{
+    bind::{sprite_pipeline_descriptor, SpritePipeline},
+    set_sprite_bind_groups, CountSpriteDraw, SPRITE_BINDLESS_SHADER_HANDLE,
+    SPRITE_DEFAULT_BLEND_MODE,
+};
+
+pub const BINDLESS_SPRITE_RENDER_FUNCTION: usize = 5;
+/// Cap of the texture binding array, the bind group is rebuilt with every view each frame.
+pub const BINDLESS_TEXTURE_COUNT_MAX: u32 = 1024;
+
+/// Size of the texture binding array on the device, slot 0 holds the dummy texture.
+pub fn bindless_texture_count(render_device: &RenderDevice) -> u32 {
+    render_device
+        .limits()
+        .max_sampled_textures_per_shader_stage
+        .min(BINDLESS_TEXTURE_COUNT_MAX)
+}
+
+///
+/// Sprite path binding every sprite texture at once in a `binding_array`,
+/// the sprite uniform carries the slot to sample from.
+///
+/// The texture bind group stays the same across draws so sprites with different textures
+/// do not switch it. Use it by giving a sprite the [`BINDLESS_SPRITE_RENDER_FUNCTION`].
+/// Textures past [`bindless_texture_count`] fall back to the dummy texture.
+///
+pub type DrawBindlessSprite = (
+    SetBindlessSpritePipeline,
+    SetBindlessSpriteBindGroups,
+    DrawMesh<Vertex>,
+    CountSpriteDraw,
+);
+
+/// Texture slots of the bindless sprites, reassigned every frame.
+#[derive(Resource)]
+pub struct BindlessTextures {
+    count: u32,
+    slots: Vec<HandleId>,
+    indices: HashMap<HandleId, u32>,
+    overflowed: bool,
+}
+
+impl FromWorld for BindlessTextures {
+    fn from_world(world: &mut World) -> Self {
+        Self::with_count(bindless_texture_count(world.resource::<RenderDevice>()))
+    }
+}
+
+impl BindlessTextures {
+    /// Slots of a binding array of `count` textures, the dummy one included.
+    pub fn with_count(count: u32) -> Self {
+        Self {
+            count,
+            slots: Vec::new(),
+            indices: HashMap::new(),
+            overflowed: false,
+        }
+    }
+
+    /// Size of the binding array, the dummy texture included.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Slot of the texture in the binding array, 0 (dummy) if it has none.
+    pub fn index(&self, handle_id: &HandleId) -> u32 {
+        self.indices.get(handle_id).copied().unwrap_or(0)
+    }
+
+    pub fn slots(&self) -> &[HandleId] {
+        &self.slots
+    }
+
+    fn clear(&mut self) {
+        self.slots.clear();
+        self.indices.clear();
+        self.overflowed = false;
+    }
+
+    fn insert(&mut self, handle_id: HandleId) {
+        if self.indices.contains_key(&handle_id) {
+            return;
+        }
+        if self.slots.len() + 1 >= self.count as usize {
+            if !self.overflowed {
+                warn!(
+                    "More than {} bindless sprite textures, the rest use the dummy texture",
+                    self.count - 1
+                );
+                self.overflowed = true;
+            }
+            return;
+        }
+        self.slots.push(handle_id);
+        self.indices.insert(handle_id, self.slots.len() as u32);
+    }
+}
+
+pub fn prepare_bindless_textures(
+    render_ordering: Res<RenderOrdering>,
+    mut bindless_textures: ResMut<BindlessTextures>,
+    query: Query<(Entity, &Handle<Image>, &RenderFunctionId)>,
+) {
+    bindless_textures.clear();
+    let mut textures: Vec<(Entity, HandleId)> = query
+        .iter()
+        .filter(|(_, _, render_function)| {
+            **render_function == BINDLESS_SPRITE_RENDER_FUNCTION.into()
+        })
+        .map(|(entity, image_handle, _)| (entity, image_handle.id()))
+        .collect();
+    // Slots past the array fall back to the dummy texture, which ones depends on the order
+    if render_ordering.deterministic {
+        textures.sort_unstable_by_key(|(entity, _)| *entity);
+    }
+    for (_, handle_id) in textures {
+        bindless_textures.insert(handle_id);
+    }
+}
+
+#[derive(Resource)]
+pub struct BindlessSpritePipeline {
+    pub model_layout: BindGroupLayout,
+    pub view_layout: BindGroupLayout,
+    pub texture_array_layout: BindGroupLayout,
+    pub sprite_layout: BindGroupLayout,
+}
+
+impl FromWorld for BindlessSpritePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<SpritePipeline>,
+            ResMut<PipelineCache>,
+            ResMut<Specialized<Self>>,
+        )> = SystemState::new(world);
+        let (render_device, sprite_pipeline, mut pipeline_cache, mut specialized_self) =
+            state.get_mut(world);
+
+        let texture_array_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("bindless_sprite_texture_array_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: std::num::NonZeroU32::new(bindless_texture_count(&render_device)),
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let bindless_sprite_pipeline = BindlessSpritePipeline {
+            model_layout: sprite_pipeline.model_layout.clone(),
+            view_layout: sprite_pipeline.view_layout.clone(),
+            texture_array_layout,
+            sprite_layout: sprite_pipeline.sprite_layout.clone(),
+        };
+
+        for blend_mode in BlendMode::ALL {
+            let key = (*blend_mode, RasterKey::default());
+            let id = pipeline_cache.queue(bindless_sprite_pipeline.specialize(&render_device, key));
+            specialized_self.pipelines.insert(key, id);
+        }
+
+        bindless_sprite_pipeline
+    }
+}
+
+impl PipelineSpecialize for BindlessSpritePipeline {
+    type Key = (BlendMode, RasterKey);
+
+    fn specialize(&self, _render_device: &RenderDevice, key: Self::Key) -> RenderPipelineDescriptor {
+        sprite_pipeline_descriptor(
+            vec![
+                self.model_layout.clone(),
+                self.view_layout.clone(),
+                self.texture_array_layout.clone(),
+                self.sprite_layout.clone(),
+            ],
+            SPRITE_BINDLESS_SHADER_HANDLE.typed(),
+            key,
+        )
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct BindlessTextureBindGroup(pub Option<wgpu::BindGroup>);
+
+pub fn create_bindless_texture_bind_group(
+    render_device: Res<RenderDevice>,
+    sprite_pipeline: Res<SpritePipeline>,
+    bindless_sprite_pipeline: Res<BindlessSpritePipeline>,
+    bindless_textures: Res<BindlessTextures>,
+    render_images: Res<RenderAssets<Image>>,
+    mut bindless_texture_bind_group: ResMut<BindlessTextureBindGroup>,
+) {
+    let dummy_view = &sprite_pipeline.dummy_texture.view;
+    let mut views = vec![dummy_view; bindless_textures.count() as usize];
+    for (slot, handle_id) in bindless_textures.slots().iter().enumerate() {
+        if let Some(gpu_image) = render_images.get(handle_id) {
+            views[slot + 1] = &gpu_image.view;
+        }
+    }
+
+    bindless_texture_bind_group.0 = Some(render_device.create_bind_group(
+        &wgpu::BindGroupDescriptor {
+            label: Some("bindless_sprite_texture_bind_group"),
+            layout: &bindless_sprite_pipeline.texture_array_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureViewArray(&views),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(
+                        &sprite_pipeline.dummy_texture.sampler,
+                    ),
+                },
+            ],
+        },
+    ));
+}
+
+pub struct SetBindlessSpritePipeline;
+impl RenderCommand for SetBindlessSpritePipeline {
+    fn render<'w>(
+        _camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(specialized_pipeline) =
+            world.get_resource::<Specialized<BindlessSpritePipeline>>()
+        else {
+            return RenderResult::Failure("Specialized<BindlessSpritePipeline> resource missing");
+        };
+        let Some(pipeline_cache) = world.get_resource::<PipelineCache>() else {
+            return RenderResult::Failure("PipelineCache resource missing");
+        };
+
+        let blend_mode = world
+            .get::<BlendMode>(object)
+            .copied()
+            .unwrap_or(SPRITE_DEFAULT_BLEND_MODE);
+        let raster_key = RasterKey::of(world, object);
+        let Some(pipeline_id) = specialized_pipeline.pipelines.get(&(blend_mode, raster_key)) else {
+            return RenderResult::Failure("pipeline not specialized");
+        };
+        let Some(render_pipeline) = pipeline_cache.get(pipeline_id) else {
+            return RenderResult::Failure("pipeline not compiled yet");
+        };
+        render_pass.set_pipeline(render_pipeline);
+        let Some(render_stats) = world.get_resource::<RenderStats>() else {
+            return RenderResult::Failure("RenderStats resource missing");
+        };
+        render_stats.pipeline_switch();
+
+        RenderResult::Success
+    }
+}
+
+/// Binds the sprite bind groups with the shared texture array at group 2.
+pub struct SetBindlessSpriteBindGroups;
+impl RenderCommand for SetBindlessSpriteBindGroups {
+    fn render<'w>(
+        camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(bindless_texture_bind_group) =
+            world.get_resource::<BindlessTextureBindGroup>()
+        else {
+            return RenderResult::Failure("BindlessTextureBindGroup resource missing");
+        };
+        let Some(texture_bind_group) = bindless_texture_bind_group.0.as_ref() else {
+            return RenderResult::Failure("bindless texture bind group not created");
+        };
+
+        set_sprite_bind_groups(camera, object, world, render_pass, texture_bind_group)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overflow_falls_back_to_the_dummy_slot_until_cleared() {
+        let mut bindless_textures = BindlessTextures::with_count(3);
+        let handles: Vec<HandleId> = (0..3).map(|_| HandleId::random::<Image>()).collect();
+        for handle_id in &handles {
+            bindless_textures.insert(*handle_id);
+        }
+        assert_eq!(bindless_textures.index(&handles[0]), 1);
+        assert_eq!(bindless_textures.index(&handles[1]), 2);
+        assert_eq!(bindless_textures.index(&handles[2]), 0);
+        assert!(bindless_textures.overflowed);
+
+        bindless_textures.clear();
+        assert!(!bindless_textures.overflowed);
+        assert!(bindless_textures.slots().is_empty());
+    }
+}
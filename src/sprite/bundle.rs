@@ -24,8 +24,8 @@ impl Default for SpriteBundle {
             transform: Transform::default(),
             mesh: Handle::default(),
             texture: Handle::default(),
-            color: Color::WHITE,
-            visibility: Visibility { visible: true },
+            color: Color::NO_TINT,
+            visibility: Visibility::Inherited,
             render_function: SPRITE_RENDER_FUNCTION.into(),
         }
     }
@@ -0,0 +1,655 @@
+//!
+//! Glowing sprites: neon signs, projectiles, magic.
+//!
+//! Cameras with [`SpriteGlow`] draw the [`SpriteEmissive`] of their sprites into a glow
+//! target, separate from what they drew, blur it and add it over their target.
+//! Only emissive sprites bloom, the bright parts of the rest of the scene do not.
+//!
+//! Emissive sprites behind opaque draws are hidden by the depth of the camera.
+//!
+
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{
+        Assets, Commands, Component, Deref, DerefMut, Entity, FromWorld, GlobalTransform, Handle,
+        Mat4, Query, Res, ResMut, Resource, Vec2, Vec4, With, World,
+    },
+    utils::HashMap,
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        camera::component::{
+            layers_intersect, Camera, CameraUniforms, ExtractedVisibility, RenderLayers,
+        },
+        cleanup::EntityRenderState,
+        color::{Color, ColorSpace},
+        mesh::{GpuMeshAssembly, Mesh},
+        pass::CameraAttachments,
+        post::begin_post_pass,
+        resource::{
+            buffer::{MeshVertex, Vertex},
+            buffer_pool::BufferPool,
+            component_uniform::ComponentUniforms,
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, RenderPipelineId, VertexState,
+            },
+            renderer::{RenderDevice, RenderQueue},
+            shader::Shader,
+            uniform::{DynamicUniformId, HandleGpuUniform},
+        },
+        texture::{DepthTexture, Image},
+        view::{grab::GrabTextureLayout, window::PreparedWindows},
+        RenderAssets,
+    },
+    util::EngineDefault,
+};
+
+use super::{
+    bind::{SpritePipeline, TextureBindGroups},
+    light::HdrTexture,
+    uniform::{SpriteRect, SpriteTiling},
+    SPRITE_EMISSIVE_SHADER_HANDLE, SPRITE_GLOW_SHADER_HANDLE,
+};
+
+/// Light a sprite gives off, only cameras with [`SpriteGlow`] draw it.
+#[derive(Component, Clone, Debug)]
+pub struct SpriteEmissive {
+    /// The alpha is ignored
+    pub color: Color,
+    /// Above 1 blooms wider and brighter
+    pub intensity: f32,
+    /// Multiplies the color, without it the alpha of the sprite texture masks the color
+    pub texture: Option<Handle<Image>>,
+}
+
+impl Default for SpriteEmissive {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            intensity: 1.0,
+            texture: None,
+        }
+    }
+}
+
+/// 2D bloom of the [`SpriteEmissive`]s the camera sees.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SpriteGlow {
+    /// Brightness of the glow added to the target
+    pub intensity: f32,
+    /// Blur radius in pixels
+    pub radius: f32,
+}
+
+impl Default for SpriteGlow {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            radius: 16.0,
+        }
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct SpriteGlowUniform {
+    intensity: f32,
+    radius: f32,
+}
+
+impl HandleGpuUniform for SpriteGlow {
+    type GU = SpriteGlowUniform;
+
+    fn into_uniform(&self) -> Self::GU {
+        SpriteGlowUniform {
+            intensity: self.intensity.max(0.0),
+            radius: self.radius.max(0.0),
+        }
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct SpriteEmissiveUniform {
+    model: Mat4,
+    color: Vec4,
+    /// Same as the sprite uniform, the mask follows the sprite texture
+    rect: Vec4,
+    tiling: Vec2,
+    has_texture: u32,
+}
+
+/// Emissive sprites of the frame, drawn by every camera with [`SpriteGlow`].
+#[derive(Resource)]
+pub struct EmissiveSprites {
+    pub sprites: Vec<Entity>,
+    pub uniforms: ComponentUniforms<SpriteEmissiveUniform>,
+}
+
+impl FromWorld for EmissiveSprites {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            sprites: Vec::new(),
+            uniforms: ComponentUniforms::from_world(world),
+        }
+    }
+}
+
+pub fn prepare_emissive_sprites(
+    mut commands: Commands,
+    mut emissive_sprites: ResMut<EmissiveSprites>,
+    color_space: Res<ColorSpace>,
+    query: Query<
+        (
+            Entity,
+            &SpriteEmissive,
+            &GlobalTransform,
+            Option<&SpriteRect>,
+            Option<&SpriteTiling>,
+            Option<&ExtractedVisibility>,
+        ),
+        With<Handle<Mesh<Vertex>>>,
+    >,
+) {
+    let mut spawns: Vec<(Entity, DynamicUniformId<SpriteEmissiveUniform>)> = Vec::new();
+
+    let emissive_sprites = emissive_sprites.as_mut();
+    emissive_sprites.sprites.clear();
+    emissive_sprites.uniforms.clear();
+    for (entity, emissive, global_transform, sprite_rect, sprite_tiling, computed_visibility) in
+        query.iter()
+    {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        let color = emissive.color.as_gpu_vec(*color_space).truncate() * emissive.intensity.max(0.0);
+        let mut uniform = SpriteEmissiveUniform {
+            model: global_transform.compute_matrix(),
+            color: color.extend(1.0),
+            rect: Vec4::ZERO,
+            tiling: Vec2::ZERO,
+            has_texture: emissive.texture.is_some() as u32,
+        };
+        if let Some(sprite_rect) = sprite_rect {
+            uniform.rect = Vec4::new(
+                sprite_rect.min.x,
+                sprite_rect.min.y,
+                sprite_rect.width(),
+                sprite_rect.height(),
+            );
+        }
+        if let Some(sprite_tiling) = sprite_tiling {
+            let (scale, _, _) = global_transform.to_scale_rotation_translation();
+            uniform.tiling = (scale.truncate() / sprite_tiling.tile_size).abs();
+        }
+        spawns.push((entity, emissive_sprites.uniforms.push(uniform).into()));
+        emissive_sprites.sprites.push(entity);
+    }
+
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn queue_emissive_sprites(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffer_pool: ResMut<BufferPool>,
+    mut emissive_sprites: ResMut<EmissiveSprites>,
+) {
+    emissive_sprites
+        .uniforms
+        .write_buffer_pooled(&render_device, &render_queue, &mut buffer_pool);
+}
+
+#[derive(Resource)]
+pub struct SpriteGlowPipeline {
+    pub view_layout: BindGroupLayout,
+    pub emissive_layout: BindGroupLayout,
+    pub glow_layout: BindGroupLayout,
+    /// Draws the emissive sprites into the glow target
+    pub emissive_pipeline_id: RenderPipelineId,
+    pub blur_horizontal_pipeline_id: RenderPipelineId,
+    pub blur_vertical_pipeline_id: RenderPipelineId,
+    /// Adds the blurred glow over the camera target
+    pub composite_pipeline_id: RenderPipelineId,
+}
+
+impl SpriteGlowPipeline {
+    fn glow_descriptor(
+        layouts: Vec<BindGroupLayout>,
+        label: &'static str,
+        entry_point: &'static str,
+        target: wgpu::ColorTargetState,
+    ) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some(label),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: layouts,
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: SPRITE_GLOW_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: SPRITE_GLOW_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point,
+                targets: vec![Some(target)],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        }
+    }
+}
+
+fn uniform_layout_entry(
+    visibility: wgpu::ShaderStages,
+    min_binding_size: wgpu::BufferSize,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: true,
+            min_binding_size: Some(min_binding_size),
+        },
+        count: None,
+    }
+}
+
+impl FromWorld for SpriteGlowPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<GrabTextureLayout>,
+            Res<SpritePipeline>,
+            ResMut<PipelineCache>,
+        )> = SystemState::new(world);
+        let (render_device, grab_layout, sprite_pipeline, mut pipeline_cache) =
+            state.get_mut(world);
+
+        let create_layout = |label: &str, entry: wgpu::BindGroupLayoutEntry| -> BindGroupLayout {
+            render_device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(label),
+                    entries: &[entry],
+                })
+                .into()
+        };
+        let view_layout = create_layout(
+            "sprite_glow_view_layout",
+            uniform_layout_entry(wgpu::ShaderStages::VERTEX, CameraUniforms::min_size()),
+        );
+        let emissive_layout = create_layout(
+            "sprite_emissive_layout",
+            uniform_layout_entry(
+                wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                SpriteEmissiveUniform::min_size(),
+            ),
+        );
+        let glow_layout = create_layout(
+            "sprite_glow_layout",
+            uniform_layout_entry(wgpu::ShaderStages::FRAGMENT, SpriteGlowUniform::min_size()),
+        );
+
+        let emissive_pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("sprite_emissive_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![
+                    view_layout.clone(),
+                    emissive_layout.clone(),
+                    sprite_pipeline.texture_layout.clone(),
+                    sprite_pipeline.texture_layout.clone(),
+                ],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: SPRITE_EMISSIVE_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: vec![Vertex::layout()],
+            },
+            fragment: Some(FragmentState {
+                shader: SPRITE_EMISSIVE_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: HdrTexture::FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent::REPLACE,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Tested against what the camera drew, never written
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let blur_layouts = vec![grab_layout.layout.clone(), glow_layout.clone()];
+        let blur_target = wgpu::ColorTargetState {
+            format: HdrTexture::FORMAT,
+            blend: Some(wgpu::BlendState::REPLACE),
+            write_mask: wgpu::ColorWrites::ALL,
+        };
+        let blur_horizontal_pipeline_id = pipeline_cache.queue(Self::glow_descriptor(
+            blur_layouts.clone(),
+            "sprite_glow_blur_horizontal_pipeline",
+            "fs_blur_horizontal",
+            blur_target.clone(),
+        ));
+        let blur_vertical_pipeline_id = pipeline_cache.queue(Self::glow_descriptor(
+            blur_layouts.clone(),
+            "sprite_glow_blur_vertical_pipeline",
+            "fs_blur_vertical",
+            blur_target,
+        ));
+        // Adds the color, keeps the alpha of the target
+        let composite_pipeline_id = pipeline_cache.queue(Self::glow_descriptor(
+            blur_layouts,
+            "sprite_glow_composite_pipeline",
+            "fs_composite",
+            wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::engine_default(),
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Zero,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+        ));
+
+        Self {
+            view_layout,
+            emissive_layout,
+            glow_layout,
+            emissive_pipeline_id,
+            blur_horizontal_pipeline_id,
+            blur_vertical_pipeline_id,
+            composite_pipeline_id,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SpriteGlowBindGroups {
+    pub view_bind_group: Option<wgpu::BindGroup>,
+    pub emissive_bind_group: Option<wgpu::BindGroup>,
+    pub glow_bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn create_sprite_glow_bind_groups(
+    render_device: Res<RenderDevice>,
+    glow_pipeline: Res<SpriteGlowPipeline>,
+    emissive_sprites: Res<EmissiveSprites>,
+    camera_uniforms: Res<ComponentUniforms<CameraUniforms>>,
+    glow_uniforms: Res<ComponentUniforms<SpriteGlowUniform>>,
+    mut bind_groups: ResMut<SpriteGlowBindGroups>,
+) {
+    let create_bind_group = |layout: &BindGroupLayout, resource: wgpu::BindingResource| {
+        render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource,
+            }],
+        })
+    };
+    bind_groups.view_bind_group = camera_uniforms
+        .binding()
+        .map(|binding| create_bind_group(&glow_pipeline.view_layout, binding));
+    bind_groups.emissive_bind_group = emissive_sprites
+        .uniforms
+        .binding()
+        .map(|binding| create_bind_group(&glow_pipeline.emissive_layout, binding));
+    bind_groups.glow_bind_group = glow_uniforms
+        .binding()
+        .map(|binding| create_bind_group(&glow_pipeline.glow_layout, binding));
+}
+
+/// Glow target of a camera and the target its blur goes through.
+pub struct GlowTexture {
+    pub glow: HdrTexture,
+    pub blur: HdrTexture,
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct GlowTextures(pub HashMap<Entity, GlowTexture>);
+
+impl EntityRenderState for GlowTextures {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(&entity);
+    }
+}
+
+/// Creates the glow textures of the cameras with [`SpriteGlow`] at the size of their target.
+pub fn prepare_glow_textures(
+    render_device: Res<RenderDevice>,
+    layout: Res<GrabTextureLayout>,
+    images: Res<Assets<Image>>,
+    windows: Res<PreparedWindows>,
+    mut glow_textures: ResMut<GlowTextures>,
+    cameras: Query<(Entity, &Camera), With<SpriteGlow>>,
+) {
+    for (entity, camera) in cameras.iter() {
+        let Some(size) = camera.render_target.get_size(&images, &windows) else {
+            continue;
+        };
+        if size.0 == 0 || size.1 == 0 {
+            continue;
+        }
+        if glow_textures.get(&entity).map(|glow| glow.glow.size) == Some(size) {
+            continue;
+        }
+        glow_textures.insert(
+            entity,
+            GlowTexture {
+                glow: HdrTexture::create(&render_device, &layout, "sprite_glow_texture", size),
+                blur: HdrTexture::create(&render_device, &layout, "sprite_glow_blur_texture", size),
+            },
+        );
+    }
+}
+
+fn begin_glow_pass<'a>(
+    label: &'static str,
+    target: &'a HdrTexture,
+    command_encoder: &'a mut wgpu::CommandEncoder,
+) -> wgpu::RenderPass<'a> {
+    command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &target.view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    })
+}
+
+/// Camera pass drawing the emissive sprites of cameras with [`SpriteGlow`] and blooming them.
+pub fn encode_sprite_glow<'w>(
+    camera: Entity,
+    world: &'w World,
+    attachments: &CameraAttachments<'w>,
+    command_encoder: &mut wgpu::CommandEncoder,
+) {
+    if !world.entity(camera).contains::<SpriteGlow>() {
+        return;
+    }
+    let Some(depth) = attachments.depth else {
+        return;
+    };
+    let glow_textures = world.get_resource::<GlowTextures>().unwrap();
+    let Some(glow_texture) = glow_textures
+        .get(&camera)
+        .filter(|glow_texture| glow_texture.glow.size == attachments.size)
+    else {
+        return;
+    };
+    let glow_pipeline = world.get_resource::<SpriteGlowPipeline>().unwrap();
+    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
+    let (
+        Some(emissive_pipeline),
+        Some(blur_horizontal_pipeline),
+        Some(blur_vertical_pipeline),
+        Some(composite_pipeline),
+    ) = (
+        pipeline_cache.get(&glow_pipeline.emissive_pipeline_id),
+        pipeline_cache.get(&glow_pipeline.blur_horizontal_pipeline_id),
+        pipeline_cache.get(&glow_pipeline.blur_vertical_pipeline_id),
+        pipeline_cache.get(&glow_pipeline.composite_pipeline_id),
+    )
+    else {
+        return;
+    };
+    let bind_groups = world.get_resource::<SpriteGlowBindGroups>().unwrap();
+    let (Some(view_bind_group), Some(glow_bind_group)) = (
+        bind_groups.view_bind_group.as_ref(),
+        bind_groups.glow_bind_group.as_ref(),
+    ) else {
+        return;
+    };
+    let (Some(view_uniform_id), Some(glow_uniform_id)) = (
+        world.get::<DynamicUniformId<CameraUniforms>>(camera),
+        world.get::<DynamicUniformId<SpriteGlowUniform>>(camera),
+    ) else {
+        return;
+    };
+
+    {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("sprite_emissive_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &glow_texture.glow.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        let emissive_sprites = world.get_resource::<EmissiveSprites>().unwrap();
+        if let Some(emissive_bind_group) = bind_groups.emissive_bind_group.as_ref() {
+            let sprite_pipeline = world.get_resource::<SpritePipeline>().unwrap();
+            let texture_bind_groups = world.get_resource::<TextureBindGroups>().unwrap();
+            let gpu_meshes = world.get_resource::<RenderAssets<Mesh<Vertex>>>().unwrap();
+            let texture_bind_group = |handle: Option<&Handle<Image>>| {
+                handle
+                    .and_then(|handle| texture_bind_groups.get(&handle.id()))
+                    .unwrap_or(&sprite_pipeline.dummy_texture_bind_group)
+            };
+            let camera_layers = world.get::<RenderLayers>(camera);
+
+            render_pass.set_pipeline(emissive_pipeline);
+            render_pass.set_bind_group(0, view_bind_group, &[**view_uniform_id]);
+            for sprite in &emissive_sprites.sprites {
+                if !layers_intersect(world.get::<RenderLayers>(*sprite), camera_layers) {
+                    continue;
+                }
+                let (Some(uniform_id), Some(emissive), Some(mesh_handle)) = (
+                    world.get::<DynamicUniformId<SpriteEmissiveUniform>>(*sprite),
+                    world.get::<SpriteEmissive>(*sprite),
+                    world.get::<Handle<Mesh<Vertex>>>(*sprite),
+                ) else {
+                    continue;
+                };
+                let Some(mesh) = gpu_meshes.get(&mesh_handle.id()) else {
+                    continue;
+                };
+                render_pass.set_bind_group(1, emissive_bind_group, &[**uniform_id]);
+                render_pass.set_bind_group(
+                    2,
+                    texture_bind_group(world.get::<Handle<Image>>(*sprite)),
+                    &[],
+                );
+                render_pass.set_bind_group(3, texture_bind_group(emissive.texture.as_ref()), &[]);
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                match &mesh.assembly {
+                    GpuMeshAssembly::Indexed {
+                        index_buffer,
+                        index_count,
+                        index_format,
+                    } => {
+                        render_pass.set_index_buffer(index_buffer.slice(..), *index_format);
+                        render_pass.draw_indexed(0..*index_count as u32, 0, 0..1);
+                    }
+                    GpuMeshAssembly::NonIndexed { vertex_count } => {
+                        render_pass.draw(0..*vertex_count as u32, 0..1);
+                    }
+                }
+            }
+        }
+    }
+
+    {
+        let mut render_pass = begin_glow_pass(
+            "sprite_glow_blur_horizontal_pass",
+            &glow_texture.blur,
+            command_encoder,
+        );
+        render_pass.set_pipeline(blur_horizontal_pipeline);
+        render_pass.set_bind_group(0, &glow_texture.glow.bind_group, &[]);
+        render_pass.set_bind_group(1, glow_bind_group, &[**glow_uniform_id]);
+        render_pass.draw(0..3, 0..1);
+    }
+    {
+        let mut render_pass = begin_glow_pass(
+            "sprite_glow_blur_vertical_pass",
+            &glow_texture.glow,
+            command_encoder,
+        );
+        render_pass.set_pipeline(blur_vertical_pipeline);
+        render_pass.set_bind_group(0, &glow_texture.blur.bind_group, &[]);
+        render_pass.set_bind_group(1, glow_bind_group, &[**glow_uniform_id]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    let mut render_pass =
+        begin_post_pass("sprite_glow_composite_pass", attachments, command_encoder);
+    render_pass.set_pipeline(composite_pipeline);
+    render_pass.set_bind_group(0, &glow_texture.glow.bind_group, &[]);
+    render_pass.set_bind_group(1, glow_bind_group, &[**glow_uniform_id]);
+    render_pass.draw(0..3, 0..1);
+}
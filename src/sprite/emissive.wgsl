@@ -0,0 +1,85 @@
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+}
+
+struct Emissive {
+    model: mat4x4<f32>,
+    color: vec4<f32>,
+    // (x, y, width, height) in pixels of the sprite texture, zero size means the whole texture
+    rect: vec4<f32>,
+    // repeat count on each axis, zero means no tiling
+    tiling: vec2<f32>,
+    has_texture: u32,
+}
+
+struct VertexInput {
+    @location(0)    position: vec3<f32>,
+    @location(1)    uv: vec2<f32>,
+    @location(2)    color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> emissive: Emissive;
+
+// Sprite texture, its alpha masks the glow
+@group(2) @binding(0)
+var t_sprite: texture_2d<f32>;
+@group(2) @binding(1)
+var s_sprite: sampler;
+
+@group(3) @binding(0)
+var t_emissive: texture_2d<f32>;
+@group(3) @binding(1)
+var s_emissive: sampler;
+
+// Same as the sprite shader, the depth matches what it drew
+@vertex
+fn vs_main(
+    vertex: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.clip_position = camera.view_proj * emissive.model * vec4<f32>(vertex.position, 1.0);
+    out.uv = vertex.uv;
+
+    return out;
+}
+
+fn tiled_uv(in_uv: vec2<f32>) -> vec2<f32> {
+    if (emissive.tiling.x > 0.0 && emissive.tiling.y > 0.0) {
+        return fract(in_uv * emissive.tiling);
+    }
+    return in_uv;
+}
+
+fn sprite_uv(uv: vec2<f32>) -> vec2<f32> {
+    if (emissive.rect.z <= 0.0 || emissive.rect.w <= 0.0) {
+        return uv;
+    }
+    let dimensions = vec2<f32>(textureDimensions(t_sprite));
+    return (emissive.rect.xy + uv * emissive.rect.zw) / dimensions;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = tiled_uv(in.uv);
+    let mask = textureSample(t_sprite, s_sprite, sprite_uv(uv)).a;
+    // Sampled before branching, textureSample needs uniform control flow
+    let emissive_texture = textureSample(t_emissive, s_emissive, uv);
+
+    var glow = emissive.color.rgb * mask;
+    if (emissive.has_texture != 0u) {
+        glow = emissive.color.rgb * emissive_texture.rgb * emissive_texture.a;
+    }
+    return vec4<f32>(glow, 1.0);
+}
@@ -0,0 +1,69 @@
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        uv: vec2<f32>,
+}
+
+// One triangle covering the target
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+
+    return out;
+}
+
+struct Glow {
+    intensity: f32,
+    radius: f32,
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(1) @binding(0)
+var<uniform> glow: Glow;
+
+// Taps on each side of the pixel, spread over the radius
+let SAMPLES: i32 = 8;
+
+// Gaussian blur along `direction`, the linear sampler averages between taps
+fn blur(uv: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+    let spacing = max(glow.radius / f32(SAMPLES), 1.0);
+    let sigma = max(glow.radius * 0.5, 1.0);
+
+    var sum = textureSampleLevel(t_source, s_source, uv, 0.0).rgb;
+    var total = 1.0;
+    for (var i = 1; i <= SAMPLES; i = i + 1) {
+        let d = f32(i) * spacing;
+        let weight = exp(-(d * d) / (2.0 * sigma * sigma));
+        let offset = direction * texel * d;
+        sum = sum + textureSampleLevel(t_source, s_source, uv + offset, 0.0).rgb * weight;
+        sum = sum + textureSampleLevel(t_source, s_source, uv - offset, 0.0).rgb * weight;
+        total = total + 2.0 * weight;
+    }
+    return vec4<f32>(sum / total, 1.0);
+}
+
+@fragment
+fn fs_blur_horizontal(in: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_vertical(in: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2<f32>(0.0, 1.0));
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_source, s_source, in.uv).rgb;
+    return vec4<f32>(color * glow.intensity, 0.0);
+}
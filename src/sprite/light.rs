@@ -0,0 +1,707 @@
+//!
+//! 2D lights for top-down and side-view games.
+//!
+//! Cameras with [`Lighting2d`] draw every [`Light2d`] into a light texture the size
+//! of their target, starting from black, then multiply what they drew by the ambient light
+//! plus the light texture. Lights with `shadows` are blocked by [`Occluder2d`] boxes.
+//!
+//! Everything the camera drew is lit, draw unlit overlays (UI, debug shapes)
+//! with another camera after it.
+//!
+
+use bevy::{
+    ecs::system::SystemState,
+    log::warn,
+    prelude::{
+        Assets, Commands, Component, Deref, DerefMut, Entity, FromWorld, GlobalTransform, Local,
+        Query, Res, ResMut, Resource, Vec2, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles, With, World,
+    },
+    utils::HashMap,
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        camera::component::{Camera, CameraUniforms, ExtractedVisibility},
+        cleanup::EntityRenderState,
+        color::{Color, ColorSpace},
+        pass::CameraAttachments,
+        post::{begin_post_pass, copy_to_post_texture},
+        resource::{
+            buffer_pool::BufferPool,
+            component_uniform::ComponentUniforms,
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, RenderPipelineId, VertexState,
+            },
+            renderer::{RenderDevice, RenderQueue},
+            shader::Shader,
+            uniform::{DynamicUniformId, HandleGpuUniform, UniformBuffer},
+        },
+        texture::Image,
+        view::{grab::GrabTextureLayout, window::PreparedWindows},
+    },
+    util::EngineDefault,
+};
+
+use super::{LIGHT_2D_COMPOSITE_SHADER_HANDLE, LIGHT_2D_SHADER_HANDLE};
+
+/// Occluders past this count do not cast shadows.
+pub const MAX_OCCLUDERS_2D: usize = 128;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightShape2d {
+    Point,
+    /// Lights along the X axis of the entity, rotate it to aim.
+    /// `half_angle` in radians, from the axis to the edge of the cone
+    Cone {
+        half_angle: f32,
+    },
+}
+
+/// A light at the position of the entity, fading out towards `radius`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Light2d {
+    pub shape: LightShape2d,
+    /// The alpha is ignored
+    pub color: Color,
+    pub intensity: f32,
+    /// World units
+    pub radius: f32,
+    /// Whether [`Occluder2d`]s block it, hard shadows
+    pub shadows: bool,
+}
+
+impl Default for Light2d {
+    fn default() -> Self {
+        Self {
+            shape: LightShape2d::Point,
+            color: Color::WHITE,
+            intensity: 1.0,
+            radius: 200.0,
+            shadows: false,
+        }
+    }
+}
+
+/// A box blocking the lights with shadows, `half_size` in the local space of the entity.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Occluder2d {
+    pub half_size: Vec2,
+}
+
+impl Occluder2d {
+    /// Whether the box blocks the segment `from` to `to`, what the shader does.
+    /// A point inside the box is not blocked by it, occluders are lit themselves.
+    pub fn blocks(&self, transform: &GlobalTransform, from: Vec2, to: Vec2) -> bool {
+        let data = Occluder2dData::new(self, transform);
+        data.blocks(from, to)
+    }
+}
+
+/// Lights the camera draws with, without it lights are ignored.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Lighting2d {
+    /// Light everywhere, black is only the lights
+    pub ambient: Color,
+}
+
+impl Default for Lighting2d {
+    fn default() -> Self {
+        Self {
+            ambient: Color::rgb(0.1, 0.1, 0.15),
+        }
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct Lighting2dUniform {
+    ambient: Vec4,
+}
+
+impl HandleGpuUniform for Lighting2d {
+    type GU = Lighting2dUniform;
+
+    fn into_uniform(&self, color_space: ColorSpace) -> Self::GU {
+        Lighting2dUniform {
+            ambient: self.ambient.as_gpu_vec(color_space),
+        }
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct Light2dUniform {
+    color: Vec4,
+    position: Vec2,
+    /// X axis of the light
+    direction: Vec2,
+    radius: f32,
+    /// -1 for point lights, every direction is in the cone
+    cos_half_angle: f32,
+    shadows: u32,
+}
+
+impl Light2dUniform {
+    pub fn new(light: &Light2d, transform: &GlobalTransform, color_space: ColorSpace) -> Self {
+        let color = light.color.as_gpu_vec(color_space).truncate() * light.intensity.max(0.0);
+        Self {
+            color: color.extend(1.0),
+            position: transform.translation().xy(),
+            direction: transform
+                .affine()
+                .transform_vector3(Vec3::X)
+                .xy()
+                .normalize_or_zero(),
+            radius: light.radius.max(1e-4),
+            cos_half_angle: match light.shape {
+                LightShape2d::Point => -1.0,
+                LightShape2d::Cone { half_angle } => half_angle.cos(),
+            },
+            shadows: light.shadows as u32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, ShaderType)]
+pub struct Occluder2dData {
+    /// Center in xy, half size in zw
+    center_half_size: Vec4,
+    /// X axis of the box in xy, its Y axis is perpendicular
+    axis: Vec4,
+}
+
+impl Occluder2dData {
+    pub fn new(occluder: &Occluder2d, transform: &GlobalTransform) -> Self {
+        let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+        let half_size = occluder.half_size * scale.xy().abs();
+        let axis = (rotation * Vec3::X).xy().normalize_or_zero();
+        Self {
+            center_half_size: Vec4::new(translation.x, translation.y, half_size.x, half_size.y),
+            axis: axis.extend(0.0).extend(0.0),
+        }
+    }
+
+    fn blocks(&self, from: Vec2, to: Vec2) -> bool {
+        let center = self.center_half_size.xy();
+        let half_size = self.center_half_size.zw();
+        let axis = self.axis.xy();
+        let to_local = |point: Vec2| {
+            let offset = point - center;
+            Vec2::new(offset.dot(axis), offset.dot(axis.perp()))
+        };
+        let (from, to) = (to_local(from), to_local(to));
+        let delta = to - from;
+
+        // Slab test, entering the box after `from` and before `to`
+        let mut t_enter = f32::NEG_INFINITY;
+        let mut t_exit = f32::INFINITY;
+        for i in 0..2 {
+            if delta[i].abs() < 1e-6 {
+                if from[i].abs() > half_size[i] {
+                    return false;
+                }
+                continue;
+            }
+            let t0 = (-half_size[i] - from[i]) / delta[i];
+            let t1 = (half_size[i] - from[i]) / delta[i];
+            t_enter = t_enter.max(t0.min(t1));
+            t_exit = t_exit.min(t0.max(t1));
+        }
+        t_enter > 0.0 && t_enter <= t_exit && t_enter < 1.0
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct Occluders2dUniform {
+    count: u32,
+    occluders: [Occluder2dData; MAX_OCCLUDERS_2D],
+}
+
+impl Default for Occluders2dUniform {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            occluders: [Occluder2dData::default(); MAX_OCCLUDERS_2D],
+        }
+    }
+}
+
+/// Lights and occluders of the frame, drawn by every camera with [`Lighting2d`].
+#[derive(Resource)]
+pub struct Lights2d {
+    pub lights: Vec<Entity>,
+    pub light_uniforms: ComponentUniforms<Light2dUniform>,
+    pub occluders: UniformBuffer<Occluders2dUniform>,
+}
+
+impl FromWorld for Lights2d {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            lights: Vec::new(),
+            light_uniforms: ComponentUniforms::from_world(world),
+            occluders: Default::default(),
+        }
+    }
+}
+
+pub fn prepare_lights_2d(
+    mut commands: Commands,
+    mut lights_2d: ResMut<Lights2d>,
+    mut overflowed: Local<bool>,
+    color_space: Res<ColorSpace>,
+    lights: Query<(
+        Entity,
+        &Light2d,
+        &GlobalTransform,
+        Option<&ExtractedVisibility>,
+    )>,
+    occluders: Query<(&Occluder2d, &GlobalTransform, Option<&ExtractedVisibility>)>,
+) {
+    let mut spawns: Vec<(Entity, DynamicUniformId<Light2dUniform>)> = Vec::new();
+
+    let lights_2d = lights_2d.as_mut();
+    lights_2d.lights.clear();
+    lights_2d.light_uniforms.clear();
+    for (entity, light, transform, computed_visibility) in lights.iter() {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        let uniform = Light2dUniform::new(light, transform, *color_space);
+        spawns.push((entity, lights_2d.light_uniforms.push(uniform).into()));
+        lights_2d.lights.push(entity);
+    }
+    commands.insert_or_spawn_batch(spawns);
+
+    let occluders_uniform = lights_2d.occluders.get_mut();
+    occluders_uniform.count = 0;
+    for (occluder, transform, computed_visibility) in occluders.iter() {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        if occluders_uniform.count as usize == MAX_OCCLUDERS_2D {
+            if !*overflowed {
+                warn!(
+                    "More than {} 2D occluders, the rest cast no shadows",
+                    MAX_OCCLUDERS_2D
+                );
+                *overflowed = true;
+            }
+            break;
+        }
+        occluders_uniform.occluders[occluders_uniform.count as usize] =
+            Occluder2dData::new(occluder, transform);
+        occluders_uniform.count += 1;
+    }
+}
+
+pub fn queue_lights_2d(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffer_pool: ResMut<BufferPool>,
+    mut lights_2d: ResMut<Lights2d>,
+) {
+    let lights_2d = lights_2d.as_mut();
+    lights_2d
+        .light_uniforms
+        .write_buffer_pooled(&render_device, &render_queue, &mut buffer_pool);
+    lights_2d
+        .occluders
+        .write_buffer(&render_device, &render_queue);
+}
+
+#[derive(Resource)]
+pub struct Light2dPipeline {
+    pub view_layout: BindGroupLayout,
+    pub light_layout: BindGroupLayout,
+    pub occluders_layout: BindGroupLayout,
+    pub lighting_layout: BindGroupLayout,
+    /// Adds the lights into the light texture
+    pub light_pipeline_id: RenderPipelineId,
+    /// Multiplies the target by the ambient and the light texture
+    pub composite_pipeline_id: RenderPipelineId,
+}
+
+fn uniform_layout_entry(
+    min_binding_size: wgpu::BufferSize,
+    dynamic: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: dynamic,
+            min_binding_size: Some(min_binding_size),
+        },
+        count: None,
+    }
+}
+
+impl FromWorld for Light2dPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<GrabTextureLayout>,
+            ResMut<PipelineCache>,
+        )> = SystemState::new(world);
+        let (render_device, grab_layout, mut pipeline_cache) = state.get_mut(world);
+
+        let create_layout = |label: &str, entry: wgpu::BindGroupLayoutEntry| -> BindGroupLayout {
+            render_device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(label),
+                    entries: &[entry],
+                })
+                .into()
+        };
+        let view_layout = create_layout(
+            "light_2d_view_layout",
+            uniform_layout_entry(CameraUniforms::min_size(), true),
+        );
+        let light_layout = create_layout(
+            "light_2d_layout",
+            uniform_layout_entry(Light2dUniform::min_size(), true),
+        );
+        let occluders_layout = create_layout(
+            "occluders_2d_layout",
+            uniform_layout_entry(Occluders2dUniform::min_size(), false),
+        );
+        let lighting_layout = create_layout(
+            "lighting_2d_layout",
+            uniform_layout_entry(Lighting2dUniform::min_size(), true),
+        );
+
+        let light_pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("light_2d_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![
+                    view_layout.clone(),
+                    light_layout.clone(),
+                    occluders_layout.clone(),
+                ],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: LIGHT_2D_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: LIGHT_2D_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: HdrTexture::FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent::REPLACE,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let composite_pipeline_id = pipeline_cache.queue(RenderPipelineDescriptor {
+            label: Some("light_2d_composite_pipeline"),
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![
+                    grab_layout.layout.clone(),
+                    grab_layout.layout.clone(),
+                    lighting_layout.clone(),
+                ],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: LIGHT_2D_COMPOSITE_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: LIGHT_2D_COMPOSITE_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            view_layout,
+            light_layout,
+            occluders_layout,
+            lighting_layout,
+            light_pipeline_id,
+            composite_pipeline_id,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Light2dBindGroups {
+    pub view_bind_group: Option<wgpu::BindGroup>,
+    pub light_bind_group: Option<wgpu::BindGroup>,
+    pub occluders_bind_group: Option<wgpu::BindGroup>,
+    pub lighting_bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn create_light_2d_bind_groups(
+    render_device: Res<RenderDevice>,
+    light_pipeline: Res<Light2dPipeline>,
+    lights_2d: Res<Lights2d>,
+    camera_uniforms: Res<ComponentUniforms<CameraUniforms>>,
+    lighting_uniforms: Res<ComponentUniforms<Lighting2dUniform>>,
+    mut bind_groups: ResMut<Light2dBindGroups>,
+) {
+    let create_bind_group = |layout: &BindGroupLayout, resource: wgpu::BindingResource| {
+        render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource,
+            }],
+        })
+    };
+    bind_groups.view_bind_group = camera_uniforms
+        .binding()
+        .map(|binding| create_bind_group(&light_pipeline.view_layout, binding));
+    bind_groups.light_bind_group = lights_2d
+        .light_uniforms
+        .binding()
+        .map(|binding| create_bind_group(&light_pipeline.light_layout, binding));
+    bind_groups.occluders_bind_group = lights_2d
+        .occluders
+        .binding()
+        .map(|binding| create_bind_group(&light_pipeline.occluders_layout, binding));
+    bind_groups.lighting_bind_group = lighting_uniforms
+        .binding()
+        .map(|binding| create_bind_group(&light_pipeline.lighting_layout, binding));
+}
+
+/// Float render target of a camera holding light, bound with [`GrabTextureLayout`] to composite it.
+pub struct HdrTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub size: (u32, u32),
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl HdrTexture {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn create(
+        render_device: &RenderDevice,
+        layout: &GrabTextureLayout,
+        label: &'static str,
+        size: (u32, u32),
+    ) -> Self {
+        let texture = render_device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = render_device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("hdr_texture_bind_group"),
+            layout: &layout.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Self {
+            texture,
+            view,
+            size,
+            bind_group,
+        }
+    }
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct LightTextures(pub HashMap<Entity, HdrTexture>);
+
+impl EntityRenderState for LightTextures {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(&entity);
+    }
+}
+
+/// Creates the light textures of the cameras with [`Lighting2d`] at the size of their target.
+pub fn prepare_light_textures(
+    render_device: Res<RenderDevice>,
+    layout: Res<GrabTextureLayout>,
+    images: Res<Assets<Image>>,
+    windows: Res<PreparedWindows>,
+    mut light_textures: ResMut<LightTextures>,
+    cameras: Query<(Entity, &Camera), With<Lighting2d>>,
+) {
+    for (entity, camera) in cameras.iter() {
+        let Some(size) = camera.render_target.get_size(&images, &windows) else {
+            continue;
+        };
+        if size.0 == 0 || size.1 == 0 {
+            continue;
+        }
+        if light_textures.get(&entity).map(|light| light.size) == Some(size) {
+            continue;
+        }
+        light_textures.insert(
+            entity,
+            HdrTexture::create(&render_device, &layout, "light_2d_texture", size),
+        );
+    }
+}
+
+/// Camera pass drawing the lights of cameras with [`Lighting2d`] and lighting their target.
+pub fn encode_lighting_2d<'w>(
+    camera: Entity,
+    world: &'w World,
+    attachments: &CameraAttachments<'w>,
+    command_encoder: &mut wgpu::CommandEncoder,
+) {
+    if !world.entity(camera).contains::<Lighting2d>() {
+        return;
+    }
+    let light_textures = world.get_resource::<LightTextures>().unwrap();
+    let Some(light_texture) = light_textures
+        .get(&camera)
+        .filter(|light_texture| light_texture.size == attachments.size)
+    else {
+        return;
+    };
+    let light_pipeline = world.get_resource::<Light2dPipeline>().unwrap();
+    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
+    let (Some(render_pipeline), Some(composite_pipeline)) = (
+        pipeline_cache.get(&light_pipeline.light_pipeline_id),
+        pipeline_cache.get(&light_pipeline.composite_pipeline_id),
+    ) else {
+        return;
+    };
+    let bind_groups = world.get_resource::<Light2dBindGroups>().unwrap();
+    let (Some(view_bind_group), Some(lighting_bind_group)) = (
+        bind_groups.view_bind_group.as_ref(),
+        bind_groups.lighting_bind_group.as_ref(),
+    ) else {
+        return;
+    };
+    let (Some(view_uniform_id), Some(lighting_uniform_id)) = (
+        world.get::<DynamicUniformId<CameraUniforms>>(camera),
+        world.get::<DynamicUniformId<Lighting2dUniform>>(camera),
+    ) else {
+        return;
+    };
+
+    {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("light_2d_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &light_texture.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        let lights_2d = world.get_resource::<Lights2d>().unwrap();
+        if let (Some(light_bind_group), Some(occluders_bind_group)) = (
+            bind_groups.light_bind_group.as_ref(),
+            bind_groups.occluders_bind_group.as_ref(),
+        ) {
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_bind_group(0, view_bind_group, &[**view_uniform_id]);
+            render_pass.set_bind_group(2, occluders_bind_group, &[]);
+            for light in &lights_2d.lights {
+                let Some(light_uniform_id) = world.get::<DynamicUniformId<Light2dUniform>>(*light)
+                else {
+                    continue;
+                };
+                render_pass.set_bind_group(1, light_bind_group, &[**light_uniform_id]);
+                render_pass.draw(0..6, 0..1);
+            }
+        }
+    }
+
+    let Some(source) = copy_to_post_texture(camera, world, attachments, command_encoder) else {
+        return;
+    };
+    let mut render_pass = begin_post_pass("light_2d_composite_pass", attachments, command_encoder);
+    render_pass.set_pipeline(composite_pipeline);
+    render_pass.set_bind_group(0, &source.bind_group, &[]);
+    render_pass.set_bind_group(1, &light_texture.bind_group, &[]);
+    render_pass.set_bind_group(2, lighting_bind_group, &[**lighting_uniform_id]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Quat, Transform};
+
+    use super::*;
+
+    #[test]
+    fn occluders_block_segments_through_them() {
+        let occluder = Occluder2d {
+            half_size: Vec2::new(10.0, 1.0),
+        };
+        let transform = GlobalTransform::from(Transform::from_xyz(0.0, 20.0, 0.0));
+        assert!(occluder.blocks(&transform, Vec2::ZERO, Vec2::new(0.0, 40.0)));
+        assert!(!occluder.blocks(&transform, Vec2::ZERO, Vec2::new(0.0, 10.0)));
+        assert!(!occluder.blocks(&transform, Vec2::new(20.0, 0.0), Vec2::new(20.0, 40.0)));
+        // Points on the occluder are lit
+        assert!(!occluder.blocks(&transform, Vec2::new(0.0, 20.0), Vec2::new(0.0, 40.0)));
+
+        // Rotated upright the wall no longer spans x = 5
+        let transform = GlobalTransform::from(
+            Transform::from_xyz(0.0, 20.0, 0.0)
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)),
+        );
+        assert!(!occluder.blocks(&transform, Vec2::new(5.0, 0.0), Vec2::new(5.0, 40.0)));
+        assert!(occluder.blocks(&transform, Vec2::ZERO, Vec2::new(0.0, 40.0)));
+    }
+}
@@ -0,0 +1,121 @@
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+}
+
+struct Light {
+    color: vec4<f32>,
+    position: vec2<f32>,
+    direction: vec2<f32>,
+    radius: f32,
+    cos_half_angle: f32,
+    shadows: u32,
+}
+
+struct Occluder {
+    // center in xy, half size in zw
+    center_half_size: vec4<f32>,
+    axis: vec4<f32>,
+}
+
+struct Occluders {
+    count: u32,
+    occluders: array<Occluder, 128>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> light: Light;
+
+@group(2) @binding(0)
+var<uniform> occluders: Occluders;
+
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        world_position: vec2<f32>,
+}
+
+// Two triangles covering the radius of the light
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    var out: VertexOutput;
+
+    let world_position = light.position + corners[vertex_index] * light.radius;
+    let clip_position = camera.view_proj * vec4<f32>(world_position, 0.0, 1.0);
+    // Lights are flat, never clipped by the depth range
+    out.clip_position = vec4<f32>(clip_position.xy, 0.0, clip_position.w);
+    out.world_position = world_position;
+
+    return out;
+}
+
+// Same as Occluder2d::blocks, a point inside the box is not blocked by it
+fn blocks(occluder: Occluder, from: vec2<f32>, to: vec2<f32>) -> bool {
+    let center = occluder.center_half_size.xy;
+    let half_size = occluder.center_half_size.zw;
+    let axis = occluder.axis.xy;
+    let perp = vec2<f32>(-axis.y, axis.x);
+
+    let from_offset = from - center;
+    let to_offset = to - center;
+    let from_local = vec2<f32>(dot(from_offset, axis), dot(from_offset, perp));
+    let to_local = vec2<f32>(dot(to_offset, axis), dot(to_offset, perp));
+    let delta = to_local - from_local;
+
+    var t_enter = -1e30;
+    var t_exit = 1e30;
+    for (var i = 0; i < 2; i = i + 1) {
+        if abs(delta[i]) < 1e-6 {
+            if abs(from_local[i]) > half_size[i] {
+                return false;
+            }
+            continue;
+        }
+        let t0 = (-half_size[i] - from_local[i]) / delta[i];
+        let t1 = (half_size[i] - from_local[i]) / delta[i];
+        t_enter = max(t_enter, min(t0, t1));
+        t_exit = min(t_exit, max(t0, t1));
+    }
+    return t_enter > 0.0 && t_enter <= t_exit && t_enter < 1.0;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let to_pixel = in.world_position - light.position;
+    let distance = length(to_pixel);
+    if distance >= light.radius {
+        discard;
+    }
+
+    if light.cos_half_angle > -1.0 && distance > 0.0 {
+        if dot(to_pixel / distance, light.direction) < light.cos_half_angle {
+            discard;
+        }
+    }
+
+    if light.shadows != 0u {
+        // Occluders block the way from the pixel to the light
+        for (var i = 0u; i < occluders.count; i = i + 1u) {
+            if blocks(occluders.occluders[i], in.world_position, light.position) {
+                discard;
+            }
+        }
+    }
+
+    // Smooth falloff reaching zero at the radius
+    let falloff = 1.0 - distance / light.radius;
+    return vec4<f32>(light.color.rgb * falloff * falloff, 1.0);
+}
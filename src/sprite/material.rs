@@ -0,0 +1,389 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{
+        App, Bundle, Changed, Component, Entity, FromWorld, GlobalTransform, Handle,
+        IntoSystemDescriptor, Or, Plugin, Query, Res, ResMut, Resource, Transform, With, World,
+    },
+};
+use encase::ShaderType;
+
+use crate::render::{
+    blend::{AlphaMode, BlendMode},
+    camera::component::Visibility,
+    color::Color,
+    command::{AddRenderCommand, DrawMesh, RenderCommand, RenderWith},
+    mesh::Mesh,
+    raster::{CullMode, DepthBias, RasterKey},
+    resource::{
+        buffer::Vertex,
+        component_uniform::{queue_component_uniforms, AddComponentUniform, ComponentUniforms},
+        pipeline::{BindGroupLayout, PipelineCache, RenderPipelineDescriptor},
+        renderer::RenderDevice,
+        shader::Shader,
+        specialized_pipeline::{AddPipelineWarmup, PipelineSpecialize, Specialized},
+        uniform::{DynamicUniformId, HandleGpuUniform},
+    },
+    stats::RenderStats,
+    system::RenderResult,
+    texture::Image,
+    RenderStage,
+};
+
+use super::{
+    bind::{sprite_pipeline_descriptor, SpritePipeline, TextureBindGroups},
+    set_sprite_bind_groups,
+    uniform::{queue_sprite_uniforms, SpriteUniform},
+    CountSpriteDraw, SPRITE_DEFAULT_BLEND_MODE, SPRITE_SHADER_HANDLE,
+};
+
+///
+/// Sprite drawn with a custom fragment shader: water, dissolve, palette swap, ...
+///
+/// The vertex stage and bind groups are the ones of sprites, the shader only provides `fs_main`:
+///
+/// ```wgsl
+/// struct VertexOutput {
+///     @builtin(position)  clip_position: vec4<f32>,
+///     @location(0)        uv: vec2<f32>,
+///     @location(2)        color: vec4<f32>,
+/// }
+///
+/// // group 0 model, group 1 camera and globals, like sprite.wgsl
+/// @group(2) @binding(0) var t_diffuse: texture_2d<f32>;
+/// @group(2) @binding(1) var s_diffuse: sampler;
+/// // @group(3) @binding(0) is the sprite uniform
+/// @group(3) @binding(1) var<uniform> material: MyMaterial; // Material2d::GU
+/// ```
+///
+/// The component is uploaded every frame through [`HandleGpuUniform`],
+/// register the material with [`MaterialSpritePlugin`].
+/// Shaders supporting [`BlendMode::Multiply`] premultiply their output under `BLEND_MULTIPLY`.
+///
+pub trait Material2d: HandleGpuUniform + Component {
+    fn fragment_shader() -> Handle<Shader>;
+}
+
+/// Draws the entities with `M` and [`DrawMaterialSprite<M>`], add it after the `FlatSpritePlugin`.
+pub struct MaterialSpritePlugin<M: Material2d>(PhantomData<fn() -> M>);
+
+impl<M: Material2d> Default for MaterialSpritePlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: Material2d> Plugin for MaterialSpritePlugin<M> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Specialized<MaterialSpritePipeline<M>>>()
+            .init_resource::<MaterialSpritePipeline<M>>()
+            .add_pipeline_warmup::<MaterialSpritePipeline<M>>()
+            .init_resource::<MaterialSpriteBindGroup<M>>()
+            .add_component_uniform::<M>()
+            .add_render_command::<DrawMaterialSprite<M>>()
+            .add_system_to_stage(RenderStage::Prepare, specialize_material_sprite_pipelines::<M>)
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_material_sprite_bind_group::<M>
+                    .after(queue_sprite_uniforms)
+                    .after(queue_component_uniforms::<M>),
+            );
+    }
+}
+
+#[derive(Bundle)]
+pub struct MaterialSpriteBundle<M: Material2d> {
+    pub global_transform: GlobalTransform,
+    pub transform: Transform,
+    pub mesh: Handle<Mesh<Vertex>>,
+    pub texture: Handle<Image>,
+    pub color: Color,
+    pub visibility: Visibility,
+    pub material: M,
+    pub render_with: RenderWith<DrawMaterialSprite<M>>,
+}
+
+impl<M: Material2d + Default> Default for MaterialSpriteBundle<M> {
+    fn default() -> Self {
+        Self {
+            global_transform: GlobalTransform::default(),
+            transform: Transform::default(),
+            mesh: Handle::default(),
+            texture: Handle::default(),
+            color: Color::NO_TINT,
+            visibility: Visibility::Inherited,
+            material: M::default(),
+            render_with: RenderWith::default(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct MaterialSpritePipeline<M: Material2d> {
+    /// Layouts of groups 0 to 2, shared with the [`SpritePipeline`]
+    pub sprite_layouts: [BindGroupLayout; 3],
+    /// [`SpriteUniform`] at binding 0 and the material at binding 1
+    pub material_layout: BindGroupLayout,
+    marker: PhantomData<fn() -> M>,
+}
+
+impl<M: Material2d> FromWorld for MaterialSpritePipeline<M> {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            Res<SpritePipeline>,
+            ResMut<PipelineCache>,
+            ResMut<Specialized<Self>>,
+        )> = SystemState::new(world);
+        let (render_device, sprite_pipeline, mut pipeline_cache, mut specialized_self) =
+            state.get_mut(world);
+
+        let material_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: Some(SpriteUniform::min_size()),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: Some(M::GU::min_size()),
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("material_sprite_uniform_layout"),
+            });
+
+        let material_pipeline = Self {
+            sprite_layouts: [
+                sprite_pipeline.model_layout.clone(),
+                sprite_pipeline.view_layout.clone(),
+                sprite_pipeline.texture_layout.clone(),
+            ],
+            material_layout,
+            marker: PhantomData,
+        };
+
+        for blend_mode in BlendMode::ALL {
+            let key = (*blend_mode, RasterKey::default());
+            let id = pipeline_cache.queue(material_pipeline.specialize(&render_device, key));
+            specialized_self.pipelines.insert(key, id);
+        }
+
+        material_pipeline
+    }
+}
+
+impl<M: Material2d> PipelineSpecialize for MaterialSpritePipeline<M> {
+    type Key = (BlendMode, RasterKey);
+
+    fn specialize(
+        &self,
+        _render_device: &RenderDevice,
+        key: Self::Key,
+    ) -> RenderPipelineDescriptor {
+        let [model_layout, view_layout, texture_layout] = self.sprite_layouts.clone();
+        let mut descriptor = sprite_pipeline_descriptor(
+            vec![model_layout, view_layout, texture_layout, self.material_layout.clone()],
+            SPRITE_SHADER_HANDLE.typed(),
+            key,
+        );
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader = M::fragment_shader();
+        }
+        descriptor
+    }
+}
+
+/// Queues the pipelines of material sprites with a [`CullMode`], [`DepthBias`]
+/// or masked [`AlphaMode`], the default ones are queued up front.
+pub fn specialize_material_sprite_pipelines<M: Material2d>(
+    render_device: Res<RenderDevice>,
+    material_pipeline: Res<MaterialSpritePipeline<M>>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    mut specialized_material_pipeline: ResMut<Specialized<MaterialSpritePipeline<M>>>,
+    query: Query<
+        (
+            Option<&BlendMode>,
+            Option<&CullMode>,
+            Option<&DepthBias>,
+            Option<&AlphaMode>,
+        ),
+        (
+            With<M>,
+            Or<(
+                Changed<CullMode>,
+                Changed<DepthBias>,
+                Changed<BlendMode>,
+                Changed<AlphaMode>,
+            )>,
+        ),
+    >,
+) {
+    for (blend_mode, cull_mode, depth_bias, alpha_mode) in query.iter() {
+        let key = (
+            blend_mode.copied().unwrap_or(SPRITE_DEFAULT_BLEND_MODE),
+            RasterKey::new(cull_mode, depth_bias, alpha_mode),
+        );
+        specialized_material_pipeline.specialize(
+            &mut pipeline_cache,
+            &material_pipeline,
+            &render_device,
+            key,
+        );
+    }
+}
+
+/// Group 3 of the material sprites, the sprite and material uniforms of the frame.
+#[derive(Resource)]
+pub struct MaterialSpriteBindGroup<M: Material2d> {
+    pub bind_group: Option<wgpu::BindGroup>,
+    marker: PhantomData<fn() -> M>,
+}
+
+impl<M: Material2d> Default for MaterialSpriteBindGroup<M> {
+    fn default() -> Self {
+        Self {
+            bind_group: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+pub fn create_material_sprite_bind_group<M: Material2d>(
+    render_device: Res<RenderDevice>,
+    material_pipeline: Res<MaterialSpritePipeline<M>>,
+    sprite_uniforms: Res<ComponentUniforms<SpriteUniform>>,
+    material_uniforms: Res<ComponentUniforms<M::GU>>,
+    mut material_bind_group: ResMut<MaterialSpriteBindGroup<M>>,
+) {
+    let (Some(sprite_binding), Some(material_binding)) =
+        (sprite_uniforms.binding(), material_uniforms.binding())
+    else {
+        return;
+    };
+    material_bind_group.bind_group =
+        Some(render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &material_pipeline.material_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sprite_binding,
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: material_binding,
+                },
+            ],
+        }));
+}
+
+/// Draws a sprite with the fragment shader of the material `M`.
+pub type DrawMaterialSprite<M> = (
+    SetMaterialSpritePipeline<M>,
+    SetMaterialSpriteBindGroups<M>,
+    DrawMesh<Vertex>,
+    CountSpriteDraw,
+);
+
+pub struct SetMaterialSpritePipeline<M: Material2d>(PhantomData<fn() -> M>);
+impl<M: Material2d> RenderCommand for SetMaterialSpritePipeline<M> {
+    fn render<'w>(
+        _camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(specialized_material_pipeline) =
+            world.get_resource::<Specialized<MaterialSpritePipeline<M>>>()
+        else {
+            return RenderResult::Failure("Specialized<MaterialSpritePipeline<M>> resource missing");
+        };
+        let Some(pipeline_cache) = world.get_resource::<PipelineCache>() else {
+            return RenderResult::Failure("PipelineCache resource missing");
+        };
+
+        let blend_mode = world
+            .get::<BlendMode>(object)
+            .copied()
+            .unwrap_or(SPRITE_DEFAULT_BLEND_MODE);
+        let raster_key = RasterKey::of(world, object);
+        let Some(pipeline_id) = specialized_material_pipeline
+            .pipelines
+            .get(&(blend_mode, raster_key))
+        else {
+            return RenderResult::Failure("pipeline not specialized");
+        };
+        let Some(render_pipeline) = pipeline_cache.get(pipeline_id) else {
+            return RenderResult::Failure("pipeline not compiled yet");
+        };
+        render_pass.set_pipeline(render_pipeline);
+        let Some(render_stats) = world.get_resource::<RenderStats>() else {
+            return RenderResult::Failure("RenderStats resource missing");
+        };
+        render_stats.pipeline_switch();
+
+        RenderResult::Success
+    }
+}
+
+/// Binds the sprite bind groups, with the sprite and material uniforms at group 3.
+pub struct SetMaterialSpriteBindGroups<M: Material2d>(PhantomData<fn() -> M>);
+impl<M: Material2d> RenderCommand for SetMaterialSpriteBindGroups<M> {
+    fn render<'w>(
+        camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(sprite_pipeline) = world.get_resource::<SpritePipeline>() else {
+            return RenderResult::Failure("SpritePipeline resource missing");
+        };
+        let Some(texture_bind_groups) = world.get_resource::<TextureBindGroups>() else {
+            return RenderResult::Failure("TextureBindGroups resource missing");
+        };
+        let texture_bind_group = world
+            .get::<Handle<Image>>(object)
+            .and_then(|image_handle| texture_bind_groups.get(&image_handle.id()))
+            .unwrap_or(&sprite_pipeline.dummy_texture_bind_group);
+
+        let result = set_sprite_bind_groups(camera, object, world, render_pass, texture_bind_group);
+        if let RenderResult::Failure(reason) = result {
+            return RenderResult::Failure(reason);
+        }
+
+        let Some(material_bind_group) = world.get_resource::<MaterialSpriteBindGroup<M>>() else {
+            return RenderResult::Failure("MaterialSpriteBindGroup<M> resource missing");
+        };
+        let Some(bind_group) = material_bind_group.bind_group.as_ref() else {
+            return RenderResult::Failure("bind groups not created");
+        };
+        let (Some(sprite_uniform_id), Some(material_uniform_id)) = (
+            world.get::<DynamicUniformId<SpriteUniform>>(object),
+            world.get::<DynamicUniformId<M::GU>>(object),
+        ) else {
+            return RenderResult::Failure("no material uniform id");
+        };
+        // Replaces the sprite bind group set above
+        render_pass.set_bind_group(3, bind_group, &[**sprite_uniform_id, **material_uniform_id]);
+        let Some(render_stats) = world.get_resource::<RenderStats>() else {
+            return RenderResult::Failure("RenderStats resource missing");
+        };
+        render_stats.bind_group_switches(1);
+
+        RenderResult::Success
+    }
+}
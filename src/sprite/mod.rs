@@ -1,31 +1,99 @@
 use bevy::{
     asset::load_internal_asset,
-    prelude::{Assets, Entity, Handle, HandleUntyped, Plugin, World},
+    input::InputSystem,
+    prelude::{
+        Assets, CoreStage, Entity, Handle, HandleUntyped, IntoSystemDescriptor, Plugin, World,
+    },
     reflect::TypeUuid,
+    transform::TransformSystem,
 };
 
 use crate::{
     render::{
-        camera::component::CameraUniforms,
-        mesh::{primitive::quad::create_unit_square, GpuMeshAssembly, Mesh},
-        resource::{buffer::Vertex, pipeline::PipelineCache, shader::Shader, uniform::DynamicUniformId, component_uniform::ModelUniform},
+        blend::BlendMode,
+        camera::component::{Camera, CameraUniforms},
+        cleanup::AddEntityCleanup,
+        command::{AddRenderCommand, DrawMesh, RenderCommand},
+        globals::GlobalsUniform,
+        mesh::{primitive::quad::create_unit_square, Mesh},
+        pass::{AddCameraPass, CameraPassStage},
+        phase::QueueRenderPhases,
+        post::{prepare_post_textures, PostTextures},
+        raster::RasterKey,
+        resource::{
+            buffer::Vertex,
+            component_uniform::{
+                queue_component_uniforms, queue_stable_component_uniforms, AddComponentUniform,
+                ComponentUniforms, ModelUniform,
+            },
+            pipeline::PipelineCache,
+            push_constant::set_model_push_constant,
+            shader::Shader,
+            specialized_pipeline::{AddPipelineWarmup, Specialized},
+            uniform::DynamicUniformId,
+        },
+        stats::{RenderCategory, RenderStats},
         system::{AddRenderFunction, RenderResult},
         texture::Image,
-        RenderAssets, RenderStage,
+        RenderStage,
     },
     sprite::bind::{
-        create_sprite_bind_groups, create_texture_bind_groups,
+        create_sprite_bind_groups, create_texture_bind_groups, specialize_sprite_pipelines,
         SpritePipeline, TextureBindGroups,
     },
 };
 
-use self::bind::SpriteBindGroups;
+use self::{
+    batch::{
+        queue_sprite_batches, render_sprite_batch, SpriteBatches, SpriteBatching,
+        SPRITE_BATCH_RENDER_FUNCTION,
+    },
+    bind::SpriteBindGroups,
+    bindless::{
+        create_bindless_texture_bind_group, prepare_bindless_textures, BindlessSpritePipeline,
+        BindlessTextureBindGroup, BindlessTextures, DrawBindlessSprite,
+        BINDLESS_SPRITE_RENDER_FUNCTION,
+    },
+    emissive::{
+        create_sprite_glow_bind_groups, encode_sprite_glow, prepare_emissive_sprites,
+        prepare_glow_textures, queue_emissive_sprites, EmissiveSprites, GlowTextures,
+        SpriteGlow, SpriteGlowBindGroups, SpriteGlowPipeline,
+    },
+    light::{
+        create_light_2d_bind_groups, encode_lighting_2d, prepare_light_textures, prepare_lights_2d,
+        queue_lights_2d, Light2dBindGroups, Light2dPipeline, LightTextures, Lighting2d, Lights2d,
+    },
+    panel::{layout_panels, spawn_panel_slices},
+    uniform::{prepare_sprite_uniforms, queue_sprite_uniforms, SpriteUniform},
+    widget::{drag_sliders, tint_widgets, toggle_checkboxes, update_interactions, WidgetEvent},
+    ysort::{y_sort_system, YSortSettings},
+};
 
+pub mod batch;
 pub mod bind;
+pub mod bindless;
 pub mod bundle;
+pub mod emissive;
+pub mod light;
+pub mod material;
+pub mod panel;
+pub mod uniform;
+pub mod widget;
+pub mod ysort;
 
 const SPRITE_SHADER_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 45678909876445673);
+const SPRITE_BINDLESS_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 45678909876445675);
+
+const LIGHT_2D_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 45678909876445676);
+const LIGHT_2D_COMPOSITE_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 45678909876445677);
+const SPRITE_EMISSIVE_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 45678909876445678);
+const SPRITE_GLOW_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 45678909876445679);
 
 pub const BASE_QUAD_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(Mesh::<Vertex>::TYPE_UUID, 45678909876445674);
@@ -34,6 +102,37 @@ pub struct FlatSpritePlugin;
 impl Plugin for FlatSpritePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         load_internal_asset!(app, SPRITE_SHADER_HANDLE, "sprite.wgsl", Shader::from_wgsl);
+        load_internal_asset!(
+            app,
+            SPRITE_BINDLESS_SHADER_HANDLE,
+            "sprite_bindless.wgsl",
+            Shader::from_wgsl
+        );
+
+        load_internal_asset!(
+            app,
+            LIGHT_2D_SHADER_HANDLE,
+            "light2d.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            LIGHT_2D_COMPOSITE_SHADER_HANDLE,
+            "light2d_composite.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            SPRITE_EMISSIVE_SHADER_HANDLE,
+            "emissive.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            SPRITE_GLOW_SHADER_HANDLE,
+            "glow.wgsl",
+            Shader::from_wgsl
+        );
 
         {
             let mut meshes = app
@@ -43,89 +142,243 @@ impl Plugin for FlatSpritePlugin {
             meshes.set_untracked(BASE_QUAD_HANDLE, create_unit_square());
         }
 
-        app.init_resource::<SpritePipeline>()
+        app.init_resource::<Specialized<SpritePipeline>>()
+            .init_resource::<SpritePipeline>()
             .init_resource::<SpriteBindGroups>()
             .init_resource::<TextureBindGroups>()
-            .add_render_function(SPRITE_RENDER_FUNCTION, render_sprite)
+            .init_resource::<Specialized<BindlessSpritePipeline>>()
+            .init_resource::<BindlessSpritePipeline>()
+            .add_pipeline_warmup::<SpritePipeline>()
+            .add_pipeline_warmup::<BindlessSpritePipeline>()
+            .init_resource::<BindlessTextures>()
+            .init_resource::<BindlessTextureBindGroup>()
+            .init_resource::<SpriteBatching>()
+            .init_resource::<SpriteBatches>()
+            .init_resource::<YSortSettings>()
+            .init_resource::<ComponentUniforms<SpriteUniform>>()
+            .init_resource::<PostTextures>()
+            .init_resource::<Lights2d>()
+            .init_resource::<LightTextures>()
+            .init_resource::<Light2dPipeline>()
+            .init_resource::<Light2dBindGroups>()
+            .init_resource::<EmissiveSprites>()
+            .init_resource::<GlowTextures>()
+            .init_resource::<SpriteGlowPipeline>()
+            .init_resource::<SpriteGlowBindGroups>()
+            .add_entity_cleanup::<LightTextures, Camera>()
+            .add_entity_cleanup::<GlowTextures, Camera>()
+            .add_component_uniform::<Lighting2d>()
+            .add_component_uniform::<SpriteGlow>()
+            .add_render_command_with_id::<DrawSprite>(SPRITE_RENDER_FUNCTION)
+            .add_render_command_with_id::<DrawBindlessSprite>(BINDLESS_SPRITE_RENDER_FUNCTION)
+            .add_render_function(SPRITE_BATCH_RENDER_FUNCTION, render_sprite_batch)
+            .add_system_to_stage(RenderStage::Prepare, specialize_sprite_pipelines)
+            .add_system_to_stage(RenderStage::Prepare, prepare_bindless_textures)
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_sprite_uniforms.after(prepare_bindless_textures),
+            )
+            .add_system_to_stage(RenderStage::Create, queue_sprite_uniforms)
             .add_system_to_stage(RenderStage::Create, create_sprite_bind_groups)
-            .add_system_to_stage(RenderStage::Create, create_texture_bind_groups);
+            .add_system_to_stage(RenderStage::Create, create_texture_bind_groups)
+            .add_system_to_stage(RenderStage::Create, create_bindless_texture_bind_group)
+            .add_system_to_stage(
+                RenderStage::Create,
+                queue_sprite_batches.after(QueueRenderPhases),
+            )
+            .add_system_to_stage(RenderStage::Prepare, prepare_lights_2d)
+            .add_system_to_stage(RenderStage::Create, queue_lights_2d)
+            .add_system_to_stage(RenderStage::Create, prepare_light_textures)
+            .add_system_to_stage(RenderStage::Create, prepare_post_textures::<Lighting2d>)
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_light_2d_bind_groups
+                    .after(queue_lights_2d)
+                    .after(queue_component_uniforms::<Lighting2d>)
+                    .after(queue_stable_component_uniforms::<Camera>),
+            )
+            .add_system_to_stage(RenderStage::Prepare, prepare_emissive_sprites)
+            .add_system_to_stage(RenderStage::Create, queue_emissive_sprites)
+            .add_system_to_stage(RenderStage::Create, prepare_glow_textures)
+            .add_system_to_stage(
+                RenderStage::Create,
+                create_sprite_glow_bind_groups
+                    .after(queue_emissive_sprites)
+                    .after(queue_component_uniforms::<SpriteGlow>)
+                    .after(queue_stable_component_uniforms::<Camera>),
+            )
+            // Before the passes of the plugins added after, outlines and post passes are not lit.
+            // Glow is added after lighting, emissive sprites are not darkened by it
+            .add_camera_pass(CameraPassStage::AfterMain, encode_lighting_2d)
+            .add_camera_pass(CameraPassStage::AfterMain, encode_sprite_glow)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                y_sort_system.after(TransformSystem::TransformPropagate),
+            )
+            .add_event::<WidgetEvent>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_interactions.after(InputSystem),
+            )
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                toggle_checkboxes.after(update_interactions),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, drag_sliders.after(update_interactions))
+            .add_system_to_stage(CoreStage::PostUpdate, tint_widgets.before(layout_panels))
+            .add_system_to_stage(CoreStage::PostUpdate, spawn_panel_slices)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                layout_panels.before(TransformSystem::TransformPropagate),
+            );
     }
 }
 
 pub const SPRITE_RENDER_FUNCTION: usize = 1;
-fn render_sprite<'w>(
+/// Used for sprites without a [`BlendMode`].
+pub const SPRITE_DEFAULT_BLEND_MODE: BlendMode = BlendMode::Opaque;
+/// Draws a textured mesh with the sprite pipeline.
+pub type DrawSprite = (
+    SetSpritePipeline,
+    SetSpriteBindGroups,
+    DrawMesh<Vertex>,
+    CountSpriteDraw,
+);
+
+/// Counts the sprite drawn by the commands before it in the [`RenderStats`] sprite category.
+pub struct CountSpriteDraw;
+impl RenderCommand for CountSpriteDraw {
+    fn render<'w>(
+        _camera: Entity,
+        _object: Entity,
+        world: &'w World,
+        _render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(render_stats) = world.get_resource::<RenderStats>() else {
+            return RenderResult::Failure("RenderStats resource missing");
+        };
+        render_stats.category_draw(RenderCategory::Sprite, 1);
+
+        RenderResult::Success
+    }
+}
+
+pub struct SetSpritePipeline;
+impl RenderCommand for SetSpritePipeline {
+    fn render<'w>(
+        _camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(specialized_sprite_pipeline) =
+            world.get_resource::<Specialized<SpritePipeline>>()
+        else {
+            return RenderResult::Failure("Specialized<SpritePipeline> resource missing");
+        };
+        let Some(pipeline_cache) = world.get_resource::<PipelineCache>() else {
+            return RenderResult::Failure("PipelineCache resource missing");
+        };
+
+        let blend_mode = world
+            .get::<BlendMode>(object)
+            .copied()
+            .unwrap_or(SPRITE_DEFAULT_BLEND_MODE);
+        let raster_key = RasterKey::of(world, object);
+        let Some(pipeline_id) = specialized_sprite_pipeline
+            .pipelines
+            .get(&(blend_mode, raster_key))
+        else {
+            return RenderResult::Failure("pipeline not specialized");
+        };
+        let Some(render_pipeline) = pipeline_cache.get(pipeline_id) else {
+            return RenderResult::Failure("pipeline not compiled yet");
+        };
+        render_pass.set_pipeline(render_pipeline);
+        let Some(render_stats) = world.get_resource::<RenderStats>() else {
+            return RenderResult::Failure("RenderStats resource missing");
+        };
+        render_stats.pipeline_switch();
+
+        RenderResult::Success
+    }
+}
+
+/// Binds the model, view, texture and sprite bind groups.
+pub struct SetSpriteBindGroups;
+impl RenderCommand for SetSpriteBindGroups {
+    fn render<'w>(
+        camera: Entity,
+        object: Entity,
+        world: &'w World,
+        render_pass: &mut wgpu::RenderPass<'w>,
+    ) -> RenderResult {
+        let Some(sprite_pipeline) = world.get_resource::<SpritePipeline>() else {
+            return RenderResult::Failure("SpritePipeline resource missing");
+        };
+        let Some(texture_bind_groups) = world.get_resource::<TextureBindGroups>() else {
+            return RenderResult::Failure("TextureBindGroups resource missing");
+        };
+        let texture_bind_group = match world.get::<Handle<Image>>(object) {
+            Some(image_handle) => match texture_bind_groups.get(&image_handle.id()) {
+                Some(bind) => bind,
+                None => &sprite_pipeline.dummy_texture_bind_group,
+            },
+            None => &sprite_pipeline.dummy_texture_bind_group,
+        };
+
+        set_sprite_bind_groups(camera, object, world, render_pass, texture_bind_group)
+    }
+}
+
+/// Binds the sprite bind groups around the given texture bind group at group 2.
+pub(crate) fn set_sprite_bind_groups<'w>(
     camera: Entity,
     object: Entity,
     world: &'w World,
     render_pass: &mut wgpu::RenderPass<'w>,
+    texture_bind_group: &'w wgpu::BindGroup,
 ) -> RenderResult {
-    // -- Set Pipeline --
-    let sprite_pipeline = world.get_resource::<SpritePipeline>().unwrap();
-    let pipeline_cache = world.get_resource::<PipelineCache>().unwrap();
-    let Some(render_pipeline) = pipeline_cache.get(&sprite_pipeline.pipeline_id) else {
-        return RenderResult::Failure;
+    let Some(sprite_bind_groups) = world.get_resource::<SpriteBindGroups>() else {
+        return RenderResult::Failure("SpriteBindGroups resource missing");
     };
-    render_pass.set_pipeline(render_pipeline);
-    // -- -- -- -------- -- -- --
+    let (Some(model_bind_group), Some(view_bind_group), Some(sprite_bind_group)) = (
+        sprite_bind_groups.model_bind_group.as_ref(),
+        sprite_bind_groups.view_bind_group.as_ref(),
+        sprite_bind_groups.sprite_bind_group.as_ref(),
+    ) else {
+        return RenderResult::Failure("bind groups not created");
+    };
+
+    if cfg!(feature = "push_constants") {
+        render_pass.set_bind_group(0, model_bind_group, &[]);
+        set_model_push_constant(camera, object, world, render_pass);
+    } else {
+        let Some(model_uniform_id) = world.get::<DynamicUniformId<ModelUniform>>(object) else {
+            return RenderResult::Failure("no ModelUniform id");
+        };
+        render_pass.set_bind_group(0, model_bind_group, &[**model_uniform_id]);
+    }
 
-    // -- Get Mesh --
-    let Some(mesh_handle) = world.get::<Handle<Mesh<Vertex>>>(object) else {
-        return RenderResult::Failure;
+    let Some(view_uniform_id) = world.get::<DynamicUniformId<CameraUniforms>>(camera) else {
+        return RenderResult::Failure("no CameraUniforms id");
     };
-    let gpu_meshes = world.get_resource::<RenderAssets<Mesh<Vertex>>>().unwrap();
-    let Some(mesh) = gpu_meshes.get(&mesh_handle.id()) else {
-        return RenderResult::Failure;
-    };
-    // -- -- -- -------- -- -- --
-
-    // -- Bind Model, View, Texture BindGroups --
-    let sprite_bind_groups = world.get_resource::<SpriteBindGroups>().unwrap();
-
-    let model_uniform_id = world.get::<DynamicUniformId<ModelUniform>>(object).unwrap();
-    render_pass.set_bind_group(
-        0,
-        sprite_bind_groups.model_bind_group.as_ref().unwrap(),
-        &[**model_uniform_id],
-    );
-
-    let view_uniform_id = world
-        .get::<DynamicUniformId<CameraUniforms>>(camera)
-        .unwrap();
-    render_pass.set_bind_group(
-        1,
-        sprite_bind_groups.view_bind_group.as_ref().unwrap(),
-        &[**view_uniform_id],
-    );
-
-    let texture_bind_groups = world.get_resource::<TextureBindGroups>().unwrap();
-    let texture_bind_group = match world.get::<Handle<Image>>(object) {
-        Some(image_handle) => match texture_bind_groups.get(&image_handle.id()) {
-            Some(bind) => bind,
-            None => &sprite_pipeline.dummy_texture_bind_group,
-        },
-        None => &sprite_pipeline.dummy_texture_bind_group,
+    let Some(globals_uniform_id) = world.get::<DynamicUniformId<GlobalsUniform>>(camera) else {
+        return RenderResult::Failure("no GlobalsUniform id");
     };
+    render_pass.set_bind_group(1, view_bind_group, &[**view_uniform_id, **globals_uniform_id]);
+
     render_pass.set_bind_group(2, texture_bind_group, &[]);
-    // -- -- -- -------- -- -- --
-
-    // -- Set Mesh Buffers --
-    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-    let instance_count = 1;
-    // render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-    match &mesh.assembly {
-        GpuMeshAssembly::Indexed {
-            index_buffer,
-            index_count,
-            index_format,
-        } => {
-            render_pass.set_index_buffer(index_buffer.slice(..), *index_format);
-            render_pass.draw_indexed(0..*index_count as u32, 0, 0..instance_count);
-        }
-        GpuMeshAssembly::NonIndexed { vertex_count } => {
-            render_pass.draw(0..*vertex_count as u32, 0..instance_count);
-        }
-    }
-    // -- -- -- -------- -- -- --
+
+    let Some(sprite_uniform_id) = world.get::<DynamicUniformId<SpriteUniform>>(object) else {
+        return RenderResult::Failure("no SpriteUniform id");
+    };
+    render_pass.set_bind_group(3, sprite_bind_group, &[**sprite_uniform_id]);
+
+    let Some(render_stats) = world.get_resource::<RenderStats>() else {
+        return RenderResult::Failure("RenderStats resource missing");
+    };
+    render_stats.bind_group_switches(4);
 
     RenderResult::Success
 }
@@ -0,0 +1,386 @@
+//!
+//! Nine-slice panels for menus and dialogs drawn by an overlay camera.
+//!
+//! Panels are laid out in overlay space, logical window pixels with the origin
+//! at the center of the window and Y up, the space of an `OrthographicProjection`
+//! spanning the window. Give the panel the [`RenderLayers`] of the overlay camera.
+//!
+
+use bevy::{
+    hierarchy::BuildChildren,
+    math::Rect,
+    prelude::{
+        Added, Assets, Bundle, Commands, Component, Entity, GlobalTransform, Handle, Query, Res,
+        Transform, Vec2, Vec3, Without,
+    },
+    window::Windows,
+};
+use image::GenericImageView;
+
+use crate::render::{
+    camera::component::{RenderLayers, Visibility},
+    color::Color,
+    texture::Image,
+};
+
+use super::{bundle::SpriteBundle, uniform::SpriteRect, BASE_QUAD_HANDLE};
+
+/// Point of the window, and of the panel, a [`Panel`] is pinned at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Position in a rect of size one centered at the origin, Y up.
+    pub fn position(&self) -> Vec2 {
+        match self {
+            Anchor::TopLeft => Vec2::new(-0.5, 0.5),
+            Anchor::Top => Vec2::new(0.0, 0.5),
+            Anchor::TopRight => Vec2::new(0.5, 0.5),
+            Anchor::Left => Vec2::new(-0.5, 0.0),
+            Anchor::Center => Vec2::new(0.0, 0.0),
+            Anchor::Right => Vec2::new(0.5, 0.0),
+            Anchor::BottomLeft => Vec2::new(-0.5, -0.5),
+            Anchor::Bottom => Vec2::new(0.0, -0.5),
+            Anchor::BottomRight => Vec2::new(0.5, -0.5),
+        }
+    }
+}
+
+///
+/// Borders of the texture in texels, kept at their size while the middle stretches.
+///
+/// Corners are drawn as they are, edges stretch along the side of the panel.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NineSlice {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl NineSlice {
+    pub fn uniform(border: f32) -> Self {
+        Self {
+            left: border,
+            right: border,
+            top: border,
+            bottom: border,
+        }
+    }
+}
+
+///
+/// Panel of `size` overlay pixels pinned at `anchor` of the window, moved by `offset`.
+///
+/// The panel entity keeps its `Transform` at the center of the panel,
+/// the nine slices are child sprites.
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Panel {
+    pub size: Vec2,
+    pub anchor: Anchor,
+    pub offset: Vec2,
+    pub slice: NineSlice,
+    /// Overlay pixels per texel of the borders.
+    pub border_scale: f32,
+}
+
+impl Default for Panel {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(100.0, 100.0),
+            anchor: Anchor::Center,
+            offset: Vec2::ZERO,
+            slice: NineSlice::default(),
+            border_scale: 1.0,
+        }
+    }
+}
+
+impl Panel {
+    /// Center of the panel in overlay space for a window of `window_size`.
+    pub fn center(&self, window_size: Vec2) -> Vec2 {
+        let anchor = self.anchor.position();
+        anchor * window_size - anchor * self.size + self.offset
+    }
+}
+
+/// Overlay space rect the slices of a [`Panel`] are cut to, e.g. the viewport of a scrolled list.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ClipRect(pub Rect);
+
+#[derive(Bundle)]
+pub struct PanelBundle {
+    pub panel: Panel,
+    pub texture: Handle<Image>,
+    pub color: Color,
+    pub visibility: Visibility,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+impl Default for PanelBundle {
+    fn default() -> Self {
+        Self {
+            panel: Panel::default(),
+            texture: Handle::default(),
+            color: Color::NO_TINT,
+            visibility: Visibility::Inherited,
+            transform: Transform::default(),
+            global_transform: GlobalTransform::default(),
+        }
+    }
+}
+
+/// Child sprites of a [`Panel`], row by row from the top left.
+#[derive(Component)]
+pub struct PanelSlices(pub [Entity; 9]);
+
+/// Quad of a slice relative to the panel center and the texels drawn on it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SliceLayout {
+    pub quad: Rect,
+    pub texels: Rect,
+}
+
+///
+/// Cuts a panel of `size` into its nine slices, `None` for the empty ones.
+///
+/// Borders shrink to fit panels smaller than them, with `clip` (relative to the panel center)
+/// the slices are cut to it along with their texels.
+///
+pub fn layout_slices(
+    size: Vec2,
+    texture_size: Vec2,
+    slice: &NineSlice,
+    border_scale: f32,
+    clip: Option<Rect>,
+) -> [Option<SliceLayout>; 9] {
+    let half = size / 2.0;
+    let scale_x = border_scale.min(size.x / (slice.left + slice.right).max(f32::EPSILON));
+    let scale_y = border_scale.min(size.y / (slice.top + slice.bottom).max(f32::EPSILON));
+
+    let xs = [
+        -half.x,
+        -half.x + slice.left * scale_x,
+        half.x - slice.right * scale_x,
+        half.x,
+    ];
+    let us = [
+        0.0,
+        slice.left,
+        texture_size.x - slice.right,
+        texture_size.x,
+    ];
+    // Rows from the top, texel rows grow down while overlay Y grows up
+    let ys = [
+        half.y,
+        half.y - slice.top * scale_y,
+        -half.y + slice.bottom * scale_y,
+        -half.y,
+    ];
+    let vs = [
+        0.0,
+        slice.top,
+        texture_size.y - slice.bottom,
+        texture_size.y,
+    ];
+
+    let mut slices = [None; 9];
+    for row in 0..3 {
+        for column in 0..3 {
+            let quad = Rect::new(xs[column], ys[row + 1], xs[column + 1], ys[row]);
+            let texels = Rect::new(us[column], vs[row], us[column + 1], vs[row + 1]);
+            let Some(layout) = clip_slice(SliceLayout { quad, texels }, clip) else {
+                continue;
+            };
+            slices[row * 3 + column] = Some(layout);
+        }
+    }
+    slices
+}
+
+fn clip_slice(layout: SliceLayout, clip: Option<Rect>) -> Option<SliceLayout> {
+    let SliceLayout { quad, texels } = layout;
+    let clipped = match clip {
+        Some(clip) => quad.intersect(clip),
+        None => quad,
+    };
+    if clipped.width() <= 0.0 || clipped.height() <= 0.0 {
+        return None;
+    }
+    let texels_per_x = texels.width() / quad.width();
+    let texels_per_y = texels.height() / quad.height();
+    Some(SliceLayout {
+        quad: clipped,
+        texels: Rect::new(
+            texels.min.x + (clipped.min.x - quad.min.x) * texels_per_x,
+            texels.min.y + (quad.max.y - clipped.max.y) * texels_per_y,
+            texels.max.x - (quad.max.x - clipped.max.x) * texels_per_x,
+            texels.max.y - (clipped.min.y - quad.min.y) * texels_per_y,
+        ),
+    })
+}
+
+/// Spawns the child sprites of new panels.
+pub fn spawn_panel_slices(mut commands: Commands, panels: Query<Entity, Added<Panel>>) {
+    for entity in panels.iter() {
+        let slices = [(); 9].map(|_| {
+            commands
+                .spawn(SpriteBundle {
+                    mesh: BASE_QUAD_HANDLE.typed(),
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                })
+                .id()
+        });
+        commands
+            .entity(entity)
+            .push_children(&slices)
+            .insert(PanelSlices(slices));
+    }
+}
+
+/// Pins the panels to the window and lays out their slices.
+pub fn layout_panels(
+    windows: Res<Windows>,
+    images: Res<Assets<Image>>,
+    mut panels: Query<(
+        &Panel,
+        &PanelSlices,
+        &Handle<Image>,
+        &Color,
+        Option<&RenderLayers>,
+        Option<&ClipRect>,
+        &mut Transform,
+    )>,
+    mut sprites: Query<
+        (
+            &mut Transform,
+            &mut Handle<Image>,
+            &mut Color,
+            &mut Visibility,
+            Option<&mut SpriteRect>,
+        ),
+        Without<Panel>,
+    >,
+    mut commands: Commands,
+) {
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+
+    for (panel, slices, texture, color, render_layers, clip_rect, mut transform) in
+        panels.iter_mut()
+    {
+        let center = panel.center(window_size);
+        transform.translation = center.extend(transform.translation.z);
+
+        let layouts = match images.get(texture) {
+            Some(image) => {
+                let (width, height) = image.img.dimensions();
+                let texture_size = Vec2::new(width as f32, height as f32);
+                let clip = clip_rect.map(|clip_rect| {
+                    Rect::from_corners(clip_rect.0.min - center, clip_rect.0.max - center)
+                });
+                layout_slices(
+                    panel.size,
+                    texture_size,
+                    &panel.slice,
+                    panel.border_scale,
+                    clip,
+                )
+            }
+            None => [None; 9],
+        };
+
+        for (slice, layout) in slices.0.iter().zip(layouts) {
+            let Ok((
+                mut slice_transform,
+                mut slice_texture,
+                mut slice_color,
+                mut slice_visibility,
+                sprite_rect,
+            )) = sprites.get_mut(*slice)
+            else {
+                continue;
+            };
+            // Children of the panel, hidden with it
+            *slice_visibility = Visibility::shown_if(layout.is_some());
+            let Some(layout) = layout else {
+                continue;
+            };
+            slice_transform.translation = layout.quad.center().extend(0.0);
+            slice_transform.scale = Vec3::new(layout.quad.width(), layout.quad.height(), 1.0);
+            if *slice_texture != *texture {
+                *slice_texture = texture.clone();
+            }
+            *slice_color = *color;
+            let rect = SpriteRect(layout.texels);
+            match sprite_rect {
+                Some(mut sprite_rect) => *sprite_rect = rect,
+                None => {
+                    commands.entity(*slice).insert(rect);
+                }
+            }
+            if let Some(render_layers) = render_layers {
+                commands.entity(*slice).insert(*render_layers);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchored_center_stays_inside_window() {
+        let panel = Panel {
+            size: Vec2::new(100.0, 50.0),
+            anchor: Anchor::TopRight,
+            offset: Vec2::new(-10.0, 0.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            panel.center(Vec2::new(800.0, 600.0)),
+            Vec2::new(400.0 - 50.0 - 10.0, 300.0 - 25.0)
+        );
+    }
+
+    #[test]
+    fn slices_keep_borders_and_clip_texels() {
+        let slice = NineSlice::uniform(4.0);
+        let texture_size = Vec2::new(16.0, 16.0);
+        let slices = layout_slices(Vec2::new(40.0, 20.0), texture_size, &slice, 1.0, None);
+
+        let top_left = slices[0].unwrap();
+        assert_eq!(top_left.quad, Rect::new(-20.0, 6.0, -16.0, 10.0));
+        assert_eq!(top_left.texels, Rect::new(0.0, 0.0, 4.0, 4.0));
+        let middle = slices[4].unwrap();
+        assert_eq!(middle.quad, Rect::new(-16.0, -6.0, 16.0, 6.0));
+        assert_eq!(middle.texels, Rect::new(4.0, 4.0, 12.0, 12.0));
+
+        // Cuts the left half of the middle column and everything below the top row
+        let clip = Rect::new(0.0, 6.0, 20.0, 10.0);
+        let slices = layout_slices(Vec2::new(40.0, 20.0), texture_size, &slice, 1.0, Some(clip));
+        assert!(slices[0].is_none());
+        assert!(slices[3..].iter().all(Option::is_none));
+        let top = slices[1].unwrap();
+        assert_eq!(top.quad, Rect::new(0.0, 6.0, 16.0, 10.0));
+        assert_eq!(top.texels, Rect::new(8.0, 0.0, 12.0, 4.0));
+    }
+}
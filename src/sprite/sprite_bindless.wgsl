@@ -0,0 +1,116 @@
+
+// -- Vertex -----
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // inverse_view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    // inverse_view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    // inverse_projection: mat4x4<f32>,
+    // world_position: vec3<f32>,
+    // viewport(x_origin, y_origin, width, height)
+    // viewport: vec4<f32>,
+}
+
+struct Globals {
+    time: f32,
+    delta_time: f32,
+    frame_count: u32,
+    resolution: vec2<f32>,
+}
+
+struct Model {
+    model: mat4x4<f32>,
+}
+
+struct VertexInput {
+    @location(0)    position: vec3<f32>,
+    @location(1)    uv: vec2<f32>,
+    @location(2)    color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        uv: vec2<f32>,
+    @location(2)        color: vec4<f32>,
+}
+
+#ifdef MODEL_PUSH_CONSTANT
+var<push_constant> model: Model;
+#else
+@group(0) @binding(0)
+var<uniform> model: Model;
+#endif
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(1)
+var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(
+    vertex: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.clip_position = camera.view_proj * model.model * vec4<f32>(vertex.position, 1.0);
+    out.uv = vertex.uv;
+    out.color = vertex.color;
+
+    return out;
+}
+
+// -- Fragment -----
+
+@group(2) @binding(0)
+// Sized by the layout, see `bindless_texture_count`
+var textures: binding_array<texture_2d<f32>>;
+@group(2) @binding(1)
+var s_diffuse: sampler;
+
+struct Sprite {
+    // (x, y, width, height) in pixels, zero size means the whole texture
+    rect: vec4<f32>,
+    // repeat count on each axis, zero means no tiling
+    tiling: vec2<f32>,
+    // fragments below this alpha are discarded, zero means no masking
+    alpha_cutoff: f32,
+    // slot in the textures array, 0 is the dummy texture
+    texture_index: u32,
+}
+
+@group(3) @binding(0)
+var<uniform> sprite: Sprite;
+
+fn sprite_uv(in_uv: vec2<f32>) -> vec2<f32> {
+    var uv = in_uv;
+    if (sprite.tiling.x > 0.0 && sprite.tiling.y > 0.0) {
+        uv = fract(uv * sprite.tiling);
+    }
+    if (sprite.rect.z <= 0.0 || sprite.rect.w <= 0.0) {
+        return uv;
+    }
+    let dimensions = vec2<f32>(textureDimensions(textures[sprite.texture_index]));
+    return (sprite.rect.xy + uv * sprite.rect.zw) / dimensions;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var tex_color = textureSample(textures[sprite.texture_index], s_diffuse, sprite_uv(in.uv));
+    tex_color += in.color;
+
+    if (sprite.alpha_cutoff > 0.0) {
+        if (tex_color.a < sprite.alpha_cutoff) {
+            discard;
+        }
+        tex_color.a = 1.0;
+    }
+
+#ifdef BLEND_MULTIPLY
+    // The Multiply blend state expects premultiplied alpha
+    tex_color = vec4<f32>(tex_color.rgb * tex_color.a, tex_color.a);
+#endif
+    return tex_color;
+}
\ No newline at end of file
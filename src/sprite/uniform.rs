@@ -0,0 +1,158 @@
+use bevy::{
+    math::Rect,
+    prelude::{
+        Commands, Component, Deref, DerefMut, Entity, GlobalTransform, Handle, Query, Res, ResMut,
+        Vec2, Vec4, With,
+    },
+};
+use encase::ShaderType;
+
+use crate::render::{
+    blend::AlphaMode,
+    camera::component::ExtractedVisibility,
+    mesh::Mesh,
+    resource::{
+        buffer::Vertex,
+        buffer_pool::BufferPool,
+        component_uniform::ComponentUniforms,
+        renderer::{RenderDevice, RenderQueue},
+        uniform::DynamicUniformId,
+    },
+    texture::Image,
+};
+
+use super::bindless::BindlessTextures;
+
+/// Pixel sub-region of the sprite texture to draw, the whole texture is drawn without it.
+#[derive(Component, Clone, Copy, Deref, DerefMut)]
+pub struct SpriteRect(pub Rect);
+
+impl SpriteRect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self(Rect::new(x, y, x + width, y + height))
+    }
+}
+
+/// Repeats the texture (or its [`SpriteRect`]) across the sprite instead of stretching it.
+///
+/// The repeat count follows the entity scale, so resizing the sprite adds tiles.
+#[derive(Component, Clone, Copy)]
+pub struct SpriteTiling {
+    /// World size of a single tile, zero or negative sizes do not tile.
+    pub tile_size: Vec2,
+}
+
+impl SpriteTiling {
+    pub fn new(tile_size: Vec2) -> Self {
+        Self { tile_size }
+    }
+
+    /// Repeat count on each axis of a sprite of world `size`, 1.0 on axes without a positive tile.
+    pub fn repeat(&self, size: Vec2) -> Vec2 {
+        let axis = |size: f32, tile: f32| if tile > 0.0 { (size / tile).abs() } else { 1.0 };
+        Vec2::new(axis(size.x, self.tile_size.x), axis(size.y, self.tile_size.y))
+    }
+}
+
+#[derive(Clone, Default, ShaderType)]
+pub struct SpriteUniform {
+    /// (x, y, width, height) in pixels, zero size means the whole texture
+    rect: Vec4,
+    /// Repeat count on each axis, zero means no tiling
+    tiling: Vec2,
+    /// Fragments below this alpha are discarded, zero means no masking
+    alpha_cutoff: f32,
+    /// Slot in the bindless texture array, unused by the regular sprite pipeline
+    texture_index: u32,
+}
+
+impl SpriteUniform {
+    /// Whole texture, no tiling, for sprites merged into a batch.
+    pub(super) fn masked(alpha_cutoff: f32) -> Self {
+        Self {
+            alpha_cutoff,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn prepare_sprite_uniforms(
+    mut commands: Commands,
+    mut sprite_uniforms: ResMut<ComponentUniforms<SpriteUniform>>,
+    bindless_textures: Res<BindlessTextures>,
+    query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &Handle<Image>,
+            Option<&SpriteRect>,
+            Option<&SpriteTiling>,
+            Option<&AlphaMode>,
+            Option<&ExtractedVisibility>,
+        ),
+        With<Handle<Mesh<Vertex>>>,
+    >,
+) {
+    let mut spawns: Vec<(Entity, DynamicUniformId<SpriteUniform>)> = Vec::new();
+
+    sprite_uniforms.clear();
+    for (
+        entity,
+        global_transform,
+        image_handle,
+        sprite_rect,
+        sprite_tiling,
+        alpha_mode,
+        computed_visibility,
+    ) in query.iter()
+    {
+        if ExtractedVisibility::is_culled(computed_visibility) {
+            continue;
+        }
+        let mut sprite_uniform = SpriteUniform {
+            texture_index: bindless_textures.index(&image_handle.id()),
+            ..Default::default()
+        };
+        if let Some(sprite_rect) = sprite_rect {
+            sprite_uniform.rect = Vec4::new(
+                sprite_rect.min.x,
+                sprite_rect.min.y,
+                sprite_rect.width(),
+                sprite_rect.height(),
+            );
+        }
+        if let Some(sprite_tiling) = sprite_tiling {
+            let (scale, _, _) = global_transform.to_scale_rotation_translation();
+            sprite_uniform.tiling = sprite_tiling.repeat(scale.truncate());
+        }
+        if let Some(alpha_mode) = alpha_mode {
+            sprite_uniform.alpha_cutoff = alpha_mode.cutoff();
+        }
+        spawns.push((entity, sprite_uniforms.push(sprite_uniform).into()));
+    }
+
+    commands.insert_or_spawn_batch(spawns);
+}
+
+pub fn queue_sprite_uniforms(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffer_pool: ResMut<BufferPool>,
+    mut sprite_uniforms: ResMut<ComponentUniforms<SpriteUniform>>,
+) {
+    sprite_uniforms.write_buffer_pooled(&render_device, &render_queue, &mut buffer_pool);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_positive_tile_size_repeats_once() {
+        let tiling = SpriteTiling::new(Vec2::new(2.0, -4.0));
+        assert_eq!(tiling.repeat(Vec2::new(8.0, 8.0)), Vec2::new(4.0, 1.0));
+
+        let repeat = SpriteTiling::new(Vec2::ZERO).repeat(Vec2::new(8.0, 0.0));
+        assert_eq!(repeat, Vec2::ONE);
+    }
+}
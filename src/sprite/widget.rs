@@ -0,0 +1,323 @@
+//!
+//! Buttons, checkboxes and sliders built on [`Panel`]s, enough for a settings menu.
+//!
+//! Widgets are picked under the cursor in overlay space, the front most one
+//! (highest `GlobalTransform` Z) gets the [`Interaction`] and the [`WidgetEvent`]s.
+//!
+
+use bevy::{
+    input::Input,
+    prelude::{
+        Bundle, Component, Entity, EventWriter, GlobalTransform, Local, MouseButton, Query, Res,
+        Transform, Vec2, Without,
+    },
+    window::Windows,
+};
+
+use crate::render::{
+    camera::component::{ComputedVisibility, Visibility},
+    color::Color,
+};
+
+use super::panel::{ClipRect, Panel, PanelBundle};
+
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interaction {
+    #[default]
+    None,
+    Hovered,
+    /// Pressed on the widget, kept while the button is held even off the widget.
+    Pressed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WidgetEvent {
+    HoverStart(Entity),
+    HoverEnd(Entity),
+    Pressed(Entity),
+    Released(Entity),
+    /// Released over the widget it was pressed on.
+    Clicked(Entity),
+    Toggled {
+        entity: Entity,
+        checked: bool,
+    },
+    SliderChanged {
+        entity: Entity,
+        value: f32,
+    },
+}
+
+/// Tint of the panel of a widget by its [`Interaction`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WidgetColors {
+    pub normal: Color,
+    pub hovered: Color,
+    pub pressed: Color,
+}
+
+impl Default for WidgetColors {
+    fn default() -> Self {
+        Self {
+            normal: Color::NO_TINT,
+            hovered: Color(0.1, 0.1, 0.1, 1.0),
+            pressed: Color(-0.1, -0.1, -0.1, 1.0),
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Button;
+
+/// `mark` is shown while checked, e.g. a check mark sprite parented to the box.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Checkbox {
+    pub checked: bool,
+    pub mark: Option<Entity>,
+}
+
+///
+/// Value in `min..=max` picked along the width of the panel, snapped to `step` if not zero.
+///
+/// `handle`, parented to the slider, is moved along its X to the value.
+///
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Slider {
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    pub handle: Option<Entity>,
+}
+
+impl Default for Slider {
+    fn default() -> Self {
+        Self {
+            value: 0.0,
+            min: 0.0,
+            max: 1.0,
+            step: 0.0,
+            handle: None,
+        }
+    }
+}
+
+impl Slider {
+    /// Value at `x` from the left edge of a slider `width` wide.
+    pub fn value_at(&self, x: f32, width: f32) -> f32 {
+        let t = (x / width.max(f32::EPSILON)).clamp(0.0, 1.0);
+        let mut value = self.min + t * (self.max - self.min);
+        if self.step > 0.0 {
+            value = self.min + ((value - self.min) / self.step).round() * self.step;
+        }
+        value.clamp(self.min.min(self.max), self.max.max(self.min))
+    }
+
+    /// Offset of the value from the center of a slider `width` wide.
+    pub fn handle_offset(&self, width: f32) -> f32 {
+        let range = self.max - self.min;
+        let t = if range == 0.0 {
+            0.0
+        } else {
+            (self.value - self.min) / range
+        };
+        (t - 0.5) * width
+    }
+}
+
+#[derive(Bundle, Default)]
+pub struct ButtonBundle {
+    #[bundle]
+    pub panel: PanelBundle,
+    pub button: Button,
+    pub interaction: Interaction,
+    pub colors: WidgetColors,
+}
+
+#[derive(Bundle, Default)]
+pub struct CheckboxBundle {
+    #[bundle]
+    pub panel: PanelBundle,
+    pub checkbox: Checkbox,
+    pub interaction: Interaction,
+    pub colors: WidgetColors,
+}
+
+#[derive(Bundle, Default)]
+pub struct SliderBundle {
+    #[bundle]
+    pub panel: PanelBundle,
+    pub slider: Slider,
+    pub interaction: Interaction,
+    pub colors: WidgetColors,
+}
+
+/// Cursor position in overlay space, origin at the center of the window and Y up.
+fn overlay_cursor(windows: &Windows) -> Option<(Vec2, Vec2)> {
+    let window = windows.get_primary()?;
+    let window_size = Vec2::new(window.width(), window.height());
+    let cursor = window.cursor_position()?;
+    Some((cursor - window_size / 2.0, window_size))
+}
+
+fn panel_contains(panel: &Panel, center: Vec2, clip: Option<&ClipRect>, point: Vec2) -> bool {
+    let offset = (point - center).abs();
+    offset.x <= panel.size.x / 2.0
+        && offset.y <= panel.size.y / 2.0
+        && clip.map_or(true, |clip| clip.0.contains(point))
+}
+
+/// Picks the widget under the cursor and updates the [`Interaction`]s.
+pub fn update_interactions(
+    windows: Option<Res<Windows>>,
+    mouse: Option<Res<Input<MouseButton>>>,
+    mut widgets: Query<(
+        Entity,
+        &Panel,
+        Option<&ComputedVisibility>,
+        &GlobalTransform,
+        Option<&ClipRect>,
+        &mut Interaction,
+    )>,
+    mut widget_events: EventWriter<WidgetEvent>,
+) {
+    // Without bevy's input and window plugins there is nothing to interact with
+    let (Some(windows), Some(mouse)) = (windows, mouse) else {
+        return;
+    };
+    let cursor = overlay_cursor(&windows);
+    let hovered = cursor.and_then(|(cursor, window_size)| {
+        widgets
+            .iter()
+            .filter(|(_, panel, computed_visibility, _, clip, _)| {
+                !ComputedVisibility::is_culled(*computed_visibility)
+                    && panel_contains(panel, panel.center(window_size), *clip, cursor)
+            })
+            .max_by(|(.., a, _, _), (.., b, _, _)| a.translation().z.total_cmp(&b.translation().z))
+            .map(|(entity, ..)| entity)
+    });
+
+    for (entity, .., mut interaction) in widgets.iter_mut() {
+        let over = hovered == Some(entity);
+        let next = match *interaction {
+            Interaction::Pressed if mouse.pressed(MouseButton::Left) => Interaction::Pressed,
+            _ if over && mouse.just_pressed(MouseButton::Left) => Interaction::Pressed,
+            _ if over => Interaction::Hovered,
+            _ => Interaction::None,
+        };
+        if next == *interaction {
+            continue;
+        }
+        match (*interaction, next) {
+            (Interaction::Pressed, _) => {
+                widget_events.send(WidgetEvent::Released(entity));
+                if over {
+                    widget_events.send(WidgetEvent::Clicked(entity));
+                } else {
+                    widget_events.send(WidgetEvent::HoverEnd(entity));
+                }
+            }
+            (Interaction::None, _) => widget_events.send(WidgetEvent::HoverStart(entity)),
+            (Interaction::Hovered, Interaction::None) => {
+                widget_events.send(WidgetEvent::HoverEnd(entity))
+            }
+            _ => {}
+        }
+        if next == Interaction::Pressed {
+            widget_events.send(WidgetEvent::Pressed(entity));
+        }
+        *interaction = next;
+    }
+}
+
+pub fn tint_widgets(mut widgets: Query<(&Interaction, &WidgetColors, &mut Color)>) {
+    for (interaction, colors, mut color) in widgets.iter_mut() {
+        let tint = match interaction {
+            Interaction::None => colors.normal,
+            Interaction::Hovered => colors.hovered,
+            Interaction::Pressed => colors.pressed,
+        };
+        if *color != tint {
+            *color = tint;
+        }
+    }
+}
+
+pub fn toggle_checkboxes(
+    mut widget_events: EventWriter<WidgetEvent>,
+    mut checkboxes: Query<(Entity, &Interaction, &mut Checkbox)>,
+    mut marks: Query<&mut Visibility, (Without<Checkbox>, Without<Panel>)>,
+    mut clicked: Local<Vec<Entity>>,
+) {
+    for (entity, interaction, mut checkbox) in checkboxes.iter_mut() {
+        // Toggled on the release over the box, the frame the interaction leaves `Pressed`
+        let was_pressed = clicked.contains(&entity);
+        if *interaction == Interaction::Pressed {
+            if !was_pressed {
+                clicked.push(entity);
+            }
+        } else if was_pressed {
+            clicked.retain(|pressed| *pressed != entity);
+            if *interaction == Interaction::Hovered {
+                checkbox.checked = !checkbox.checked;
+                widget_events.send(WidgetEvent::Toggled {
+                    entity,
+                    checked: checkbox.checked,
+                });
+            }
+        }
+        if let Some(mut mark) = checkbox.mark.and_then(|mark| marks.get_mut(mark).ok()) {
+            *mark = Visibility::shown_if(checkbox.checked);
+        }
+    }
+}
+
+pub fn drag_sliders(
+    windows: Res<Windows>,
+    mut widget_events: EventWriter<WidgetEvent>,
+    mut sliders: Query<(Entity, &Panel, &Interaction, &mut Slider)>,
+    mut handles: Query<&mut Transform, (Without<Slider>, Without<Panel>)>,
+) {
+    let cursor = overlay_cursor(&windows);
+    for (entity, panel, interaction, mut slider) in sliders.iter_mut() {
+        if let (Interaction::Pressed, Some((cursor, window_size))) = (interaction, cursor) {
+            let left = panel.center(window_size).x - panel.size.x / 2.0;
+            let value = slider.value_at(cursor.x - left, panel.size.x);
+            if value != slider.value {
+                slider.value = value;
+                widget_events.send(WidgetEvent::SliderChanged { entity, value });
+            }
+        }
+        if let Some(mut handle) = slider
+            .handle
+            .and_then(|handle| handles.get_mut(handle).ok())
+        {
+            handle.translation.x = slider.handle_offset(panel.size.x);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slider_snaps_and_clamps() {
+        let slider = Slider {
+            min: 0.0,
+            max: 10.0,
+            step: 2.5,
+            ..Default::default()
+        };
+        assert_eq!(slider.value_at(0.3 * 200.0, 200.0), 2.5);
+        assert_eq!(slider.value_at(0.45 * 200.0, 200.0), 5.0);
+        assert_eq!(slider.value_at(-20.0, 200.0), 0.0);
+        assert_eq!(slider.value_at(260.0, 200.0), 10.0);
+
+        let slider = Slider {
+            value: 7.5,
+            ..slider
+        };
+        assert_eq!(slider.handle_offset(200.0), 50.0);
+    }
+}
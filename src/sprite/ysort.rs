@@ -0,0 +1,100 @@
+use bevy::prelude::{Component, GlobalTransform, Query, Res, Resource};
+
+/// Derives the depth of the entity from its world Y, for top-down 2.5D scenes.
+///
+/// Entities lower on the screen end up in front of the ones above them,
+/// the original z of the entity is kept as the base layer.
+#[derive(Component, Default, Clone, Copy)]
+pub struct YSort {
+    /// Added to the world Y before sorting, e.g. to sort by the feet of a character.
+    pub offset: f32,
+    applied: f32,
+    written_z: Option<f32>,
+}
+
+impl YSort {
+    pub fn with_offset(offset: f32) -> Self {
+        Self {
+            offset,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Resource, Clone, Copy)]
+pub struct YSortSettings {
+    /// Depth change per world unit of Y,
+    /// keep it small enough to stay inside the layer of the entity.
+    pub depth_per_unit: f32,
+}
+
+impl Default for YSortSettings {
+    fn default() -> Self {
+        Self {
+            depth_per_unit: 0.001,
+        }
+    }
+}
+
+pub fn y_sort_system(
+    settings: Res<YSortSettings>,
+    mut query: Query<(&mut YSort, &mut GlobalTransform)>,
+) {
+    for (mut y_sort, mut global_transform) in query.iter_mut() {
+        let mut affine = global_transform.affine();
+        let z = affine.translation.z;
+
+        // GlobalTransform is only rewritten by propagation when Transform changes,
+        // remove the previously applied offset otherwise
+        let base = match y_sort.written_z {
+            Some(written_z) if written_z == z => z - y_sort.applied,
+            _ => z,
+        };
+        let applied = -(affine.translation.y + y_sort.offset) * settings.depth_per_unit;
+
+        // Only written when the depth moves, to keep change detection quiet
+        if base + applied == z {
+            continue;
+        }
+        affine.translation.z = base + applied;
+        y_sort.applied = applied;
+        y_sort.written_z = Some(affine.translation.z);
+
+        *global_transform = GlobalTransform::from(affine);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{App, Changed, Entity, IntoSystemDescriptor, Query, ResMut};
+
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct ChangedCount(usize);
+
+    fn count_changed(
+        mut count: ResMut<ChangedCount>,
+        query: Query<Entity, Changed<GlobalTransform>>,
+    ) {
+        count.0 += query.iter().count();
+    }
+
+    #[test]
+    fn unchanged_depth_is_not_written() {
+        let mut app = App::new();
+        app.init_resource::<YSortSettings>()
+            .init_resource::<ChangedCount>()
+            .add_system(y_sort_system)
+            .add_system(count_changed.after(y_sort_system));
+
+        app.world
+            .spawn((YSort::default(), GlobalTransform::from_xyz(0.0, 10.0, 1.0)));
+        app.update();
+        assert_eq!(app.world.resource::<ChangedCount>().0, 1);
+
+        app.update();
+        app.update();
+        assert_eq!(app.world.resource::<ChangedCount>().0, 1);
+    }
+}
@@ -0,0 +1,12 @@
+use bevy::prelude::{Bundle, GlobalTransform, Handle, Transform};
+
+use super::{Tilemap, TilemapChunks};
+
+/// Root of a tilemap, chunk entities are spawned as its children.
+#[derive(Bundle, Default)]
+pub struct TilemapBundle {
+    pub global_transform: GlobalTransform,
+    pub transform: Transform,
+    pub tilemap: Handle<Tilemap>,
+    pub chunks: TilemapChunks,
+}
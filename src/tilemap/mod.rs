@@ -0,0 +1,348 @@
+use bevy::{
+    asset::HandleId,
+    prelude::{
+        AddAsset, AssetEvent, Assets, BuildChildren, Commands, Component, CoreStage,
+        DespawnRecursiveExt, Entity, EventReader, Handle, IntoSystemDescriptor, Plugin, Query,
+        Res, ResMut, Transform, UVec2, Vec2,
+    },
+    reflect::TypeUuid,
+    transform::TransformSystem,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    render::{
+        color::Color,
+        mesh::Mesh,
+        resource::buffer::{Indices, Vertex},
+        texture::Image,
+    },
+    sprite::bundle::SpriteBundle,
+};
+
+use self::tiled::{spawn_tiled_maps, TiledMap, TiledMapLoader};
+
+pub mod bundle;
+pub mod tiled;
+
+pub struct FlatTilemapPlugin;
+impl Plugin for FlatTilemapPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_asset::<Tilemap>()
+            .add_asset::<TiledMap>()
+            .init_asset_loader::<TiledMapLoader>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                spawn_tiled_maps.before(update_tilemap_chunks),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_tilemap_chunks.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+pub type Tile = Option<u32>;
+
+/// Grid of tile indices into a texture atlas.
+///
+/// The map is split into chunks of `chunk_size` x `chunk_size` tiles,
+/// each chunk is rendered as a single mesh and only the chunks
+/// modified through [`Tilemap::set`] are rebuilt.
+#[derive(TypeUuid)]
+#[uuid = "8C752C5D-C9B7-4C40-8C9C-DE88D22CD8EB"]
+pub struct Tilemap {
+    pub tile_size: Vec2,
+    pub atlas: Handle<Image>,
+    /// Number of tile columns and rows in the atlas image.
+    pub atlas_grid: UVec2,
+    /// Pixel layout of atlases with a margin or spacing, `None` if the tiles fill the image.
+    pub atlas_spacing: Option<AtlasSpacing>,
+    size: UVec2,
+    chunk_size: u32,
+    tiles: Vec<Tile>,
+    revision: u64,
+    chunk_revisions: Vec<u64>,
+}
+
+impl Tilemap {
+    pub const DEFAULT_CHUNK_SIZE: u32 = 32;
+
+    pub fn new(size: UVec2, tile_size: Vec2, atlas: Handle<Image>, atlas_grid: UVec2) -> Self {
+        Self::with_chunk_size(size, tile_size, atlas, atlas_grid, Self::DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(
+        size: UVec2,
+        tile_size: Vec2,
+        atlas: Handle<Image>,
+        atlas_grid: UVec2,
+        chunk_size: u32,
+    ) -> Self {
+        assert!(chunk_size > 0, "Tilemap chunk size cannot be zero");
+        let chunk_count = Self::chunk_count_for(size, chunk_size);
+        Self {
+            tile_size,
+            atlas,
+            atlas_grid,
+            atlas_spacing: None,
+            size,
+            chunk_size,
+            tiles: vec![None; (size.x * size.y) as usize],
+            revision: 1,
+            chunk_revisions: vec![1; (chunk_count.x * chunk_count.y) as usize],
+        }
+    }
+
+    fn chunk_count_for(size: UVec2, chunk_size: u32) -> UVec2 {
+        UVec2::new(
+            (size.x + chunk_size - 1) / chunk_size,
+            (size.y + chunk_size - 1) / chunk_size,
+        )
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    pub fn chunk_count(&self) -> UVec2 {
+        Self::chunk_count_for(self.size, self.chunk_size)
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        (x < self.size.x && y < self.size.y).then(|| (y * self.size.x + x) as usize)
+    }
+
+    fn chunk_index(&self, x: u32, y: u32) -> usize {
+        let chunk_count = self.chunk_count();
+        ((y / self.chunk_size) * chunk_count.x + (x / self.chunk_size)) as usize
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Tile {
+        self.tiles[self.index(x, y)?]
+    }
+
+    /// Sets the tile at (x, y), out of bounds positions are ignored.
+    pub fn set(&mut self, x: u32, y: u32, tile: Tile) {
+        let Some(index) = self.index(x, y) else {
+            return;
+        };
+        if self.tiles[index] == tile {
+            return;
+        }
+        self.tiles[index] = tile;
+        self.revision += 1;
+        let chunk_index = self.chunk_index(x, y);
+        self.chunk_revisions[chunk_index] = self.revision;
+    }
+
+    pub fn fill(&mut self, tile: Tile) {
+        self.tiles.fill(tile);
+        self.revision += 1;
+        self.chunk_revisions.fill(self.revision);
+    }
+
+    fn tile_uvs(&self, tile: u32) -> ([f32; 2], [f32; 2]) {
+        let grid = self.atlas_grid.max(UVec2::ONE);
+        let (col, row) = (tile % grid.x, tile / grid.x);
+        if let Some(spacing) = &self.atlas_spacing {
+            let image_size = spacing.image_size.max(UVec2::ONE).as_vec2();
+            let stride = spacing.tile_size + UVec2::splat(spacing.spacing);
+            let min = UVec2::splat(spacing.margin) + UVec2::new(col, row) * stride;
+            let max = min + spacing.tile_size;
+            let (min, max) = (min.as_vec2() / image_size, max.as_vec2() / image_size);
+            return ([min.x, min.y], [max.x, max.y]);
+        }
+        let (w, h) = (1.0 / grid.x as f32, 1.0 / grid.y as f32);
+        (
+            [col as f32 * w, row as f32 * h],
+            [(col + 1) as f32 * w, (row + 1) as f32 * h],
+        )
+    }
+
+    /// Builds the mesh of a chunk in map local space, `None` if the chunk has no tiles.
+    pub fn build_chunk_mesh(&self, chunk: UVec2) -> Option<Mesh<Vertex>> {
+        let start = chunk * self.chunk_size;
+        let end = (start + UVec2::splat(self.chunk_size)).min(self.size);
+
+        let mut vertices = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        let color = Color::NO_TINT.as_arr();
+
+        for y in start.y..end.y {
+            for x in start.x..end.x {
+                let Some(tile) = self.get(x, y) else {
+                    continue;
+                };
+                let (uv_min, uv_max) = self.tile_uvs(tile);
+                let (x0, y0) = (x as f32 * self.tile_size.x, y as f32 * self.tile_size.y);
+                let (x1, y1) = (x0 + self.tile_size.x, y0 + self.tile_size.y);
+
+                let offset = vertices.len() as u32;
+                vertices.extend([
+                    Vertex {
+                        position: [x0, y1, 0.0],
+                        uv: [uv_min[0], uv_min[1]],
+                        color,
+                    }, // tl
+                    Vertex {
+                        position: [x0, y0, 0.0],
+                        uv: [uv_min[0], uv_max[1]],
+                        color,
+                    }, // bl
+                    Vertex {
+                        position: [x1, y0, 0.0],
+                        uv: [uv_max[0], uv_max[1]],
+                        color,
+                    }, // br
+                    Vertex {
+                        position: [x1, y1, 0.0],
+                        uv: [uv_max[0], uv_min[1]],
+                        color,
+                    }, // tr
+                ]);
+                indices.extend([0, 1, 2, 2, 3, 0].map(|i| offset + i));
+            }
+        }
+
+        if vertices.is_empty() {
+            return None;
+        }
+
+        Some(Mesh::new_with(
+            wgpu::PrimitiveTopology::TriangleList,
+            vertices,
+            Some(Indices::U32(indices)),
+        ))
+    }
+}
+
+/// Pixel layout of an atlas whose tiles are not packed edge to edge, as exported by Tiled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasSpacing {
+    pub image_size: UVec2,
+    pub tile_size: UVec2,
+    /// Border around the tiles.
+    pub margin: u32,
+    /// Gap between neighbouring tiles.
+    pub spacing: u32,
+}
+
+pub struct TilemapChunk {
+    pub entity: Entity,
+    pub mesh: Handle<Mesh<Vertex>>,
+}
+
+/// Chunk entities spawned for a [`Tilemap`], kept in sync by [`update_tilemap_chunks`].
+///
+/// Chunks without tiles, or outside the map after it is replaced, are despawned.
+#[derive(Component, Default)]
+pub struct TilemapChunks {
+    pub chunks: HashMap<UVec2, TilemapChunk>,
+    built_revision: u64,
+    built_map: Option<HandleId>,
+}
+
+impl TilemapChunks {
+    fn despawn(&mut self, commands: &mut Commands, chunk: &UVec2) {
+        if let Some(tilemap_chunk) = self.chunks.remove(chunk) {
+            commands.entity(tilemap_chunk.entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn update_tilemap_chunks(
+    mut commands: Commands,
+    tilemaps: Res<Assets<Tilemap>>,
+    mut meshes: ResMut<Assets<Mesh<Vertex>>>,
+    mut tilemap_events: EventReader<AssetEvent<Tilemap>>,
+    mut query: Query<(Entity, &Handle<Tilemap>, &mut TilemapChunks)>,
+) {
+    // Replaced maps restart their revisions, every chunk is rebuilt
+    let modified: HashSet<HandleId> = tilemap_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.id()),
+            _ => None,
+        })
+        .collect();
+
+    for (map_entity, tilemap_handle, mut tilemap_chunks) in query.iter_mut() {
+        let Some(tilemap) = tilemaps.get(tilemap_handle) else {
+            continue;
+        };
+        let map_id = tilemap_handle.id();
+        let replaced = tilemap_chunks.built_map != Some(map_id) || modified.contains(&map_id);
+        if replaced {
+            tilemap_chunks.built_revision = 0;
+            tilemap_chunks.built_map = Some(map_id);
+        }
+        if tilemap.revision() == tilemap_chunks.built_revision {
+            continue;
+        }
+
+        let chunk_count = tilemap.chunk_count();
+        let outside: Vec<UVec2> = tilemap_chunks
+            .chunks
+            .keys()
+            .filter(|chunk| chunk.x >= chunk_count.x || chunk.y >= chunk_count.y)
+            .copied()
+            .collect();
+        for chunk in &outside {
+            tilemap_chunks.despawn(&mut commands, chunk);
+        }
+
+        for cy in 0..chunk_count.y {
+            for cx in 0..chunk_count.x {
+                let chunk = UVec2::new(cx, cy);
+                let chunk_revision = tilemap.chunk_revisions[(cy * chunk_count.x + cx) as usize];
+                let spawned = tilemap_chunks.chunks.contains_key(&chunk);
+                if spawned && chunk_revision <= tilemap_chunks.built_revision {
+                    continue;
+                }
+
+                let Some(mesh) = tilemap.build_chunk_mesh(chunk) else {
+                    tilemap_chunks.despawn(&mut commands, &chunk);
+                    continue;
+                };
+
+                match tilemap_chunks.chunks.get(&chunk) {
+                    Some(tilemap_chunk) => {
+                        // Triggers AssetEvent::Modified, only this chunk is uploaded again
+                        let _ = meshes.set(&tilemap_chunk.mesh, mesh);
+                        if replaced {
+                            commands
+                                .entity(tilemap_chunk.entity)
+                                .insert(tilemap.atlas.clone());
+                        }
+                    }
+                    None => {
+                        let mesh = meshes.add(mesh);
+                        let entity = commands
+                            .spawn(SpriteBundle {
+                                transform: Transform::IDENTITY,
+                                mesh: mesh.clone(),
+                                texture: tilemap.atlas.clone(),
+                                ..Default::default()
+                            })
+                            .id();
+                        commands.entity(map_entity).add_child(entity);
+                        tilemap_chunks
+                            .chunks
+                            .insert(chunk, TilemapChunk { entity, mesh });
+                    }
+                }
+            }
+        }
+
+        tilemap_chunks.built_revision = tilemap.revision();
+    }
+}
@@ -0,0 +1,444 @@
+use std::{path::Path, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::{
+        AssetEvent, Assets, BuildChildren, Bundle, Commands, Component, DespawnRecursiveExt,
+        Entity, EventReader, GlobalTransform, Handle, Query, Res, Transform, UVec2, Vec2,
+    },
+    reflect::TypeUuid,
+    utils::{HashMap, HashSet},
+};
+
+use crate::render::texture::Image;
+
+use super::{bundle::TilemapBundle, AtlasSpacing, Tilemap};
+
+/// Map imported from a Tiled `.tmx` file.
+///
+/// Tile layers are loaded as labeled [`Tilemap`] assets (`layer0`, `layer1`, ...),
+/// object layers are kept as [`TiledObject`]s and spawned as entities.
+/// Only the first tileset of the map is used as the atlas.
+#[derive(TypeUuid)]
+#[uuid = "256E63CA-B9F3-40D4-8CEB-BC0BBC9D7A8F"]
+pub struct TiledMap {
+    pub size: UVec2,
+    pub tile_size: Vec2,
+    pub layers: Vec<Handle<Tilemap>>,
+    pub objects: Vec<TiledObject>,
+}
+
+/// Object from a Tiled object layer, positioned at its top-left corner in map space.
+#[derive(Component, Clone, Debug)]
+pub struct TiledObject {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    pub layer: String,
+    pub position: Vec2,
+    pub size: Vec2,
+    pub properties: HashMap<String, String>,
+}
+
+#[derive(Bundle, Default)]
+pub struct TiledMapBundle {
+    pub global_transform: GlobalTransform,
+    pub transform: Transform,
+    pub map: Handle<TiledMap>,
+}
+
+/// Layer and object entities spawned for a [`TiledMap`] entity,
+/// despawned and spawned again when the map asset is modified.
+#[derive(Component)]
+pub struct TiledMapSpawned {
+    pub entities: Vec<Entity>,
+}
+
+/// Depth between consecutive tile layers.
+pub const TILED_LAYER_DEPTH: f32 = 0.01;
+
+pub fn spawn_tiled_maps(
+    mut commands: Commands,
+    tiled_maps: Res<Assets<TiledMap>>,
+    mut map_events: EventReader<AssetEvent<TiledMap>>,
+    query: Query<(Entity, &Handle<TiledMap>, Option<&TiledMapSpawned>)>,
+) {
+    let modified: HashSet<&Handle<TiledMap>> = map_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, map_handle, spawned) in query.iter() {
+        if let Some(spawned) = spawned {
+            if !modified.contains(map_handle) {
+                continue;
+            }
+            // Hot reloaded, the layers are spawned again from the new asset
+            for spawned_entity in &spawned.entities {
+                commands.entity(*spawned_entity).despawn_recursive();
+            }
+            commands.entity(entity).remove::<TiledMapSpawned>();
+        }
+        let Some(tiled_map) = tiled_maps.get(map_handle) else {
+            continue;
+        };
+
+        let mut entities = Vec::new();
+        commands.entity(entity).with_children(|parent| {
+            for (i, layer) in tiled_map.layers.iter().enumerate() {
+                let layer_entity = parent.spawn(TilemapBundle {
+                    transform: Transform::from_xyz(0.0, 0.0, i as f32 * TILED_LAYER_DEPTH),
+                    tilemap: layer.clone(),
+                    ..Default::default()
+                });
+                entities.push(layer_entity.id());
+            }
+            for object in &tiled_map.objects {
+                let object_entity = parent.spawn((
+                    object.clone(),
+                    Transform::from_translation(object.position.extend(0.0)),
+                    GlobalTransform::default(),
+                ));
+                entities.push(object_entity.id());
+            }
+        });
+        commands.entity(entity).insert(TiledMapSpawned { entities });
+    }
+}
+
+#[derive(Default)]
+pub struct TiledMapLoader;
+impl AssetLoader for TiledMapLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> bevy::asset::BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let tmx = TmxMap::parse(std::str::from_utf8(bytes)?)?;
+            let map_dir = load_context.path().parent().unwrap_or(Path::new("")).to_owned();
+
+            // Image paths of external tilesets are relative to the .tsx file
+            let (tileset, image_dir) = match &tmx.tileset {
+                TmxTilesetRef::Embedded(tileset) => (tileset.clone(), map_dir),
+                TmxTilesetRef::External(source) => {
+                    let tsx_path = map_dir.join(source);
+                    let tsx_bytes = load_context.read_asset_bytes(&tsx_path).await?;
+                    let tileset = TmxTileset::parse(std::str::from_utf8(&tsx_bytes)?)?;
+                    let tsx_dir = tsx_path.parent().unwrap_or(Path::new("")).to_owned();
+                    (tileset, tsx_dir)
+                }
+            };
+
+            let atlas_path = AssetPath::new(image_dir.join(&tileset.image), None);
+            let atlas: Handle<Image> = load_context.get_handle(atlas_path.clone());
+            let atlas_grid = tileset.grid();
+            let atlas_spacing = tileset.spacing();
+            let tile_size = Vec2::new(tmx.tile_width as f32, tmx.tile_height as f32);
+            let size = UVec2::new(tmx.width, tmx.height);
+
+            let mut layers = Vec::with_capacity(tmx.layers.len());
+            for (i, gids) in tmx.layers.iter().enumerate() {
+                let mut tilemap = Tilemap::new(size, tile_size, atlas.clone(), atlas_grid);
+                tilemap.atlas_spacing = atlas_spacing;
+                for (index, gid) in gids.iter().enumerate() {
+                    let gid = gid & TMX_GID_MASK;
+                    if gid < tmx.first_gid || gid - tmx.first_gid >= tileset.tile_count {
+                        continue;
+                    }
+                    let (x, row) = (index as u32 % tmx.width, index as u32 / tmx.width);
+                    // Tiled rows go downwards, tilemap rows go upwards
+                    tilemap.set(x, tmx.height - 1 - row, Some(gid - tmx.first_gid));
+                }
+                layers.push(load_context.set_labeled_asset(
+                    &format!("layer{i}"),
+                    LoadedAsset::new(tilemap).with_dependency(atlas_path.clone()),
+                ));
+            }
+
+            let map_height = (tmx.height * tmx.tile_height) as f32;
+            let objects = tmx
+                .objects
+                .into_iter()
+                .map(|mut object| {
+                    object.position.y = map_height - object.position.y;
+                    object
+                })
+                .collect();
+
+            load_context.set_default_asset(LoadedAsset::new(TiledMap {
+                size,
+                tile_size,
+                layers,
+                objects,
+            }));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmx"]
+    }
+}
+
+// Upper bits of a gid hold the flip flags
+const TMX_GID_MASK: u32 = 0x1FFF_FFFF;
+
+struct TmxMap {
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    first_gid: u32,
+    tileset: TmxTilesetRef,
+    layers: Vec<Vec<u32>>,
+    objects: Vec<TiledObject>,
+}
+
+enum TmxTilesetRef {
+    Embedded(TmxTileset),
+    External(String),
+}
+
+#[derive(Clone)]
+struct TmxTileset {
+    columns: u32,
+    tile_count: u32,
+    tile_size: UVec2,
+    margin: u32,
+    spacing: u32,
+    image: String,
+    /// Size from the `<image>` element, older exports can omit it
+    image_size: Option<UVec2>,
+}
+
+fn attribute<T: FromStr>(node: &roxmltree::Node, name: &str) -> Result<T> {
+    node.attribute(name)
+        .ok_or_else(|| anyhow!("Missing attribute '{}' on <{}>", name, node.tag_name().name()))?
+        .parse()
+        .map_err(|_| anyhow!("Invalid attribute '{}' on <{}>", name, node.tag_name().name()))
+}
+
+fn attribute_or<T: FromStr>(node: &roxmltree::Node, name: &str, default: T) -> T {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn child<'a, 'input>(
+    node: &roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|c| c.has_tag_name(name))
+}
+
+impl TmxMap {
+    fn parse(text: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(text)?;
+        let map = doc.root_element();
+        if !map.has_tag_name("map") {
+            bail!("Root element of a .tmx file must be <map>");
+        }
+        if map.attribute("orientation").unwrap_or("orthogonal") != "orthogonal" {
+            bail!("Only orthogonal Tiled maps are supported");
+        }
+
+        let width: u32 = attribute(&map, "width")?;
+        let height: u32 = attribute(&map, "height")?;
+
+        let tileset_node = child(&map, "tileset").context("Tiled map has no tileset")?;
+        let first_gid = attribute(&tileset_node, "firstgid")?;
+        let tileset = match tileset_node.attribute("source") {
+            Some(source) => TmxTilesetRef::External(source.to_string()),
+            None => TmxTilesetRef::Embedded(TmxTileset::from_node(&tileset_node)?),
+        };
+
+        let mut layers = Vec::new();
+        let mut objects = Vec::new();
+        for node in map.children().filter(|c| c.is_element()) {
+            match node.tag_name().name() {
+                "layer" => layers.push(Self::parse_layer(&node, width * height)?),
+                "objectgroup" => Self::parse_objects(&node, &mut objects)?,
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            tile_width: attribute(&map, "tilewidth")?,
+            tile_height: attribute(&map, "tileheight")?,
+            first_gid,
+            tileset,
+            layers,
+            objects,
+        })
+    }
+
+    fn parse_layer(node: &roxmltree::Node, tile_count: u32) -> Result<Vec<u32>> {
+        let data = child(node, "data").context("Tile layer has no <data>")?;
+        if data.attribute("encoding") != Some("csv") {
+            bail!("Only CSV encoded tile layers are supported");
+        }
+        let gids = data
+            .text()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()?;
+        if gids.len() != tile_count as usize {
+            bail!("Tile layer has {} tiles, expected {}", gids.len(), tile_count);
+        }
+        Ok(gids)
+    }
+
+    fn parse_objects(node: &roxmltree::Node, objects: &mut Vec<TiledObject>) -> Result<()> {
+        let layer = node.attribute("name").unwrap_or_default().to_string();
+        for object in node.children().filter(|c| c.has_tag_name("object")) {
+            let properties = child(&object, "properties")
+                .map(|props| {
+                    props
+                        .children()
+                        .filter(|p| p.has_tag_name("property"))
+                        .filter_map(|p| {
+                            Some((
+                                p.attribute("name")?.to_string(),
+                                p.attribute("value").or(p.text())?.to_string(),
+                            ))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            objects.push(TiledObject {
+                id: attribute(&object, "id")?,
+                name: object.attribute("name").unwrap_or_default().to_string(),
+                // "type" before Tiled 1.9
+                class: object
+                    .attribute("class")
+                    .or(object.attribute("type"))
+                    .unwrap_or_default()
+                    .to_string(),
+                layer: layer.clone(),
+                position: Vec2::new(attribute(&object, "x")?, attribute(&object, "y")?),
+                size: Vec2::new(
+                    attribute_or(&object, "width", 0.0),
+                    attribute_or(&object, "height", 0.0),
+                ),
+                properties,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl TmxTileset {
+    fn parse(text: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(text)?;
+        let tileset = doc.root_element();
+        if !tileset.has_tag_name("tileset") {
+            bail!("Root element of a .tsx file must be <tileset>");
+        }
+        Self::from_node(&tileset)
+    }
+
+    fn from_node(node: &roxmltree::Node) -> Result<Self> {
+        let image = child(node, "image").context("Only single image tilesets are supported")?;
+        let image_size = match (image.attribute("width"), image.attribute("height")) {
+            (Some(_), Some(_)) => Some(UVec2::new(
+                attribute(&image, "width")?,
+                attribute(&image, "height")?,
+            )),
+            _ => None,
+        };
+        Ok(Self {
+            columns: attribute(node, "columns")?,
+            tile_count: attribute(node, "tilecount")?,
+            tile_size: UVec2::new(attribute(node, "tilewidth")?, attribute(node, "tileheight")?),
+            margin: attribute_or(node, "margin", 0),
+            spacing: attribute_or(node, "spacing", 0),
+            image: attribute(&image, "source")?,
+            image_size,
+        })
+    }
+
+    fn grid(&self) -> UVec2 {
+        let columns = self.columns.max(1);
+        UVec2::new(columns, (self.tile_count + columns - 1) / columns)
+    }
+
+    /// `None` when the tiles fill the image edge to edge.
+    fn spacing(&self) -> Option<AtlasSpacing> {
+        if self.margin == 0 && self.spacing == 0 {
+            return None;
+        }
+        let grid = self.grid();
+        let packed = |count: u32, tile: u32| {
+            2 * self.margin + count * tile + count.saturating_sub(1) * self.spacing
+        };
+        Some(AtlasSpacing {
+            image_size: self.image_size.unwrap_or_else(|| {
+                UVec2::new(packed(grid.x, self.tile_size.x), packed(grid.y, self.tile_size.y))
+            }),
+            tile_size: self.tile_size,
+            margin: self.margin,
+            spacing: self.spacing,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.9" orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16">
+ <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16" spacing="2" margin="1" tilecount="4" columns="2">
+  <image source="tiles.png" width="36" height="36"/>
+ </tileset>
+ <layer id="1" name="ground" width="2" height="2">
+  <data encoding="csv">
+1,2,
+0,4
+</data>
+ </layer>
+ <objectgroup id="2" name="spawns">
+  <object id="3" name="player" class="Spawn" x="8" y="24"/>
+ </objectgroup>
+</map>"#;
+
+    #[test]
+    fn parse_map_with_spaced_tileset() {
+        let tmx = TmxMap::parse(TMX).unwrap();
+        assert_eq!((tmx.width, tmx.height, tmx.first_gid), (2, 2, 1));
+        assert_eq!(tmx.layers, vec![vec![1, 2, 0, 4]]);
+        assert_eq!(tmx.objects[0].name, "player");
+        assert_eq!(tmx.objects[0].position, Vec2::new(8.0, 24.0));
+
+        let TmxTilesetRef::Embedded(tileset) = tmx.tileset else {
+            panic!("tileset is embedded");
+        };
+        let spacing = tileset.spacing().unwrap();
+        assert_eq!(spacing.image_size, UVec2::new(36, 36));
+        assert_eq!((spacing.margin, spacing.spacing), (1, 2));
+
+        let mut tilemap = Tilemap::new(
+            UVec2::new(2, 2),
+            Vec2::splat(16.0),
+            Handle::default(),
+            tileset.grid(),
+        );
+        tilemap.atlas_spacing = Some(spacing);
+        // Second column starts after the margin, a tile and the spacing
+        let (uv_min, uv_max) = tilemap.tile_uvs(1);
+        assert_eq!(uv_min, [19.0 / 36.0, 1.0 / 36.0]);
+        assert_eq!(uv_max, [35.0 / 36.0, 17.0 / 36.0]);
+    }
+}
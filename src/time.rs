@@ -0,0 +1,233 @@
+//!
+//! Fixed timestep updates.
+//!
+//! [`FixedUpdateStage`] runs after `CoreStage::Update` and before `CoreStage::PostUpdate`,
+//! as many steps as the frame time covers, so the transforms the steps write are propagated
+//! and extracted by the render stages of the same frame.
+//!
+//! Entities with [`InterpolatedTransform`] are drawn between their last two steps,
+//! movement written in fixed steps looks smooth at any frame rate.
+//! Move them in the fixed steps only, their `Transform` is the state of the last step
+//! from `CoreStage::First` until the interpolation in `CoreStage::PostUpdate`.
+//!
+
+use std::time::Duration;
+
+use bevy::{
+    ecs::schedule::ShouldRun,
+    prelude::{
+        App, Component, CoreStage, IntoSystemDescriptor, Plugin, Query, Res, ResMut, Resource,
+        Schedule, StageLabel, SystemStage, Transform,
+    },
+    time::Time,
+    transform::TransformSystem,
+};
+
+/// Runs [`FixedStage`]s in a loop, once per fixed step.
+#[derive(StageLabel)]
+pub struct FixedUpdateStage;
+
+#[derive(StageLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FixedStage {
+    /// Saves the transforms before the step.
+    First,
+    Update,
+    /// Saves the transforms after the step.
+    Last,
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct FixedTime {
+    step: Duration,
+    /// Steps after a long frame are capped, the simulation slows down instead of spiraling.
+    pub max_steps_per_frame: u32,
+    accumulator: Duration,
+    looping: bool,
+    steps_this_frame: u32,
+}
+
+impl Default for FixedTime {
+    fn default() -> Self {
+        Self::from_hz(60.0)
+    }
+}
+
+impl FixedTime {
+    pub fn from_hz(hz: f64) -> Self {
+        Self {
+            step: Duration::from_secs_f64(1.0 / hz),
+            max_steps_per_frame: 8,
+            accumulator: Duration::ZERO,
+            looping: false,
+            steps_this_frame: 0,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn set_hz(&mut self, hz: f64) {
+        self.step = Duration::from_secs_f64(1.0 / hz);
+    }
+
+    /// Fraction of a step the frame time is past the last step, what the interpolation uses.
+    pub fn overstep(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.step.as_secs_f64()) as f32
+    }
+
+    /// Whether another step runs this frame, adds the frame time on the first call of a frame.
+    fn next_step(&mut self, delta: Duration) -> bool {
+        if !self.looping {
+            self.accumulator += delta;
+            self.steps_this_frame = 0;
+        }
+        if self.accumulator >= self.step && self.steps_this_frame < self.max_steps_per_frame {
+            self.accumulator -= self.step;
+            self.steps_this_frame += 1;
+            self.looping = true;
+            return true;
+        }
+        if self.accumulator >= self.step {
+            // Dropped, the next frame starts with less than a step left
+            self.accumulator = Duration::from_nanos(
+                (self.accumulator.as_nanos() % self.step.as_nanos().max(1)) as u64,
+            );
+        }
+        self.looping = false;
+        false
+    }
+}
+
+fn run_fixed_step(time: Res<Time>, mut fixed_time: ResMut<FixedTime>) -> ShouldRun {
+    if fixed_time.next_step(time.delta()) {
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// Draws the entity between its `Transform` of the last two fixed steps.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct InterpolatedTransform {
+    pub previous: Transform,
+    pub current: Transform,
+}
+
+impl InterpolatedTransform {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            current: transform,
+        }
+    }
+
+    pub fn at(&self, t: f32) -> Transform {
+        Transform {
+            translation: self.previous.translation.lerp(self.current.translation, t),
+            rotation: self.previous.rotation.slerp(self.current.rotation, t),
+            scale: self.previous.scale.lerp(self.current.scale, t),
+        }
+    }
+}
+
+fn restore_fixed_transforms(mut query: Query<(&InterpolatedTransform, &mut Transform)>) {
+    for (interpolated, mut transform) in query.iter_mut() {
+        *transform = interpolated.current;
+    }
+}
+
+fn save_previous_transforms(mut query: Query<(&mut InterpolatedTransform, &Transform)>) {
+    for (mut interpolated, transform) in query.iter_mut() {
+        interpolated.previous = *transform;
+    }
+}
+
+fn save_current_transforms(mut query: Query<(&mut InterpolatedTransform, &Transform)>) {
+    for (mut interpolated, transform) in query.iter_mut() {
+        interpolated.current = *transform;
+    }
+}
+
+fn interpolate_transforms(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&InterpolatedTransform, &mut Transform)>,
+) {
+    let t = fixed_time.overstep().clamp(0.0, 1.0);
+    for (interpolated, mut transform) in query.iter_mut() {
+        *transform = interpolated.at(t);
+    }
+}
+
+pub trait AddFixedSystem {
+    /// Adds a system to [`FixedStage::Update`].
+    fn add_fixed_system<Params>(&mut self, system: impl IntoSystemDescriptor<Params>) -> &mut Self;
+}
+
+impl AddFixedSystem for App {
+    fn add_fixed_system<Params>(&mut self, system: impl IntoSystemDescriptor<Params>) -> &mut Self {
+        self.schedule
+            .stage(FixedUpdateStage, |schedule: &mut Schedule| {
+                schedule.add_system_to_stage(FixedStage::Update, system)
+            });
+        self
+    }
+}
+
+/// Insert a [`FixedTime`] before it to step at other than 60 Hz.
+pub struct FixedUpdatePlugin;
+impl Plugin for FixedUpdatePlugin {
+    fn build(&self, app: &mut App) {
+        let fixed_schedule = Schedule::default()
+            .with_run_criteria(run_fixed_step)
+            .with_stage(
+                FixedStage::First,
+                SystemStage::parallel().with_system(save_previous_transforms),
+            )
+            .with_stage(FixedStage::Update, SystemStage::parallel())
+            .with_stage(
+                FixedStage::Last,
+                SystemStage::parallel().with_system(save_current_transforms),
+            );
+
+        app.init_resource::<FixedTime>()
+            .add_stage_before(CoreStage::PostUpdate, FixedUpdateStage, fixed_schedule)
+            .add_system_to_stage(CoreStage::First, restore_fixed_transforms)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                interpolate_transforms.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_cover_frame_time_and_keep_overstep() {
+        let mut fixed_time = FixedTime::from_hz(10.0);
+        let mut steps = 0;
+        while fixed_time.next_step(Duration::from_millis(250)) {
+            steps += 1;
+        }
+        assert_eq!(steps, 2);
+        assert!((fixed_time.overstep() - 0.5).abs() < 1e-4);
+
+        // The left over half step completes with the next frame
+        steps = 0;
+        while fixed_time.next_step(Duration::from_millis(50)) {
+            steps += 1;
+        }
+        assert_eq!(steps, 1);
+        assert!(fixed_time.overstep().abs() < 1e-4);
+
+        fixed_time.max_steps_per_frame = 3;
+        steps = 0;
+        while fixed_time.next_step(Duration::from_secs(10)) {
+            steps += 1;
+        }
+        assert_eq!(steps, 3);
+        assert!(fixed_time.overstep() < 1.0);
+    }
+}
@@ -0,0 +1,282 @@
+use bevy::{
+    ecs::system::SystemState,
+    prelude::{Deref, DerefMut, Entity, FromWorld, Query, Res, ResMut, Resource, Vec4, World},
+};
+use encase::ShaderType;
+
+use crate::{
+    render::{
+        blend::BlendMode,
+        camera::component::CameraUniforms,
+        cleanup::EntityRenderState,
+        color::ColorSpace,
+        resource::{
+            component_uniform::ComponentUniforms,
+            pipeline::{
+                BindGroupLayout, FragmentState, PipelineCache, PipelineLayoutDescriptor,
+                RenderPipelineDescriptor, VertexState,
+            },
+            renderer::{RenderDevice, RenderQueue},
+            shader::Shader,
+            specialized_pipeline::{PipelineSpecialize, Specialized},
+        },
+        texture,
+    },
+    util::{EngineDefault, EntityBound},
+};
+
+use super::{Trail, TRAIL_SHADER_HANDLE};
+
+#[derive(Resource)]
+pub struct TrailPipeline {
+    pub view_layout: BindGroupLayout,
+    pub trail_layout: BindGroupLayout,
+}
+
+impl FromWorld for TrailPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let mut state: SystemState<(
+            Res<RenderDevice>,
+            ResMut<PipelineCache>,
+            ResMut<Specialized<Self>>,
+        )> = SystemState::new(world);
+        let (render_device, mut pipeline_cache, mut specialized_self) = state.get_mut(world);
+
+        let view_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: Some(CameraUniforms::min_size()),
+                    },
+                    count: None,
+                }],
+                label: Some("trail_view_layout"),
+            });
+
+        let trail_layout =
+            render_device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(TrailData::min_size()),
+                    },
+                    count: None,
+                }],
+                label: Some("trail_points_layout"),
+            });
+
+
+        let trail_pipeline = TrailPipeline {
+            view_layout,
+            trail_layout,
+        };
+
+        for key in BlendMode::ALL {
+            let id = pipeline_cache.queue(trail_pipeline.specialize(&render_device, *key));
+            specialized_self.pipelines.insert(*key, id);
+        }
+
+        trail_pipeline
+    }
+}
+
+impl PipelineSpecialize for TrailPipeline {
+    type Key = BlendMode;
+
+    fn specialize(&self, _render_device: &RenderDevice, key: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: None,
+            layout: PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: vec![self.view_layout.clone(), self.trail_layout.clone()],
+                push_constant_ranges: Vec::new(),
+            },
+            vertex: VertexState {
+                shader: TRAIL_SHADER_HANDLE.typed(),
+                shader_defs: Vec::new(),
+                entry_point: Shader::VS_ENTRY_DEFAULT,
+                buffers: Vec::new(),
+            },
+            fragment: Some(FragmentState {
+                shader: TRAIL_SHADER_HANDLE.typed(),
+                shader_defs: key.shader_defs(),
+                entry_point: Shader::FS_ENTRY_DEFAULT,
+                targets: vec![Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::engine_default(),
+                    blend: Some(key.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None, // Ribbon twists, both faces are visible
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::DepthTexture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        }
+    }
+}
+
+#[derive(ShaderType)]
+pub struct TrailData {
+    color: Vec4,
+    width: f32,
+    count: u32,
+    #[size(runtime)]
+    points: Vec<Vec4>,
+}
+
+impl TrailData {
+    pub fn new(trail: &Trail, color_space: ColorSpace) -> Self {
+        Self {
+            color: trail.color.as_gpu_vec(color_space),
+            width: trail.width,
+            count: trail.points().len() as u32,
+            points: trail.points().iter().map(|p| p.extend(1.0)).collect(),
+        }
+    }
+}
+
+pub struct GpuTrail {
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub point_count: u32,
+}
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct TrailBuffers(pub EntityBound<GpuTrail>);
+
+impl EntityRenderState for TrailBuffers {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(&entity);
+    }
+}
+
+/// Storage buffer contents of `trail`, padded to `TrailData::min_size` when it has no points.
+fn trail_bytes(trail: &Trail, color_space: ColorSpace) -> Vec<u8> {
+    let mut scratch = encase::StorageBuffer::new(Vec::<u8>::new());
+    scratch.write(&TrailData::new(trail, color_space)).unwrap();
+    let mut bytes = scratch.into_inner();
+
+    // The layout binds at least one point, empty trails are not drawn but still bound
+    let min_size = TrailData::min_size().get() as usize;
+    if bytes.len() < min_size {
+        bytes.resize(min_size, 0);
+    }
+    bytes
+}
+
+pub fn prepare_trail_buffers(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    trail_pipeline: Res<TrailPipeline>,
+    mut trail_buffers: ResMut<TrailBuffers>,
+    color_space: Res<ColorSpace>,
+    query: Query<(Entity, &Trail)>,
+) {
+    for (entity, trail) in query.iter() {
+        let bytes = trail_bytes(trail, *color_space);
+        let point_count = trail.points().len() as u32;
+
+        if let Some(gpu_trail) = trail_buffers.get_mut(&entity) {
+            if gpu_trail.buffer.size() >= bytes.len() as u64 {
+                render_queue.write_buffer(&gpu_trail.buffer, 0, &bytes);
+                gpu_trail.point_count = point_count;
+                continue;
+            }
+        }
+
+        let buffer = render_device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("trail_points_buffer"),
+            contents: &bytes,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &trail_pipeline.trail_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        trail_buffers.insert(
+            entity,
+            GpuTrail {
+                buffer,
+                bind_group,
+                point_count,
+            },
+        );
+    }
+}
+
+#[derive(Default, Resource)]
+pub struct TrailBindGroups {
+    pub view_bind_group: Option<wgpu::BindGroup>,
+}
+
+pub fn create_trail_bind_groups(
+    mut trail_bind_groups: ResMut<TrailBindGroups>,
+    render_device: Res<RenderDevice>,
+    trail_pipeline: Res<TrailPipeline>,
+    view_uniforms: Res<ComponentUniforms<CameraUniforms>>,
+) {
+    let Some(view_binding) = view_uniforms.binding() else {
+        return;
+    };
+    let view_bind_group = render_device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &trail_pipeline.view_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: view_binding,
+        }],
+    });
+
+    trail_bind_groups.view_bind_group = Some(view_bind_group);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use crate::render::color::Color;
+
+    use super::*;
+
+    #[test]
+    fn empty_trails_fill_the_binding() {
+        let min_size = TrailData::min_size().get() as usize;
+
+        let mut trail = Trail::new(8, 1.0, Color::WHITE);
+        assert_eq!(trail_bytes(&trail, ColorSpace::Srgb).len(), min_size);
+
+        trail.push(Vec3::ZERO);
+        trail.push(Vec3::X);
+        assert!(trail_bytes(&trail, ColorSpace::Srgb).len() > min_size);
+    }
+}
@@ -0,0 +1,30 @@
+use bevy::prelude::{Bundle, GlobalTransform, Transform};
+
+use crate::render::{
+    blend::BlendMode, camera::component::Visibility, system::RenderFunctionId,
+};
+
+use super::{Trail, TRAIL_DEFAULT_BLEND_MODE, TRAIL_RENDER_FUNCTION};
+
+#[derive(Bundle)]
+pub struct TrailBundle {
+    pub global_transform: GlobalTransform,
+    pub transform: Transform,
+    pub trail: Trail,
+    pub blend_mode: BlendMode,
+    pub visibility: Visibility,
+    pub render_function: RenderFunctionId,
+}
+
+impl Default for TrailBundle {
+    fn default() -> Self {
+        Self {
+            global_transform: GlobalTransform::default(),
+            transform: Transform::default(),
+            trail: Trail::default(),
+            blend_mode: TRAIL_DEFAULT_BLEND_MODE,
+            visibility: Visibility::Inherited,
+            render_function: TRAIL_RENDER_FUNCTION.into(),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    asset::load_internal_asset,
+    prelude::{
+        Component, CoreStage, Entity, GlobalTransform, HandleUntyped, IntoSystemDescriptor,
+        Plugin, Query, Vec3, World,
+    },
+    reflect::TypeUuid,
+    transform::TransformSystem,
+};
+
+use crate::render::{
+    blend::BlendMode,
+    camera::component::CameraUniforms,
+    cleanup::AddEntityCleanup,
+    color::Color,
+    resource::{
+        pipeline::PipelineCache, shader::Shader, specialized_pipeline::Specialized,
+        uniform::DynamicUniformId,
+    },
+    stats::RenderStats,
+    system::{AddRenderFunction, RenderResult},
+    RenderStage,
+};
+
+use self::bind::{
+    create_trail_bind_groups, prepare_trail_buffers, TrailBindGroups, TrailBuffers, TrailPipeline,
+};
+
+pub mod bind;
+pub mod bundle;
+
+const TRAIL_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 55678909876445673);
+
+pub struct FlatTrailPlugin;
+impl Plugin for FlatTrailPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        load_internal_asset!(app, TRAIL_SHADER_HANDLE, "trail.wgsl", Shader::from_wgsl);
+
+        app.init_resource::<Specialized<TrailPipeline>>()
+            .init_resource::<TrailPipeline>()
+            .init_resource::<TrailBuffers>()
+            .init_resource::<TrailBindGroups>()
+            .add_entity_cleanup::<TrailBuffers, Trail>()
+            .add_render_function(TRAIL_RENDER_FUNCTION, render_trail)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                record_trail_points.after(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(RenderStage::Prepare, prepare_trail_buffers)
+            .add_system_to_stage(RenderStage::Create, create_trail_bind_groups);
+    }
+}
+
+/// Ribbon following the entity's [`GlobalTransform`].
+///
+/// Only the control points are uploaded to the GPU, the ribbon triangles
+/// are expanded in the vertex shader facing the camera.
+#[derive(Component, Clone)]
+pub struct Trail {
+    pub max_points: usize,
+    pub min_distance: f32,
+    pub width: f32,
+    pub color: Color,
+    points: VecDeque<Vec3>,
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Self::new(64, 1.0, Color::WHITE)
+    }
+}
+
+impl Trail {
+    pub fn new(max_points: usize, width: f32, color: Color) -> Self {
+        Self {
+            max_points,
+            min_distance: 0.05,
+            width,
+            color,
+            points: VecDeque::with_capacity(max_points),
+        }
+    }
+
+    /// Control points ordered from the oldest to the newest.
+    pub fn points(&self) -> &VecDeque<Vec3> {
+        &self.points
+    }
+
+    pub fn push(&mut self, point: Vec3) {
+        if let Some(last) = self.points.back() {
+            if last.distance(point) < self.min_distance {
+                return;
+            }
+        }
+        while self.points.len() >= self.max_points.max(2) {
+            self.points.pop_front();
+        }
+        self.points.push_back(point);
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+}
+
+pub fn record_trail_points(mut query: Query<(&mut Trail, &GlobalTransform)>) {
+    for (mut trail, transform) in query.iter_mut() {
+        trail.push(transform.translation());
+    }
+}
+
+pub const TRAIL_RENDER_FUNCTION: usize = 4;
+/// Used for trails without a [`BlendMode`].
+pub const TRAIL_DEFAULT_BLEND_MODE: BlendMode = BlendMode::AlphaBlend;
+fn render_trail<'w>(
+    camera: Entity,
+    object: Entity,
+    world: &'w World,
+    render_pass: &mut wgpu::RenderPass<'w>,
+) -> RenderResult {
+    // -- Set Pipeline --
+    let Some(specialized_trail_pipeline) = world.get_resource::<Specialized<TrailPipeline>>() else {
+        return RenderResult::Failure("Specialized<TrailPipeline> resource missing");
+    };
+    let Some(pipeline_cache) = world.get_resource::<PipelineCache>() else {
+        return RenderResult::Failure("PipelineCache resource missing");
+    };
+
+    let blend_mode = world
+        .get::<BlendMode>(object)
+        .copied()
+        .unwrap_or(TRAIL_DEFAULT_BLEND_MODE);
+    let Some(pipeline_id) = specialized_trail_pipeline.pipelines.get(&blend_mode) else {
+        return RenderResult::Failure("pipeline not specialized");
+    };
+    let Some(render_pipeline) = pipeline_cache.get(pipeline_id) else {
+        return RenderResult::Failure("pipeline not compiled yet");
+    };
+    render_pass.set_pipeline(render_pipeline);
+    let Some(render_stats) = world.get_resource::<RenderStats>() else {
+        return RenderResult::Failure("RenderStats resource missing");
+    };
+    render_stats.pipeline_switch();
+    // -- -- -- -------- -- -- --
+
+    // -- Get Trail --
+    let Some(trail_buffers) = world.get_resource::<TrailBuffers>() else {
+        return RenderResult::Failure("TrailBuffers resource missing");
+    };
+    let Some(gpu_trail) = trail_buffers.get(&object) else {
+        return RenderResult::Failure("trail buffer not prepared yet");
+    };
+    if gpu_trail.point_count < 2 {
+        return RenderResult::Success;
+    }
+    // -- -- -- -------- -- -- --
+
+    // -- Bind View, Trail BindGroups --
+    let Some(trail_bind_groups) = world.get_resource::<TrailBindGroups>() else {
+        return RenderResult::Failure("TrailBindGroups resource missing");
+    };
+
+    let Some(view_uniform_id) = world.get::<DynamicUniformId<CameraUniforms>>(camera) else {
+        return RenderResult::Failure("no CameraUniforms id");
+    };
+    let Some(view_bind_group) = trail_bind_groups.view_bind_group.as_ref() else {
+        return RenderResult::Failure("view bind group not created");
+    };
+    render_pass.set_bind_group(0, view_bind_group, &[**view_uniform_id]);
+    render_pass.set_bind_group(1, &gpu_trail.bind_group, &[]);
+    render_stats.bind_group_switches(2);
+    // -- -- -- -------- -- -- --
+
+    // -- Draw --
+    // Two vertices per control point, expanded in the vertex shader
+    render_pass.draw(0..2 * gpu_trail.point_count, 0..1);
+    render_stats.draw(2 * gpu_trail.point_count, 1);
+    // -- -- -- -------- -- -- --
+
+    RenderResult::Success
+}
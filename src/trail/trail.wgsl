@@ -0,0 +1,76 @@
+
+// -- Vertex -----
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+}
+
+struct Trail {
+    color: vec4<f32>,
+    width: f32,
+    count: u32,
+    points: array<vec4<f32>>,
+}
+
+struct VertexOutput {
+    @builtin(position)  clip_position: vec4<f32>,
+    @location(0)        age: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<storage, read> trail: Trail;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Two vertices per control point, one on each side of the ribbon
+    let last = trail.count - 1u;
+    let i = min(vertex_index / 2u, last);
+    let side = f32(vertex_index % 2u) * 2.0 - 1.0;
+
+    let point = trail.points[i].xyz;
+    let prev = trail.points[max(i, 1u) - 1u].xyz;
+    let next = trail.points[min(i + 1u, last)].xyz;
+
+    var tangent = next - prev;
+    if (length(tangent) < 0.0001) {
+        tangent = vec3<f32>(1.0, 0.0, 0.0);
+    }
+
+    // camera.view holds the camera transform, translation is the world position
+    let to_camera = camera.view[3].xyz - point;
+    var normal = cross(normalize(tangent), to_camera);
+    if (length(normal) < 0.0001) {
+        normal = vec3<f32>(0.0, 1.0, 0.0);
+    }
+    normal = normalize(normal);
+
+    // 0.0 at the oldest point, 1.0 at the newest
+    let age = f32(i) / f32(max(last, 1u));
+    let position = point + normal * side * 0.5 * trail.width * age;
+
+    out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.age = age;
+
+    return out;
+}
+
+// -- Fragment -----
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = vec4<f32>(trail.color.rgb, trail.color.a * in.age);
+#ifdef BLEND_MULTIPLY
+    // The Multiply blend state expects premultiplied alpha
+    color = vec4<f32>(color.rgb * color.a, color.a);
+#endif
+    return color;
+}
@@ -0,0 +1,164 @@
+//!
+//! Parenting of engine entities.
+//!
+//! Transforms are propagated by bevy's `TransformPlugin`, added by [`FlatBevyPlugins`](crate::FlatBevyPlugins),
+//! in `CoreStage::PostUpdate` under [`TransformSystem::TransformPropagate`].
+//! The render stages run after `CoreStage::PostUpdate`, so from `RenderStage::Extract` on
+//! every `GlobalTransform` matches the `Transform`s and parents of the frame being rendered.
+//!
+//! Systems moving or parenting entities in `CoreStage::PostUpdate` have to run
+//! `.before(TransformSystem::TransformPropagate)`, later changes are rendered in the next frame.
+//!
+
+use bevy::{
+    ecs::system::{Command, EntityCommands},
+    hierarchy::BuildWorldChildren,
+    prelude::{Entity, GlobalTransform, Transform, World},
+};
+
+pub use bevy::transform::TransformSystem;
+
+pub trait ParentInPlace {
+    /// Parents the entity to `parent`, changing its `Transform` so it stays where it is in the world.
+    fn set_parent_in_place(&mut self, parent: Entity) -> &mut Self;
+    /// Removes the parent, changing the `Transform` so the entity stays where it is in the world.
+    fn remove_parent_in_place(&mut self) -> &mut Self;
+}
+
+impl<'w, 's, 'a> ParentInPlace for EntityCommands<'w, 's, 'a> {
+    fn set_parent_in_place(&mut self, parent: Entity) -> &mut Self {
+        let child = self.id();
+        self.commands().add(SetParentInPlace { child, parent });
+        self
+    }
+
+    fn remove_parent_in_place(&mut self) -> &mut Self {
+        let child = self.id();
+        self.commands().add(RemoveParentInPlace { child });
+        self
+    }
+}
+
+pub struct SetParentInPlace {
+    pub child: Entity,
+    pub parent: Entity,
+}
+
+impl Command for SetParentInPlace {
+    fn write(self, world: &mut World) {
+        let parent_global = world
+            .get::<GlobalTransform>(self.parent)
+            .copied()
+            .unwrap_or_default();
+        let child_global = world
+            .get::<GlobalTransform>(self.child)
+            .copied()
+            .unwrap_or_default();
+        let transform = Transform::from_matrix(
+            parent_global.compute_matrix().inverse() * child_global.compute_matrix(),
+        );
+
+        let mut child = world.entity_mut(self.child);
+        child.insert(transform);
+        child.set_parent(self.parent);
+    }
+}
+
+pub struct RemoveParentInPlace {
+    pub child: Entity,
+}
+
+impl Command for RemoveParentInPlace {
+    fn write(self, world: &mut World) {
+        let child_global = world
+            .get::<GlobalTransform>(self.child)
+            .copied()
+            .unwrap_or_default();
+
+        let mut child = world.entity_mut(self.child);
+        child.insert(Transform::from_matrix(child_global.compute_matrix()));
+        child.remove_parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::system::SystemState,
+        hierarchy::HierarchyPlugin,
+        prelude::{App, Commands, CoreStage, Query, ResMut, Resource, SystemStage, Vec3},
+        transform::TransformPlugin,
+    };
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugin(TransformPlugin).add_plugin(HierarchyPlugin);
+        app
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).abs().max_element() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn parent_in_place_keeps_world_position() {
+        let mut app = app();
+        let parent = app
+            .world
+            .spawn((
+                Transform::from_xyz(1.0, 2.0, 3.0).with_scale(Vec3::splat(2.0)),
+                GlobalTransform::default(),
+            ))
+            .id();
+        let child = app
+            .world
+            .spawn((Transform::from_xyz(5.0, 0.0, 0.0), GlobalTransform::default()))
+            .id();
+        app.update();
+
+        let mut state = SystemState::<Commands>::new(&mut app.world);
+        state.get_mut(&mut app.world).entity(child).set_parent_in_place(parent);
+        state.apply(&mut app.world);
+        app.update();
+        let global = app.world.get::<GlobalTransform>(child).unwrap();
+        assert_close(global.translation(), Vec3::new(5.0, 0.0, 0.0));
+
+        state.get_mut(&mut app.world).entity(child).remove_parent_in_place();
+        state.apply(&mut app.world);
+        app.update();
+        let global = app.world.get::<GlobalTransform>(child).unwrap();
+        assert_close(global.translation(), Vec3::new(5.0, 0.0, 0.0));
+    }
+
+    #[derive(Resource, Default)]
+    struct Observed(Vec3);
+
+    /// Stands in for the render stages, which are added after `CoreStage::PostUpdate` too.
+    #[test]
+    fn global_transform_is_propagated_before_render_stages() {
+        let mut app = app();
+        app.init_resource::<Observed>()
+            .add_stage_after(CoreStage::PostUpdate, "render", SystemStage::single_threaded())
+            .add_system_to_stage(
+                "render",
+                |mut observed: ResMut<Observed>, query: Query<&GlobalTransform>| {
+                    for global in query.iter() {
+                        observed.0 = observed.0.max(global.translation());
+                    }
+                },
+            );
+
+        let parent = app
+            .world
+            .spawn((Transform::from_xyz(1.0, 0.0, 0.0), GlobalTransform::default()))
+            .id();
+        app.world.entity_mut(parent).with_children(|parent| {
+            parent.spawn((Transform::from_xyz(0.0, 2.0, 0.0), GlobalTransform::default()));
+        });
+        app.update();
+
+        assert_close(app.world.resource::<Observed>().0, Vec3::new(1.0, 2.0, 0.0));
+    }
+}
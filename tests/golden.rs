@@ -0,0 +1,36 @@
+//!
+//! Golden scenes of the engine against the references in `tests/golden`.
+//!
+//! Ignored by default as they need an adapter, `FLAT_BLESS=1` writes the references.
+//!
+
+use flat::golden::GOLDEN_SCENES;
+
+fn assert_scene(name: &str) {
+    GOLDEN_SCENES
+        .iter()
+        .find(|scene| scene.name == name)
+        .expect("No such golden scene")
+        .assert_matches();
+}
+
+#[cfg(feature = "sprite2d")]
+#[test]
+#[ignore = "needs a GPU adapter"]
+fn sprites() {
+    assert_scene("sprites");
+}
+
+#[cfg(feature = "mesh3d")]
+#[test]
+#[ignore = "needs a GPU adapter"]
+fn mesh_cube() {
+    assert_scene("mesh_cube");
+}
+
+#[cfg(feature = "sprite2d")]
+#[test]
+#[ignore = "needs a GPU adapter"]
+fn post_grading() {
+    assert_scene("post_grading");
+}